@@ -0,0 +1,35 @@
+//! Download a few files concurrently while reporting the aggregated progress.
+//!
+//! ```console
+//! cargo run --example concurrent_download -- <URL>...
+//! ```
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use rim::utils::{self, AggregateProgress, Progress};
+use url::Url;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let dest_dir = tempfile::tempdir()?;
+    let files = std::env::args()
+        .skip(1)
+        .enumerate()
+        .map(|(idx, arg)| Ok((Url::parse(&arg)?, dest_dir.path().join(idx.to_string()))))
+        .collect::<Result<Vec<(Url, PathBuf)>>>()?;
+
+    let pos_cb = |pos: f32| -> Result<()> {
+        println!("overall progress: {pos:.1}%");
+        Ok(())
+    };
+    let aggregate = AggregateProgress::new(Progress::new(&pos_cb).with_len(100.0));
+    utils::download_all_async(&files, 4, None, &aggregate).await?;
+
+    let (done, total) = aggregate.totals();
+    println!(
+        "downloaded {done}/{total} bytes into '{}'",
+        dest_dir.path().display()
+    );
+    Ok(())
+}
@@ -1,6 +1,9 @@
 use std::{
     path::PathBuf,
-    sync::mpsc::{self, Receiver},
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
     thread,
     time::Duration,
 };
@@ -9,7 +12,7 @@ use super::Result;
 use rim::{
     components::Component,
     toolset_manifest::ToolsetManifest,
-    utils::{self, Progress},
+    utils::{self, CancellationToken, Progress},
     InstallConfiguration,
 };
 
@@ -21,6 +24,9 @@ pub(crate) const BLOCK_EXIT_EVENT: &str = "toggle-exit-blocker";
 pub(crate) const LOADING_TEXT: &str = "loading-text";
 pub(crate) const LOADING_FINISHED: &str = "loading-finished";
 
+/// The token to cancel the running installation, which is replaced once another one starts.
+static INSTALL_CANCELLATION: Mutex<Option<CancellationToken>> = Mutex::new(None);
+
 /// Configure the logger to use a communication channel ([`mpsc`]),
 /// allowing us to send logs accrossing threads.
 ///
@@ -72,15 +78,24 @@ pub(crate) fn install_toolkit_in_new_thread(
         // Initialize a progress sender.
         let pos_cb =
             |pos: f32| -> anyhow::Result<()> { Ok(window.emit(PROGRESS_UPDATE_EVENT, pos)?) };
-        let progress = Progress::new(&pos_cb);
+        let cancel = CancellationToken::new();
+        *INSTALL_CANCELLATION.lock().unwrap() = Some(cancel.clone());
+        let progress = Progress::new(&pos_cb).with_cancellation_token(cancel);
 
         // TODO: Use continuous progress
         let config = InstallConfiguration::new(&install_dir, &manifest)?
             .with_progress_indicator(Some(progress));
-        if is_update {
-            config.update(components_list)?;
+        let res = if is_update {
+            config.update(components_list)
         } else {
-            config.install(components_list)?;
+            config.install(components_list)
+        };
+        if let Err(e) = res {
+            if e.is::<utils::Cancelled>() {
+                // nothing is running anymore, so it's safe to exit now
+                window.emit(BLOCK_EXIT_EVENT, false)?;
+            }
+            return Err(e);
         }
 
         // 安装完成后，发送安装完成事件
@@ -91,6 +106,14 @@ pub(crate) fn install_toolkit_in_new_thread(
     });
 }
 
+/// Stop the running installation, its partially downloaded and extracted files are removed.
+#[tauri::command]
+pub(crate) fn cancel_installation() {
+    if let Some(token) = &*INSTALL_CANCELLATION.lock().unwrap() {
+        token.cancel();
+    }
+}
+
 #[derive(serde::Serialize)]
 pub struct Language {
     pub id: String,
@@ -6,7 +6,7 @@ use tauri::api::dialog::FileDialogBuilder;
 
 use super::{common, INSTALL_DIR};
 use crate::error::Result;
-use rim::components::Component;
+use rim::components::{self, Component};
 use rim::toolset_manifest::{get_toolset_manifest, ToolsetManifest};
 use rim::{try_it, utils};
 
@@ -22,11 +22,13 @@ pub(super) fn main() -> Result<()> {
             select_folder,
             check_install_path,
             get_component_list,
+            get_install_plan,
             install_toolchain,
             run_app,
             welcome_label,
             load_manifest_and_ret_version,
             window_title,
+            common::cancel_installation,
             common::supported_languages,
             common::set_locale,
         ])
@@ -104,6 +106,15 @@ fn get_component_list() -> Result<Vec<Component>> {
     Ok(cached_manifest().current_target_components(true)?)
 }
 
+/// Expand the selected components into the install plan, which also contains the components
+/// they require, in the order of installation.
+#[tauri::command(rename_all = "snake_case")]
+fn get_install_plan(components_list: Vec<Component>) -> Result<Vec<Component>> {
+    let all_components = cached_manifest().current_target_components(true)?;
+    let plan = components::resolve_dependencies(&all_components, &components_list)?;
+    Ok(plan.into_iter().cloned().collect())
+}
+
 #[tauri::command]
 fn welcome_label() -> String {
     t!("welcome", product = t!("product")).into()
@@ -44,6 +44,7 @@ pub(super) fn main() -> Result<()> {
             maybe_self_update,
             handle_toolkit_install_click,
             window_title,
+            common::cancel_installation,
             common::supported_languages,
             common::set_locale,
         ])
@@ -8,6 +8,7 @@ export interface Component {
   desc: string;
   groupName: string | null;
   isToolchainComponent: boolean;
+  requires: string[];
   toolInstaller?: {
     required: boolean;
     optional: boolean;
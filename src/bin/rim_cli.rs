@@ -33,7 +33,11 @@ impl Mode {
 }
 
 fn main() -> Result<()> {
-    Mode::detect().run()?;
+    if let Err(e) = Mode::detect().run() {
+        // same as returning the error, but with an exit code that tells config errors apart
+        eprintln!("Error: {e:?}");
+        std::process::exit(cli::exit_code(&e));
+    }
 
     // pause the console in case the user launch
     // the program with double click, which most people do.
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Subcommand, ValueHint};
+use url::Url;
+
+use super::{ManagerSubcommands, PathOrUrl};
+use crate::bundle::BundleOpt;
+use crate::core::install::{default_rustup_dist_server, default_rustup_update_root};
+use crate::toolset_manifest::get_toolset_manifest;
+use crate::utils;
+
+#[derive(Subcommand, Debug)]
+pub(super) enum BundleCommand {
+    /// Download a toolkit and the selected components of current target into a single archive,
+    /// which can be installed with `--from-bundle` option of the installer.
+    Create {
+        /// Allow insecure connections when download packages from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
+        /// Download at most this number of files at the same time, default is 4.
+        #[arg(short, long, value_name = "NUM", value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+        /// Specify a path or url of manifest file of the toolkit, defaulting to the built-in one.
+        #[arg(long, value_name = "PATH or URL")]
+        manifest: Option<PathOrUrl>,
+        /// Include a list of optional components (separated by comma) in the bundle.
+        /// Note that required components will be included whether selected or not.
+        #[arg(short, long, value_delimiter = ',')]
+        component: Option<Vec<String>>,
+        /// Specify another server to download Rust toolchain.
+        #[arg(hide = true, long, value_name = "URL", value_hint = ValueHint::Url)]
+        rustup_dist_server: Option<Url>,
+        /// Specify another server to download rustup.
+        #[arg(hide = true, long, value_name = "URL", value_hint = ValueHint::Url)]
+        rustup_update_root: Option<Url>,
+        /// The path of the archive to create, such as `rust-bundle.tar.gz`.
+        #[arg(value_name = "OUTPUT", value_hint = ValueHint::FilePath)]
+        output: PathBuf,
+    },
+}
+
+impl BundleCommand {
+    fn execute(&self) -> Result<()> {
+        match self {
+            Self::Create {
+                insecure,
+                jobs,
+                manifest,
+                component,
+                rustup_dist_server,
+                rustup_update_root,
+                output,
+            } => {
+                let manifest_url = manifest.as_ref().map(|s| s.to_url()).transpose()?;
+                let manifest = get_toolset_manifest(manifest_url, *insecure)?;
+                BundleOpt::new(&manifest)
+                    .with_components(component.clone().unwrap_or_default())
+                    .with_rustup_dist_server(
+                        rustup_dist_server
+                            .clone()
+                            .unwrap_or_else(|| default_rustup_dist_server().clone()),
+                    )
+                    .with_rustup_update_root(
+                        rustup_update_root
+                            .clone()
+                            .unwrap_or_else(|| default_rustup_update_root().clone()),
+                    )
+                    .insecure(*insecure)
+                    .with_jobs(jobs.map_or(utils::DEFAULT_DOWNLOAD_JOBS, usize::from))
+                    .create(&utils::to_nomalized_abspath(output, None)?)
+            }
+        }
+    }
+}
+
+pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Bundle { command } = cmd else {
+        return Ok(false);
+    };
+
+    command.execute()?;
+
+    Ok(true)
+}
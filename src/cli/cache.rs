@@ -0,0 +1,103 @@
+//! Separated module to handle the download cache in command line.
+
+use anyhow::{bail, Result};
+use clap::Subcommand;
+use indicatif::HumanBytes;
+
+use super::{common, ManagerSubcommands};
+use crate::core::cache;
+use crate::utils::DownloadCache;
+
+#[derive(Subcommand, Debug)]
+pub(super) enum CacheCommand {
+    /// Display the cached packages, the most recently used first
+    List,
+    /// Remove every cached package
+    Clean,
+    /// Show or set the size limit of the cache
+    ///
+    /// The least recently used packages are removed once the cache exceeds the limit.
+    Limit {
+        /// The size limit, such as `500M` or `2G`, or `none` for no limit.
+        #[arg(value_name = "SIZE")]
+        size: Option<String>,
+    },
+}
+
+impl CacheCommand {
+    fn execute(&self, cache: DownloadCache) -> Result<()> {
+        match self {
+            Self::List => {
+                let entries = cache.entries()?;
+                if entries.is_empty() {
+                    info!("{}", t!("cache_empty", dir = cache.dir().display()));
+                    return Ok(());
+                }
+                for entry in &entries {
+                    println!("{:>10}  {}", HumanBytes(entry.size).to_string(), entry.url);
+                }
+                let total = entries.iter().map(|entry| entry.size).sum::<u64>();
+                println!(
+                    "\n{}",
+                    t!(
+                        "cache_total",
+                        count = entries.len(),
+                        size = HumanBytes(total),
+                        limit = display_limit(cache.max_size()),
+                        dir = cache.dir().display()
+                    )
+                );
+            }
+            Self::Clean => {
+                let size = cache.size()?;
+                if size > 0
+                    && !common::confirm(
+                        t!("cache_clean_confirmation", size = HumanBytes(size)),
+                        false,
+                    )?
+                {
+                    return Ok(());
+                }
+                let freed = cache.clean()?;
+                info!("{}", t!("cache_cleaned", size = HumanBytes(freed)));
+            }
+            Self::Limit { size: None } => {
+                println!("{}", display_limit(cache.max_size()));
+            }
+            Self::Limit { size: Some(size) } => {
+                let limit = cache::parse_size_limit(size)?;
+                cache::set_size_limit(size)?;
+                let freed = limit.map_or(Ok(0), |limit| cache.shrink_to(limit))?;
+                info!(
+                    "{}",
+                    t!(
+                        "cache_limit_set",
+                        limit = display_limit(limit),
+                        freed = HumanBytes(freed)
+                    )
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn display_limit(limit: Option<u64>) -> String {
+    match limit {
+        Some(limit) => HumanBytes(limit).to_string(),
+        None => t!("cache_no_limit").to_string(),
+    }
+}
+
+pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Cache { command } = cmd else {
+        return Ok(false);
+    };
+    let Some(cache) = cache::download_cache()? else {
+        bail!("unable to determine the cache directory of current user");
+    };
+
+    command.execute(cache)?;
+
+    Ok(true)
+}
@@ -0,0 +1,88 @@
+//! Separated module to handle checking for updates in command line.
+
+use anyhow::Result;
+
+use super::update::{latest_toolkit_manifest, ComponentsUpdater};
+use super::ManagerSubcommands;
+use crate::core::toolkit::Toolkit;
+use crate::core::update::{check_self_update, SelfUpdateKind};
+
+/// Execute `check` command.
+pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Check { insecure } = cmd else {
+        return Ok(false);
+    };
+
+    let mut has_update = false;
+    if let Some(installed) = Toolkit::installed(false)? {
+        let installed = &*installed.lock().unwrap();
+        has_update |= check_toolkit(installed, *insecure)?;
+    } else {
+        info!("{}", t!("no_toolkit_installed"));
+    }
+
+    let cur_version = env!("CARGO_PKG_VERSION");
+    match check_self_update(*insecure) {
+        SelfUpdateKind::Newer(version) => {
+            has_update = true;
+            println!(
+                "{}",
+                t!(
+                    "manager_update_available",
+                    current_version = cur_version,
+                    target_version = version
+                )
+            );
+        }
+        SelfUpdateKind::UnNeeded => {
+            info!("{}", t!("latest_manager_installed", version = cur_version))
+        }
+        // a warning was already printed
+        SelfUpdateKind::Uncertain => (),
+    }
+
+    if has_update {
+        info!("{}", t!("check_update_hint"));
+    }
+    Ok(true)
+}
+
+/// Print the changes that the `update` command would make to the installed toolkit,
+/// returns `false` if it's already the latest.
+fn check_toolkit(installed: &Toolkit, insecure: bool) -> Result<bool> {
+    let Some((latest, manifest)) = latest_toolkit_manifest(installed, insecure)? else {
+        return Ok(false);
+    };
+    let new_components = manifest.current_target_components(false)?;
+    let updater = ComponentsUpdater::new(&installed.components, &new_components);
+
+    println!(
+        "{}",
+        t!(
+            "toolkit_update_available",
+            name = installed.name,
+            current_version = installed.version,
+            target_version = latest.version
+        )
+    );
+    let outdated = updater.outdated();
+    if outdated.is_empty() {
+        println!("\t{}", t!("no_component_changes"));
+    }
+    for (comp, diff) in outdated {
+        println!(
+            "\t{}: {} -> {}",
+            comp.name,
+            diff.from.unwrap_or("N/A"),
+            diff.to.unwrap_or("N/A")
+        );
+    }
+    let newly_supported = updater.newly_supported();
+    if !newly_supported.is_empty() {
+        println!("{}", t!("newly_supported_components"));
+        for (comp, diff) in newly_supported {
+            println!("\t{} ({})", comp.name, diff.to.unwrap_or("N/A"));
+        }
+    }
+    Ok(true)
+}
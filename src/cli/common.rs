@@ -14,6 +14,7 @@ use anyhow::{Context, Result};
 use indexmap::IndexMap;
 
 use crate::components::Component;
+use crate::utils;
 
 /// A "convenient" helper macro to [`question_single_choice`].
 ///
@@ -104,46 +105,48 @@ where
     A: Display,
     F: Fn(&str) -> bool,
 {
-    let mut stdout = io::stdout();
-    let default_badge = format!("[{}: {default}]", t!("default"));
-    // if there's a specified prompt or if the extra lines are too long,
-    // we will display the default label above the actual input, making it more visible to users.
-    let show_default_above_input =
-        prompt.is_some() || extra.map(|e| e.lines().count() > 2).unwrap_or_default();
-
-    // print question, with or without default label.
-    if show_default_above_input {
-        writeln!(&mut stdout, "{question}")?;
-    } else {
-        writeln!(&mut stdout, "{question} {default_badge}")?;
-    }
-    // print extra info, such as a list of selectable options.
-    if let Some(ex) = extra {
-        writeln!(&mut stdout, "\n{}", ex)?;
-    }
-
-    loop {
-        if let Some(prmt) = prompt {
-            write!(&mut stdout, "{prmt} ")?;
-        }
+    utils::suspend_for_prompt(|| {
+        let mut stdout = io::stdout();
+        let default_badge = format!("[{}: {default}]", t!("default"));
+        // if there's a specified prompt or if the extra lines are too long,
+        // we will display the default label above the actual input, making it more visible to users.
+        let show_default_above_input =
+            prompt.is_some() || extra.map(|e| e.lines().count() > 2).unwrap_or_default();
+
+        // print question, with or without default label.
         if show_default_above_input {
-            writeln!(&mut stdout, "{default_badge}")?;
+            writeln!(&mut stdout, "{question}")?;
+        } else {
+            writeln!(&mut stdout, "{question} {default_badge}")?;
+        }
+        // print extra info, such as a list of selectable options.
+        if let Some(ex) = extra {
+            writeln!(&mut stdout, "\n{}", ex)?;
         }
-        write!(&mut stdout, "> ")?;
-        _ = stdout.flush();
 
-        let input_raw = readline()?;
-        let input = input_raw.trim();
-        writeln!(&mut stdout)?;
+        loop {
+            if let Some(prmt) = prompt {
+                write!(&mut stdout, "{prmt} ")?;
+            }
+            if show_default_above_input {
+                writeln!(&mut stdout, "{default_badge}")?;
+            }
+            write!(&mut stdout, "> ")?;
+            _ = stdout.flush();
 
-        if input.is_empty() {
-            return Ok(default.to_string());
-        } else if !cond(input) {
-            continue;
-        } else {
-            return Ok(input.to_string());
+            let input_raw = readline()?;
+            let input = input_raw.trim();
+            writeln!(&mut stdout)?;
+
+            if input.is_empty() {
+                return Ok(default.to_string());
+            } else if !cond(input) {
+                continue;
+            } else {
+                return Ok(input.to_string());
+            }
         }
-    }
+    })
 }
 
 /// Display a list of given `choices` and ask for user input that related to choice's index.
@@ -265,26 +268,28 @@ pub(crate) fn confirm<Q: Display>(question: Q, default: bool) -> Result<bool> {
         return Ok(true);
     }
 
-    let mut stdout = io::stdout();
-    writeln!(
-        &mut stdout,
-        "{} ({})",
-        question,
-        if default { "Y/n" } else { "y/N" }
-    )?;
-    write!(&mut stdout, "> ")?;
-    _ = stdout.flush();
+    utils::suspend_for_prompt(|| {
+        let mut stdout = io::stdout();
+        writeln!(
+            &mut stdout,
+            "{} ({})",
+            question,
+            if default { "Y/n" } else { "y/N" }
+        )?;
+        write!(&mut stdout, "> ")?;
+        _ = stdout.flush();
 
-    let input = readline()?;
-    let choice = match input.to_lowercase().as_str() {
-        "y" | "yes" => true,
-        "n" | "no" => false,
-        "" => default,
-        _ => false,
-    };
+        let input = readline()?;
+        let choice = match input.to_lowercase().as_str() {
+            "y" | "yes" => true,
+            "n" | "no" => false,
+            "" => default,
+            _ => false,
+        };
 
-    writeln!(&mut stdout)?;
-    Ok(choice)
+        writeln!(&mut stdout)?;
+        Ok(choice)
+    })
 }
 
 pub(crate) enum Confirm {
@@ -294,24 +299,26 @@ pub(crate) enum Confirm {
 }
 
 pub(crate) fn confirm_install() -> Result<Confirm> {
-    let mut stdout = io::stdout();
+    utils::suspend_for_prompt(|| {
+        let mut stdout = io::stdout();
 
-    writeln!(&mut stdout, "\n{}\n", t!("question_install_options"))?;
-    writeln!(&mut stdout, "1) {} ({})", t!("confirm"), t!("default"))?;
-    writeln!(&mut stdout, "2) {}", t!("reenter"))?;
-    writeln!(&mut stdout, "3) {}", t!("cancel"))?;
-    write!(&mut stdout, "> ")?;
-    _ = stdout.flush();
+        writeln!(&mut stdout, "\n{}\n", t!("question_install_options"))?;
+        writeln!(&mut stdout, "1) {} ({})", t!("confirm"), t!("default"))?;
+        writeln!(&mut stdout, "2) {}", t!("reenter"))?;
+        writeln!(&mut stdout, "3) {}", t!("cancel"))?;
+        write!(&mut stdout, "> ")?;
+        _ = stdout.flush();
 
-    let input = readline()?;
-    let choice = match input.as_str() {
-        "1" | "" => Confirm::Yes,
-        "2" => Confirm::No,
-        _ => Confirm::Abort,
-    };
+        let input = readline()?;
+        let choice = match input.as_str() {
+            "1" | "" => Confirm::Yes,
+            "2" => Confirm::No,
+            _ => Confirm::Abort,
+        };
 
-    writeln!(&mut stdout)?;
-    Ok(choice)
+        writeln!(&mut stdout)?;
+        Ok(choice)
+    })
 }
 
 #[cfg(windows)]
@@ -351,9 +358,10 @@ pub(crate) enum ComponentDecoration<'c> {
     /// is installed or required but not installed, i.e.:
     /// `hello-world (required)`
     InstalledOrRequired,
-    /// A label to show during confirmation page, indicates whether a tool is installed but will be re-install,
-    /// i.e.: `hello-world (installed, reinstalling)`
-    Confirmation,
+    /// Labels to show during confirmation page, indicates whether a tool is installed but will be
+    /// re-install, and which of the other components in the install plan require it,
+    /// i.e.: `rust-src (installed, reinstalling, required by rust-analyzer)`
+    Confirmation(&'c [&'c Component]),
     /// No decoration string, this is the default variant, i.e.:
     /// `hello-world`
     #[default]
@@ -373,11 +381,23 @@ impl ComponentDecoration<'_> {
                     String::new()
                 }
             }
-            Self::Confirmation => {
+            Self::Confirmation(plan) => {
+                let mut labels = vec![];
                 if comp.installed {
-                    format!(" ({})", t!("reinstall"))
-                } else {
+                    labels.push(t!("reinstall").to_string());
+                }
+                let dependents = plan
+                    .iter()
+                    .filter(|c| c.requires.contains(&comp.name))
+                    .map(|c| c.name.as_str())
+                    .collect::<Vec<_>>();
+                if !dependents.is_empty() {
+                    labels.push(t!("required_by", components = dependents.join(", ")).to_string());
+                }
+                if labels.is_empty() {
                     String::new()
+                } else {
+                    format!(" ({})", labels.join(", "))
                 }
             }
             Self::VersionDiff(diff_map) => diff_map
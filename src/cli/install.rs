@@ -4,23 +4,28 @@ use std::collections::HashSet;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 
+use crate::bundle::extract_bundle;
 use crate::cli::common::{self, Confirm};
 use crate::cli::GlobalOpts;
-use crate::components::Component;
+use crate::components::{resolve_dependencies, Component};
 use crate::core::install::{
     default_rustup_dist_server, default_rustup_update_root, InstallConfiguration,
     DEFAULT_CARGO_REGISTRY,
 };
-use crate::core::try_it;
+use crate::core::parser::answer_file::AnswerFile;
+use crate::core::parser::TomlParser;
+use crate::core::{mirrors, try_it};
 use crate::toolset_manifest::get_toolset_manifest;
+use crate::utils::Progress;
 use crate::{default_install_dir, utils};
 
 use super::common::{
     question_single_choice, ComponentChoices, ComponentDecoration, ComponentListBuilder,
 };
-use super::{Installer, ManagerSubcommands};
+use super::{ConfigError, Installer, ManagerSubcommands};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use url::Url;
 
 /// Perform installer actions.
 ///
@@ -33,18 +38,49 @@ pub(super) fn execute_installer(installer: &Installer) -> Result<()> {
         rustup_dist_server,
         rustup_update_root,
         manifest: manifest_src,
+        from_bundle,
         insecure,
+        jobs,
+        insecure_skip_verify,
         list_components,
         component,
+        config,
         ..
     } = installer;
 
-    if matches!(&prefix, Some(p) if utils::is_root_dir(p)) {
+    let answers = config.as_deref().map(Answers::load).transpose()?;
+    let prefix = match &answers {
+        Some(answers) => Some(&answers.file.install_dir),
+        None => prefix.as_ref(),
+    };
+    if matches!(prefix, Some(p) if utils::is_root_dir(p)) {
         bail!(t!("notify_root_dir"));
     }
+    if let Some(answers) = &answers {
+        if !answers.file.mirrors.dist_server.is_empty() {
+            mirrors::set_dist_server(answers.file.mirrors.dist_server.clone());
+        }
+    }
 
-    let manifest_url = manifest_src.as_ref().map(|s| s.to_url()).transpose()?;
-    let mut manifest = get_toolset_manifest(manifest_url, *insecure)?;
+    utils::set_skip_verify(*insecure_skip_verify);
+    let json = utils::CliProgress::is_json_mode();
+    // The packages of a bundle are extracted into this directory, which must be kept until
+    // the installation is finished.
+    let (_bundle_dir, mut manifest) = if let Some(bundle) = from_bundle {
+        if answers.as_ref().is_some_and(|a| a.manifest.is_some()) {
+            bail!(ConfigError(
+                "the `manifest` of answer file cannot be used with `--from-bundle`".into()
+            ));
+        }
+        let (dir, manifest) = extract_bundle(&utils::to_nomalized_abspath(bundle, None)?)?;
+        (Some(dir), manifest)
+    } else {
+        let manifest_url = match &answers {
+            Some(answers) => answers.manifest.clone(),
+            None => manifest_src.as_ref().map(|s| s.to_url()).transpose()?,
+        };
+        (None, get_toolset_manifest(manifest_url, *insecure)?)
+    };
 
     if *list_components {
         // print a list of available components then return, don't do anything else
@@ -52,39 +88,64 @@ pub(super) fn execute_installer(installer: &Installer) -> Result<()> {
     }
 
     manifest.adjust_paths()?;
+    if let Some(proxy) = answers.as_ref().and_then(|a| a.file.proxy.clone()) {
+        manifest.proxy = Some(proxy);
+    }
 
     let component_list = manifest.current_target_components(true)?;
+    let selected_comps = match &answers {
+        Some(answers) => {
+            answers.check_components(&component_list)?;
+            Some(answers.file.components.as_slice())
+        }
+        None => component.as_deref(),
+    };
     let abs_prefix = if let Some(path) = prefix {
         utils::to_nomalized_abspath(path, None)?
     } else {
         default_install_dir()
     };
     let user_opt =
-        CustomInstallOpt::collect_from_user(&abs_prefix, component_list, component.as_deref())?;
-
-    let (registry_name, registry_value) = registry_url
-        .as_deref()
-        .map(|u| (registry_name.as_str(), u))
-        .unwrap_or(DEFAULT_CARGO_REGISTRY);
+        CustomInstallOpt::collect_from_user(&abs_prefix, component_list, selected_comps)?;
+
+    let mirror_answers = answers.as_ref().map(|a| &a.file.mirrors);
+    let (registry_name, registry_value) =
+        match mirror_answers.and_then(|m| m.cargo_registry.as_ref()) {
+            Some(registry) => (registry.name.as_str(), registry.url.as_str()),
+            None => registry_url
+                .as_deref()
+                .map(|u| (registry_name.as_str(), u))
+                .unwrap_or(DEFAULT_CARGO_REGISTRY),
+        };
+    let rustup_dist_server = mirror_answers
+        .and_then(|m| m.rustup_dist_server.as_ref())
+        .or(rustup_dist_server.as_ref());
+    let rustup_update_root = mirror_answers
+        .and_then(|m| m.rustup_update_root.as_ref())
+        .or(rustup_update_root.as_ref());
     let install_dir = user_opt.prefix;
 
     InstallConfiguration::new(&install_dir, &manifest)?
         .with_cargo_registry(registry_name, registry_value)
         .with_rustup_dist_server(
             rustup_dist_server
-                .clone()
-                .unwrap_or_else(|| default_rustup_dist_server().clone()),
+                .unwrap_or_else(|| default_rustup_dist_server())
+                .clone(),
         )
         .with_rustup_update_root(
             rustup_update_root
-                .clone()
-                .unwrap_or_else(|| default_rustup_update_root().clone()),
+                .unwrap_or_else(|| default_rustup_update_root())
+                .clone(),
         )
         .insecure(*insecure)
+        .with_jobs(jobs.map_or(utils::DEFAULT_DOWNLOAD_JOBS, usize::from))
+        .with_progress_indicator(json.then(json_progress))
         .install(user_opt.components)?;
 
     let g_opts = GlobalOpts::get();
-    if !g_opts.quiet {
+    if json {
+        info!("{}", t!("install_finish_info"));
+    } else if !g_opts.quiet {
         println!("\n{}\n", t!("install_finish_info"));
     }
 
@@ -99,7 +160,7 @@ pub(super) fn execute_installer(installer: &Installer) -> Result<()> {
 
     #[cfg(unix)]
     if let Some(cmd) = crate::core::os::unix::source_command() {
-        if !g_opts.quiet {
+        if !g_opts.quiet && !json {
             println!("\n{}", t!("linux_source_hint", cmd = cmd));
         }
     }
@@ -107,6 +168,70 @@ pub(super) fn execute_installer(installer: &Installer) -> Result<()> {
     Ok(())
 }
 
+/// The answer file of a `--non-interactive` installation, with its paths resolved.
+struct Answers {
+    file: AnswerFile,
+    manifest: Option<Url>,
+}
+
+impl Answers {
+    /// Load the answer file at `path`, where the relative paths are relative to the file.
+    ///
+    /// Every error is a [`ConfigError`].
+    fn load(path: &Path) -> Result<Self> {
+        let config_error = || ConfigError(format!("invalid answer file '{}'", path.display()));
+        let path = utils::to_nomalized_abspath(path, None).with_context(config_error)?;
+        let mut file = AnswerFile::load(&path).with_context(config_error)?;
+
+        let base_dir = path.parent().unwrap_or(&path);
+        file.install_dir = utils::to_nomalized_abspath(&file.install_dir, Some(base_dir))
+            .with_context(config_error)?;
+        if utils::is_root_dir(&file.install_dir) {
+            return Err(anyhow::anyhow!(t!("notify_root_dir")).context(config_error()));
+        }
+        let manifest = file
+            .manifest
+            .as_deref()
+            .map(|manifest| match Url::parse(manifest) {
+                // NB: a windows path such as `C:\manifest.toml` is also a valid url
+                Ok(url) if url.scheme().len() > 1 => Ok(url),
+                _ => {
+                    let path = utils::to_nomalized_abspath(manifest, Some(base_dir))?;
+                    Url::from_file_path(&path)
+                        .map_err(|_| anyhow::anyhow!("invalid path '{}'", path.display()))
+                }
+            })
+            .transpose()
+            .with_context(config_error)?;
+
+        Ok(Self { file, manifest })
+    }
+
+    /// Make sure every component in the answer file is available.
+    fn check_components(&self, all_components: &[Component]) -> Result<()> {
+        let unknown = self
+            .file
+            .components
+            .iter()
+            .filter(|name| !all_components.iter().any(|c| &&c.name == name))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            bail!(ConfigError(format!(
+                "unknown components in answer file: {}, use `--list-components` to see \
+                the available ones",
+                unknown.join(", ")
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The progress of the whole installation, reported as JSON events on `stdout`.
+fn json_progress() -> Progress<'static> {
+    Progress::noop().with_observer(utils::JsonEventWriter::stdout())
+}
+
 /// Contains customized install options that will be collected from user input.
 ///
 /// Check [`collect_from_user`](CustomInstallOpt::collect_from_user) for more detail.
@@ -128,12 +253,13 @@ impl CustomInstallOpt {
         user_selected_comps: Option<&[String]>,
     ) -> Result<Self> {
         if GlobalOpts::get().yes_to_all {
+            let choices = default_component_choices(&all_components, user_selected_comps);
+            let plan = resolve_dependencies(&all_components, choices.values().copied())?;
+            let names = plan.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+            info!("{}", t!("install_plan_info", components = names.join(", ")));
             return Ok(Self {
                 prefix: prefix.to_path_buf(),
-                components: default_component_choices(&all_components, user_selected_comps)
-                    .values()
-                    .map(|c| (*c).to_owned())
-                    .collect(),
+                components: plan.into_iter().cloned().collect(),
             });
         }
 
@@ -163,14 +289,15 @@ impl CustomInstallOpt {
             }
 
             let choices = read_component_selections(&all_components, user_selected_comps)?;
+            let plan = resolve_dependencies(&all_components, choices.values().copied())?;
 
-            show_confirmation(&install_dir, &choices)?;
+            show_confirmation(&install_dir, &plan)?;
 
             match common::confirm_install()? {
                 Confirm::Yes => {
                     return Ok(Self {
                         prefix: install_dir.into(),
-                        components: choices.values().map(|c| (*c).to_owned()).collect(),
+                        components: plan.into_iter().cloned().collect(),
                     });
                 }
                 Confirm::No => (),
@@ -273,14 +400,16 @@ fn read_component_selections<'a>(
     Ok(selection)
 }
 
-fn show_confirmation(install_dir: &str, choices: &ComponentChoices<'_>) -> Result<()> {
+/// Show the install directory and the plan of components to install, in order, including the
+/// ones required by the selected components.
+fn show_confirmation(install_dir: &str, plan: &[&Component]) -> Result<()> {
     let mut stdout = std::io::stdout();
 
     writeln!(&mut stdout, "\n{}\n", t!("current_install_option"))?;
     writeln!(&mut stdout, "{}:\n\t{install_dir}", t!("install_dir"))?;
-    writeln!(&mut stdout, "\n{}:", t!("selected_components"))?;
-    let list_of_comp = ComponentListBuilder::new(choices.values().copied())
-        .decorate(ComponentDecoration::Confirmation)
+    writeln!(&mut stdout, "\n{}:", t!("install_plan"))?;
+    let list_of_comp = ComponentListBuilder::new(plan.iter().copied())
+        .decorate(ComponentDecoration::Confirmation(plan))
         .build()
         .join("\n");
     for line in list_of_comp.lines() {
@@ -0,0 +1,78 @@
+//! Separated module to handle the log files of previous runs in command line.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+
+use super::ManagerSubcommands;
+use crate::utils;
+
+#[derive(Subcommand, Debug)]
+pub(super) enum LogCommand {
+    /// Print the log of the last run
+    Show {
+        /// Print only the last this number of lines.
+        #[arg(long, value_name = "NUM")]
+        tail: Option<usize>,
+    },
+    /// Print the path to the log file of the last run
+    Path {
+        /// Print the directory that contains every log file instead.
+        #[arg(long)]
+        dir: bool,
+    },
+}
+
+impl LogCommand {
+    fn execute(&self) -> Result<()> {
+        match self {
+            Self::Show { tail } => {
+                let Some(path) = last_log_file()? else {
+                    return Ok(());
+                };
+                let content = utils::read_to_string("log", &path)?;
+                match tail {
+                    Some(num) => {
+                        let lines = content.lines().collect::<Vec<_>>();
+                        for line in &lines[lines.len().saturating_sub(*num)..] {
+                            println!("{line}");
+                        }
+                    }
+                    None => print!("{content}"),
+                }
+            }
+            Self::Path { dir: true } => println!("{}", utils::log_dir().display()),
+            Self::Path { dir: false } => {
+                if let Some(path) = last_log_file()? {
+                    println!("{}", path.display());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Get the most recent log file other than the one of current run, which is the log of the
+/// last run of either installer or manager.
+fn last_log_file() -> Result<Option<PathBuf>> {
+    let current = utils::log_file_path();
+    let last = utils::log_files()
+        .context("unable to read log files")?
+        .into_iter()
+        .find(|path| Some(path.as_path()) != current);
+    if last.is_none() {
+        info!("{}", t!("no_log_file", dir = utils::log_dir().display()));
+    }
+    Ok(last)
+}
+
+pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Log { command } = cmd else {
+        return Ok(false);
+    };
+
+    command.execute()?;
+
+    Ok(true)
+}
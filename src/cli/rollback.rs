@@ -0,0 +1,43 @@
+//! Separated module to handle reverting the last installation or update in command line.
+
+use crate::core::parser::get_installed_dir;
+use crate::core::transaction::Transaction;
+
+use super::{common, ManagerSubcommands};
+
+use anyhow::{bail, Result};
+
+/// Execute `rollback` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Rollback = subcommand else {
+        return Ok(false);
+    };
+
+    let install_dir = get_installed_dir();
+    let Some(tx) = Transaction::last(install_dir)? else {
+        info!("{}", t!("rollback_nothing"));
+        return Ok(true);
+    };
+    if tx.removes_install_dir() {
+        bail!(t!("rollback_first_installation"));
+    }
+    if !tx.is_committed() {
+        warn!("{}", t!("rollback_interrupted"));
+    }
+
+    let changes = tx
+        .changes()
+        .iter()
+        .rev()
+        .map(|change| format!("\t{change}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !common::confirm(t!("rollback_confirmation", list = changes), false)? {
+        return Ok(true);
+    }
+
+    tx.rollback()?;
+    info!("{}", t!("rollback_complete"));
+
+    Ok(true)
+}
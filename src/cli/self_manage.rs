@@ -0,0 +1,45 @@
+//! Separated module to handle the `self` command, which manages this manager tool itself.
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use super::ManagerSubcommands;
+use crate::core::update::{ReleaseChannel, UpdateOpt};
+
+#[derive(Subcommand, Debug)]
+pub(super) enum SelfCommand {
+    /// Update this manager to the latest release
+    ///
+    /// The new binary is verified with its checksum before replacing the current one.
+    Update {
+        /// Allow insecure connections when download packages from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
+        /// The release channel to update from.
+        #[arg(long, value_enum, default_value_t)]
+        channel: ReleaseChannel,
+    },
+    /// Uninstall this manager, along with the toolkit and its environment configurations
+    /// (such as `PATH` entries and environment variables).
+    Uninstall,
+}
+
+/// Execute `self` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::SelfManage { command } = subcommand else {
+        return Ok(false);
+    };
+
+    match command {
+        SelfCommand::Update { insecure, channel } => {
+            UpdateOpt::new()
+                .insecure(*insecure)
+                .with_channel(*channel)
+                .self_update()?;
+        }
+        // the same as `uninstall` without `--keep-self`
+        SelfCommand::Uninstall => super::uninstall::uninstall(false)?,
+    }
+
+    Ok(true)
+}
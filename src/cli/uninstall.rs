@@ -12,6 +12,12 @@ pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
         return Ok(false);
     };
 
+    uninstall(*keep_self)?;
+    Ok(true)
+}
+
+/// Uninstall the toolkit after confirmation, along with this manager unless `keep_self`.
+pub(super) fn uninstall(keep_self: bool) -> Result<()> {
     let config = UninstallConfiguration::init(None)?;
     let installed = config.install_record.print_installation();
 
@@ -26,10 +32,8 @@ pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
         t!("uninstall_confirmation", list = installed)
     };
     if !common::confirm(prompt, false)? {
-        return Ok(true);
+        return Ok(());
     }
 
-    config.uninstall(!keep_self)?;
-
-    Ok(true)
+    config.uninstall(!keep_self)
 }
@@ -7,7 +7,7 @@ use crate::components::Component;
 use crate::core::toolkit::Toolkit;
 use crate::core::update::UpdateOpt;
 use crate::toolkit::latest_installable_toolkit;
-use crate::toolset_manifest::get_toolset_manifest;
+use crate::toolset_manifest::{get_toolset_manifest, ToolsetManifest};
 use crate::InstallConfiguration;
 
 use super::common::{
@@ -21,36 +21,44 @@ pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<bool> {
         manager_only,
         insecure,
         component,
+        components,
     } = cmd
     else {
         return Ok(false);
     };
 
+    let selection = if components.is_empty() {
+        component.as_deref().map(Selection::Override)
+    } else {
+        Some(Selection::OutdatedOnly(components))
+    };
     let update_opt = UpdateOpt::new().insecure(*insecure);
     if !manager_only {
-        update_opt.update_toolkit(|path| update_toolkit_(path, *insecure, component.as_deref()))?;
+        update_opt.update_toolkit(|path| update_toolkit_(path, *insecure, selection))?;
     }
-    if !toolkit_only {
+    if !toolkit_only && components.is_empty() {
         update_opt.self_update()?;
     }
 
     Ok(true)
 }
 
-fn update_toolkit_(
-    install_dir: &Path,
-    insecure: bool,
-    user_selected_comps: Option<&[String]>,
-) -> Result<()> {
-    let Some(installed) = Toolkit::installed(false)? else {
-        info!("{}", t!("no_toolkit_installed"));
-        return Ok(());
-    };
-    let installed = &*installed.lock().unwrap();
+/// The components that the user selected via commandline.
+#[derive(Clone, Copy)]
+enum Selection<'a> {
+    /// The list of `--component` option, check [`ComponentsUpdater::default_component_choices`].
+    Override(&'a [String]),
+    /// The positional arguments, only the ones that have new versions are selected.
+    OutdatedOnly(&'a [String]),
+}
 
-    // get possible update
+/// Fetch the manifest of the latest toolkit, if it's newer than the `installed` one.
+pub(super) fn latest_toolkit_manifest(
+    installed: &Toolkit,
+    insecure: bool,
+) -> Result<Option<(&'static Toolkit, ToolsetManifest)>> {
     let Some(latest_toolkit) = latest_installable_toolkit(installed, insecure)? else {
-        return Ok(());
+        return Ok(None);
     };
     log::debug!(
         "detected latest toolkit: {}-{}",
@@ -70,6 +78,20 @@ fn update_toolkit_(
             )
         })?;
     let manifest = get_toolset_manifest(Some(manifest_url), insecure)?;
+    Ok(Some((latest_toolkit, manifest)))
+}
+
+fn update_toolkit_(install_dir: &Path, insecure: bool, selection: Option<Selection>) -> Result<()> {
+    let Some(installed) = Toolkit::installed(false)? else {
+        info!("{}", t!("no_toolkit_installed"));
+        return Ok(());
+    };
+    let installed = &*installed.lock().unwrap();
+
+    // get possible update
+    let Some((latest_toolkit, manifest)) = latest_toolkit_manifest(installed, insecure)? else {
+        return Ok(());
+    };
     let new_components = manifest.current_target_components(false)?;
 
     // notify user that we will install the latest update to replace their current installation
@@ -83,10 +105,23 @@ fn update_toolkit_(
     );
 
     let updater = ComponentsUpdater::new(&installed.components, &new_components);
+    let default = match selection {
+        Some(Selection::OutdatedOnly(names)) => {
+            let choices = updater.outdated_component_choices(names);
+            if choices.is_empty() {
+                info!("{}", t!("no_available_updates", toolkit = &installed.name));
+                return Ok(());
+            }
+            choices
+        }
+        Some(Selection::Override(names)) => updater.default_component_choices(Some(names)),
+        None => updater.default_component_choices(None),
+    };
     // let user choose if they want to update installed component only, or want to select more components to install
-    if let UpdateOption::Yes(components) = updater.to_update_option(user_selected_comps)? {
+    if let UpdateOption::Yes(components) = updater.handle_update_interaction_(default)? {
         // install update for selected components
-        let config = InstallConfiguration::new(install_dir, &manifest)?;
+        let config = InstallConfiguration::new(install_dir, &manifest)?
+            .partial_update(!updater.updates_all_outdated(&components));
         config.update(components.into_values().cloned().collect())
     } else {
         Ok(())
@@ -98,13 +133,15 @@ enum UpdateOption<'c> {
     NoUpdate,
 }
 
-struct ComponentsUpdater<'c> {
+pub(super) struct ComponentsUpdater<'c> {
     target: &'c [Component],
     version_diff: VersionDiffMap<'c>,
+    /// The names of previously installed components.
+    installed: HashSet<&'c str>,
 }
 
 impl<'c> ComponentsUpdater<'c> {
-    fn new(installed: &'c [Component], target: &'c [Component]) -> Self {
+    pub(super) fn new(installed: &'c [Component], target: &'c [Component]) -> Self {
         let version_diff = target
             .iter()
             .map(|c| {
@@ -132,9 +169,66 @@ impl<'c> ComponentsUpdater<'c> {
         Self {
             target,
             version_diff,
+            installed: installed
+                .iter()
+                .filter(|c| c.installed)
+                .map(|c| c.name.as_str())
+                .collect(),
         }
     }
 
+    /// Get the previously installed components that have a different version in the
+    /// target list, as well as their version changes.
+    pub(super) fn outdated(&self) -> Vec<(&'c Component, &VersionDiff<'c>)> {
+        let names = self.component_names_with_diff_version();
+        self.target
+            .iter()
+            .filter(|c| names.contains(c.name.as_str()))
+            .filter_map(|c| Some((c, self.version_diff.get(c.name.as_str())?)))
+            .collect()
+    }
+
+    /// Check if `choices` contains every outdated component (including the toolchain).
+    ///
+    /// The toolchain components share the same version, thus are all updated if any of them
+    /// was chosen.
+    fn updates_all_outdated(&self, choices: &ComponentChoices<'c>) -> bool {
+        let toolchain_chosen = choices.values().any(|c| c.is_toolchain_component);
+        let chosen: HashSet<&str> = choices.values().map(|c| c.name.as_str()).collect();
+        self.outdated().iter().all(|(c, _)| {
+            chosen.contains(c.name.as_str()) || (c.is_toolchain_component && toolchain_chosen)
+        })
+    }
+
+    /// Get the components that are newly supported by the target list.
+    pub(super) fn newly_supported(&self) -> Vec<(&'c Component, &VersionDiff<'c>)> {
+        self.target
+            .iter()
+            .filter_map(|c| Some((c, self.version_diff.get(c.name.as_str())?)))
+            .filter(|(_, diff)| diff.is_newly_supported)
+            .collect()
+    }
+
+    /// Select the components in `names` that are outdated, the others are reported as
+    /// up to date (or not found).
+    fn outdated_component_choices(&self, names: &[String]) -> ComponentChoices<'c> {
+        let outdated = self.component_names_with_diff_version();
+        for name in names {
+            if !self.version_diff.contains_key(name.as_str()) {
+                warn!("{}", t!("component_not_found", name = name));
+            } else if !outdated.contains(name.as_str()) {
+                info!("{}", t!("component_up_to_date", name = name));
+            }
+        }
+        self.target
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| {
+                outdated.contains(c.name.as_str()) && names.iter().any(|n| n == &c.name)
+            })
+            .collect()
+    }
+
     // We are only pre-selecting the components for update if the component exists in both lists
     // and having different version.
     // Note that we don't check if the new version is actually "newer" than the installed version,
@@ -144,7 +238,7 @@ impl<'c> ComponentsUpdater<'c> {
             .iter()
             .filter_map(|(name, diff)| {
                 // return only the components that are previously installed
-                if diff.is_newly_supported {
+                if diff.is_newly_supported || !self.installed.contains(name) {
                     None
                 } else {
                     (diff.from != diff.to).then_some(*name)
@@ -153,11 +247,6 @@ impl<'c> ComponentsUpdater<'c> {
             .collect()
     }
 
-    fn to_update_option(&self, user_selected_comps: Option<&[String]>) -> Result<UpdateOption<'c>> {
-        let default = self.default_component_choices(user_selected_comps);
-        self.handle_update_interaction_(default)
-    }
-
     /// Default component set contains components that:
     /// - User provide a list of components via commandline, such as `--components comp_a,comp_b`.
     /// - Was previously installed and have new version available.
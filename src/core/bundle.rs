@@ -0,0 +1,504 @@
+//! Offline bundles, which are single archives containing a toolkit's manifest along with
+//! every package needed to install it, so the toolkit can be installed on machines
+//! without network access.
+//!
+//! A bundle is a `.tar.gz` archive with the following layout:
+//!
+//! ```text
+//! toolset-manifest.toml
+//! packages/
+//! ├── dist/                  // a copy of the rustup dist server with just enough files
+//! │   ├── channel-rust-<version>.toml
+//! │   ├── channel-rust-<version>.toml.sha256
+//! │   └── <date>/<component>-<version>-<target>.tar.xz
+//! ├── <target>/rustup-init   // the `rustup-init` binary of the target
+//! └── tools/<name>/<file>    // the package of each tool
+//! ```
+//!
+//! where the manifest was rewritten to only point to the packages inside of the bundle,
+//! so it can be installed just like a manifest with `offline-dist-server` specified.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Deserialize;
+use tempfile::TempDir;
+use url::Url;
+
+use super::install::{
+    default_rustup_dist_server, default_rustup_update_root, downloadable_file_name,
+};
+use super::parser::toolset_manifest::{ToolInfo, ToolsetManifest};
+use super::parser::TomlParser;
+use super::rustup::RUSTUP_INIT;
+use crate::setter;
+use crate::utils::{self, Progress};
+
+/// The directory containing all packages, relative to the root of a bundle.
+const PACKAGES_DIR: &str = "packages";
+
+/// Options to create an offline bundle of a toolkit.
+#[derive(Debug)]
+pub struct BundleOpt<'a> {
+    manifest: &'a ToolsetManifest,
+    /// The optional components to include, the required ones are always included.
+    components: Vec<String>,
+    rustup_dist_server: Url,
+    rustup_update_root: Url,
+    insecure: bool,
+    jobs: usize,
+}
+
+impl<'a> BundleOpt<'a> {
+    pub fn new(manifest: &'a ToolsetManifest) -> Self {
+        Self {
+            manifest,
+            components: vec![],
+            rustup_dist_server: default_rustup_dist_server().clone(),
+            rustup_update_root: default_rustup_update_root().clone(),
+            insecure: false,
+            jobs: utils::DEFAULT_DOWNLOAD_JOBS,
+        }
+    }
+
+    setter!(with_components(self.components, Vec<String>));
+    setter!(with_rustup_dist_server(self.rustup_dist_server, Url));
+    setter!(with_rustup_update_root(self.rustup_update_root, Url));
+    setter!(insecure(self.insecure, bool));
+    setter!(with_jobs(self.jobs, usize));
+
+    /// Download the toolchain and tools of the current target, then pack them along with
+    /// the rewritten manifest into a `.tar.gz` archive at `dest`.
+    ///
+    /// # Errors
+    /// Return `Result::Err` if any of the selected components is unknown or cannot be
+    /// bundled, such as tools that are installed by `cargo install`, or failed to download.
+    pub fn create(&self, dest: &Path) -> Result<()> {
+        info!("{}", t!("creating_bundle", path = dest.display()));
+
+        let staging = tempfile::Builder::new()
+            .prefix("rim-bundle_")
+            .tempdir()
+            .context("unable to create temp directory for the bundle")?;
+        let root = staging.path();
+        let packages = root.join(PACKAGES_DIR);
+
+        let mut manifest = self.manifest.clone();
+        manifest.adjust_paths()?;
+        self.check_components(&manifest)?;
+
+        let mut files = vec![];
+        let mut checksums = vec![];
+        self.collect_toolchain(&mut manifest, &packages, &mut files, &mut checksums)?;
+        self.collect_tools(&mut manifest, &packages, &mut files, &mut checksums)?;
+
+        utils::DownloadOpt::new("packages")
+            .insecure(self.insecure)
+            .with_proxy(manifest.proxy.clone())
+            .download_all(&files, self.jobs, &Progress::noop())?;
+        for (path, expected) in &checksums {
+            utils::verify_sha256(path, expected)?;
+        }
+
+        utils::write_file(
+            root.join(ToolsetManifest::FILENAME),
+            &manifest.to_toml()?,
+            false,
+        )?;
+        pack_dir(root, dest)?;
+
+        info!("{}", t!("bundle_created", path = dest.display()));
+        Ok(())
+    }
+
+    fn check_components(&self, manifest: &ToolsetManifest) -> Result<()> {
+        let known = manifest
+            .current_target_components(false)?
+            .into_iter()
+            .map(|c| c.name)
+            .collect::<HashSet<_>>();
+        if let Some(unknown) = self.components.iter().find(|c| !known.contains(*c)) {
+            bail!("unknown component '{unknown}', check `list component` for available options");
+        }
+        Ok(())
+    }
+
+    fn is_selected(&self, name: &str) -> bool {
+        self.components.iter().any(|c| c == name)
+    }
+
+    /// Add the `rustup-init` binary, the dist manifest and the packages of the toolchain
+    /// components to download, then point the manifest to their paths inside of the bundle.
+    fn collect_toolchain(
+        &self,
+        manifest: &mut ToolsetManifest,
+        packages: &Path,
+        files: &mut Vec<(Url, PathBuf)>,
+        checksums: &mut Vec<(PathBuf, String)>,
+    ) -> Result<()> {
+        let target = env!("TARGET");
+
+        // rustup-init
+        let rustup_rel = format!("{PACKAGES_DIR}/{target}/{RUSTUP_INIT}");
+        utils::ensure_dir(packages.join(target))?;
+        let rustup_dest = packages.join(target).join(RUSTUP_INIT);
+        if let Some(bundled) = manifest.rustup_bin()?.filter(|p| p.is_file()) {
+            utils::copy_as(bundled, &rustup_dest)?;
+        } else {
+            let url = utils::url_join(
+                &self.rustup_update_root,
+                &format!("dist/{target}/{RUSTUP_INIT}"),
+            )?;
+            files.push((url, rustup_dest));
+        }
+
+        // dist manifest, which needs to be parsed before knowing what else to download
+        let dist_dir = packages.join("dist");
+        utils::ensure_dir(&dist_dir)?;
+        let channel_file = format!("channel-rust-{}.toml", manifest.rust_version());
+        let channel_url =
+            utils::url_join(&self.rustup_dist_server, &format!("dist/{channel_file}"))?;
+        let sha_file = format!("{channel_file}.sha256");
+        let sha_url = utils::url_join(&self.rustup_dist_server, &format!("dist/{sha_file}"))?;
+        utils::DownloadOpt::new(&channel_file)
+            .insecure(self.insecure)
+            .with_proxy(manifest.proxy.clone())
+            .download_file(&channel_url, &dist_dir.join(&channel_file), false)?;
+        files.push((sha_url, dist_dir.join(sha_file)));
+
+        let dist = ChannelManifest::load(dist_dir.join(&channel_file))?;
+        let profile = manifest.toolchain_profile().cloned().unwrap_or_default();
+        let mut components = dist
+            .profiles
+            .get(&profile.name)
+            .ok_or_else(|| anyhow!("unknown toolchain profile '{}'", profile.name))?
+            .clone();
+        manifest
+            .rust
+            .optional_components
+            .retain(|c| self.is_selected(c));
+        components.extend(manifest.rust.components.iter().cloned());
+        components.extend(manifest.rust.optional_components.iter().cloned());
+        let mut seen = HashSet::new();
+        for name in components {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            let (url, hash) = dist.package(&name, target)?;
+            let rel_path = dist_relative_path(url)?;
+            let dest = packages.join(&rel_path);
+            utils::ensure_parent_dir(&dest)?;
+            files.push((
+                utils::url_join(&self.rustup_dist_server, &rel_path)?,
+                dest.clone(),
+            ));
+            checksums.push((dest, hash.to_string()));
+        }
+
+        manifest.rust.offline_dist_server = Some(format!("{PACKAGES_DIR}/"));
+        manifest.rust.rustup = HashMap::from([(target.to_string(), rustup_rel)]);
+        Ok(())
+    }
+
+    /// Add or copy the packages of the selected (or required) tools of the current target,
+    /// then rewrite them as [`ToolInfo::Path`] pointing inside of the bundle.
+    fn collect_tools(
+        &self,
+        manifest: &mut ToolsetManifest,
+        packages: &Path,
+        files: &mut Vec<(Url, PathBuf)>,
+        checksums: &mut Vec<(PathBuf, String)>,
+    ) -> Result<()> {
+        let target = env!("TARGET");
+        // tools of other targets are useless, as the toolchain is only bundled for this one
+        manifest.tools.target.retain(|t, _| t == target);
+        let Some(tools) = manifest.current_target_tools_mut() else {
+            return Ok(());
+        };
+        tools.retain(|name, info| {
+            let name = info.identifier().unwrap_or(name);
+            info.is_required() || self.is_selected(name)
+        });
+
+        for (name, info) in tools.iter_mut() {
+            let tool_dir = packages.join("tools").join(name);
+            utils::ensure_dir(&tool_dir)?;
+            let dest = match &*info {
+                ToolInfo::Url { url, sha256, .. } => {
+                    let dest = tool_dir.join(downloadable_file_name(url)?);
+                    files.push((url.clone(), dest.clone()));
+                    if let Some(expected) = sha256 {
+                        checksums.push((dest.clone(), expected.clone()));
+                    }
+                    dest
+                }
+                ToolInfo::Path { path, .. } => utils::copy_into(path, &tool_dir)?,
+                _ => bail!(
+                    "component '{name}' is installed by `cargo install` thus cannot be bundled"
+                ),
+            };
+            let rel_path = dest
+                .strip_prefix(packages.parent().unwrap_or(packages))
+                .unwrap_or(&dest)
+                .to_path_buf();
+            *info = ToolInfo::Path {
+                path: rel_path,
+                version: info.version().map(ToString::to_string),
+                required: info.is_required(),
+                optional: info.is_optional(),
+                identifier: info.identifier().map(ToString::to_string),
+                requires: info.requires().to_vec(),
+            };
+        }
+        Ok(())
+    }
+}
+
+/// The parts of rustup's dist manifest (`channel-rust-*.toml`) that are needed to know
+/// which packages to download.
+#[derive(Debug, Deserialize)]
+struct ChannelManifest {
+    #[serde(default)]
+    pkg: HashMap<String, ChannelPackage>,
+    #[serde(default)]
+    profiles: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    renames: HashMap<String, ChannelRename>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelPackage {
+    #[serde(default)]
+    target: HashMap<String, ChannelTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelTarget {
+    #[serde(default)]
+    available: bool,
+    url: Option<Url>,
+    hash: Option<String>,
+    xz_url: Option<Url>,
+    xz_hash: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelRename {
+    to: String,
+}
+
+impl TomlParser for ChannelManifest {
+    const FILENAME: &'static str = "channel-rust.toml";
+}
+
+impl ChannelManifest {
+    /// Get the url and SHA-256 checksum of a component's package, preferring the `.xz` one.
+    fn package(&self, name: &str, target: &str) -> Result<(&Url, &str)> {
+        let name = self.renames.get(name).map_or(name, |r| r.to.as_str());
+        let available = self
+            .pkg
+            .get(name)
+            .and_then(|pkg| pkg.target.get(target).or_else(|| pkg.target.get("*")))
+            .filter(|t| t.available);
+        available
+            .and_then(|t| match (&t.xz_url, &t.xz_hash, &t.url, &t.hash) {
+                (Some(url), Some(hash), ..) | (.., Some(url), Some(hash)) => {
+                    Some((url, hash.as_str()))
+                }
+                _ => None,
+            })
+            .ok_or_else(|| {
+                anyhow!("toolchain component '{name}' is not available for target '{target}'")
+            })
+    }
+}
+
+/// Get the path of a package relative to the root of a dist server, such as
+/// `dist/2024-09-05/rustc-1.81.0-x86_64-unknown-linux-gnu.tar.xz`.
+///
+/// Packages are always listed with the official server in dist manifests, and are
+/// downloaded from the same path of the other servers, which is what rustup does as well.
+fn dist_relative_path(url: &Url) -> Result<String> {
+    let path = url.path();
+    let start = path
+        .find("/dist/")
+        .ok_or_else(|| anyhow!("'{url}' is not a url of rustup dist server"))?;
+    Ok(path[start + 1..].to_string())
+}
+
+/// Pack everything inside of `dir` into a `.tar.gz` archive.
+fn pack_dir(dir: &Path, dest: &Path) -> Result<()> {
+    utils::ensure_parent_dir(dest)?;
+    let file =
+        File::create(dest).with_context(|| format!("unable to create '{}'", dest.display()))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    builder.follow_symlinks(false);
+    builder.append_dir_all(".", dir)?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Extract a bundle created by [`BundleOpt::create`] into a temporary directory, then load
+/// the manifest in it, which points to the packages in that directory.
+///
+/// The packages are removed once the returned [`TempDir`] is dropped, so it must be kept
+/// until the installation is finished.
+pub fn extract_bundle(path: &Path) -> Result<(TempDir, ToolsetManifest)> {
+    info!("{}", t!("extracting_bundle", path = path.display()));
+    if !path.is_file() {
+        bail!("bundle '{}' does not exist", path.display());
+    }
+    let dir = tempfile::Builder::new()
+        .prefix("rim-bundle_")
+        .tempdir()
+        .context("unable to create temp directory for the bundle")?;
+    let file = File::open(path)?;
+    tar::Archive::new(flate2::read::GzDecoder::new(file))
+        .unpack(dir.path())
+        .with_context(|| format!("'{}' is not a valid bundle", path.display()))?;
+
+    let manifest_path = dir.path().join(ToolsetManifest::FILENAME);
+    if !manifest_path.is_file() {
+        bail!(
+            "'{}' is not a valid bundle: missing {}",
+            path.display(),
+            ToolsetManifest::FILENAME
+        );
+    }
+    let mut manifest = ToolsetManifest::load(manifest_path)?;
+    manifest.adjust_paths()?;
+    Ok((dir, manifest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a fake dist server containing a toolchain with a `rustc` and `rust-src` package,
+    /// and `rustup-init` of current target.
+    fn fake_dist_server(root: &Path, version: &str) {
+        let target = env!("TARGET");
+        let date_dir = root.join("dist/2024-01-01");
+        utils::ensure_dir(&date_dir).unwrap();
+        utils::ensure_dir(root.join(format!("rustup/dist/{target}"))).unwrap();
+        utils::write_file(
+            root.join(format!("rustup/dist/{target}/{RUSTUP_INIT}")),
+            "rustup",
+            false,
+        )
+        .unwrap();
+
+        let mut channel = String::from("[profiles]\nminimal = [\"rustc\"]\n");
+        for (name, pkg_target) in [("rustc", target), ("rust-src", "*")] {
+            let file = format!("{name}-{version}-{target}.tar.xz");
+            utils::write_file(date_dir.join(&file), name, false).unwrap();
+            // `write_file` appends a new line
+            let hash = ring::digest::digest(&ring::digest::SHA256, format!("{name}\n").as_bytes())
+                .as_ref()
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<String>();
+            channel.push_str(&format!(
+                "[pkg.{name}.target.\"{pkg_target}\"]\navailable = true\n\
+                xz_url = \"https://static.rust-lang.org/dist/2024-01-01/{file}\"\n\
+                xz_hash = \"{hash}\"\n"
+            ));
+        }
+        channel.push_str("[renames.rust-src-preview]\nto = \"rust-src\"\n");
+        let channel_file = root.join(format!("dist/channel-rust-{version}.toml"));
+        utils::write_file(&channel_file, &channel, false).unwrap();
+        utils::write_file(
+            root.join(format!("dist/channel-rust-{version}.toml.sha256")),
+            "checksum",
+            false,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn create_then_extract() {
+        let target = env!("TARGET");
+        let server = tempfile::tempdir().unwrap();
+        fake_dist_server(server.path(), "1.0.0");
+        let tool = server.path().join("tool.zip");
+        utils::write_file(&tool, "tool", false).unwrap();
+        let tool_url = Url::from_file_path(&tool).unwrap();
+        let raw = format!(
+            r#"
+[rust]
+version = "1.0.0"
+components = ["rust-src-preview"]
+profile = {{ name = "minimal" }}
+
+[tools.target.{target}]
+a = {{ required = true, url = "{tool_url}" }}
+b = {{ optional = true, url = "{tool_url}" }}
+c = "0.1.0"
+
+[tools.target.other-target]
+d = {{ required = true, url = "{tool_url}" }}
+"#
+        );
+        let manifest = ToolsetManifest::from_str(&raw).unwrap();
+        let output = server.path().join("out/bundle.tar.gz");
+        let server_url = Url::from_directory_path(server.path()).unwrap();
+        BundleOpt::new(&manifest)
+            .with_rustup_dist_server(server_url.clone())
+            .with_rustup_update_root(server_url.join("rustup/").unwrap())
+            .create(&output)
+            .unwrap();
+
+        let (dir, bundled) = extract_bundle(&output).unwrap();
+        let tools = bundled.current_target_tools().unwrap();
+        assert_eq!(tools.keys().collect::<Vec<_>>(), ["a"]);
+        let ToolInfo::Path { path, required, .. } = &tools["a"] else {
+            panic!("tool wasn't rewritten to a path: {:?}", tools["a"]);
+        };
+        assert!(required);
+        assert_eq!(fs_read(path), "tool");
+        assert!(!bundled.tools.target.contains_key("other-target"));
+
+        assert_eq!(fs_read(&bundled.rustup_bin().unwrap().unwrap()), "rustup");
+        let dist = bundled
+            .offline_dist_server()
+            .unwrap()
+            .unwrap()
+            .to_file_path()
+            .unwrap();
+        assert!(dist.starts_with(dir.path()));
+        assert_eq!(
+            fs_read(&dist.join(format!("dist/2024-01-01/rustc-1.0.0-{target}.tar.xz"))),
+            "rustc"
+        );
+        assert_eq!(
+            fs_read(&dist.join(format!("dist/2024-01-01/rust-src-1.0.0-{target}.tar.xz"))),
+            "rust-src"
+        );
+        assert!(dist.join("dist/channel-rust-1.0.0.toml.sha256").is_file());
+
+        // cargo tools need the network to install
+        let err = BundleOpt::new(&manifest)
+            .with_components(vec!["c".into()])
+            .with_rustup_dist_server(server_url.clone())
+            .with_rustup_update_root(server_url.join("rustup/").unwrap())
+            .create(&output)
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot be bundled"), "{err}");
+        let err = BundleOpt::new(&manifest)
+            .with_components(vec!["nope".into()])
+            .create(&output)
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown component"), "{err}");
+    }
+
+    fn fs_read(path: &Path) -> String {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .trim_end()
+            .to_string()
+    }
+}
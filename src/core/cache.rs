@@ -0,0 +1,88 @@
+//! The cache of downloaded packages, which is shared by every installation of current user.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+
+use super::directories::vendor_dir;
+use super::parser::cache_config::CacheConfig;
+use super::parser::TomlParser;
+use crate::utils::{self, DownloadCache, DEFAULT_CACHE_SIZE};
+
+/// The environment variable to override the directory of download cache.
+const RIM_CACHE_DIR: &str = "RIM_CACHE_DIR";
+
+/// Get the download cache, which is placed (in the order of precedence):
+///
+/// 1. In the directory of `RIM_CACHE_DIR` environment variable.
+/// 2. In the `downloads` directory under the user's cache directory,
+///    such as `~/.cache/<vendor>/downloads` on Linux.
+///
+/// Its size limit is the `max-size` in `cache.toml` under the user's config directory,
+/// or [`DEFAULT_CACHE_SIZE`]. Returns `None` if the cache directory cannot be determined.
+pub(crate) fn download_cache() -> Result<Option<DownloadCache>> {
+    let dir = match std::env::var_os(RIM_CACHE_DIR).filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => match vendor_dir(dirs::cache_dir()) {
+            Some(dir) => dir.join("downloads"),
+            None => return Ok(None),
+        },
+    };
+    let max_size = match load_config()?.max_size {
+        Some(size) => parse_size_limit(&size)
+            .with_context(|| format!("invalid `max-size` in '{}'", CacheConfig::FILENAME))?,
+        None => Some(DEFAULT_CACHE_SIZE),
+    };
+    Ok(Some(DownloadCache::new(dir).with_max_size(max_size)))
+}
+
+/// Save the size limit of download cache into `cache.toml`, such as `2G` or `none`.
+pub(crate) fn set_size_limit(limit: &str) -> Result<()> {
+    parse_size_limit(limit)?;
+    let Some(path) = config_path() else {
+        bail!("unable to determine the config directory of current user");
+    };
+    let mut config = load_config()?;
+    config.max_size = Some(limit.to_string());
+    utils::ensure_parent_dir(&path)?;
+    utils::write_file(path, &config.to_toml()?, false)
+}
+
+fn config_path() -> Option<PathBuf> {
+    vendor_dir(dirs::config_dir()).map(|dir| dir.join(CacheConfig::FILENAME))
+}
+
+fn load_config() -> Result<CacheConfig> {
+    match config_path() {
+        Some(path) if path.is_file() => CacheConfig::load(&path)
+            .with_context(|| format!("invalid cache configuration '{}'", path.display())),
+        _ => Ok(CacheConfig::default()),
+    }
+}
+
+/// Parse a size limit in bytes (check [`utils::parse_size`] for the format), such as `500M`,
+/// or `none` for no limit, which returns `None`.
+pub(crate) fn parse_size_limit(input: &str) -> Result<Option<u64>> {
+    let input = input.trim();
+    if input.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    utils::parse_size(input).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_limits() {
+        assert_eq!(parse_size_limit("1024").unwrap(), Some(1024));
+        assert_eq!(parse_size_limit("500M").unwrap(), Some(500 << 20));
+        assert_eq!(parse_size_limit("2 GiB").unwrap(), Some(2 << 30));
+        assert_eq!(parse_size_limit("1kb").unwrap(), Some(1024));
+        assert_eq!(parse_size_limit("None").unwrap(), None);
+        assert!(parse_size_limit("2X").is_err());
+        assert!(parse_size_limit("G").is_err());
+        assert!(parse_size_limit("99999999999T").is_err());
+    }
+}
@@ -1,7 +1,7 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     sync::atomic::{AtomicU32, Ordering},
 };
 
@@ -27,6 +27,9 @@ pub struct Component {
     pub is_toolchain_component: bool,
     /// Indicates whether this component was already installed or not.
     pub installed: bool,
+    /// The names of other components that this component needs in order to work.
+    #[serde(default)]
+    pub requires: Vec<String>,
 }
 
 impl Component {
@@ -43,6 +46,7 @@ impl Component {
             tool_installer: None,
             is_toolchain_component: false,
             installed: false,
+            requires: vec![],
         };
         COMPONENTS_COUNTER.fetch_add(1, Ordering::SeqCst);
 
@@ -56,6 +60,7 @@ impl Component {
     setter!(with_group(self.group_name, group: Option<&str>) { group.map(ToOwned::to_owned) });
     setter!(with_tool_installer(self.tool_installer, installer: &ToolInfo) { Some(installer.clone()) });
     setter!(with_version(self.version, version: Option<&str>) { version.map(ToOwned::to_owned) });
+    setter!(with_requires(self.requires, Vec<String>));
 }
 
 /// Get a combined list of tools and toolchain components in Vec<[Component]> format,
@@ -105,3 +110,139 @@ pub fn component_list_to_tool_map(list: Vec<&Component>) -> ToolMap {
         })
         .collect()
 }
+
+/// Expand the `selected` components into an install plan, which also contains every component
+/// they require (check [`Component::requires`]), directly or indirectly.
+///
+/// Each component appears only once in the plan, and always after the components it requires,
+/// otherwise the order of `selected` is kept. A requirement that is already installed is not
+/// added to the plan.
+///
+/// # Errors
+/// Return `Result::Err` if a requirement is not in `all_components`, or if there is a circular
+/// requirement, such as `a` requires `b` while `b` also requires `a`.
+pub fn resolve_dependencies<'c, I>(
+    all_components: &'c [Component],
+    selected: I,
+) -> Result<Vec<&'c Component>>
+where
+    I: IntoIterator<Item = &'c Component>,
+{
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Visit {
+        InProgress,
+        Done,
+    }
+
+    fn visit<'c>(
+        comp: &'c Component,
+        all_components: &'c [Component],
+        visits: &mut HashMap<&'c str, Visit>,
+        path: &mut Vec<&'c str>,
+        plan: &mut Vec<&'c Component>,
+    ) -> Result<()> {
+        match visits.get(comp.name.as_str()) {
+            Some(Visit::Done) => return Ok(()),
+            Some(Visit::InProgress) => {
+                let start = path.iter().position(|name| *name == comp.name).unwrap_or(0);
+                let mut cycle = path[start..].to_vec();
+                cycle.push(&comp.name);
+                bail!(
+                    "circular requirement between components: {}",
+                    cycle.join(" -> ")
+                );
+            }
+            None => (),
+        }
+        visits.insert(&comp.name, Visit::InProgress);
+        path.push(&comp.name);
+
+        for name in &comp.requires {
+            let Some(dep) = all_components.iter().find(|c| &c.name == name) else {
+                bail!(
+                    "component '{}' requires '{name}', which is not available in this toolkit",
+                    comp.name
+                );
+            };
+            if !dep.installed {
+                visit(dep, all_components, visits, path, plan)?;
+            }
+        }
+
+        path.pop();
+        visits.insert(&comp.name, Visit::Done);
+        plan.push(comp);
+        Ok(())
+    }
+
+    let mut visits = HashMap::new();
+    let mut plan = vec![];
+    for comp in selected {
+        visit(comp, all_components, &mut visits, &mut vec![], &mut plan)?;
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(name: &str, requires: &[&str]) -> Component {
+        Component::new(name, "").with_requires(requires.iter().map(ToString::to_string).collect())
+    }
+
+    fn names(plan: &[&Component]) -> Vec<String> {
+        plan.iter().map(|c| c.name.clone()).collect()
+    }
+
+    #[test]
+    fn requirements_come_first() {
+        let all = [
+            component("toolchain", &[]),
+            component("rust-src", &[]),
+            component("rust-analyzer", &["rust-src"]),
+            component("a", &["b", "rust-analyzer"]),
+            component("b", &["rust-src", "toolchain"]),
+            component("c", &[]),
+        ];
+
+        let plan = resolve_dependencies(&all, [&all[5], &all[3]]).unwrap();
+        assert_eq!(
+            names(&plan),
+            ["c", "rust-src", "toolchain", "b", "rust-analyzer", "a"]
+        );
+        // selected requirements are not duplicated
+        let plan = resolve_dependencies(&all, [&all[0], &all[2], &all[1]]).unwrap();
+        assert_eq!(names(&plan), ["toolchain", "rust-src", "rust-analyzer"]);
+    }
+
+    #[test]
+    fn installed_requirements_are_skipped() {
+        let all = [
+            component("rust-src", &[]).installed(true),
+            component("rust-analyzer", &["rust-src"]),
+        ];
+        let plan = resolve_dependencies(&all, [&all[1]]).unwrap();
+        assert_eq!(names(&plan), ["rust-analyzer"]);
+    }
+
+    #[test]
+    fn unresolvable_requirements() {
+        let all = [
+            component("a", &["b"]),
+            component("b", &["c"]),
+            component("c", &["a"]),
+            component("d", &["e"]),
+        ];
+        let err = resolve_dependencies(&all, [&all[0]]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "circular requirement between components: a -> b -> c -> a"
+        );
+        let err = resolve_dependencies(&all, [&all[3]]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "component 'd' requires 'e', which is not available in this toolkit"
+        );
+    }
+}
@@ -9,7 +9,7 @@ use std::path::{Path, PathBuf};
 use crate::core::directories::RimDir;
 use crate::core::install::InstallConfiguration;
 use crate::core::uninstall::UninstallConfiguration;
-use crate::{core::os::add_to_path, utils};
+use crate::utils;
 use anyhow::Result;
 
 #[derive(Debug)]
@@ -41,11 +41,12 @@ impl VSCodeInstaller<'_> {
     pub(crate) fn install(&self, path: &Path, config: &InstallConfiguration) -> Result<Vec<PathBuf>> {
         // Step 1: Move the root of the directory into `tools` directory
         let vscode_dir = config.tools_dir().join(self.tool_name);
+        config.journal(|tx| tx.will_create(&vscode_dir))?;
         utils::move_to(path, &vscode_dir, false)?;
 
         // Step 2: Add the `bin/` folder to path
         let bin_dir = vscode_dir.join("bin");
-        add_to_path(&bin_dir)?;
+        config.add_to_path(&bin_dir)?;
 
         // Step 3: Create a shortcuts
         // Shortcuts are not important, make sure it won't throw error even if it fails.
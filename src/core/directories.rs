@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Declare a statically allocated `OnceLock` path, and create that directory if it does not exists.
 macro_rules! get_path_and_create {
@@ -11,6 +11,12 @@ macro_rules! get_path_and_create {
     }};
 }
 
+/// Get the directory of this program under a user directory `base`, such as
+/// [`dirs::config_dir`] or [`dirs::cache_dir`].
+pub(crate) fn vendor_dir(base: Option<PathBuf>) -> Option<PathBuf> {
+    base.map(|dir| dir.join(t!("vendor_en").as_ref()))
+}
+
 pub(crate) trait RimDir {
     fn install_dir(&self) -> &Path;
 
@@ -1,21 +1,25 @@
 use super::{
+    cache,
     components::{component_list_to_tool_map, Component},
     directories::RimDir,
     parser::{
         cargo_config::CargoConfig,
         fingerprint::{InstallationRecord, ToolRecord},
-        toolset_manifest::{ToolInfo, ToolsetManifest},
+        toolset_manifest::{Proxy, ToolInfo, ToolsetManifest},
         TomlParser,
     },
-    rustup::ToolchainInstaller,
+    rustup::{self, ToolchainInstaller},
     tools::Tool,
+    transaction::{Change, Transaction},
     CARGO_HOME, RUSTUP_DIST_SERVER, RUSTUP_HOME, RUSTUP_UPDATE_ROOT,
 };
 use crate::{
-    core::os::add_to_path,
     setter,
     toolset_manifest::ToolMap,
-    utils::{self, Extractable, Progress},
+    utils::{
+        self, CancellationToken, DownloadCache, Extractable, Phase, Progress, ProgressMsg,
+        ProgressReporter,
+    },
 };
 use anyhow::{anyhow, bail, Context, Result};
 use std::{
@@ -78,6 +82,16 @@ pub struct InstallConfiguration<'a> {
     pub(crate) progress_indicator: Option<Progress<'a>>,
     manifest: &'a ToolsetManifest,
     insecure: bool,
+    /// The number of files to download at the same time.
+    jobs: usize,
+    /// The journal of changes made by [`install`](Self::install) or [`update`](Self::update),
+    /// which are reverted if they failed.
+    transaction: Option<Transaction>,
+    /// Whether the `install_dir` did not exist before.
+    created_install_dir: bool,
+    /// Whether [`update`](Self::update) leaves some of the outdated components as they are,
+    /// in which case the toolkit version is not recorded, so the others are still reported.
+    partial_update: bool,
 }
 
 impl RimDir for InstallConfiguration<'_> {
@@ -88,6 +102,7 @@ impl RimDir for InstallConfiguration<'_> {
 
 impl<'a> InstallConfiguration<'a> {
     pub fn new(install_dir: &'a Path, manifest: &'a ToolsetManifest) -> Result<Self> {
+        let created_install_dir = !install_dir.exists();
         Ok(Self {
             install_dir: install_dir.to_path_buf(),
             // Note: `InstallationRecord::load` creates `install_dir` if it does not exist
@@ -99,12 +114,17 @@ impl<'a> InstallConfiguration<'a> {
             progress_indicator: None,
             manifest,
             insecure: false,
+            jobs: utils::DEFAULT_DOWNLOAD_JOBS,
+            transaction: None,
+            created_install_dir,
+            partial_update: false,
         })
     }
     /// Creating install diretory and other preperations related to filesystem.
     ///
     /// This is suitable for first-time installation.
     pub fn setup(&mut self) -> Result<()> {
+        let _phase = tracing::info_span!("phase", name = "setup").entered();
         let install_dir = &self.install_dir;
         let manifest = self.manifest;
 
@@ -112,6 +132,7 @@ impl<'a> InstallConfiguration<'a> {
 
         // Create a copy of the manifest which is later used for component management.
         let manifest_out_path = install_dir.join(ToolsetManifest::FILENAME);
+        self.journal(|tx| tx.will_write(&manifest_out_path))?;
         utils::write_file(manifest_out_path, &manifest.to_toml()?, false)?;
 
         // Create a copy of this binary
@@ -121,12 +142,15 @@ impl<'a> InstallConfiguration<'a> {
 
         // Add this manager to the `PATH` environment
         let manager_exe = install_dir.join(utils::exe!(manager_name));
+        self.journal(|tx| tx.will_write(&manager_exe))?;
         utils::copy_as(self_exe, &manager_exe)?;
-        add_to_path(install_dir)?;
+        self.add_to_path(install_dir)?;
 
         #[cfg(windows)]
         // Create registry entry to add this program into "installed programs".
-        super::os::windows::do_add_to_programs(&manager_exe)?;
+        if super::os::windows::do_add_to_programs(&manager_exe)? {
+            self.journal(|tx| tx.record(Change::AddedToPrograms))?;
+        }
 
         if let Some(prog) = &self.progress_indicator {
             prog.inc(Some(5.0))?;
@@ -135,7 +159,18 @@ impl<'a> InstallConfiguration<'a> {
         Ok(())
     }
 
+    /// Install the `components`, every change made to the machine is reverted if the
+    /// installation failed, and can be reverted manually later, check [`Transaction`].
     pub fn install(mut self, components: Vec<Component>) -> Result<()> {
+        self.transaction = Some(Transaction::begin(
+            &self.install_dir,
+            self.created_install_dir,
+        )?);
+        let res = self.install_(components);
+        self.finish_transaction(res)
+    }
+
+    fn install_(&mut self, components: Vec<Component>) -> Result<()> {
         let (tc_components, tools) = split_components(components);
 
         self.setup()?;
@@ -149,6 +184,64 @@ impl<'a> InstallConfiguration<'a> {
         Ok(())
     }
 
+    /// Commit the transaction if `res` is `Ok`, otherwise roll it back, then return `res`.
+    fn finish_transaction(mut self, res: Result<()>) -> Result<()> {
+        let Some(tx) = self.transaction.take() else {
+            return res;
+        };
+        let Err(e) = res else {
+            return tx.commit();
+        };
+        warn!("{}", t!("install_rollback", reason = e));
+        if let Err(rollback_err) = tx.rollback() {
+            error!("{rollback_err}");
+        }
+        Err(e)
+    }
+
+    /// Call `f` with the current transaction, if there is one.
+    pub(crate) fn journal<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(&Transaction) -> Result<()>,
+    {
+        self.transaction.as_ref().map_or(Ok(()), f)
+    }
+
+    /// Add `path` to the `PATH` variable, which is recorded in the current transaction
+    /// unless it's already in there.
+    pub(crate) fn add_to_path(&self, path: &Path) -> Result<()> {
+        let in_path = std::env::var_os("PATH")
+            .is_some_and(|paths| std::env::split_paths(&paths).any(|p| p == path));
+        if !in_path {
+            self.journal(|tx| {
+                tx.record(Change::AddedToPath {
+                    path: path.to_path_buf(),
+                })
+            })?;
+        }
+        super::os::add_to_path(path)
+    }
+
+    /// Add the record of an installed tool, which is also recorded in the current transaction
+    /// so that it can be uninstalled on rollback, unless the tool was already installed.
+    fn add_tool_record(&mut self, name: &str, record: ToolRecord) -> Result<()> {
+        if !self
+            .install_record
+            .installed_tools()
+            .any(|tool| tool == name)
+        {
+            self.journal(|tx| {
+                tx.record(Change::InstalledTool {
+                    name: name.to_string(),
+                    kind: record.tool_kind(),
+                    paths: record.paths.clone(),
+                })
+            })?;
+        }
+        self.install_record.add_tool_record(name, record);
+        Ok(())
+    }
+
     pub(crate) fn inc_progress(&self, val: f32) -> Result<()> {
         if let Some(prog) = &self.progress_indicator {
             prog.inc(Some(val))?;
@@ -156,6 +249,15 @@ impl<'a> InstallConfiguration<'a> {
         Ok(())
     }
 
+    /// The token that cancels the installation, which is the one of the progress indicator,
+    /// or a token that never gets cancelled if there's no progress indicator.
+    pub(crate) fn cancellation_token(&self) -> CancellationToken {
+        self.progress_indicator
+            .as_ref()
+            .map(|prog| prog.cancellation_token().clone())
+            .unwrap_or_default()
+    }
+
     setter!(
         with_cargo_registry(self.cargo_registry, name: impl ToString, value: impl ToString) {
             Some((name.to_string(), value.to_string()))
@@ -165,6 +267,8 @@ impl<'a> InstallConfiguration<'a> {
     setter!(with_rustup_update_root(self.rustup_update_root, Url));
     setter!(with_progress_indicator(self.progress_indicator, Option<Progress<'a>>));
     setter!(insecure(self.insecure, bool));
+    setter!(with_jobs(self.jobs, usize));
+    setter!(partial_update(self.partial_update, bool));
 
     pub(crate) fn env_vars(&self) -> Result<HashMap<&'static str, String>> {
         let cargo_home = self
@@ -184,7 +288,7 @@ impl<'a> InstallConfiguration<'a> {
         ]);
 
         // Add proxy settings if has
-        if let Some(proxy) = &self.manifest.proxy {
+        if let Some(proxy) = self.proxy() {
             if let Some(url) = &proxy.http {
                 env_vars.insert("http_proxy", url.to_string());
             }
@@ -199,6 +303,12 @@ impl<'a> InstallConfiguration<'a> {
         Ok(env_vars)
     }
 
+    /// The proxy used for the installation, which is the [global](Proxy::global) one set
+    /// by the command line if there is, otherwise the one in the manifest.
+    pub(crate) fn proxy(&self) -> Option<Proxy> {
+        Proxy::global().or_else(|| self.manifest.proxy.clone())
+    }
+
     fn install_tools_(&mut self, use_cargo: bool, tools: &ToolMap, weight: f32) -> Result<()> {
         let to_install = tools
             .iter()
@@ -214,9 +324,34 @@ impl<'a> InstallConfiguration<'a> {
         if to_install.is_empty() {
             return self.inc_progress(weight);
         }
-        let sub_progress_delta = weight / to_install.len() as f32;
 
-        for (name, tool) in to_install {
+        // download the tools from urls first, so that they can be downloaded concurrently
+        let download_dir = self.create_temp_dir("download")?;
+        let downloaded = self.download_tools(&to_install, download_dir.path(), weight / 2.0)?;
+        let install_weight = if downloaded.is_empty() {
+            weight
+        } else {
+            weight / 2.0
+        };
+        let sub_progress_delta = install_weight / to_install.len() as f32;
+
+        let res = self.install_each_tool(use_cargo, to_install, &downloaded, sub_progress_delta);
+        // record the tools installed so far even if it failed or got cancelled midway,
+        // so that they can still be uninstalled.
+        self.install_record.write()?;
+
+        res
+    }
+
+    fn install_each_tool(
+        &mut self,
+        use_cargo: bool,
+        tools: Vec<(&str, &ToolInfo)>,
+        downloaded: &HashMap<String, PathBuf>,
+        progress_delta: f32,
+    ) -> Result<()> {
+        for (name, tool) in tools {
+            let _component = tracing::info_span!("component", name).entered();
             let info = if use_cargo {
                 t!("installing_via_cargo_info", name = name)
             } else {
@@ -224,27 +359,33 @@ impl<'a> InstallConfiguration<'a> {
             };
             info!("{info}");
 
-            self.install_tool(name, tool)?;
+            match downloaded.get(name) {
+                Some(path) => {
+                    let record = self.try_install_from_path(name, tool.version(), path)?;
+                    self.add_tool_record(name, record)?;
+                }
+                None => self.install_tool(name, tool)?,
+            }
 
-            self.inc_progress(sub_progress_delta)?;
+            self.inc_progress(progress_delta)?;
         }
-
-        self.install_record.write()?;
-
         Ok(())
     }
 
     pub fn install_tools(&mut self, tools: &ToolMap) -> Result<()> {
+        let _phase = tracing::info_span!("phase", name = "tools").entered();
         info!("{}", t!("install_tools"));
         self.install_tools_(false, tools, 30.0)
     }
 
     pub fn cargo_install(&mut self, tools: &ToolMap) -> Result<()> {
+        let _phase = tracing::info_span!("phase", name = "cargo-tools").entered();
         info!("{}", t!("install_via_cargo"));
         self.install_tools_(true, tools, 30.0)
     }
 
     pub fn install_rust(&mut self, optional_components: &[String]) -> Result<()> {
+        let _phase = tracing::info_span!("phase", name = "toolchain").entered();
         info!("{}", t!("install_toolchain"));
 
         let manifest = self.manifest;
@@ -254,7 +395,7 @@ impl<'a> InstallConfiguration<'a> {
             manifest,
             optional_components,
         )?;
-        add_to_path(self.cargo_bin())?;
+        self.add_to_path(self.cargo_bin())?;
         self.cargo_is_installed = true;
 
         // Add the rust info to the fingerprint.
@@ -266,8 +407,79 @@ impl<'a> InstallConfiguration<'a> {
             .clone_toolkit_meta_from_manifest(manifest);
         // write changes
         self.install_record.write()?;
+        self.inc_progress(27.0)?;
 
-        self.inc_progress(30.0)
+        let noop = Progress::noop();
+        let progress = self.progress_indicator.as_ref().unwrap_or(&noop);
+        rustup::verify_install_with_progress(self.cargo_home(), &progress.child(3.0))
+    }
+
+    /// Download every tool that is installed from an url into `dir` concurrently, which
+    /// advances the progress by `weight` if there's any, returns the downloaded file of
+    /// each tool.
+    ///
+    /// Tools found in the download cache are copied from there instead, the downloaded ones
+    /// are added to the cache once verified.
+    fn download_tools(
+        &self,
+        tools: &[(&str, &ToolInfo)],
+        dir: &Path,
+        weight: f32,
+    ) -> Result<HashMap<String, PathBuf>> {
+        let progress = self
+            .progress_indicator
+            .as_ref()
+            .map_or_else(Progress::noop, |p| p.clone().with_len(weight));
+        let cache = cache::download_cache()?;
+        let mut files = vec![];
+        let mut downloaded = HashMap::new();
+        for (name, tool) in tools {
+            let ToolInfo::Url { url, .. } = tool else {
+                continue;
+            };
+            // NB: different tools might have the same file name
+            let dest = dir.join(name).join(downloadable_file_name(url)?);
+            utils::ensure_dir(dir.join(name))?;
+            downloaded.insert(name.to_string(), dest.clone());
+            if let Some(cache) = &cache {
+                if cache.restore(url, tool.sha256(), &dest)? {
+                    progress.show_msg(ProgressMsg::Cached(name))?;
+                    continue;
+                }
+            }
+            files.push((name, tool, url.clone(), dest));
+        }
+        if files.is_empty() {
+            // every tool was cached, no need to show a download bar
+            if !downloaded.is_empty() {
+                progress.inc(None)?;
+            }
+            return Ok(downloaded);
+        }
+
+        progress.start_phase(Phase::Download);
+        let urls = files
+            .iter()
+            .map(|(_, _, url, dest)| (url.clone(), dest.clone()))
+            .collect::<Vec<_>>();
+        utils::DownloadOpt::new("tools")
+            .with_proxy(self.proxy())
+            .download_all(&urls, self.jobs, &progress)?;
+
+        progress.start_phase(Phase::Verify);
+        for (_, tool, _, path) in &files {
+            if let Some(expected) = tool.sha256() {
+                utils::verify_sha256(path, expected)?;
+            }
+        }
+        progress.end_phase();
+
+        if let Some(cache) = &cache {
+            for (_, tool, url, path) in &files {
+                add_to_cache(cache, url, tool.sha256(), path);
+            }
+        }
+        Ok(downloaded)
     }
 
     // TODO: Write version info after installing each tool,
@@ -304,23 +516,29 @@ impl<'a> InstallConfiguration<'a> {
             // so then we can have the `resume download` feature.
             ToolInfo::Url { url, .. } => {
                 let temp_dir = self.create_temp_dir("download")?;
-                let downloaded_file_name = url
-                    .path_segments()
-                    .ok_or_else(|| anyhow!("unsupported url format '{url}'"))?
-                    .last()
-                    // Sadly, a path segment could be empty string, so we need to filter that out
-                    .filter(|seg| !seg.is_empty())
-                    .ok_or_else(|| anyhow!("'{url}' doesn't appear to be a downloadable file"))?;
-                let dest = temp_dir.path().join(downloaded_file_name);
-                utils::download_with_proxy(name, url, &dest, self.manifest.proxy.as_ref())?;
+                let dest = temp_dir.path().join(downloadable_file_name(url)?);
+                let cache = cache::download_cache()?;
+                let cached = match &cache {
+                    Some(cache) => cache.restore(url, tool.sha256(), &dest)?,
+                    None => false,
+                };
+                if cached {
+                    info!("{}", ProgressMsg::Cached(name));
+                } else {
+                    utils::download_with_proxy(name, url, &dest, self.proxy().as_ref())?;
+                    if let Some(expected) = tool.sha256() {
+                        utils::verify_sha256(&dest, expected)?;
+                    }
+                    if let Some(cache) = &cache {
+                        add_to_cache(cache, url, tool.sha256(), &dest);
+                    }
+                }
 
                 self.try_install_from_path(name, tool_ver, &dest)?
             }
         };
 
-        self.install_record.add_tool_record(name, record);
-
-        Ok(())
+        self.add_tool_record(name, record)
     }
 
     fn try_install_from_path(
@@ -347,6 +565,7 @@ impl<'a> InstallConfiguration<'a> {
     ///
     /// This will write a `config.toml` file to `CARGO_HOME`.
     pub fn config_cargo(&self) -> Result<()> {
+        let _phase = tracing::info_span!("phase", name = "cargo-config").entered();
         info!("{}", t!("install_cargo_config"));
 
         let mut config = CargoConfig::new();
@@ -357,6 +576,7 @@ impl<'a> InstallConfiguration<'a> {
         let config_toml = config.to_toml()?;
         if !config_toml.trim().is_empty() {
             let config_path = self.cargo_home().join(CargoConfig::FILENAME);
+            self.journal(|tx| tx.will_write(&config_path))?;
             utils::write_file(config_path, &config_toml, false)?;
         }
 
@@ -378,8 +598,10 @@ impl<'a> InstallConfiguration<'a> {
     /// If `maybe_file` is a path to compressed file, this will try to extract it to `dest`;
     /// otherwise this will copy that file into dest.
     fn extract_or_copy_to(&self, maybe_file: &Path, dest: &Path) -> Result<PathBuf> {
-        if let Ok(mut extractable) = Extractable::load(maybe_file) {
-            extractable.extract_then_skip_solo_dir(dest, Some("bin"))
+        if let Ok(extractable) = Extractable::load(maybe_file) {
+            extractable
+                .with_cancellation_token(self.cancellation_token())
+                .extract_then_skip_solo_dir(dest, Some("bin"))
         } else {
             utils::copy_into(maybe_file, dest)
         }
@@ -388,7 +610,17 @@ impl<'a> InstallConfiguration<'a> {
 
 // For updates
 impl InstallConfiguration<'_> {
+    /// Update the `components`, the changes are reverted if the update failed, except for the
+    /// toolchain, which is managed by `rustup`.
     pub fn update(mut self, components: Vec<Component>) -> Result<()> {
+        self.transaction = Some(Transaction::begin(&self.install_dir, false)?);
+        let res = self.update_(components);
+        self.finish_transaction(res)
+    }
+
+    fn update_(&mut self, components: Vec<Component>) -> Result<()> {
+        // the toolchain components share the same version, so any of them means the toolchain
+        let update_toolchain = components.iter().any(|c| c.is_toolchain_component);
         let (_, tools) = split_components(components);
         // setup env for current process
         for (key, val) in self.env_vars()? {
@@ -396,12 +628,24 @@ impl InstallConfiguration<'_> {
         }
         self.inc_progress(10.0)?;
 
-        self.update_toolchain()?;
+        if update_toolchain {
+            self.update_toolchain()?;
+        } else {
+            self.inc_progress(60.0)?;
+        }
         self.update_tools(&tools)?;
-        Ok(())
+
+        // record meta info once everything is up to date, even if the toolchain already was,
+        // otherwise the remaining updates would no longer be found
+        if !self.partial_update {
+            self.install_record
+                .clone_toolkit_meta_from_manifest(self.manifest);
+        }
+        self.install_record.write()
     }
 
     fn update_toolchain(&mut self) -> Result<()> {
+        let _phase = tracing::info_span!("phase", name = "toolchain").entered();
         info!("{}", t!("update_toolchain"));
 
         let manifest = self.manifest;
@@ -413,8 +657,6 @@ impl InstallConfiguration<'_> {
         let record = &mut self.install_record;
         // Add the rust info to the fingerprint.
         record.update_rust(manifest.rust_version());
-        // record meta info
-        record.clone_toolkit_meta_from_manifest(manifest);
         // write changes
         record.write()?;
 
@@ -422,6 +664,7 @@ impl InstallConfiguration<'_> {
     }
 
     fn update_tools(&mut self, tools: &ToolMap) -> Result<()> {
+        let _phase = tracing::info_span!("phase", name = "tools").entered();
         info!("{}", t!("update_tools"));
         self.install_tools_(false, tools, 15.0)?;
         self.install_tools_(true, tools, 15.0)?;
@@ -462,6 +705,24 @@ fn split_components(components: Vec<Component>) -> (Vec<String>, ToolMap) {
     (toolchain_components, toolset_components)
 }
 
+/// Add a downloaded `file` to the download cache, which only logs the error if it failed,
+/// because the installation can continue without it.
+fn add_to_cache(cache: &DownloadCache, url: &Url, checksum: Option<&str>, file: &Path) {
+    if let Err(e) = cache.store(url, checksum, file) {
+        warn!("{}", t!("cache_store_failed", url = url, reason = e));
+    }
+}
+
+/// Get the name of the file that `url` points to.
+pub(crate) fn downloadable_file_name(url: &Url) -> Result<&str> {
+    url.path_segments()
+        .ok_or_else(|| anyhow!("unsupported url format '{url}'"))?
+        .last()
+        // Sadly, a path segment could be empty string, so we need to filter that out
+        .filter(|seg| !seg.is_empty())
+        .ok_or_else(|| anyhow!("'{url}' doesn't appear to be a downloadable file"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,119 @@
+//! Mirrors of the servers that this program downloads from.
+
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use url::Url;
+
+use super::directories::vendor_dir;
+use super::parser::mirror_config::MirrorConfig;
+use super::parser::TomlParser;
+use crate::utils::Mirrors;
+
+/// The environment variable to override the mirrors of distribution server,
+/// which can be a comma separated list of urls.
+const RIM_DIST_SERVER: &str = "RIM_DIST_SERVER";
+
+static DIST_SERVER: OnceLock<Mirrors> = OnceLock::new();
+
+/// Get the mirrors of distribution server, which are (in the order of precedence):
+///
+/// 1. The comma separated urls of `RIM_DIST_SERVER` environment variable.
+/// 2. The `dist-server` list in `mirrors.toml` under the user's config directory,
+///    such as `~/.config/<vendor>/mirrors.toml` on Linux.
+/// 3. The default server.
+///
+/// The latency of each mirror is stored in the user's cache directory, so the fastest one
+/// is preferred across runs.
+pub(crate) fn dist_server() -> Result<&'static Mirrors> {
+    if let Some(mirrors) = DIST_SERVER.get() {
+        return Ok(mirrors);
+    }
+
+    let env_ovr = std::env::var(RIM_DIST_SERVER).ok();
+    let config = match vendor_dir(dirs::config_dir()).map(|dir| dir.join(MirrorConfig::FILENAME)) {
+        Some(path) if path.is_file() => Some(
+            MirrorConfig::load(&path)
+                .with_context(|| format!("invalid mirror configuration '{}'", path.display()))?,
+        ),
+        _ => None,
+    };
+    let bases = resolve_dist_server(env_ovr.as_deref(), config)?;
+    debug!(
+        "using distribution server mirrors: {}",
+        bases.iter().map(Url::as_str).collect::<Vec<_>>().join(", ")
+    );
+
+    Ok(DIST_SERVER.get_or_init(|| new_mirrors(bases)))
+}
+
+/// Use `bases` as the mirrors of distribution server for the rest of this run,
+/// instead of the ones from [`dist_server`], such as the ones in an answer file.
+///
+/// Returns `false` (and does nothing) if the mirrors were already determined.
+pub(crate) fn set_dist_server(bases: Vec<Url>) -> bool {
+    DIST_SERVER.set(new_mirrors(bases)).is_ok()
+}
+
+fn new_mirrors(bases: Vec<Url>) -> Mirrors {
+    let mirrors = Mirrors::new(bases);
+    match vendor_dir(dirs::cache_dir()) {
+        Some(cache_dir) => mirrors.with_latency_file(cache_dir.join("mirror-latency")),
+        None => mirrors,
+    }
+}
+
+fn resolve_dist_server(env_ovr: Option<&str>, config: Option<MirrorConfig>) -> Result<Vec<Url>> {
+    if let Some(list) = env_ovr.filter(|s| !s.trim().is_empty()) {
+        return list
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                Url::parse(s).with_context(|| format!("invalid url '{s}' in `{RIM_DIST_SERVER}`"))
+            })
+            .collect();
+    }
+    match config {
+        Some(config) if !config.dist_server.is_empty() => Ok(config.dist_server),
+        // safe to unwrap, this is a valid url
+        _ => Ok(vec![Url::parse(super::RIM_DIST_SERVER).unwrap()]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dist_server_precedence() {
+        let config = || {
+            Some(MirrorConfig {
+                dist_server: vec![Url::parse("https://config.example.com").unwrap()],
+            })
+        };
+        let resolved = resolve_dist_server(
+            Some("https://a.example.com/rim, https://b.example.com,"),
+            config(),
+        )
+        .unwrap();
+        assert_eq!(
+            resolved,
+            [
+                Url::parse("https://a.example.com/rim").unwrap(),
+                Url::parse("https://b.example.com").unwrap(),
+            ]
+        );
+
+        let resolved = resolve_dist_server(Some(""), config()).unwrap();
+        assert_eq!(resolved, config().unwrap().dist_server);
+
+        let resolved = resolve_dist_server(None, Some(MirrorConfig::default())).unwrap();
+        assert_eq!(
+            resolved,
+            [Url::parse(super::super::RIM_DIST_SERVER).unwrap()]
+        );
+
+        assert!(resolve_dist_server(Some("not a url"), None).is_err());
+    }
+}
@@ -2,17 +2,21 @@
 //!
 //! Including configuration, toolchain, toolset management.
 
+pub mod bundle;
+pub(crate) mod cache;
 pub mod components;
 mod custom_instructions;
 pub(crate) mod directories;
 pub mod install;
 mod locales;
+pub(crate) mod mirrors;
 pub(crate) mod os;
 pub(crate) mod parser;
 mod path_ext;
 pub(crate) mod rustup;
 pub mod toolkit;
 pub(crate) mod tools;
+pub(crate) mod transaction;
 pub mod try_it;
 pub(crate) mod uninstall;
 pub mod update;
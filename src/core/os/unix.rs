@@ -15,6 +15,7 @@ impl EnvConfig for InstallConfiguration<'_> {
     // because rustup will not write those file if a user has choose to pass `--no-modify-path`.
     // Which is not ideal for env vars such as `RUSTUP_DIST_SERVER`.
     fn config_env_vars(&self) -> Result<()> {
+        let _phase = tracing::info_span!("phase", name = "env").entered();
         let vars_raw = self.env_vars()?;
 
         if !GlobalOpts::get().no_modify_env {
@@ -27,6 +28,7 @@ impl EnvConfig for InstallConfiguration<'_> {
                 for rc in sh.update_rcs() {
                     // Do NOT fail installation if backup fails
                     _ = create_backup_for_rc(&rc, &backup_dir);
+                    self.journal(|tx| tx.will_write(&rc))?;
 
                     let old_content = utils::read_to_string("rc", &rc).unwrap_or_default();
                     let new_content =
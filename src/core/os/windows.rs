@@ -2,6 +2,7 @@ use std::env::current_exe;
 
 use crate::core::directories::RimDir;
 use crate::core::install::{EnvConfig, InstallConfiguration};
+use crate::core::transaction::Change;
 use crate::core::uninstall::{UninstallConfiguration, Uninstallation};
 use crate::core::GlobalOpts;
 use crate::utils;
@@ -11,9 +12,16 @@ pub(crate) use rustup::*;
 
 impl EnvConfig for InstallConfiguration<'_> {
     fn config_env_vars(&self) -> Result<()> {
+        let _phase = tracing::info_span!("phase", name = "env").entered();
         info!("{}", t!("install_env_config"));
 
         for (key, val) in self.env_vars()? {
+            self.journal(|tx| {
+                tx.record(Change::SetEnvVar {
+                    key: key.to_string(),
+                    previous: get_env_var(key)?,
+                })
+            })?;
             set_env_var(key, val.encode_utf16().collect())?;
         }
         update_env();
@@ -80,7 +88,7 @@ pub(crate) mod rustup {
 
     static UNINSTALL_ENTRY: OnceLock<String> = OnceLock::new();
 
-    pub(super) fn uninstall_entry() -> &'static str {
+    pub(crate) fn uninstall_entry() -> &'static str {
         UNINSTALL_ENTRY.get_or_init(|| {
             format!(
                 "Software\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\{}",
@@ -89,7 +97,9 @@ pub(crate) mod rustup {
         })
     }
 
-    pub(crate) fn do_add_to_programs(program_bin: &Path) -> Result<()> {
+    /// Add this program to the "installed programs", returns `false` if there's already
+    /// an entry of it.
+    pub(crate) fn do_add_to_programs(program_bin: &Path) -> Result<bool> {
         use std::path::PathBuf;
 
         let key = RegKey::predef(HKEY_CURRENT_USER)
@@ -105,7 +115,7 @@ pub(crate) mod rustup {
             let mut path = PathBuf::from(OsString::from_wide(&s));
             path.pop();
             if path.exists() {
-                return Ok(());
+                return Ok(false);
             }
         }
 
@@ -129,7 +139,7 @@ pub(crate) mod rustup {
         )
         .context("Failed to set `DisplayName`")?;
 
-        Ok(())
+        Ok(true)
     }
 
     /// This is used to decode the value of HKCU\Environment\PATH. If that key is
@@ -168,6 +178,29 @@ pub(crate) mod rustup {
         }
     }
 
+    /// Get the proxy server and the `;` separated list of hosts to bypass, which are
+    /// configured in the "Internet Options" of current user, if the proxy is enabled.
+    pub(crate) fn system_proxy() -> Result<Option<(String, Option<String>)>> {
+        let settings = match RegKey::predef(HKEY_CURRENT_USER).open_subkey_with_flags(
+            "Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings",
+            KEY_READ,
+        ) {
+            Ok(key) => key,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(anyhow!(e)),
+        };
+        let enabled = settings
+            .get_value::<u32, _>("ProxyEnable")
+            .unwrap_or_default();
+        let Ok(server) = settings.get_value::<String, _>("ProxyServer") else {
+            return Ok(None);
+        };
+        if enabled == 0 || server.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some((server, settings.get_value("ProxyOverride").ok())))
+    }
+
     fn environment() -> Result<RegKey> {
         RegKey::predef(HKEY_CURRENT_USER)
             .open_subkey_with_flags("Environment", KEY_READ | KEY_WRITE)
@@ -208,6 +241,26 @@ pub(crate) mod rustup {
         Ok(())
     }
 
+    /// Get the persistent value of the environment variable `key` of current user.
+    pub(super) fn get_env_var(key: &str) -> Result<Option<String>> {
+        match environment()?.get_raw_value(key) {
+            Ok(val) => Ok(from_winreg_value(&val).map(|s| String::from_utf16_lossy(&s))),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(anyhow!(e)),
+        }
+    }
+
+    /// Set the environment variable `key` back to its `previous` value, or remove it if it
+    /// didn't exist, then broadcast the change.
+    pub(crate) fn restore_env_var(key: &str, previous: Option<&str>) -> Result<()> {
+        match previous {
+            Some(val) => set_env_var(key, val.encode_utf16().collect())?,
+            None => unset_env_var(key)?,
+        }
+        update_env();
+        Ok(())
+    }
+
     /// Remove a environment variable with given `key`
     ///
     /// This will modify the environment permanently for current user,
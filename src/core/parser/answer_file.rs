@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use url::Url;
+
+use super::toolset_manifest::Proxy;
+use super::TomlParser;
+
+/// The answers to every question of the installer, for unattended installations with
+/// `--non-interactive --config <PATH>`, such as:
+///
+/// ```toml
+/// install-dir = "/opt/rust"
+/// components = ["llvm-tools", "mingw64"]
+/// # optional, a path (relative to this file) or url of the toolset manifest
+/// manifest = "toolset-manifest.toml"
+///
+/// [proxy]
+/// https = "http://proxy.example.com:8080"
+/// no-proxy = "localhost,.example.com"
+///
+/// [mirrors]
+/// dist-server = ["https://mirror.example.com/rim"]
+/// rustup-dist-server = "https://mirror.example.com/rustup"
+/// rustup-update-root = "https://mirror.example.com/rustup/rustup"
+/// cargo-registry = { name = "mirror", url = "sparse+https://mirror.example.com/crates.io-index/" }
+/// ```
+///
+/// The `install-dir` and `components` are required, so an incomplete answer file is an error
+/// instead of a silent fallback to the defaults. The required components are installed even if
+/// they are not in the `components` list.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct AnswerFile {
+    pub(crate) install_dir: PathBuf,
+    pub(crate) components: Vec<String>,
+    pub(crate) manifest: Option<String>,
+    /// Overrides the proxy of the toolset manifest.
+    pub(crate) proxy: Option<Proxy>,
+    #[serde(default)]
+    pub(crate) mirrors: MirrorAnswers,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct MirrorAnswers {
+    /// Mirrors of the distribution server, which override the `mirrors.toml` of the user.
+    #[serde(default)]
+    pub(crate) dist_server: Vec<Url>,
+    pub(crate) rustup_dist_server: Option<Url>,
+    pub(crate) rustup_update_root: Option<Url>,
+    pub(crate) cargo_registry: Option<CargoRegistry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct CargoRegistry {
+    pub(crate) name: String,
+    pub(crate) url: String,
+}
+
+impl TomlParser for AnswerFile {
+    const FILENAME: &str = "answers.toml";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_answers() {
+        let input = r#"
+install-dir = "/opt/rust"
+components = ["llvm-tools"]
+manifest = "https://example.com/toolset-manifest.toml"
+
+[proxy]
+https = "http://proxy.example.com:8080"
+
+[mirrors]
+dist-server = ["https://a.example.com/rim"]
+rustup-dist-server = "https://a.example.com/rustup"
+cargo-registry = { name = "mirror", url = "sparse+https://a.example.com/index/" }"#;
+        let answers = AnswerFile::from_str(input).unwrap();
+        assert_eq!(answers.install_dir, PathBuf::from("/opt/rust"));
+        assert_eq!(answers.components, ["llvm-tools"]);
+        assert_eq!(
+            answers.proxy.unwrap().https,
+            Some(Url::parse("http://proxy.example.com:8080").unwrap())
+        );
+        assert_eq!(answers.mirrors.dist_server.len(), 1);
+        assert!(answers.mirrors.rustup_update_root.is_none());
+        assert_eq!(answers.mirrors.cargo_registry.unwrap().name, "mirror");
+    }
+
+    #[test]
+    fn missing_or_unknown_answers() {
+        let err = AnswerFile::from_str("components = []").unwrap_err();
+        assert!(err.to_string().contains("install-dir"), "{err}");
+        assert!(AnswerFile::from_str("install-dir = '/opt/rust'").is_err());
+        // typos are not silently ignored
+        assert!(AnswerFile::from_str(
+            "install-dir = '/opt/rust'\ncomponents = []\ninstal-dir = '/opt/rust'"
+        )
+        .is_err());
+
+        let answers = AnswerFile::from_str("install-dir = '/opt/rust'\ncomponents = []").unwrap();
+        assert!(answers.manifest.is_none());
+        assert!(answers.mirrors.dist_server.is_empty());
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+use super::TomlParser;
+
+/// User configuration of the download cache, such as:
+///
+/// ```toml
+/// max-size = "2G"
+/// ```
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct CacheConfig {
+    /// The size limit of cached files, such as `500M` or `2G`, or `none` for no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) max_size: Option<String>,
+}
+
+impl TomlParser for CacheConfig {
+    const FILENAME: &str = "cache.toml";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_config() {
+        let config = CacheConfig::from_str("max-size = \"2G\"").unwrap();
+        assert_eq!(config.max_size.as_deref(), Some("2G"));
+        assert_eq!(config.to_toml().unwrap().trim(), "max-size = \"2G\"");
+
+        let config = CacheConfig::from_str("").unwrap();
+        assert!(config.max_size.is_none());
+    }
+}
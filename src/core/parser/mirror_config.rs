@@ -0,0 +1,46 @@
+use serde::Deserialize;
+use url::Url;
+
+use super::TomlParser;
+
+/// User configured mirrors of the servers, such as:
+///
+/// ```toml
+/// dist-server = [
+///     "https://mirror.example.com/rim",
+///     "https://another-mirror.example.com/rim",
+/// ]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct MirrorConfig {
+    /// Mirrors of the distribution server, tried in the given order before their latency is known.
+    #[serde(default)]
+    pub(crate) dist_server: Vec<Url>,
+}
+
+impl TomlParser for MirrorConfig {
+    const FILENAME: &str = "mirrors.toml";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dist_server_mirrors() {
+        let input = r#"
+dist-server = ["https://a.example.com/rim", "https://b.example.com"]"#;
+        let config = MirrorConfig::from_str(input).unwrap();
+        assert_eq!(
+            config.dist_server,
+            [
+                Url::parse("https://a.example.com/rim").unwrap(),
+                Url::parse("https://b.example.com").unwrap(),
+            ]
+        );
+
+        let config = MirrorConfig::from_str("").unwrap();
+        assert!(config.dist_server.is_empty());
+    }
+}
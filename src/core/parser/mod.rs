@@ -1,6 +1,9 @@
+pub(crate) mod answer_file;
+pub(crate) mod cache_config;
 pub(crate) mod cargo_config;
 pub mod dist_manifest;
 pub mod fingerprint;
+pub(crate) mod mirror_config;
 pub(crate) mod release_info;
 pub mod toolset_manifest;
 
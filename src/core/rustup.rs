@@ -2,7 +2,7 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::thread;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use url::Url;
 
 use super::directories::RimDir;
@@ -15,7 +15,7 @@ use super::RUSTUP_DIST_SERVER;
 use super::RUSTUP_HOME;
 use crate::setter;
 use crate::toolset_manifest::Proxy;
-use crate::utils::{self, set_exec_permission, url_join};
+use crate::utils::{self, set_exec_permission, url_join, ProgressMsg, ProgressReporter};
 
 #[cfg(windows)]
 pub(crate) const RUSTUP_INIT: &str = "rustup-init.exe";
@@ -27,6 +27,9 @@ const RUSTUP: &str = "rustup.exe";
 #[cfg(not(windows))]
 const RUSTUP: &str = "rustup";
 
+/// Binaries that every installed toolchain should have.
+const TOOLCHAIN_BINARIES: &[&str] = &["rustc", "cargo", "rustdoc"];
+
 pub struct ToolchainInstaller {
     insecure: bool,
 }
@@ -121,6 +124,7 @@ impl ToolchainInstaller {
             utils::CliProgressStyle::Spinner {
                 auto_tick_duration: None,
             },
+            progress.opt,
         )?;
 
         let rustup = config.cargo_bin().join(RUSTUP);
@@ -139,6 +143,51 @@ impl ToolchainInstaller {
     }
 }
 
+/// Check if the essential binaries exist in the `bin` folder of `toolchain_dir`,
+/// and they are executable, which catches broken installation immediately.
+///
+/// The progress advances an equal step after each binary is checked.
+pub(crate) fn verify_install_with_progress(
+    toolchain_dir: &Path,
+    progress: &dyn ProgressReporter,
+) -> Result<()> {
+    let bin_dir = toolchain_dir.join("bin");
+    let step = 100.0 / TOOLCHAIN_BINARIES.len() as f32;
+    for (idx, name) in TOOLCHAIN_BINARIES.iter().enumerate() {
+        let binary = bin_dir.join(format!("{name}{}", std::env::consts::EXE_SUFFIX));
+        progress.message(&ProgressMsg::Verifying(name).to_string())?;
+        if !binary.is_file() {
+            bail!(
+                "toolchain installation is broken: '{name}' does not exist in '{}'",
+                bin_dir.display()
+            );
+        }
+        if !is_executable(&binary)? {
+            bail!(
+                "toolchain installation is broken: '{}' is not executable",
+                binary.display()
+            );
+        }
+        if idx + 1 == TOOLCHAIN_BINARIES.len() {
+            progress.finish()?;
+        } else {
+            progress.set_percent(step * (idx + 1) as f32)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(std::fs::metadata(path)?.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(windows)]
+fn is_executable(_path: &Path) -> Result<bool> {
+    Ok(true)
+}
+
 fn ensure_rustup(
     config: &InstallConfiguration,
     manifest: &ToolsetManifest,
@@ -164,7 +213,7 @@ fn ensure_rustup(
             download_rustup_init(
                 &rustup_init,
                 &config.rustup_update_root,
-                manifest.proxy.as_ref(),
+                config.proxy().as_ref(),
                 insecure,
             )?;
             (rustup_init, Some(temp_dir))
@@ -216,3 +265,39 @@ fn install_rustup(rustup_init: &PathBuf) -> Result<()> {
     cmd.args(args);
     utils::execute(cmd)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Progress;
+
+    fn fake_toolchain(binaries: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let bin_dir = dir.path().join("bin");
+        utils::ensure_dir(&bin_dir).unwrap();
+        for name in binaries {
+            let path = bin_dir.join(format!("{name}{}", std::env::consts::EXE_SUFFIX));
+            std::fs::write(&path, "").unwrap();
+            set_exec_permission(&path).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn verify_complete_toolchain() {
+        let dir = fake_toolchain(TOOLCHAIN_BINARIES);
+        let progress = Progress::noop();
+        verify_install_with_progress(dir.path(), &progress.child(30.0)).unwrap();
+        assert_eq!(progress.position(), 30.0);
+    }
+
+    #[test]
+    fn verify_toolchain_with_missing_binary() {
+        let dir = fake_toolchain(&["rustc", "cargo"]);
+        let progress = Progress::noop();
+        let err = verify_install_with_progress(dir.path(), &progress.child(30.0)).unwrap_err();
+        assert!(err.to_string().contains("'rustdoc' does not exist"));
+        // the first two binaries were verified
+        assert_eq!(progress.position(), 20.0);
+    }
+}
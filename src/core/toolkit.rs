@@ -7,7 +7,6 @@ use crate::{components, utils};
 use anyhow::Result;
 use semver::Version;
 use serde::Serialize;
-use url::Url;
 
 use super::parser::dist_manifest::DistPackage;
 
@@ -105,19 +104,15 @@ pub(crate) fn toolkits_from_server(insecure: bool) -> Result<&'static [Toolkit]>
         return Ok(cached);
     }
 
-    let dist_server_env_ovr = std::env::var("RIM_DIST_SERVER");
-    let dist_server = dist_server_env_ovr
-        .as_deref()
-        .unwrap_or(super::RIM_DIST_SERVER);
-
     // download dist manifest from server
     let dist_m_filename = DistManifest::FILENAME;
     info!("{} {dist_m_filename}", t!("fetching"));
-    let dist_m_url = Url::parse(&format!("{dist_server}/dist/{dist_m_filename}"))?;
     let dist_m_file = utils::make_temp_file("dist-manifest-", None)?;
-    utils::DownloadOpt::new("distribution manifest")
-        .insecure(insecure)
-        .download_file(&dist_m_url, dist_m_file.path(), false)?;
+    super::mirrors::dist_server()?.download(
+        &format!("dist/{dist_m_filename}"),
+        utils::DownloadOpt::new("distribution manifest").insecure(insecure),
+        dist_m_file.path(),
+    )?;
     debug!("distribution manifest file successfully downloaded!");
 
     // load dist "pacakges" then convert them into `toolkit`s
@@ -150,6 +150,9 @@ impl<'a> Tool<'a> {
             ToolKind::Executables => {
                 let mut res = vec![];
                 for exe in self.path.iter() {
+                    if let Some(name) = exe.file_name() {
+                        config.journal(|tx| tx.will_write(&config.cargo_bin().join(name)))?;
+                    }
                     res.push(utils::copy_file_to(exe, config.cargo_bin())?);
                 }
                 res
@@ -166,6 +169,9 @@ impl<'a> Tool<'a> {
                 // run the installation command.
                 Plugin::install(path)?;
                 // we need to "cache" to installer, so that we could uninstall with it.
+                if let Some(name) = path.file_name() {
+                    config.journal(|tx| tx.will_write(&config.tools_dir().join(name)))?;
+                }
                 let plugin_backup = utils::copy_file_to(path, config.tools_dir())?;
                 vec![plugin_backup]
             }
@@ -224,6 +230,7 @@ fn cargo_install_or_uninstall(op: &str, args: &[&str], cargo_home: &Path) -> Res
 /// Move one path (file/dir) to a new folder with `name` under tools dir.
 fn move_to_tools(config: &InstallConfiguration, name: &str, path: &Path) -> Result<PathBuf> {
     let dir = config.tools_dir().join(name);
+    config.journal(|tx| tx.will_replace(&dir))?;
     utils::move_to(path, &dir, true)?;
     Ok(dir)
 }
@@ -238,7 +245,7 @@ fn install_dir_with_bin_(
 ) -> Result<PathBuf> {
     let dir = move_to_tools(config, name, path)?;
     let bin_dir_after_move = dir.join("bin");
-    super::os::add_to_path(&bin_dir_after_move)?;
+    config.add_to_path(&bin_dir_after_move)?;
     Ok(dir)
 }
 
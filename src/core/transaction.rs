@@ -0,0 +1,402 @@
+//! Transactions of installations, which record every change made to the user's machine
+//! (such as creating files and modifying environment variables) in a journal as they are
+//! applied, so that they can be reverted once the installation fails midway, or later with
+//! the `rollback` command.
+//!
+//! Only the last transaction is kept, under the `.transaction` directory of the installation,
+//! along with the backups of the files it replaced.
+//!
+//! NOTE: Updating an installed toolchain is not reverted, because the toolchains are managed
+//! by `rustup`, only the installation record is.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use super::parser::fingerprint::InstallationRecord;
+use super::parser::TomlParser;
+use super::tools::{Tool, ToolKind};
+use super::uninstall::UninstallConfiguration;
+use crate::utils;
+
+/// The directory under installation root that holds the journal and backups.
+const TRANSACTION_DIR: &str = ".transaction";
+
+/// A change made by a [`Transaction`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case")]
+pub(crate) enum Change {
+    /// A file or directory was created, which is removed on rollback.
+    Created { path: PathBuf },
+    /// A file or directory was replaced, the original one is kept as `backup`, which is
+    /// restored on rollback.
+    Replaced { path: PathBuf, backup: PathBuf },
+    /// A directory was added to the `PATH` variable.
+    AddedToPath { path: PathBuf },
+    /// An persistent environment variable was set, which had the `previous` value.
+    ///
+    /// This is only recorded on Windows, as the variables are written into shell profiles
+    /// on other platforms, and those are recorded as [`Replaced`](Change::Replaced).
+    SetEnvVar {
+        key: String,
+        previous: Option<String>,
+    },
+    /// This program was added to the "installed programs" of Windows.
+    AddedToPrograms,
+    /// A tool was installed, it's uninstalled on rollback if its installation did more than
+    /// placing files into the installation directory, such as `cargo install`.
+    InstalledTool {
+        name: String,
+        kind: ToolKind,
+        #[serde(default)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Created { path } => write!(f, "create '{}'", path.display()),
+            Self::Replaced { path, .. } => write!(f, "replace '{}'", path.display()),
+            Self::AddedToPath { path } => write!(f, "add '{}' to PATH", path.display()),
+            Self::SetEnvVar { key, .. } => write!(f, "set environment variable '{key}'"),
+            Self::AddedToPrograms => f.write_str("add to the installed programs"),
+            Self::InstalledTool { name, .. } => write!(f, "install '{name}'"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Journal {
+    /// `true` once every change of the transaction was applied successfully.
+    #[serde(default)]
+    committed: bool,
+    #[serde(default)]
+    changes: Vec<Change>,
+}
+
+impl TomlParser for Journal {
+    const FILENAME: &str = "journal.toml";
+}
+
+/// A journal of the changes made by an installation (or update) in `install_dir`,
+/// which is saved after each recorded change, so it survives a crash of the program.
+///
+/// Each `will_*` method must be called *before* the change is made, because it inspects
+/// (and backs up) the current state of the path.
+#[derive(Debug)]
+pub(crate) struct Transaction {
+    install_dir: PathBuf,
+    journal: Mutex<Journal>,
+}
+
+impl Transaction {
+    /// Start a new transaction of the installation in `install_dir`, which discards the last
+    /// one, `created_root` means the `install_dir` was created by this installation.
+    pub(crate) fn begin(install_dir: &Path, created_root: bool) -> Result<Self> {
+        let tx = Self {
+            install_dir: install_dir.to_path_buf(),
+            journal: Mutex::new(Journal::default()),
+        };
+        let dir = tx.dir();
+        utils::remove(&dir)?;
+        utils::ensure_dir(dir.join("backup"))?;
+
+        if created_root {
+            tx.record(Change::Created {
+                path: install_dir.to_path_buf(),
+            })?;
+        } else {
+            for sub_dir in [".cargo", ".rustup", "tools"] {
+                tx.will_create(&install_dir.join(sub_dir))?;
+            }
+            tx.will_write(&install_dir.join(InstallationRecord::FILENAME))?;
+        }
+        tx.save()?;
+        Ok(tx)
+    }
+
+    /// Load the last transaction of the installation in `install_dir`, if there's one.
+    pub(crate) fn last(install_dir: &Path) -> Result<Option<Self>> {
+        let path = install_dir.join(TRANSACTION_DIR).join(Journal::FILENAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            install_dir: install_dir.to_path_buf(),
+            journal: Mutex::new(Journal::load(&path)?),
+        }))
+    }
+
+    /// Returns `true` if every change of the transaction was applied successfully.
+    pub(crate) fn is_committed(&self) -> bool {
+        self.journal.lock().unwrap().committed
+    }
+
+    /// Get the recorded changes, in the order they were made.
+    pub(crate) fn changes(&self) -> Vec<Change> {
+        self.journal.lock().unwrap().changes.clone()
+    }
+
+    /// Returns `true` if rolling back removes the whole installation directory,
+    /// which means this transaction was the first installation.
+    pub(crate) fn removes_install_dir(&self) -> bool {
+        self.journal
+            .lock()
+            .unwrap()
+            .changes
+            .iter()
+            .any(|change| matches!(change, Change::Created { path } if path == &self.install_dir))
+    }
+
+    /// Add a change to the journal.
+    pub(crate) fn record(&self, change: Change) -> Result<()> {
+        self.journal.lock().unwrap().changes.push(change);
+        self.save()
+    }
+
+    /// Record that `path` is about to be created, unless it already exists.
+    pub(crate) fn will_create(&self, path: &Path) -> Result<()> {
+        if path.exists() || self.covers(path) {
+            return Ok(());
+        }
+        self.record(Change::Created {
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Record that the file at `path` is about to be written, the current file (if any)
+    /// is copied into the backups.
+    pub(crate) fn will_write(&self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return self.will_create(path);
+        }
+        if self.covers(path) {
+            return Ok(());
+        }
+        let backup = self.backup_path(path);
+        utils::copy_as(path, &backup)?;
+        self.record(Change::Replaced {
+            path: path.to_path_buf(),
+            backup,
+        })
+    }
+
+    /// Record that `path` is about to be replaced, the current file or directory (if any)
+    /// is moved into the backups.
+    pub(crate) fn will_replace(&self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return self.will_create(path);
+        }
+        if self.covers(path) {
+            return Ok(());
+        }
+        let backup = self.backup_path(path);
+        utils::move_to(path, &backup, true)?;
+        self.record(Change::Replaced {
+            path: path.to_path_buf(),
+            backup,
+        })
+    }
+
+    /// Mark the transaction as completed successfully, it's still kept so that it can be
+    /// reverted with the `rollback` command.
+    pub(crate) fn commit(&self) -> Result<()> {
+        self.journal.lock().unwrap().committed = true;
+        self.save()
+    }
+
+    /// Revert every change in the reverse order they were made, then discard the transaction.
+    ///
+    /// Changes that cannot be reverted are reported as warnings, without stopping the others
+    /// from being reverted.
+    pub(crate) fn rollback(self) -> Result<()> {
+        let changes = std::mem::take(&mut self.journal.lock().unwrap().changes);
+        let mut failed = 0;
+        for change in changes.iter().rev() {
+            debug!("reverting: {change}");
+            if let Err(e) = self.revert(change) {
+                warn!(
+                    "{}",
+                    t!("rollback_change_failed", change = change, reason = e)
+                );
+                failed += 1;
+            }
+        }
+        // NB: the directory is already gone if the whole installation was removed
+        utils::remove(self.dir())?;
+
+        if failed > 0 {
+            bail!(
+                "{failed} of {} changes could not be reverted",
+                changes.len()
+            );
+        }
+        Ok(())
+    }
+
+    fn revert(&self, change: &Change) -> Result<()> {
+        match change {
+            Change::Created { path } => utils::remove(path),
+            Change::Replaced { path, backup } => {
+                if backup.is_dir() {
+                    utils::move_to(backup, path, true)
+                } else {
+                    utils::remove(path)?;
+                    utils::copy_as(backup, path)
+                }
+            }
+            Change::AddedToPath { path } => super::os::remove_from_path(path),
+            #[cfg(windows)]
+            Change::SetEnvVar { key, previous } => {
+                super::os::windows::restore_env_var(key, previous.as_deref())
+            }
+            #[cfg(windows)]
+            Change::AddedToPrograms => {
+                super::os::windows::do_remove_from_programs(super::os::windows::uninstall_entry())
+            }
+            #[cfg(not(windows))]
+            Change::SetEnvVar { .. } | Change::AddedToPrograms => Ok(()),
+            Change::InstalledTool { name, kind, paths } => {
+                let tool = match kind {
+                    ToolKind::CargoTool => Tool::cargo_tool(name, None),
+                    ToolKind::Custom | ToolKind::Plugin => {
+                        Tool::new(name.clone(), *kind).with_path(paths.clone())
+                    }
+                    // the files are reverted by the other changes
+                    _ => return Ok(()),
+                };
+                let config = UninstallConfiguration {
+                    install_dir: self.install_dir.clone(),
+                    install_record: InstallationRecord::load(&self.install_dir)?,
+                    progress_indicator: None,
+                };
+                tool.uninstall(&config)
+            }
+        }
+    }
+
+    /// Returns `true` if `path` (or one of its parents) was already recorded,
+    /// so that the backup won't be overwritten by a modified version.
+    fn covers(&self, path: &Path) -> bool {
+        self.journal
+            .lock()
+            .unwrap()
+            .changes
+            .iter()
+            .any(|change| match change {
+                Change::Created { path: p } | Change::Replaced { path: p, .. } => {
+                    path.starts_with(p)
+                }
+                _ => false,
+            })
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.install_dir.join(TRANSACTION_DIR)
+    }
+
+    fn backup_path(&self, path: &Path) -> PathBuf {
+        let idx = self.journal.lock().unwrap().changes.len();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.dir().join("backup").join(format!("{idx}-{name}"))
+    }
+
+    fn save(&self) -> Result<()> {
+        let content = self.journal.lock().unwrap().to_toml()?;
+        utils::write_file(self.dir().join(Journal::FILENAME), &content, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn rollback_reverts_changes() {
+        let root = tempfile::tempdir().unwrap();
+        let install_dir = root.path();
+        let tools_dir = install_dir.join("tools");
+        fs::create_dir(&tools_dir).unwrap();
+        let old_tool = tools_dir.join("foo");
+        fs::create_dir(&old_tool).unwrap();
+        fs::write(old_tool.join("old"), "old").unwrap();
+        let profile = install_dir.join("profile");
+        fs::write(&profile, "original").unwrap();
+
+        let tx = Transaction::begin(install_dir, false).unwrap();
+        tx.will_write(&profile).unwrap();
+        fs::write(&profile, "modified").unwrap();
+        tx.will_write(&profile).unwrap();
+        fs::write(&profile, "modified twice").unwrap();
+        tx.will_replace(&old_tool).unwrap();
+        assert!(!old_tool.exists());
+        fs::create_dir(&old_tool).unwrap();
+        fs::write(old_tool.join("new"), "new").unwrap();
+        let new_file = install_dir.join("new-file");
+        tx.will_create(&new_file).unwrap();
+        fs::write(&new_file, "").unwrap();
+
+        let tx = Transaction::last(install_dir).unwrap().unwrap();
+        assert!(!tx.is_committed());
+        assert!(!tx.removes_install_dir());
+        tx.rollback().unwrap();
+
+        assert_eq!(fs::read_to_string(&profile).unwrap(), "original");
+        assert!(old_tool.join("old").is_file());
+        assert!(!old_tool.join("new").exists());
+        assert!(!new_file.exists());
+        assert!(!install_dir.join(".cargo").exists());
+        assert!(Transaction::last(install_dir).unwrap().is_none());
+    }
+
+    #[test]
+    fn rollback_first_installation() {
+        let root = tempfile::tempdir().unwrap();
+        let install_dir = root.path().join("rust");
+        fs::create_dir(&install_dir).unwrap();
+
+        let tx = Transaction::begin(&install_dir, true).unwrap();
+        tx.will_create(&install_dir.join("tools")).unwrap();
+        tx.commit().unwrap();
+
+        let tx = Transaction::last(&install_dir).unwrap().unwrap();
+        assert!(tx.is_committed());
+        assert!(tx.removes_install_dir());
+        // covered by the installation directory
+        assert_eq!(tx.changes().len(), 1);
+        tx.rollback().unwrap();
+        assert!(!install_dir.exists());
+    }
+
+    #[test]
+    fn journal_format() {
+        let journal = Journal {
+            committed: true,
+            changes: vec![
+                Change::Created {
+                    path: PathBuf::from("/a"),
+                },
+                Change::SetEnvVar {
+                    key: "CARGO_HOME".into(),
+                    previous: None,
+                },
+                Change::InstalledTool {
+                    name: "foo".into(),
+                    kind: ToolKind::CargoTool,
+                    paths: vec![],
+                },
+            ],
+        };
+        let loaded = Journal::from_str(&journal.to_toml().unwrap()).unwrap();
+        assert!(loaded.committed);
+        assert_eq!(loaded.changes, journal.changes);
+    }
+}
@@ -56,11 +56,14 @@ impl<'a> UninstallConfiguration<'a> {
 
     pub fn uninstall(mut self, remove_self: bool) -> Result<()> {
         // remove all tools.
+        let phase = tracing::info_span!("phase", name = "tools").entered();
         info!("{}", t!("uninstalling_third_party_tools"));
         self.remove_tools(installed_tools_fresh(&self.install_dir)?, 40.0)?;
+        drop(phase);
 
         // Remove rust toolchain via rustup.
         if self.install_record.rust.is_some() {
+            let _phase = tracing::info_span!("phase", name = "toolchain").entered();
             ToolchainInstaller::init().remove_self(&self)?;
             self.install_record.remove_rust_record();
             self.install_record.write()?;
@@ -68,8 +71,10 @@ impl<'a> UninstallConfiguration<'a> {
         self.inc_progress(40.0)?;
 
         // remove all env configuration.
+        let phase = tracing::info_span!("phase", name = "env").entered();
         info!("{}", t!("uninstall_env_config"));
         self.remove_rustup_env_vars()?;
+        drop(phase);
         self.inc_progress(10.0)?;
 
         // remove the manager binary itself or update install record
@@ -130,8 +135,10 @@ impl<'a> UninstallConfiguration<'a> {
         tools_to_uninstall.sort_by(|a, b| b.kind.cmp(&a.kind));
 
         for tool in tools_to_uninstall {
+            let _component = tracing::info_span!("component", name = tool.name()).entered();
             info!("{}", t!("uninstalling_for", name = tool.name()));
-            if tool.uninstall(self).is_err() {
+            if let Err(e) = tool.uninstall(self) {
+                debug!("unable to uninstall '{}': {e:?}", tool.name());
                 info!(
                     "{}: {}",
                     t!("uninstall_tool_skipped", tool = tool.name()),
@@ -1,10 +1,10 @@
-use std::env;
+use std::fmt::Display;
 use std::path::Path;
 use std::sync::OnceLock;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
 use semver::Version;
-use url::Url;
 
 use super::directories::RimDir;
 use super::parser::release_info::ReleaseInfo;
@@ -13,10 +13,49 @@ use crate::{setter, utils};
 
 /// Caching the latest manager release info, reduce the number of time accessing the server.
 static LATEST_RELEASE: OnceLock<ReleaseInfo> = OnceLock::new();
+/// Same as [`LATEST_RELEASE`], but for the [`Beta`](ReleaseChannel::Beta) channel.
+static LATEST_BETA_RELEASE: OnceLock<ReleaseInfo> = OnceLock::new();
+
+/// The release channel to get manager updates from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReleaseChannel {
+    /// The stable releases.
+    #[default]
+    Stable,
+    /// The pre-releases, which are published before stable ones for testing.
+    Beta,
+}
+
+impl ReleaseChannel {
+    /// The directory of this channel's releases on the dist server.
+    fn server_dir(&self) -> &'static str {
+        match self {
+            Self::Stable => "manager",
+            Self::Beta => "manager/beta",
+        }
+    }
+
+    fn cached_release(&self) -> &'static OnceLock<ReleaseInfo> {
+        match self {
+            Self::Stable => &LATEST_RELEASE,
+            Self::Beta => &LATEST_BETA_RELEASE,
+        }
+    }
+}
+
+impl Display for ReleaseChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stable => f.write_str("stable"),
+            Self::Beta => f.write_str("beta"),
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct UpdateOpt {
     insecure: bool,
+    channel: ReleaseChannel,
 }
 
 impl RimDir for UpdateOpt {
@@ -27,10 +66,11 @@ impl RimDir for UpdateOpt {
 
 impl UpdateOpt {
     pub fn new() -> Self {
-        Self { insecure: false }
+        Self::default()
     }
 
     setter!(insecure(self.insecure, bool));
+    setter!(with_channel(self.channel, ReleaseChannel));
 
     /// Calls a function to update toolkit.
     ///
@@ -48,10 +88,14 @@ impl UpdateOpt {
 
     /// Update self when applicable.
     ///
+    /// The binary of the latest release (in the given [`ReleaseChannel`]) for current platform
+    /// is downloaded along with its `.sha256` checksum file, and is verified before replacing
+    /// the running executable.
+    ///
     /// If the program is succesfully updated, this will return `Ok(true)`,
     /// which indicates the program should be restarted.
     pub fn self_update(&self) -> Result<bool> {
-        if !check_self_update(self.insecure).update_needed() {
+        if !check_self_update_in(self.channel, self.insecure).update_needed() {
             info!(
                 "{}",
                 t!(
@@ -68,11 +112,12 @@ impl UpdateOpt {
         let cli = "";
 
         let src_name = utils::exe!(format!("{}-manager{cli}", t!("vendor_en")));
-        let latest_version = &latest_manager_release(self.insecure)?.version;
-        let download_url = parse_download_url(&format!(
-            "manager/archive/{latest_version}/{}/{src_name}",
+        let latest_version = &latest_manager_release(self.channel, self.insecure)?.version;
+        let source_path = format!(
+            "{}/archive/{latest_version}/{}/{src_name}",
+            self.channel.server_dir(),
             env!("TARGET"),
-        ))?;
+        );
 
         info!(
             "{}",
@@ -86,9 +131,34 @@ impl UpdateOpt {
         // dest file don't need the `-cli` suffix to confuse users
         let dest_name = utils::exe!(format!("{}-manager", t!("vendor_en")));
         let newer_manager = temp_root.path().join(dest_name);
-        utils::download("latest manager", &download_url, &newer_manager)?;
+        let server = super::mirrors::dist_server()?;
+        server.download(
+            &source_path,
+            utils::DownloadOpt::new("latest manager").insecure(self.insecure),
+            &newer_manager,
+        )?;
+
+        // never replace self with a corrupted binary
+        let checksum = server
+            .read(
+                &format!("{source_path}.sha256"),
+                utils::DownloadOpt::new("latest manager checksum").insecure(self.insecure),
+            )
+            .map(|content| parse_checksum(&content).map(ToString::to_string));
+        match checksum {
+            Ok(Some(expected)) => utils::verify_sha256(&newer_manager, &expected)
+                .context("the downloaded manager binary is corrupted")?,
+            _ if utils::is_verify_skipped() => {
+                warn!("{}", t!("verify_skipped", name = src_name));
+            }
+            Ok(None) => bail!("invalid checksum file of the latest manager binary"),
+            Err(e) => return Err(e.context("unable to get the checksum of the latest manager")),
+        }
+        utils::set_exec_permission(&newer_manager)?;
 
-        // replace the current executable
+        // Replace the current executable. On Windows, a running executable cannot be
+        // overwritten or deleted, so it is moved aside, and then cleaned up by a sidecar
+        // process once the current process exits, `self_replace` handles all that for us.
         // TODO: restart GUI when available.
         self_replace::self_replace(newer_manager)?;
 
@@ -97,22 +167,32 @@ impl UpdateOpt {
     }
 }
 
+/// Get the checksum from the content of a `.sha256` file, which is in the format of
+/// `sha256sum` output, i.e. `<checksum>  <filename>`, or the checksum alone.
+fn parse_checksum(content: &str) -> Option<&str> {
+    content
+        .split_whitespace()
+        .next()
+        .filter(|sum| sum.len() == 64 && sum.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
 /// Try to get the manager's latest release infomation.
 ///
 /// This will try to access the internet upon first call in order to
 /// read the `release.toml` file from the server, and the result will be "cached" after.
-fn latest_manager_release(insecure: bool) -> Result<&'static ReleaseInfo> {
-    if let Some(release_info) = LATEST_RELEASE.get() {
+fn latest_manager_release(channel: ReleaseChannel, insecure: bool) -> Result<&'static ReleaseInfo> {
+    let cached = channel.cached_release();
+    if let Some(release_info) = cached.get() {
         return Ok(release_info);
     }
 
-    let download_url = parse_download_url(&format!("manager/{}", ReleaseInfo::FILENAME))?;
-    let raw = utils::DownloadOpt::new("manager release info")
-        .insecure(insecure)
-        .read(&download_url)?;
+    let raw = super::mirrors::dist_server()?.read(
+        &format!("{}/{}", channel.server_dir(), ReleaseInfo::FILENAME),
+        utils::DownloadOpt::new("manager release info").insecure(insecure),
+    )?;
     let release_info = ReleaseInfo::from_str(&raw)?;
 
-    Ok(LATEST_RELEASE.get_or_init(|| release_info))
+    Ok(cached.get_or_init(|| release_info))
 }
 
 pub enum SelfUpdateKind<'a> {
@@ -140,9 +220,14 @@ impl SelfUpdateKind<'_> {
 ///
 /// If the version info could not be fetched, this will return `false` otherwise.
 pub fn check_self_update(insecure: bool) -> SelfUpdateKind<'static> {
+    check_self_update_in(ReleaseChannel::Stable, insecure)
+}
+
+/// Same as [`check_self_update`], but compares with the latest release in `channel`.
+pub fn check_self_update_in(channel: ReleaseChannel, insecure: bool) -> SelfUpdateKind<'static> {
     info!("{}", t!("checking_manager_updates"));
 
-    let latest_version = match latest_manager_release(insecure) {
+    let latest_version = match latest_manager_release(channel, insecure) {
         Ok(release) => &release.version,
         Err(e) => {
             warn!("{}: {e}", t!("fetch_latest_manager_version_failed"));
@@ -160,16 +245,6 @@ pub fn check_self_update(insecure: bool) -> SelfUpdateKind<'static> {
     }
 }
 
-fn parse_download_url(source_path: &str) -> Result<Url> {
-    let base_obs_server: Url = env::var("RIM_DIST_SERVER")
-        .as_deref()
-        .unwrap_or(super::RIM_DIST_SERVER)
-        .parse()?;
-
-    debug!("parsing download url for '{source_path}' from server '{base_obs_server}'");
-    utils::url_join(&base_obs_server, source_path)
-}
-
 #[cfg(test)]
 mod tests {
     #[test]
@@ -190,4 +265,16 @@ mod tests {
         compare!("0.1.0-alpha.1" < "0.1.0-alpha.2");
         compare!("1.0.0" == "1.0.0");
     }
+
+    #[test]
+    fn checksum_file() {
+        let sum = "e0662228aae2b9b4ba7e1d8e0d3c8ef4a3b4db1d3f2cba5ab1dfcbe4c2a6fec7";
+        assert_eq!(super::parse_checksum(sum), Some(sum));
+        assert_eq!(
+            super::parse_checksum(&format!("{sum}  rim-manager-cli\n")),
+            Some(sum)
+        );
+        assert_eq!(super::parse_checksum("not found"), None);
+        assert_eq!(super::parse_checksum(""), None);
+    }
 }
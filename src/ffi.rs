@@ -0,0 +1,275 @@
+//! C-ABI bindings for hosts that embed rim, such as native GUIs written in C or Swift.
+//!
+//! The host creates a handle with [`rim_progress_new`], then either polls the progress with
+//! [`rim_progress_percentage`] and [`rim_progress_message`], or registers a callback with
+//! [`rim_progress_set_callback`]. The handle must be freed with [`rim_progress_free`].
+//!
+//! On the Rust side, [`RimProgressHandle::progress`] gives the [`Progress`] to pass to
+//! the installation APIs.
+//!
+//! This module is only available with the `ffi` feature.
+
+use std::ffi::{c_char, c_void, CString};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::utils::{Progress, ProgressObserver};
+
+/// A callback that receives the opaque `user_data` passed to [`rim_progress_set_callback`],
+/// the current percentage in range `0..=100`, and the message being displayed,
+/// or a null pointer if only the percentage has changed.
+///
+/// The message is only valid until the callback returns.
+pub type RimProgressCallback =
+    extern "C" fn(user_data: *mut c_void, percentage: f32, message: *const c_char);
+
+#[derive(Clone, Copy)]
+struct Callback {
+    func: RimProgressCallback,
+    user_data: *mut c_void,
+}
+
+// SAFETY: the host is responsible for making `user_data` usable from any thread,
+// as documented in `rim_progress_set_callback`.
+unsafe impl Send for Callback {}
+unsafe impl Sync for Callback {}
+
+#[derive(Default)]
+struct Shared {
+    message: Mutex<Option<CString>>,
+    callback: Mutex<Option<Callback>>,
+}
+
+/// Lock `mutex` even if a thread panicked while holding it, because panicking in the
+/// functions called by C code is undefined behavior, and the data is still consistent.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Run `f` that calls into rim, returns `None` if it panicked, so that the panic won't
+/// unwind into C code.
+fn catch_unwind<R>(f: impl FnOnce() -> R) -> Option<R> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).ok()
+}
+
+impl Shared {
+    /// Get a copy of the callback, so it's not locked while being called, which allows
+    /// the callback to replace itself.
+    fn callback(&self) -> Option<Callback> {
+        *lock(&self.callback)
+    }
+}
+
+struct FfiObserver(Arc<Shared>);
+
+impl ProgressObserver for FfiObserver {
+    fn on_pos(&self, pos: f32) {
+        if let Some(cb) = self.0.callback() {
+            (cb.func)(cb.user_data, pos, std::ptr::null());
+        }
+    }
+
+    fn on_msg(&self, msg: &str) {
+        // messages containing NUL can't be passed to C, the rest of it is still useful
+        let msg = CString::new(msg.split('\0').next().unwrap_or_default())
+            .expect("NUL bytes were removed");
+        if let Some(cb) = self.0.callback() {
+            (cb.func)(cb.user_data, f32::NAN, msg.as_ptr());
+        }
+        *lock(&self.0.message) = Some(msg);
+    }
+}
+
+/// An opaque handle owning a [`Progress`], which can be shared with C code.
+pub struct RimProgressHandle {
+    progress: Progress<'static>,
+    shared: Arc<Shared>,
+}
+
+impl RimProgressHandle {
+    pub fn new() -> Self {
+        let shared = Arc::new(Shared::default());
+        let progress = Progress::noop().with_observer(FfiObserver(shared.clone()));
+        Self { progress, shared }
+    }
+
+    /// Get the progress that is reported to the host.
+    pub fn progress(&self) -> &Progress<'static> {
+        &self.progress
+    }
+}
+
+impl Default for RimProgressHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Create a new progress handle, which must be freed by [`rim_progress_free`].
+#[no_mangle]
+pub extern "C" fn rim_progress_new() -> *mut RimProgressHandle {
+    Box::into_raw(Box::new(RimProgressHandle::new()))
+}
+
+/// Free a handle created by [`rim_progress_new`], does nothing if `handle` is null.
+///
+/// # Safety
+///
+/// `handle` must be null or returned by [`rim_progress_new`], and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rim_progress_free(handle: *mut RimProgressHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Advance the progress by `value`, returns `false` if the handle is null or the
+/// progress failed to update.
+///
+/// # Safety
+///
+/// `handle` must be null or a valid handle returned by [`rim_progress_new`].
+#[no_mangle]
+pub unsafe extern "C" fn rim_progress_inc(handle: *const RimProgressHandle, value: f32) -> bool {
+    let Some(handle) = handle.as_ref() else {
+        return false;
+    };
+    catch_unwind(|| handle.progress.inc(Some(value))).is_some_and(|res| res.is_ok())
+}
+
+/// Get the current percentage in range `0..=100`, or `0` if the handle is null.
+///
+/// # Safety
+///
+/// `handle` must be null or a valid handle returned by [`rim_progress_new`].
+#[no_mangle]
+pub unsafe extern "C" fn rim_progress_percentage(handle: *const RimProgressHandle) -> f32 {
+    handle
+        .as_ref()
+        .and_then(|h| catch_unwind(|| h.progress.position()))
+        .unwrap_or_default()
+}
+
+/// Copy the last message, including the terminating NUL, into `buf` of `buf_len` bytes,
+/// truncating it if needed.
+///
+/// Returns the length of the whole message without the NUL, so the message was truncated
+/// if the result is not less than `buf_len`. Returns `0` if there's no message yet.
+///
+/// # Safety
+///
+/// `handle` must be null or a valid handle returned by [`rim_progress_new`], and `buf`
+/// must be null or valid for writing `buf_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rim_progress_message(
+    handle: *const RimProgressHandle,
+    buf: *mut c_char,
+    buf_len: usize,
+) -> usize {
+    let Some(handle) = handle.as_ref() else {
+        return 0;
+    };
+    let message = lock(&handle.shared.message);
+    let bytes = message.as_deref().map(|m| m.to_bytes()).unwrap_or_default();
+    if !buf.is_null() && buf_len > 0 {
+        let copied = bytes.len().min(buf_len - 1);
+        std::ptr::copy_nonoverlapping(bytes.as_ptr().cast::<c_char>(), buf, copied);
+        *buf.add(copied) = 0;
+    }
+    bytes.len()
+}
+
+/// Register a `callback` that is called on every change, replacing the previous one,
+/// or pass null to remove it.
+///
+/// When a message is displayed, the callback receives `NaN` as the percentage,
+/// use [`rim_progress_percentage`] if needed. The callback may call the other functions
+/// with this handle, including replacing itself, except freeing it.
+///
+/// # Safety
+///
+/// `handle` must be null or a valid handle returned by [`rim_progress_new`].
+/// The callback may be called from any thread that updates the progress, so both the
+/// callback and `user_data` must be thread-safe, and `user_data` must remain valid
+/// until the callback is replaced or the handle is freed.
+#[no_mangle]
+pub unsafe extern "C" fn rim_progress_set_callback(
+    handle: *const RimProgressHandle,
+    callback: Option<RimProgressCallback>,
+    user_data: *mut c_void,
+) {
+    if let Some(handle) = handle.as_ref() {
+        *lock(&handle.shared.callback) = callback.map(|func| Callback { func, user_data });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    extern "C" fn record(user_data: *mut c_void, percentage: f32, message: *const c_char) {
+        // SAFETY: the tests below pass a pointer to a living `Mutex<Vec<_>>`
+        let events = unsafe { &*(user_data as *const Mutex<Vec<(f32, Option<String>)>>) };
+        let message = (!message.is_null()).then(|| {
+            unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned()
+        });
+        events.lock().unwrap().push((percentage, message));
+    }
+
+    extern "C" fn unregister(user_data: *mut c_void, _percentage: f32, _message: *const c_char) {
+        // SAFETY: the test below passes the handle as `user_data`
+        unsafe {
+            rim_progress_set_callback(user_data.cast(), None, std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn callback_replaces_itself() {
+        unsafe {
+            let handle = rim_progress_new();
+            rim_progress_set_callback(handle, Some(unregister), handle.cast());
+            assert!(rim_progress_inc(handle, 10.0));
+            assert!((*handle).shared.callback().is_none());
+            assert!(rim_progress_inc(handle, 10.0));
+            rim_progress_free(handle);
+        }
+    }
+
+    #[test]
+    fn round_trip_through_c_abi() {
+        let events: Mutex<Vec<(f32, Option<String>)>> = Mutex::default();
+        unsafe {
+            let handle = rim_progress_new();
+            assert_eq!(rim_progress_percentage(handle), 0.0);
+            rim_progress_set_callback(handle, Some(record), &events as *const _ as *mut c_void);
+
+            assert!(rim_progress_inc(handle, 30.0));
+            (*handle).progress().show_msg("downloading").unwrap();
+            assert!(rim_progress_inc(handle, 20.0));
+            assert_eq!(rim_progress_percentage(handle), 50.0);
+
+            let mut buf = [0 as c_char; 8];
+            assert_eq!(
+                rim_progress_message(handle, buf.as_mut_ptr(), buf.len()),
+                11
+            );
+            assert_eq!(CStr::from_ptr(buf.as_ptr()).to_str().unwrap(), "downloa");
+
+            rim_progress_set_callback(handle, None, std::ptr::null_mut());
+            assert!(rim_progress_inc(handle, 10.0));
+            rim_progress_free(handle);
+
+            assert_eq!(rim_progress_percentage(std::ptr::null()), 0.0);
+            assert!(!rim_progress_inc(std::ptr::null(), 1.0));
+        }
+
+        let events = events.into_inner().unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0], (30.0, None));
+        assert!(events[1].0.is_nan());
+        assert_eq!(events[1].1.as_deref(), Some("downloading"));
+        assert_eq!(events[2], (50.0, None));
+    }
+}
@@ -8,6 +8,8 @@ extern crate log;
 
 pub mod cli;
 mod core;
+#[cfg(any(test, feature = "ffi"))]
+pub mod ffi;
 pub mod utils;
 
 // Exports
@@ -15,6 +17,6 @@ pub use core::install::{default_install_dir, EnvConfig, InstallConfiguration};
 pub use core::parser::{fingerprint, get_installed_dir, toolset_manifest};
 pub use core::try_it::try_it;
 pub use core::uninstall::UninstallConfiguration;
-pub use core::{components, toolkit, update, Language};
+pub use core::{bundle, components, toolkit, update, Language};
 
 i18n!("locales", fallback = "en");
@@ -0,0 +1,309 @@
+//! A cache of downloaded files, so that identical archives are not downloaded again when
+//! reinstalling or switching toolkits.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use super::verify_sha256;
+use crate::core::install::downloadable_file_name;
+use crate::setter;
+
+/// The default size limit of a [`DownloadCache`], which is 4 GiB.
+pub const DEFAULT_CACHE_SIZE: u64 = 4 << 30;
+
+/// The file in each entry that records where the cached file was downloaded from,
+/// its modification time is the last time the entry was used.
+const SOURCE_FILE: &str = ".source";
+
+/// A file in [`DownloadCache`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// The url that the file was downloaded from.
+    pub url: String,
+    /// The expected SHA-256 checksum of the file, if there was one.
+    pub checksum: Option<String>,
+    /// The path of the cached file.
+    pub path: PathBuf,
+    /// The size in bytes.
+    pub size: u64,
+    pub last_used: SystemTime,
+}
+
+/// A content-addressed cache of downloaded files, keyed by their url and (optional) checksum,
+/// so that a new checksum of the same url never gets the old file.
+///
+/// Each entry is a directory (named after the hash of its key) under [`dir`](DownloadCache::dir),
+/// containing the cached file. Once the total size exceeds the
+/// [`max_size`](DownloadCache::max_size), the least recently used entries are removed.
+#[derive(Debug, Clone)]
+pub struct DownloadCache {
+    dir: PathBuf,
+    max_size: Option<u64>,
+}
+
+impl DownloadCache {
+    /// Create a cache in `dir`, with a size limit of [`DEFAULT_CACHE_SIZE`].
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            max_size: Some(DEFAULT_CACHE_SIZE),
+        }
+    }
+
+    // limit the total size of cached files, `None` means no limit
+    setter!(with_max_size(self.max_size, Option<u64>));
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn max_size(&self) -> Option<u64> {
+        self.max_size
+    }
+
+    /// Get the path of the cached file of `url` with `checksum`, if there is one.
+    pub fn get(&self, url: &Url, checksum: Option<&str>) -> Option<PathBuf> {
+        let entry_dir = self.entry_dir(url, checksum);
+        let path = entry_dir.join(file_name(url));
+        if !path.is_file() {
+            return None;
+        }
+        // refresh the last used time
+        if let Err(e) = write_source(&entry_dir, url, checksum) {
+            debug!(
+                "unable to update cache entry '{}': {e}",
+                entry_dir.display()
+            );
+        }
+        Some(path)
+    }
+
+    /// Copy the cached file of `url` to `dest`, returns `false` if it's not cached.
+    ///
+    /// If there's a `checksum`, the copied file is verified against it, and the entry is
+    /// removed if it doesn't match (such as the cached file got corrupted).
+    pub fn restore(&self, url: &Url, checksum: Option<&str>, dest: &Path) -> Result<bool> {
+        let Some(cached) = self.get(url, checksum) else {
+            return Ok(false);
+        };
+        super::copy_as(&cached, dest)?;
+        if let Some(expected) = checksum {
+            if let Err(e) = verify_sha256(dest, expected) {
+                warn!("{}", t!("cache_entry_corrupted", url = url, reason = e));
+                super::remove(self.entry_dir(url, checksum))?;
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Add a copy of the `file` downloaded from `url` to the cache, then remove the least
+    /// recently used entries if the cache exceeds its size limit.
+    ///
+    /// Files larger than the limit are not cached at all.
+    pub fn store(&self, url: &Url, checksum: Option<&str>, file: &Path) -> Result<()> {
+        let size = fs::metadata(file)
+            .with_context(|| format!("unable to read metadata of '{}'", file.display()))?
+            .len();
+        if self.max_size.is_some_and(|max| size > max) {
+            return Ok(());
+        }
+
+        let entry_dir = self.entry_dir(url, checksum);
+        // copy into a temporary directory first, so an interrupted copy won't be a hit
+        let partial = entry_dir.with_extension("partial");
+        super::remove(&partial)?;
+        super::ensure_dir(&partial)?;
+        super::copy_as(file, partial.join(file_name(url)))?;
+        write_source(&partial, url, checksum)?;
+        super::remove(&entry_dir)?;
+        fs::rename(&partial, &entry_dir).with_context(|| {
+            format!("unable to add '{}' to download cache", entry_dir.display())
+        })?;
+
+        if let Some(max) = self.max_size {
+            self.shrink_to(max)?;
+        }
+        Ok(())
+    }
+
+    /// Get every cached file, the most recently used first.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        if !self.dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut entries = vec![];
+        for maybe_dir in fs::read_dir(&self.dir)? {
+            let entry_dir = maybe_dir?.path();
+            match read_entry(&entry_dir) {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) => (),
+                Err(e) => debug!(
+                    "skipping invalid cache entry '{}': {e}",
+                    entry_dir.display()
+                ),
+            }
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+        Ok(entries)
+    }
+
+    /// Get the total size of cached files in bytes.
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.entries()?.iter().map(|entry| entry.size).sum())
+    }
+
+    /// Remove the least recently used entries until the total size is no more than `max`,
+    /// returns the number of bytes freed.
+    pub fn shrink_to(&self, max: u64) -> Result<u64> {
+        let mut entries = self.entries()?;
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        let mut freed = 0;
+        while total > max {
+            let Some(entry) = entries.pop() else {
+                break;
+            };
+            remove_entry(&entry)?;
+            total -= entry.size;
+            freed += entry.size;
+        }
+        Ok(freed)
+    }
+
+    /// Remove every cached file, returns the number of bytes freed.
+    pub fn clean(&self) -> Result<u64> {
+        self.shrink_to(0)
+    }
+
+    fn entry_dir(&self, url: &Url, checksum: Option<&str>) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(url.as_str());
+        if let Some(checksum) = checksum {
+            hasher.update("\n");
+            hasher.update(checksum.to_ascii_lowercase());
+        }
+        self.dir.join(format!("{:x}", hasher.finalize()))
+    }
+}
+
+/// Get the name of the file that `url` points to, so the cached file keeps its extension.
+fn file_name(url: &Url) -> &str {
+    downloadable_file_name(url).unwrap_or("file")
+}
+
+fn write_source(entry_dir: &Path, url: &Url, checksum: Option<&str>) -> Result<()> {
+    let content = match checksum {
+        Some(checksum) => format!("{url}\n{}", checksum.to_ascii_lowercase()),
+        None => url.to_string(),
+    };
+    super::write_file(entry_dir.join(SOURCE_FILE), &content, false)
+}
+
+fn read_entry(entry_dir: &Path) -> Result<Option<CacheEntry>> {
+    let source = entry_dir.join(SOURCE_FILE);
+    // such as a partial entry
+    if entry_dir.extension().is_some() || !source.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&source)?;
+    let mut lines = content.lines();
+    let Some(url) = lines.next().and_then(|line| Url::parse(line).ok()) else {
+        bail!("missing source url");
+    };
+    let path = entry_dir.join(file_name(&url));
+    let size = fs::metadata(&path)?.len();
+    Ok(Some(CacheEntry {
+        checksum: lines.next().map(ToString::to_string),
+        url: url.to_string(),
+        path,
+        size,
+        last_used: fs::metadata(&source)?.modified()?,
+    }))
+}
+
+fn remove_entry(entry: &CacheEntry) -> Result<()> {
+    match entry.path.parent() {
+        Some(entry_dir) => super::remove(entry_dir),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_file(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn keyed_by_url_and_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DownloadCache::new(dir.path().join("cache"));
+        let url = Url::parse("https://example.com/tool.tar.gz").unwrap();
+        let file = cache_file(dir.path(), "tool", "tool");
+        let checksum = "e0662228aae2b9b4ba7e1d8e0d3c8ef4a3b4db1d3f2cba5ab1dfcbe4c2a6fec7";
+
+        assert!(cache.get(&url, None).is_none());
+        cache.store(&url, Some(checksum), &file).unwrap();
+        assert!(cache.get(&url, None).is_none());
+        let cached = cache.get(&url, Some(&checksum.to_uppercase())).unwrap();
+        assert!(cached.ends_with("tool.tar.gz"));
+
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].url, url.as_str());
+        assert_eq!(entries[0].checksum.as_deref(), Some(checksum));
+        assert_eq!(cache.size().unwrap(), 4);
+
+        // a corrupted file is removed
+        let dest = dir.path().join("restored");
+        assert!(!cache.restore(&url, Some(checksum), &dest).unwrap());
+        assert!(cache.entries().unwrap().is_empty());
+
+        cache.store(&url, None, &file).unwrap();
+        assert!(cache.restore(&url, None, &dest).unwrap());
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "tool");
+        assert_eq!(cache.clean().unwrap(), 4);
+        assert!(cache.get(&url, None).is_none());
+    }
+
+    #[test]
+    fn least_recently_used_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DownloadCache::new(dir.path().join("cache")).with_max_size(Some(10));
+        let urls = ["a", "b", "c", "d"]
+            .map(|name| Url::parse(&format!("https://example.com/{name}")).unwrap());
+        let file = cache_file(dir.path(), "file", "1234");
+
+        cache.store(&urls[0], None, &file).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.store(&urls[1], None, &file).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        // `a` becomes the most recently used one
+        assert!(cache.get(&urls[0], None).is_some());
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.store(&urls[2], None, &file).unwrap();
+
+        let cached = cache
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.url)
+            .collect::<Vec<_>>();
+        assert_eq!(cached, [urls[2].as_str(), urls[0].as_str()]);
+
+        // too large to be cached
+        let large = cache_file(dir.path(), "large", "12345678901");
+        cache.store(&urls[3], None, &large).unwrap();
+        assert!(cache.get(&urls[3], None).is_none());
+        assert_eq!(cache.entries().unwrap().len(), 2);
+    }
+}
@@ -1,15 +1,28 @@
 use std::cmp::min;
 use std::fs::{self, OpenOptions};
+use std::future::Future;
 use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context, Result};
-use indicatif::ProgressBar;
+use futures::StreamExt;
+use indicatif::ProgressDrawTarget;
 use reqwest::blocking::Client;
+use tracing::Instrument;
 use url::Url;
 
-use super::progress_bar::{CliProgress, Style};
+use super::extraction::Extractable;
+use super::progress_bar::{
+    AggregateProgress, AggregateTask, CliBar, CliProgress, CliProgressOpt, MultiCliProgress, Phase,
+    Progress, ProgressMsg, StepProgress, Style,
+};
+use super::throttle::Throttled;
+use super::verify::verify_sha256;
+use super::{CancellationToken, Cancelled, RateLimiter, RetryPolicy};
+use crate::core::install::downloadable_file_name;
 use crate::core::GlobalOpts;
 use crate::setter;
 use crate::toolset_manifest::Proxy as CrateProxy;
@@ -19,7 +32,134 @@ fn default_proxy() -> reqwest::Proxy {
         .no_proxy(reqwest::NoProxy::from_env())
 }
 
-#[derive(Debug)]
+/// Check if sending a request failed because the proxy rejected its credentials, such as a
+/// `407` response to the tunneling request, or a failed SOCKS5 authentication.
+fn is_proxy_auth_error(err: &reqwest::Error) -> bool {
+    let mut source: Option<&dyn std::error::Error> = Some(err);
+    while let Some(e) = source {
+        let msg = e.to_string().to_ascii_lowercase();
+        if msg.contains("proxy authentication required")
+            || (msg.contains("socks") && msg.contains("auth"))
+        {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+/// The default amount of time to wait before a stalled download is aborted.
+pub const DEFAULT_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The timeout set by [`DownloadOpt::set_default_timeout`].
+static DEFAULT_TIMEOUT: Mutex<Option<Duration>> = Mutex::new(None);
+
+/// Errors that need to be distinguished from others when downloading.
+///
+/// These are wrapped in [`anyhow::Error`], use [`downcast_ref`](anyhow::Error::downcast_ref)
+/// to check them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadError {
+    /// No data has been received from the server for a certain amount of time,
+    /// such error is temporary, so the download can be retried.
+    Timeout { url: String, after: Duration },
+    /// The server responded with an unsuccessful HTTP status code, such as `404`.
+    Status { url: String, status: u16 },
+    /// The proxy (as `host:port`, if known) rejected the request because of missing or
+    /// wrong credentials, which are the username and password of the proxy url.
+    ProxyAuth { url: String, proxy: Option<String> },
+}
+
+impl std::fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Timeout { url, after } => write!(
+                f,
+                "download from '{url}' timed out, no data received in {}s",
+                after.as_secs_f32()
+            ),
+            Self::Status { url, status } => {
+                write!(f, "server returns error when requesting '{url}': {status}")?;
+                if let Some(reason) = reqwest::StatusCode::from_u16(*status)
+                    .ok()
+                    .and_then(|code| code.canonical_reason())
+                {
+                    write!(f, " {reason}")?;
+                }
+                Ok(())
+            }
+            Self::ProxyAuth { url, proxy } => {
+                write!(f, "proxy authentication failed when requesting '{url}'")?;
+                if let Some(proxy) = proxy {
+                    write!(f, " via '{proxy}'")?;
+                }
+                write!(
+                    f,
+                    ", please check the username and password in the proxy url"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+impl DownloadError {
+    fn timeout(url: &Url, after: Duration) -> anyhow::Error {
+        Self::Timeout {
+            url: url.to_string(),
+            after,
+        }
+        .into()
+    }
+
+    fn status(url: &Url, status: reqwest::StatusCode) -> anyhow::Error {
+        Self::Status {
+            url: url.to_string(),
+            status: status.as_u16(),
+        }
+        .into()
+    }
+
+    fn proxy_auth(url: &Url, proxy: Option<String>) -> anyhow::Error {
+        Self::ProxyAuth {
+            url: url.to_string(),
+            proxy,
+        }
+        .into()
+    }
+
+    /// Returns `true` if the error is likely temporary, or caused by the server rather
+    /// than the requested file, such as a timeout, a `5xx` status or a refused connection,
+    /// so the same request might succeed later, or on another server.
+    pub fn is_temporary(err: &anyhow::Error) -> bool {
+        match err.downcast_ref::<DownloadError>() {
+            Some(Self::Timeout { .. }) => return true,
+            Some(Self::Status { status, .. }) => return *status >= 500,
+            Some(Self::ProxyAuth { .. }) => return false,
+            None => (),
+        }
+        err.downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_timeout() || e.is_connect())
+    }
+}
+
+/// The default number of files that [`DownloadOpt::download_all`] downloads at the same time.
+pub const DEFAULT_DOWNLOAD_JOBS: usize = 4;
+
+/// The default size of each chunk of [`DownloadOpt::download_chunked`].
+pub const DEFAULT_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// The SHA-256 digest of the first `len` bytes of a file, which is used to check
+/// whether a partially downloaded file can be resumed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixDigest {
+    pub len: u64,
+    /// The digest in lowercase hex string.
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone)]
 pub struct DownloadOpt<T: Sized> {
     /// The verbose name of the file to download.
     pub name: String,
@@ -27,11 +167,27 @@ pub struct DownloadOpt<T: Sized> {
     pub handler: Option<CliProgress<T>>,
     /// Option to skip SSL certificate verification when downloading.
     pub insecure: bool,
-    /// Proxy configurations for download.
+    /// Proxy configurations for download, the [`Proxy::global`](CrateProxy::global) one
+    /// takes precedence if there is.
     pub proxy: Option<CrateProxy>,
+    /// Abort the download if no data arrives for this amount of time,
+    /// default is [`DEFAULT_DOWNLOAD_TIMEOUT`].
+    pub timeout: Duration,
+    /// The expected digest of the partial file before resuming a download.
+    pub prefix_digest: Option<PrefixDigest>,
+    /// Stops the download once cancelled, the partially downloaded file will be removed.
+    ///
+    /// Downloads with a [`Progress`] also stop once the progress is cancelled.
+    pub cancel: CancellationToken,
+    /// How to retry requests that failed with temporary errors, such as timeouts,
+    /// default is [`RetryPolicy::global`].
+    pub retry: RetryPolicy,
+    /// Limits the bandwidth, which is shared by every download using the same limiter,
+    /// default is [`RateLimiter::global`].
+    pub rate_limit: Option<Arc<RateLimiter>>,
 }
 
-impl DownloadOpt<ProgressBar> {
+impl DownloadOpt<CliBar> {
     pub fn new<S: ToString>(name: S) -> Self {
         let handler = (!GlobalOpts::get().quiet).then_some(CliProgress::new());
         Self {
@@ -39,30 +195,174 @@ impl DownloadOpt<ProgressBar> {
             handler,
             insecure: false,
             proxy: None,
+            timeout: DEFAULT_TIMEOUT
+                .lock()
+                .unwrap()
+                .unwrap_or(DEFAULT_DOWNLOAD_TIMEOUT),
+            prefix_digest: None,
+            cancel: CancellationToken::new(),
+            retry: RetryPolicy::global(),
+            rate_limit: RateLimiter::global(),
         }
     }
 
+    /// Set the timeout used by [`new`](DownloadOpt::new) instead of
+    /// [`DEFAULT_DOWNLOAD_TIMEOUT`], such as the one configured by command line options.
+    pub fn set_default_timeout(timeout: Duration) {
+        *DEFAULT_TIMEOUT.lock().unwrap() = Some(timeout);
+    }
+
     setter!(with_proxy(self.proxy, Option<CrateProxy>));
-    setter!(with_handler(self.handler, Option<CliProgress<ProgressBar>>));
+    setter!(with_handler(self.handler, Option<CliProgress<CliBar>>));
     setter!(insecure(self.insecure, bool));
+    setter!(with_timeout(self.timeout, Duration));
+    setter!(with_prefix_digest(self.prefix_digest, Option<PrefixDigest>));
+    setter!(with_cancellation_token(self.cancel, CancellationToken));
+    setter!(with_retry(self.retry, RetryPolicy));
+    setter!(with_rate_limit(self.rate_limit, Option<Arc<RateLimiter>>));
 
     /// Build and return a client for download
     fn client(&self) -> Result<Client> {
         let user_agent = format!("{}/{}", t!("vendor_en"), env!("CARGO_PKG_VERSION"));
-        let proxy = if let Some(p) = &self.proxy {
+        let proxy = if let Some(p) = &self.proxy() {
             p.try_into()?
         } else {
             default_proxy()
         };
+        // NB: the timeout of blocking client applies to each read, rather than the whole request
         let client = Client::builder()
             .user_agent(user_agent)
             .connect_timeout(Duration::from_secs(30))
+            .timeout(self.timeout)
             .danger_accept_invalid_certs(self.insecure)
             .proxy(proxy)
             .build()?;
         Ok(client)
     }
 
+    /// Build and return an async client for download, which has the same configuration as
+    /// the blocking one.
+    fn async_client(&self) -> Result<reqwest::Client> {
+        let user_agent = format!("{}/{}", t!("vendor_en"), env!("CARGO_PKG_VERSION"));
+        let proxy = if let Some(p) = &self.proxy() {
+            p.try_into()?
+        } else {
+            default_proxy()
+        };
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .connect_timeout(Duration::from_secs(30))
+            .read_timeout(self.timeout)
+            .danger_accept_invalid_certs(self.insecure)
+            .proxy(proxy)
+            .build()?;
+        Ok(client)
+    }
+
+    fn proxy(&self) -> Option<CrateProxy> {
+        CrateProxy::global().or_else(|| self.proxy.clone())
+    }
+
+    /// Get the proxy (as `host:port`, without credentials) used to download from `url`,
+    /// which is `None` if no proxy is used.
+    fn proxy_for(&self, url: &Url) -> Option<String> {
+        let proxy = self.proxy().unwrap_or_default().proxy_for(url)?;
+        let host = proxy.host_str()?;
+        Some(match proxy.port_or_known_default() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        })
+    }
+
+    /// Convert the error of sending a request to `url`, so that timeouts and proxy
+    /// authentication failures can be told apart from others.
+    fn send_error(&self, url: &Url, err: reqwest::Error) -> anyhow::Error {
+        if err.is_timeout() {
+            DownloadError::timeout(url, self.timeout)
+        } else if is_proxy_auth_error(&err) {
+            DownloadError::proxy_auth(url, self.proxy_for(url))
+        } else {
+            anyhow::Error::new(err).context(format!(
+                "failed to receive surver response when downloading from '{url}'"
+            ))
+        }
+    }
+
+    /// Convert an unsuccessful status of the response of `url` into [`DownloadError`].
+    fn status_error(&self, url: &Url, status: reqwest::StatusCode) -> anyhow::Error {
+        if status == reqwest::StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+            DownloadError::proxy_auth(url, self.proxy_for(url))
+        } else {
+            DownloadError::status(url, status)
+        }
+    }
+
+    /// Call `op` with the [`retry`](DownloadOpt::retry) policy, each retry is announced as a
+    /// warning, and as a message of the `progress` if there is one.
+    fn retrying<T, F>(&self, progress: Option<&Progress>, op: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+    {
+        let mut cancel = vec![&self.cancel];
+        cancel.extend(progress.map(Progress::cancellation_token));
+        self.retry.run(&cancel, op, |retry| {
+            let reason = retry.error.to_string();
+            let msg = ProgressMsg::Retrying {
+                name: &self.name,
+                attempt: retry.attempt,
+                max_attempts: retry.max_attempts,
+                delay: retry.delay,
+                reason: &reason,
+            };
+            warn!("{msg}");
+            progress.map_or(Ok(()), |p| p.show_msg(msg))
+        })
+    }
+
+    /// Check the partial file against the [`prefix_digest`](DownloadOpt::prefix_digest),
+    /// and return the number of bytes that can be resumed from.
+    ///
+    /// If the partial file is corrupted (or too short to be checked), it will be truncated,
+    /// so the download restarts from zero.
+    fn check_partial(&self, file: &mut fs::File) -> Result<u64> {
+        let partial_len = file.metadata()?.len();
+        let Some(expected) = &self.prefix_digest else {
+            return Ok(partial_len);
+        };
+        if partial_len == 0 {
+            return Ok(0);
+        }
+
+        let matched = partial_len >= expected.len && {
+            let maybe_indicator = self.handler.as_ref().and_then(|h| {
+                (h.start)(
+                    ProgressMsg::Verifying(&self.name).to_string(),
+                    Style::Bytes(expected.len),
+                    h.opt,
+                )
+                .ok()
+            });
+            let update = |hashed: u64| {
+                if let (Some(h), Some(indicator)) = (&self.handler, &maybe_indicator) {
+                    (h.update)(indicator, Some(hashed));
+                }
+            };
+            let digest = sha256_of_prefix(file, expected.len, update)?;
+            if let (Some(h), Some(indicator)) = (&self.handler, &maybe_indicator) {
+                (h.stop)(indicator, ProgressMsg::Verified(&self.name).to_string());
+            }
+            digest.eq_ignore_ascii_case(&expected.sha256)
+        };
+
+        if matched {
+            Ok(partial_len)
+        } else {
+            warn!("{}", t!("corrupted_partial_download", name = self.name));
+            file.set_len(0)?;
+            Ok(0)
+        }
+    }
+
     /// Consume self, and retrive text response by sending request to a given url.
     ///
     /// If the `url` is a local file, this will use [`read_to_string`](fs::read_to_string) to
@@ -85,97 +385,792 @@ impl DownloadOpt<ProgressBar> {
             warn!("{}", t!("insecure_download"));
         }
 
-        let resp = self
-            .client()?
-            .get(url.as_ref())
-            .send()
-            .with_context(|| format!("failed to receive surver response from '{url}'"))?;
-        if resp.status().is_success() {
-            Ok(resp.text()?)
-        } else {
-            bail!(
-                "unable to get text content of url '{url}': server responded with error {}",
-                resp.status()
-            );
-        }
+        let _span = download_span(url).entered();
+        debug!("reading {} from '{url}'", self.name);
+        let client = self.client()?;
+        self.retrying(None, || {
+            let resp = client
+                .get(url.as_ref())
+                .send()
+                .map_err(|e| self.send_error(url, e))?;
+            if resp.status().is_success() {
+                Ok(resp.text()?)
+            } else {
+                Err(self.status_error(url, resp.status()))
+            }
+        })
     }
     /// Consume self, and download from given `Url` to `Path`.
+    ///
+    /// If `resume` is `true`, the file is downloaded into `<path>.part` first, which is kept
+    /// when the download fails, so calling this again continues from where the previous
+    /// attempt left off by requesting the rest with the `Range` header, and the bar starts
+    /// from that position. The download restarts from zero if the server doesn't support
+    /// ranges, or the file has been changed on the server.
     // TODO: make local file download fancier
     pub fn download_file(self, url: &Url, path: &Path, resume: bool) -> Result<()> {
+        self.download_file_(url, path, resume, None, |_, _| Ok(()))
+    }
+
+    /// Consume self, and download from given `Url` to `Path`, while advancing the given
+    /// `progress` by its `len` based on the downloaded bytes.
+    pub fn download_with_progress(self, url: &Url, path: &Path, progress: &Progress) -> Result<()> {
+        self.download_advancing(url, path, progress, |_| progress.len())?;
+        Ok(())
+    }
+
+    /// Download from `url` to `path`, while advancing the `progress` by the amount returned
+    /// by `len_for` (which is called with the total size once it's known) proportionally,
+    /// returns the amount advanced.
+    fn download_advancing<F>(
+        self,
+        url: &Url,
+        path: &Path,
+        progress: &Progress,
+        len_for: F,
+    ) -> Result<f32>
+    where
+        F: Fn(u64) -> f32,
+    {
+        let mut reported = 0_f32;
+        self.download_file_(url, path, false, Some(progress), |pos, total| {
+            progress.report_transfer(pos, total)?;
+            let len = len_for(total);
+            let target = if total == 0 {
+                len
+            } else {
+                len * (pos as f64 / total as f64) as f32
+            };
+            if target > reported {
+                progress.inc(Some(target - reported))?;
+                reported = target;
+            }
+            Ok(())
+        })?;
+        Ok(reported)
+    }
+
+    /// Consume self, and download from given `Url` to `Path` by chunks of `chunk_size` bytes,
+    /// which are requested separately with the `Range` header.
+    ///
+    /// Completed chunks are recorded in a `<file name>.chunks` file next to `path`, so if the
+    /// download was interrupted (even by a restart of the process), calling this again only
+    /// fetches the missing chunks. This is more robust than resuming from a single offset
+    /// for connections that drop frequently, but requires the server to support ranges.
+    ///
+    /// The `progress` is advanced by its `len` based on the completed chunks, including the
+    /// ones completed previously. Unlike interruptions, cancelling the download removes the
+    /// partial file along with the chunk map.
+    pub fn download_chunked(
+        self,
+        url: &Url,
+        path: &Path,
+        chunk_size: u64,
+        progress: &Progress,
+    ) -> Result<()> {
         if url.scheme() == "file" {
-            fs::copy(
+            return self.download_with_progress(url, path, progress);
+        }
+        let chunk_size = chunk_size.max(1);
+        let _span = download_span(url).entered();
+        debug!(
+            "downloading {} from '{url}' by chunks of {chunk_size} bytes",
+            self.name
+        );
+        let client = self.client()?;
+        let send = |req: reqwest::blocking::RequestBuilder| match req.send() {
+            Ok(resp) if resp.status().is_success() => Ok(resp),
+            Ok(resp) => Err(self.status_error(url, resp.status())),
+            Err(e) => Err(self.send_error(url, e)),
+        };
+
+        // NB: the length of HEAD responses can only be read from the header
+        let total = self
+            .retrying(Some(progress), || send(client.head(url.as_ref())))?
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok()?.parse::<u64>().ok())
+            .ok_or_else(|| anyhow!("unable to get file length of '{url}'"))?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        let mut chunks = ChunkMap::load(path, total, chunk_size)?;
+        file.set_len(total)?;
+
+        let steps = StepProgress::new(progress.clone(), total as f32);
+        steps.advance_step(chunks.completed_bytes() as f32)?;
+        let maybe_indicator = self.handler.as_ref().and_then(|h| {
+            let msg = ProgressMsg::Downloading(&self.name).to_string();
+            let indicator = (h.start)(msg, Style::Bytes(total), h.opt).ok()?;
+            (h.update)(&indicator, Some(chunks.completed_bytes()));
+            Some(indicator)
+        });
+
+        let mut fetch_missing = || -> Result<()> {
+            for idx in chunks.missing() {
+                self.cancel.check()?;
+                progress.cancellation_token().check()?;
+                let (start, end) = chunks.range(idx);
+                let buf = self.retrying(Some(progress), || {
+                    let mut resp = send(
+                        client
+                            .get(url.as_ref())
+                            .header(reqwest::header::RANGE, format!("bytes={start}-{end}")),
+                    )?;
+                    if resp.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                        bail!(
+                            "unable to download '{url}' by chunks, as the server does not support ranges"
+                        );
+                    }
+                    let mut buf = Vec::with_capacity((end - start + 1) as usize);
+                    let mut body = Throttled::new(&mut resp, self.rate_limit.as_deref());
+                    if let Err(e) = io::Read::read_to_end(&mut body, &mut buf) {
+                        if is_io_timeout(&e) {
+                            return Err(DownloadError::timeout(url, self.timeout));
+                        }
+                        return Err(e.into());
+                    }
+                    Ok(buf)
+                })?;
+                if buf.len() as u64 != end - start + 1 {
+                    bail!(
+                        "chunk {start}-{end} of '{url}' has {} bytes, which is not the requested length",
+                        buf.len()
+                    );
+                }
+                io::Seek::seek(&mut file, io::SeekFrom::Start(start))?;
+                file.write_all(&buf)?;
+                file.sync_data()?;
+                chunks.mark_done(idx)?;
+
+                steps.advance_step(buf.len() as f32)?;
+                progress.report_transfer(chunks.completed_bytes(), total)?;
+                if let (Some(h), Some(indicator)) = (&self.handler, &maybe_indicator) {
+                    (h.update)(indicator, Some(chunks.completed_bytes()));
+                }
+            }
+            Ok(())
+        };
+        if let Err(e) = fetch_missing() {
+            if e.is::<Cancelled>() {
+                // unlike an interrupted download, there's nothing to resume
+                drop(file);
+                chunks.remove()?;
+                fs::remove_file(path)?;
+            }
+            return Err(e);
+        }
+
+        chunks.remove()?;
+        if let (Some(h), Some(indicator)) = (&self.handler, &maybe_indicator) {
+            (h.stop)(indicator, ProgressMsg::Downloaded(&self.name).to_string());
+        }
+        Ok(())
+    }
+
+    /// Consume self, and download multiple files concurrently on a pool of `jobs` threads,
+    /// stops starting new downloads once one of them failed or the `progress` was cancelled.
+    ///
+    /// Each in-flight file gets its own CLI bar, with an overall bar below them. The `progress`
+    /// is advanced by its `len` based on the downloaded bytes of all files (weighted by their
+    /// sizes), and receives a message once each file starts and finishes downloading,
+    /// so the GUI can display the same information.
+    pub fn download_all(
+        self,
+        files: &[(Url, PathBuf)],
+        jobs: usize,
+        progress: &Progress,
+    ) -> Result<()> {
+        if files.is_empty() {
+            return progress.inc(None);
+        }
+        let msg = ProgressMsg::DownloadingFiles(files.len()).to_string();
+        let bars = match &self.handler {
+            Some(h) => h.multi(msg, 0)?,
+            None => MultiCliProgress::with_draw_target(
+                msg,
+                0,
+                CliProgressOpt::default(),
+                ProgressDrawTarget::hidden(),
+            )?,
+        };
+        let aggregate = AggregateProgress::new(progress.clone());
+        // register every task first, so the aggregated total is known as early as possible
+        let tasks = files
+            .iter()
+            .map(|file| (file, aggregate.task()))
+            .collect::<Vec<_>>();
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+
+        let worker = || -> Result<()> {
+            while !failed.load(Ordering::Relaxed) {
+                self.cancel.check()?;
+                progress.cancellation_token().check()?;
+                let Some(((url, dest), task)) = tasks.get(next.fetch_add(1, Ordering::Relaxed))
+                else {
+                    break;
+                };
+                let res = self.download_task(url, dest, task, &bars, &aggregate);
+                if res.is_err() {
+                    failed.store(true, Ordering::Relaxed);
+                    return res;
+                }
+            }
+            Ok(())
+        };
+        // the workers log in the same span (such as of a component) as this thread
+        let span = tracing::Span::current();
+        std::thread::scope(|s| {
+            let workers = (0..jobs.clamp(1, files.len()))
+                .map(|_| {
+                    s.spawn(|| {
+                        let _span = span.enter();
+                        worker()
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .map(|w| w.join().expect("download thread panicked"))
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        bars.finish(ProgressMsg::DownloadedFiles(files.len()).to_string());
+        Ok(())
+    }
+
+    /// Download a single file of [`download_all`](DownloadOpt::download_all).
+    fn download_task(
+        &self,
+        url: &Url,
+        dest: &Path,
+        task: &AggregateTask<'_, '_>,
+        bars: &MultiCliProgress,
+        aggregate: &AggregateProgress<'_>,
+    ) -> Result<()> {
+        let name =
+            downloadable_file_name(url).map_or_else(|_| url.to_string(), ToString::to_string);
+        let progress = aggregate.progress();
+        let pb = bars.add_task(ProgressMsg::Downloading(&name).to_string())?;
+        progress.show_msg(ProgressMsg::Downloading(&name))?;
+
+        let opt = DownloadOpt {
+            name: name.clone(),
+            handler: None,
+            insecure: self.insecure,
+            proxy: self.proxy.clone(),
+            timeout: self.timeout,
+            prefix_digest: None,
+            cancel: self.cancel.clone(),
+            retry: self.retry,
+            rate_limit: self.rate_limit.clone(),
+        };
+        let mut total_known = false;
+        let res = opt.download_file_(url, dest, false, Some(progress), |pos, total| {
+            if !total_known {
+                bars.set_task_total(&pb, total)?;
+                task.set_total(total)?;
+                total_known = true;
+            }
+            pb.set_position(pos);
+            task.advance_to(pos)?;
+            let (done, total) = aggregate.totals();
+            bars.set_overall(done, total);
+            progress.report_transfer(done, total)
+        });
+        if res.is_err() {
+            pb.abandon();
+            return res;
+        }
+
+        bars.finish_task(&pb, ProgressMsg::Downloaded(&name).to_string())?;
+        progress.show_msg(ProgressMsg::Downloaded(&name))
+    }
+
+    /// Send a `GET` request of `url`, with a `Range` header if `offset` is not zero.
+    fn send_get(&self, url: &Url, offset: u64) -> Result<reqwest::blocking::Response> {
+        let mut req = self.client()?.get(url.as_ref());
+        if offset > 0 {
+            req = req.header(reqwest::header::RANGE, format!("bytes={offset}-"));
+        }
+        req.send().map_err(|e| self.send_error(url, e))
+    }
+
+    /// Download from given `Url` to `Path`, `on_pos` will be called with the downloaded
+    /// length and the total length.
+    ///
+    /// The download is retried with the [`retry`](DownloadOpt::retry) policy, which are
+    /// announced through the `progress` if given.
+    pub(super) fn download_file_<F>(
+        self,
+        url: &Url,
+        path: &Path,
+        resume: bool,
+        progress: Option<&Progress>,
+        mut on_pos: F,
+    ) -> Result<()>
+    where
+        F: FnMut(u64, u64) -> Result<()>,
+    {
+        let _span = download_span(url).entered();
+        debug!(
+            "downloading {} from '{url}' to '{}'",
+            self.name,
+            path.display()
+        );
+        self.retrying(progress, || {
+            self.download_file_once(url, path, resume, &mut on_pos)
+        })
+    }
+
+    fn download_file_once<F>(
+        &self,
+        url: &Url,
+        path: &Path,
+        resume: bool,
+        on_pos: &mut F,
+    ) -> Result<()>
+    where
+        F: FnMut(u64, u64) -> Result<()>,
+    {
+        self.cancel.check()?;
+        if url.scheme() == "file" {
+            let len = fs::copy(
                 url.to_file_path()
                     .map_err(|_| anyhow!("unable to convert to file path for url '{url}'"))?,
                 path,
             )?;
-            return Ok(());
+            return on_pos(len, len);
         }
 
         if self.insecure {
             warn!("{}", t!("insecure_download"));
         }
+        let via_proxy = self.proxy_for(url);
+        if let Some(proxy) = &via_proxy {
+            info!("{}", t!("connecting_via_proxy", url = url, proxy = proxy));
+        }
 
-        let mut resp = self.client()?.get(url.as_ref()).send().with_context(|| {
-            format!("failed to receive surver response when downloading from '{url}'")
-        })?;
-        let status = resp.status();
-        if !status.is_success() {
-            bail!("server returns error when attempting download from '{url}': {status}");
+        let mut part = if resume {
+            let mut part = PartFile::open(path)?;
+            let verified = self.check_partial(&mut part.file)?;
+            part.written = part.written.min(verified);
+            Some(part)
+        } else {
+            None
+        };
+        if let Some(done) = part.as_ref().filter(|p| p.total == Some(p.written)) {
+            // completed in the previous attempt, but wasn't moved to `path`
+            let total = done.written;
+            part.take().map(PartFile::finish).transpose()?;
+            return on_pos(total, total);
+        }
+
+        let offset = part.as_ref().map_or(0, |p| p.written);
+        let mut resp = self.send_get(url, offset)?;
+        let content_range = (resp.status() == reqwest::StatusCode::PARTIAL_CONTENT)
+            .then(|| resp.headers().get(reqwest::header::CONTENT_RANGE))
+            .flatten()
+            .and_then(|range| parse_content_range(range.to_str().ok()?));
+        let resumable = |(start, total): (u64, u64)| {
+            start == offset
+                && part
+                    .as_ref()
+                    .and_then(|p| p.total)
+                    .map_or(true, |t| t == total)
+        };
+        let (start, total_size) = match content_range {
+            Some(range) if resumable(range) => range,
+            _ => {
+                if offset > 0 {
+                    // the server ignored the range, or the file has been changed since then
+                    warn!("{}", t!("download_restarted", name = self.name));
+                    if resp.status() != reqwest::StatusCode::OK {
+                        resp = self.send_get(url, 0)?;
+                    }
+                }
+                let status = resp.status();
+                if !status.is_success() {
+                    return Err(self.status_error(url, status));
+                }
+                let total = resp
+                    .content_length()
+                    .ok_or_else(|| anyhow!("unable to get file length of '{url}'"))?;
+                (0, total)
+            }
+        };
+        if let Some(part) = &mut part {
+            part.start_at(start, total_size)?;
         }
-        let total_size = resp
-            .content_length()
-            .ok_or_else(|| anyhow!("unable to get file length of '{url}'"))?;
 
         let maybe_indicator = self.handler.as_ref().and_then(|h| {
-            (h.start)(
-                format!("downloading '{}'", &self.name),
-                Style::Bytes(total_size),
-            )
-            .ok()
+            let msg = match &via_proxy {
+                Some(proxy) => ProgressMsg::DownloadingViaProxy(&self.name, proxy),
+                None => ProgressMsg::Downloading(&self.name),
+            };
+            (h.start)(msg.to_string(), Style::Bytes(total_size), h.opt).ok()
         });
 
-        let (mut downloaded_len, mut file) = if resume {
-            let file = OpenOptions::new()
-                .create(true)
-                .truncate(false)
-                .write(true)
-                .open(path)?;
-            (file.metadata()?.len().saturating_sub(1), file)
-        } else {
-            (
-                0,
-                OpenOptions::new()
+        if let (Some(h), Some(indicator)) = (&self.handler, &maybe_indicator) {
+            if start > 0 {
+                // speed and ETA should not count the bytes downloaded previously
+                h.resume_from(indicator, start)?;
+            }
+        }
+
+        let update = |pos: u64| {
+            self.cancel.check()?;
+            if let (Some(h), Some(indicator)) = (&self.handler, &maybe_indicator) {
+                (h.update)(indicator, Some(pos));
+            }
+            on_pos(pos, total_size)
+        };
+        let mut body = Throttled::new(&mut resp, self.rate_limit.as_deref());
+        let written = match &mut part {
+            Some(part) => write_body(&mut body, part, &self.name, start, total_size, update),
+            None => {
+                let mut file = OpenOptions::new()
                     .create(true)
                     .write(true)
                     .truncate(true)
-                    .open(path)?,
+                    .open(path)?;
+                write_body(&mut body, &mut file, &self.name, 0, total_size, update)
+            }
+        };
+        if let Err(e) = written {
+            if e.is::<Cancelled>() {
+                // it's not wanted anymore, unlike a failed download that might be resumed
+                match part {
+                    Some(part) => part.discard()?,
+                    None => fs::remove_file(path)?,
+                }
+                return Err(e);
+            }
+            if let Some(part) = &mut part {
+                // keep what has been downloaded for the next attempt
+                let _ = part.save();
+            }
+            if e.downcast_ref::<io::Error>().is_some_and(is_io_timeout) {
+                return Err(DownloadError::timeout(url, self.timeout));
+            }
+            return Err(e);
+        }
+        if let Some(part) = part {
+            part.finish()?;
+        }
+
+        if let (Some(h), Some(indicator)) = (&self.handler, &maybe_indicator) {
+            (h.stop)(indicator, ProgressMsg::Downloaded(&self.name).to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Write the response `body` of a file with the given `name` into `dest`,
+/// `on_pos` is called with the downloaded length, starting from `start`.
+///
+/// Because the `total` length reported by server might be wrong, the downloaded length
+/// never exceeds it, and it will be set to `total` once the body ends earlier than expected,
+/// so the progress always ends properly, these mismatches are reported as warnings.
+fn write_body<R, W, F>(
+    body: &mut R,
+    dest: &mut W,
+    name: &str,
+    start: u64,
+    total: u64,
+    mut on_pos: F,
+) -> Result<()>
+where
+    R: io::Read,
+    W: Write,
+    F: FnMut(u64) -> Result<()>,
+{
+    let mut buffer = vec![0u8; 65535];
+    let mut received = 0_u64;
+    loop {
+        let bytes_read = body.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        dest.write_all(&buffer[..bytes_read])?;
+        if received <= total && received + bytes_read as u64 > total {
+            warn!(
+                "{}",
+                t!("content_length_exceeded", name = name, len = total)
+            );
+        }
+        received += bytes_read as u64;
+        on_pos(min(start + received, total))?;
+    }
+    if received < total.saturating_sub(start) {
+        warn!(
+            "{}",
+            t!(
+                "content_length_unreached",
+                name = name,
+                len = total,
+                actual = start + received
             )
+        );
+        on_pos(total)?;
+    }
+    Ok(())
+}
+
+/// Calculate the SHA-256 digest of the first `len` bytes of a file,
+/// `on_progress` is called with the number of bytes hashed so far.
+pub(super) fn sha256_of_prefix<F: Fn(u64)>(
+    file: &mut fs::File,
+    len: u64,
+    on_progress: F,
+) -> Result<String> {
+    use sha2::{Digest, Sha256};
+    use std::io::{Read, Seek, SeekFrom};
+
+    file.seek(SeekFrom::Start(0))?;
+    let mut hasher = Sha256::new();
+    let mut reader = file.take(len);
+    let mut buffer = vec![0u8; 65535];
+    let mut hashed = 0;
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        hashed += n as u64;
+        on_progress(hashed);
+    }
+    reader.into_inner().seek(SeekFrom::Start(0))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Check if an IO error from reading the response body was caused by timeout.
+fn is_io_timeout(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::TimedOut
+        || err
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<reqwest::Error>())
+            .is_some_and(reqwest::Error::is_timeout)
+}
+
+/// How often the downloaded length of a [`PartFile`] is recorded.
+const PART_CHECKPOINT_INTERVAL: u64 = 1024 * 1024;
+
+/// The `<name>.part` file that a resumable download is written into, which is renamed to the
+/// destination once completed.
+///
+/// The number of bytes that are known to be written is recorded in a `<name>.part.offset`
+/// file along with the total size, as `<total> <offset>`. This is updated once every
+/// [`PART_CHECKPOINT_INTERVAL`] bytes and when the download stops, so the next attempt
+/// resumes from the recorded offset, even if the process was killed midway.
+struct PartFile {
+    dest: PathBuf,
+    part: PathBuf,
+    checkpoint: PathBuf,
+    file: fs::File,
+    /// The total size of the file, if it has been recorded.
+    total: Option<u64>,
+    /// The number of bytes written so far.
+    written: u64,
+    /// The number of bytes recorded in the checkpoint.
+    saved: u64,
+}
+
+impl PartFile {
+    /// Open (or create) the partial file of `dest`, the bytes after the recorded offset
+    /// are not trusted, as they might be written incompletely.
+    fn open(dest: &Path) -> Result<Self> {
+        let with_ext = |ext: &str| {
+            let mut name = dest.file_name().unwrap_or_default().to_os_string();
+            name.push(ext);
+            dest.with_file_name(name)
+        };
+        let (part, checkpoint) = (with_ext(".part"), with_ext(".part.offset"));
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&part)
+            .with_context(|| format!("unable to open '{}'", part.display()))?;
+        let len = file.metadata()?.len();
+        let recorded = fs::read_to_string(&checkpoint).ok().and_then(|content| {
+            let (total, offset) = content.trim().split_once(' ')?;
+            Some((total.parse::<u64>().ok()?, offset.parse::<u64>().ok()?))
+        });
+        let (total, written) = match recorded {
+            Some((total, offset)) => (Some(total), offset.min(len)),
+            None => (None, len),
         };
+        Ok(Self {
+            dest: dest.to_path_buf(),
+            part,
+            checkpoint,
+            file,
+            total,
+            written,
+            saved: written,
+        })
+    }
 
-        let mut buffer = vec![0u8; 65535];
+    /// Continue writing from `offset`, discarding the bytes after it.
+    fn start_at(&mut self, offset: u64, total: u64) -> Result<()> {
+        self.file.set_len(offset)?;
+        io::Seek::seek(&mut self.file, io::SeekFrom::Start(offset))?;
+        self.written = offset;
+        self.total = Some(total);
+        self.save()?;
+        Ok(())
+    }
 
-        loop {
-            let bytes_read = io::Read::read(&mut resp, &mut buffer)?;
+    /// Flush the written bytes to the disk, then record the offset.
+    fn save(&mut self) -> io::Result<()> {
+        self.file.sync_data()?;
+        if let Some(total) = self.total {
+            fs::write(&self.checkpoint, format!("{total} {}", self.written))?;
+        }
+        self.saved = self.written;
+        Ok(())
+    }
 
-            if bytes_read != 0 {
-                downloaded_len = min(downloaded_len + bytes_read as u64, total_size);
-                if let Some(indicator) = &maybe_indicator {
-                    // safe to unwrap, because indicator won't exist if self.handler is none
-                    (self.handler.as_ref().unwrap().update)(indicator, Some(downloaded_len));
-                }
-                file.write_all(&buffer[..bytes_read])?;
-            } else {
-                if let Some(indicator) = &maybe_indicator {
-                    // safe to unwrap, because indicator won't exist if self.handler is none
-                    (self.handler.as_ref().unwrap().stop)(
-                        indicator,
-                        format!("'{}' successfully downloaded.", &self.name),
-                    );
-                }
+    /// Remove the partial file and its checkpoint, such as when the download was cancelled.
+    fn discard(self) -> Result<()> {
+        // NB: files cannot be removed while opened on Windows
+        drop(self.file);
+        fs::remove_file(&self.part)?;
+        if self.checkpoint.exists() {
+            fs::remove_file(&self.checkpoint)?;
+        }
+        Ok(())
+    }
+
+    /// Move the completed file to its destination.
+    fn finish(self) -> Result<()> {
+        let Self {
+            dest,
+            part,
+            checkpoint,
+            file,
+            ..
+        } = self;
+        file.sync_data()?;
+        // NB: files cannot be renamed while opened on Windows
+        drop(file);
+        fs::rename(&part, &dest).with_context(|| {
+            format!(
+                "unable to move '{}' to '{}'",
+                part.display(),
+                dest.display()
+            )
+        })?;
+        if checkpoint.exists() {
+            fs::remove_file(&checkpoint)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for PartFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        if self.written - self.saved >= PART_CHECKPOINT_INTERVAL {
+            self.save()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Parse a `Content-Range` header like `bytes 100-199/1000`, returns the start and the total
+/// size, or `None` if the total size is unknown.
+fn parse_content_range(range: &str) -> Option<(u64, u64)> {
+    let (range, total) = range.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()?))
+}
 
-                return Ok(());
+/// The completed chunks of a download by [`DownloadOpt::download_chunked`], which is stored in a
+/// file with the total size and chunk size in the first line, followed by the index of a
+/// completed chunk in each line.
+struct ChunkMap {
+    path: PathBuf,
+    total: u64,
+    chunk_size: u64,
+    done: std::collections::BTreeSet<u64>,
+}
+
+impl ChunkMap {
+    /// Load the chunk map of the partially downloaded file at `dest`, a new map is created if
+    /// there's none, or it was recorded for a different total size or chunk size.
+    fn load(dest: &Path, total: u64, chunk_size: u64) -> Result<Self> {
+        let mut name = dest.file_name().unwrap_or_default().to_os_string();
+        name.push(".chunks");
+        let mut map = Self {
+            path: dest.with_file_name(name),
+            total,
+            chunk_size,
+            done: Default::default(),
+        };
+        let header = format!("{total} {chunk_size}");
+        if let Ok(content) = fs::read_to_string(&map.path) {
+            let mut lines = content.lines();
+            if lines.next() == Some(header.as_str()) {
+                map.done = lines
+                    .filter_map(|line| line.trim().parse().ok())
+                    .filter(|idx| *idx < map.count())
+                    .collect();
+                return Ok(map);
             }
         }
+        fs::write(&map.path, format!("{header}\n"))?;
+        Ok(map)
+    }
+
+    fn count(&self) -> u64 {
+        self.total.div_ceil(self.chunk_size)
+    }
+
+    /// The start and (inclusive) end of the chunk at `idx`.
+    fn range(&self, idx: u64) -> (u64, u64) {
+        let start = idx * self.chunk_size;
+        (start, min(start + self.chunk_size, self.total) - 1)
+    }
+
+    fn missing(&self) -> Vec<u64> {
+        (0..self.count())
+            .filter(|idx| !self.done.contains(idx))
+            .collect()
+    }
+
+    fn completed_bytes(&self) -> u64 {
+        self.done
+            .iter()
+            .map(|idx| {
+                let (start, end) = self.range(*idx);
+                end - start + 1
+            })
+            .sum()
+    }
+
+    fn mark_done(&mut self, idx: u64) -> Result<()> {
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        writeln!(file, "{idx}")?;
+        self.done.insert(idx);
+        Ok(())
+    }
+
+    fn remove(self) -> Result<()> {
+        fs::remove_file(&self.path)?;
+        Ok(())
     }
 }
 
@@ -195,3 +1190,780 @@ pub fn download_with_proxy<S: ToString>(
 pub fn download<S: ToString>(name: S, url: &Url, dest: &Path) -> Result<()> {
     DownloadOpt::new(name).download_file(url, dest, false)
 }
+
+/// The portions of the whole progress of each phase in [`fetch_and_extract`],
+/// the rest is for extraction.
+const FETCH_DOWNLOAD_WEIGHT: f32 = 0.6;
+const FETCH_VERIFY_WEIGHT: f32 = 0.05;
+
+/// Download an archive from `url`, verify it against its `sha256` checksum (if provided),
+/// then extract it into `dest`.
+///
+/// This is done in three phases: download, verify and extract, which advance the `progress`
+/// by a weighted part of its `len` each. The downloaded archive is placed in a temporary
+/// directory, which will be removed afterward.
+pub fn fetch_and_extract(
+    url: &Url,
+    dest: &Path,
+    sha256: Option<&str>,
+    progress: &Progress,
+) -> Result<()> {
+    fetch_and_extract_estimated(url, dest, sha256, None, progress)
+}
+
+/// Similar to [`fetch_and_extract`], but the parts of download and extraction are
+/// weighted by their sizes, using the `estimated_size` of the extracted files (such as the
+/// `uncompressed-size` of a tool in the manifest) since the actual size is unknown yet.
+///
+/// The estimate only affects how the `progress` is split, no matter how wrong it is,
+/// the extraction takes up the rest of the `len` once it starts, so the `progress` always
+/// ends up advanced by its `len` exactly.
+pub fn fetch_and_extract_estimated(
+    url: &Url,
+    dest: &Path,
+    sha256: Option<&str>,
+    estimated_size: Option<u64>,
+    progress: &Progress,
+) -> Result<()> {
+    let name = downloadable_file_name(url)?;
+    let temp_dir = tempfile::Builder::new().prefix("rim-fetch-").tempdir()?;
+    let archive = temp_dir.path().join(name);
+    let len = progress.len();
+    let verify_len = len * FETCH_VERIFY_WEIGHT;
+    // the share of download is reconciled once the archive size is known
+    let download_len = |archive_size: u64| match estimated_size {
+        Some(estimated) if archive_size + estimated > 0 => {
+            (len - verify_len) * (archive_size as f64 / (archive_size + estimated) as f64) as f32
+        }
+        _ => len * FETCH_DOWNLOAD_WEIGHT,
+    };
+
+    progress.start_phase(Phase::Download);
+    let downloaded =
+        DownloadOpt::new(name).download_advancing(url, &archive, progress, download_len)?;
+
+    progress.start_phase(Phase::Verify);
+    if let Some(expected) = sha256 {
+        verify_sha256(&archive, expected)?;
+    }
+    // make sure the downloaded file is an archive that can be extracted
+    let extractable =
+        Extractable::load(&archive)?.with_cancellation_token(progress.cancellation_token().clone());
+    progress.inc(Some(verify_len))?;
+
+    progress.start_phase(Phase::Extract);
+    let extract_progress = progress.clone().with_len(len - downloaded - verify_len);
+    extractable
+        .with_progress(&extract_progress)
+        .extract_to(dest)?;
+    progress.end_phase();
+
+    Ok(())
+}
+
+/// Verify a local `archive` against its `sha256` checksum (if provided), then extract it
+/// into `dest`, such as installing from an archive that is already downloaded.
+///
+/// Similar to [`fetch_and_extract`] without downloading, so the `progress` is advanced
+/// by the weighted part of its `len` for each of the two phases: verify and extract.
+pub fn verify_and_extract(
+    archive: &Path,
+    dest: &Path,
+    sha256: Option<&str>,
+    progress: &Progress,
+) -> Result<()> {
+    let len = progress.len();
+    let verify_len = len * FETCH_VERIFY_WEIGHT / (1.0 - FETCH_DOWNLOAD_WEIGHT);
+
+    progress.start_phase(Phase::Verify);
+    if let Some(expected) = sha256 {
+        verify_sha256(archive, expected)?;
+    }
+    let extractable =
+        Extractable::load(archive)?.with_cancellation_token(progress.cancellation_token().clone());
+    progress.inc(Some(verify_len))?;
+
+    progress.start_phase(Phase::Extract);
+    let extract_progress = progress.clone().with_len(len - verify_len);
+    extractable
+        .with_progress(&extract_progress)
+        .extract_to(dest)?;
+    progress.end_phase();
+
+    Ok(())
+}
+
+/// Download multiple files concurrently, with at most `concurrency` downloads at a time.
+///
+/// Each `(url, dest)` pair is downloaded as a separated task of the given `aggregate`
+/// progress, which is the async counterpart to a thread pool, so this requires an async
+/// runtime (such as `tokio`) to run.
+///
+/// Note that the [`RateLimiter::global`] is not applied, as waiting requires a timer of
+/// the runtime.
+pub async fn download_all_async(
+    files: &[(Url, PathBuf)],
+    concurrency: usize,
+    proxy: Option<&CrateProxy>,
+    aggregate: &AggregateProgress<'_>,
+) -> Result<()> {
+    let opt = &DownloadOpt::new("files").with_proxy(proxy.cloned());
+    let client = &opt.async_client()?;
+    run_concurrently(
+        files,
+        concurrency,
+        aggregate,
+        |(url, dest), task| async move {
+            download_file_async(client, opt, url, dest, task)
+                .instrument(download_span(url))
+                .await
+        },
+    )
+    .await
+}
+
+/// The span of requesting `url`, so the messages logged meanwhile (such as the retries) are
+/// associated with the url in the log file.
+fn download_span(url: &Url) -> tracing::Span {
+    tracing::debug_span!("download", url = %url)
+}
+
+/// Run a `job` for each item in `jobs` concurrently, with at most `concurrency` jobs
+/// running at the same time, stops at the first error.
+async fn run_concurrently<'p, 'a, J, F, Fut>(
+    jobs: J,
+    concurrency: usize,
+    aggregate: &'p AggregateProgress<'a>,
+    job: F,
+) -> Result<()>
+where
+    J: IntoIterator,
+    F: Fn(J::Item, AggregateTask<'p, 'a>) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    // register every task first, so the aggregated total is known as early as possible
+    let tasks = jobs
+        .into_iter()
+        .map(|item| (item, aggregate.task()))
+        .collect::<Vec<_>>();
+    let mut running = futures::stream::iter(tasks.into_iter().map(|(item, task)| job(item, task)))
+        .buffer_unordered(concurrency.max(1));
+    while let Some(res) = running.next().await {
+        res?;
+    }
+    Ok(())
+}
+
+async fn download_file_async(
+    client: &reqwest::Client,
+    opt: &DownloadOpt<CliBar>,
+    url: &Url,
+    dest: &Path,
+    task: AggregateTask<'_, '_>,
+) -> Result<()> {
+    if url.scheme() == "file" {
+        let src = url
+            .to_file_path()
+            .map_err(|_| anyhow!("unable to convert to file path for url '{url}'"))?;
+        let len = fs::copy(src, dest)?;
+        task.set_total(len)?;
+        return task.advance(len);
+    }
+
+    let mut resp = client
+        .get(url.as_ref())
+        .send()
+        .await
+        .map_err(|e| opt.send_error(url, e))?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(opt.status_error(url, status));
+    }
+    let total_size = resp.content_length();
+    if let Some(total) = total_size {
+        task.set_total(total)?;
+    }
+
+    let mut file = fs::File::create(dest)?;
+    let mut downloaded_len = 0;
+    loop {
+        let chunk = match resp.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) if e.is_timeout() => return Err(DownloadError::timeout(url, opt.timeout)),
+            Err(e) => return Err(e.into()),
+        };
+        file.write_all(&chunk)?;
+        downloaded_len += chunk.len() as u64;
+        if let Err(e) = task.advance(chunk.len() as u64) {
+            if e.is::<Cancelled>() {
+                drop(file);
+                fs::remove_file(dest)?;
+            }
+            return Err(e);
+        }
+    }
+    match total_size {
+        Some(total) if downloaded_len > total => {
+            let name = url.as_str();
+            warn!(
+                "{}",
+                t!("content_length_exceeded", name = name, len = total)
+            );
+        }
+        Some(total) if downloaded_len < total => {
+            let name = url.as_str();
+            warn!(
+                "{}",
+                t!(
+                    "content_length_unreached",
+                    name = name,
+                    len = total,
+                    actual = downloaded_len
+                )
+            );
+            task.set_total(downloaded_len)?;
+        }
+        Some(_) => (),
+        None => task.set_total(downloaded_len)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mock_server::{MockResponse, MockServer};
+    use crate::utils::{EventSender, Progress, ProgressEventKind};
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context as TaskContext, Poll};
+
+    /// Yield to the executor once, so other futures get a chance to run.
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn concurrent_mock_downloads_aggregate() {
+        let pos_cb = |_: f32| Ok(());
+        let aggregate = AggregateProgress::new(Progress::new(&pos_cb).with_len(80.0));
+        // each mocked download is a list of chunk sizes
+        let downloads: Vec<Vec<u64>> = vec![vec![100, 200], vec![50; 8], vec![1000], vec![10]];
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+
+        let fut = run_concurrently(&downloads, 2, &aggregate, |chunks, task| {
+            let in_flight = &in_flight;
+            let max_in_flight = &max_in_flight;
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                task.set_total(chunks.iter().sum())?;
+                for chunk in chunks {
+                    YieldOnce(false).await;
+                    task.advance(*chunk)?;
+                }
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        futures::executor::block_on(fut).unwrap();
+
+        assert_eq!(aggregate.totals(), (1710, 1710));
+        assert_eq!(aggregate.progress().position(), 80.0);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    fn assert_timeout(res: Result<()>) {
+        let err = res.unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<DownloadError>(),
+                Some(DownloadError::Timeout { .. })
+            ),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn never_responding_download_times_out() {
+        let server = MockServer::start();
+        server.serve(
+            "/file",
+            MockResponse::new("never sent").delay(Duration::from_secs(10)),
+        );
+        let url = server.url("file");
+        let dir = tempfile::tempdir().unwrap();
+        let res = DownloadOpt::new("stalled")
+            .with_handler(None)
+            .with_timeout(Duration::from_millis(300))
+            .with_retry(RetryPolicy::none())
+            .download_file(&url, &dir.path().join("file"), false);
+        assert_timeout(res);
+    }
+
+    #[test]
+    fn stalled_body_download_times_out() {
+        let server = MockServer::start();
+        server.serve("/file", MockResponse::new(vec![1; 100]).stall_at(16));
+        let url = server.url("file");
+        let dir = tempfile::tempdir().unwrap();
+        let res = DownloadOpt::new("stalled")
+            .with_handler(None)
+            .with_timeout(Duration::from_millis(300))
+            .with_retry(RetryPolicy::none())
+            .download_file(&url, &dir.path().join("file"), false);
+        assert_timeout(res);
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        use sha2::{Digest, Sha256};
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    fn write_partial(content: &[u8]) -> (tempfile::TempDir, fs::File) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("partial");
+        fs::write(&path, content).unwrap();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap();
+        (dir, file)
+    }
+
+    #[test]
+    fn corrupted_partial_restarts() {
+        let expected = b"hello world, this is the correct content";
+        let opt = DownloadOpt::new("partial")
+            .with_handler(None)
+            .with_prefix_digest(Some(PrefixDigest {
+                len: 10,
+                sha256: sha256_hex(&expected[..10]),
+            }));
+
+        let (_dir, mut corrupted) = write_partial(b"hello\0\0\0\0\0\0\0");
+        assert_eq!(opt.check_partial(&mut corrupted).unwrap(), 0);
+        assert_eq!(corrupted.metadata().unwrap().len(), 0);
+
+        // too short to be checked
+        let (_dir, mut short) = write_partial(b"hello");
+        assert_eq!(opt.check_partial(&mut short).unwrap(), 0);
+
+        let (_dir, mut valid) = write_partial(&expected[..20]);
+        assert_eq!(opt.check_partial(&mut valid).unwrap(), 20);
+        assert_eq!(valid.metadata().unwrap().len(), 20);
+    }
+
+    fn write_mocked_body(body: &[u8], total: u64) -> (Vec<u8>, Vec<u64>) {
+        let positions = std::sync::Mutex::new(vec![]);
+        let mut dest = vec![];
+        // read in small chunks, to make sure the positions are updated multiple times
+        let mut reader = io::Read::chain(&body[..body.len() / 2], &body[body.len() / 2..]);
+        write_body(&mut reader, &mut dest, "mocked", 0, total, |pos| {
+            positions.lock().unwrap().push(pos);
+            Ok(())
+        })
+        .unwrap();
+        (dest, positions.into_inner().unwrap())
+    }
+
+    #[test]
+    fn body_longer_than_content_length() {
+        let (written, positions) = write_mocked_body(&[1; 20], 10);
+        // the content is not truncated, only the position is clamped
+        assert_eq!(written.len(), 20);
+        assert_eq!(positions, [10, 10]);
+    }
+
+    #[test]
+    fn body_shorter_than_content_length() {
+        let (written, positions) = write_mocked_body(&[1; 6], 10);
+        assert_eq!(written.len(), 6);
+        assert_eq!(positions, [3, 6, 10]);
+    }
+
+    #[test]
+    fn fetch_and_extract_archive() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/simple_gz.tar.gz");
+        let server = MockServer::start();
+        server.serve(
+            "/dist/simple_gz.tar.gz",
+            MockResponse::new(fs::read(fixture).unwrap()),
+        );
+        let url = server.url("dist/simple_gz.tar.gz");
+        let dest = tempfile::tempdir().unwrap();
+
+        let sha256 = "d663b7c8e356d7be8bc97d0d0122e135a56b718c3603078f1d98392598c04678";
+        let progress = Progress::noop().with_len(50.0);
+        fetch_and_extract(&url, dest.path(), Some(sha256), &progress).unwrap();
+
+        let extracted = dest.path().join("simple_gz");
+        assert!(extracted.join("aaa.txt").is_file());
+        assert!(extracted.join("ccc.txt").is_file());
+        assert_eq!(progress.position(), 50.0);
+        let phases = progress.phase_timings();
+        let phases = phases.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>();
+        assert_eq!(phases, ["download", "verify", "extract"]);
+
+        let progress = Progress::noop().with_len(50.0);
+        let wrong = "0".repeat(64);
+        let dest = tempfile::tempdir().unwrap();
+        assert!(fetch_and_extract(&url, dest.path(), Some(&wrong), &progress).is_err());
+        assert!(!dest.path().join("simple_gz").exists());
+    }
+
+    #[test]
+    fn wrong_size_estimates_still_complete() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/simple_gz.tar.gz");
+        let url = Url::from_file_path(&fixture).unwrap();
+        let archive_size = fs::metadata(&fixture).unwrap().len();
+
+        let download_share = |estimated: Option<u64>| {
+            let (tx, rx) = std::sync::mpsc::channel();
+            let progress = Progress::noop()
+                .with_len(50.0)
+                .with_observer(EventSender::new(tx));
+            let dest = tempfile::tempdir().unwrap();
+            fetch_and_extract_estimated(&url, dest.path(), None, estimated, &progress).unwrap();
+            assert!(dest.path().join("simple_gz").join("aaa.txt").is_file());
+            assert_eq!(progress.position(), 50.0);
+            drop(progress);
+            rx.iter()
+                .filter(|e| e.phase == Some(Phase::Download))
+                .filter_map(|e| match e.kind {
+                    ProgressEventKind::Position { pos, .. } => Some(pos),
+                    _ => None,
+                })
+                .last()
+                .unwrap()
+        };
+        assert_eq!(download_share(None), 50.0 * FETCH_DOWNLOAD_WEIGHT);
+        let half = 50.0 * (1.0 - FETCH_VERIFY_WEIGHT) / 2.0;
+        assert!((download_share(Some(archive_size)) - half).abs() < 1e-4);
+        // way too small or too big
+        assert!(download_share(Some(0)) > half);
+        assert!(download_share(Some(u32::MAX as u64)) < 1.0);
+    }
+
+    #[test]
+    fn verify_and_extract_local_archive() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/simple_gz.tar.gz");
+        let sha256 = {
+            let mut file = fs::File::open(&fixture).unwrap();
+            let len = file.metadata().unwrap().len();
+            sha256_of_prefix(&mut file, len, |_| ()).unwrap()
+        };
+        let dest = tempfile::tempdir().unwrap();
+
+        let progress = Progress::noop().with_len(40.0);
+        verify_and_extract(&fixture, dest.path(), Some(&sha256), &progress).unwrap();
+
+        assert!(dest.path().join("simple_gz").join("aaa.txt").is_file());
+        assert_eq!(progress.position(), 40.0);
+        let phases = progress.phase_timings();
+        let phases = phases.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>();
+        assert_eq!(phases, ["verify", "extract"]);
+
+        let progress = Progress::noop().with_len(40.0);
+        let wrong = "0".repeat(64);
+        let dest = tempfile::tempdir().unwrap();
+        assert!(verify_and_extract(&fixture, dest.path(), Some(&wrong), &progress).is_err());
+        assert_eq!(progress.position(), 0.0);
+    }
+
+    /// Get the ranges that were served successfully, such as `3000-3999`.
+    fn served_ranges(server: &MockServer) -> Vec<String> {
+        server
+            .requests()
+            .into_iter()
+            .filter(|req| req.status == 206)
+            .filter_map(|req| Some(req.range?.strip_prefix("bytes=")?.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn chunked_download_resumes_missing_chunks() {
+        let body = (0..10_000_u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let server = MockServer::start();
+        server.serve("/big.tar.xz", MockResponse::new(body.clone()).fail_after(3));
+        let url = server.url("big.tar.xz");
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("big.tar.xz");
+
+        // the connection "drops" after 3 chunks
+        let progress = Progress::noop().with_len(50.0);
+        let opt = || {
+            DownloadOpt::new("big")
+                .with_handler(None)
+                .with_retry(RetryPolicy::none())
+        };
+        assert!(opt()
+            .download_chunked(&url, &dest, 1000, &progress)
+            .is_err());
+        assert_eq!(served_ranges(&server).len(), 3);
+        assert!(dir.path().join("big.tar.xz.chunks").is_file());
+
+        // restart with a working server
+        let server = MockServer::start();
+        server.serve("/big.tar.xz", MockResponse::new(body.clone()));
+        let url = server.url("big.tar.xz");
+        let positions = std::sync::Mutex::new(vec![]);
+        let pos_cb = |pos: f32| {
+            positions.lock().unwrap().push(pos);
+            Ok(())
+        };
+        let progress = Progress::new(&pos_cb).with_len(50.0);
+        opt()
+            .download_chunked(&url, &dest, 1000, &progress)
+            .unwrap();
+
+        let fetched = served_ranges(&server);
+        assert_eq!(fetched.len(), 7);
+        assert_eq!(fetched[0], "3000-3999");
+        assert_eq!(fetched[6], "9000-9999");
+        assert_eq!(fs::read(&dest).unwrap(), body);
+        assert!(!dir.path().join("big.tar.xz.chunks").exists());
+        // the pre-completed chunks are accounted at once
+        let positions = positions.lock().unwrap();
+        assert_eq!(positions.first(), Some(&15.0));
+        assert_eq!(positions.last(), Some(&50.0));
+    }
+
+    #[test]
+    fn download_all_on_a_pool() {
+        let server = MockServer::start();
+        let bodies = [vec![1_u8; 3000], vec![2; 100_000], vec![3; 10]];
+        for (i, body) in bodies.iter().enumerate() {
+            server.serve(format!("/file{i}"), MockResponse::new(body.clone()));
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let files = (0..bodies.len())
+            .map(|i| {
+                let dest = dir.path().join(format!("file{i}"));
+                (server.url(&format!("file{i}")), dest)
+            })
+            .collect::<Vec<_>>();
+
+        let positions = std::sync::Mutex::new(vec![]);
+        let pos_cb = |pos: f32| {
+            positions.lock().unwrap().push(pos);
+            Ok(())
+        };
+        let progress = Progress::new(&pos_cb).with_len(40.0);
+        DownloadOpt::new("files")
+            .with_handler(None)
+            .download_all(&files, 2, &progress)
+            .unwrap();
+
+        for ((_, dest), body) in files.iter().zip(&bodies) {
+            assert_eq!(&fs::read(dest).unwrap(), body);
+        }
+        let positions = positions.lock().unwrap();
+        assert!(positions.windows(2).all(|w| w[0] <= w[1]), "{positions:?}");
+        assert_eq!(progress.position(), 40.0);
+        let history = progress.message_history();
+        assert_eq!(history.len(), 6);
+        assert!(history.contains(&ProgressMsg::Downloaded("file1").to_string()));
+
+        // a failed download fails the whole batch
+        let mut files = files;
+        files.push((server.url("missing"), dir.path().join("missing")));
+        assert!(DownloadOpt::new("files")
+            .with_handler(None)
+            .download_all(&files, 4, &Progress::noop().with_len(40.0))
+            .is_err());
+    }
+
+    #[test]
+    fn temporary_errors_are_retried() {
+        let server = MockServer::start();
+        server.serve("/flaky", MockResponse::new(vec![7_u8; 100]).fail_first(2));
+        let url = server.url("flaky");
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("flaky");
+        let retry = RetryPolicy::new()
+            .with_initial_delay(Duration::from_millis(1))
+            .jitter(false);
+
+        let progress = Progress::noop().with_len(10.0);
+        DownloadOpt::new("flaky")
+            .with_handler(None)
+            .with_retry(retry)
+            .download_with_progress(&url, &dest, &progress)
+            .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), [7; 100]);
+        assert_eq!(progress.position(), 10.0);
+        // each retry is announced
+        let reason = DownloadError::status(&url, reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let history = progress.message_history();
+        for (attempt, delay) in [(2, 1), (3, 2)] {
+            let msg = ProgressMsg::Retrying {
+                name: "flaky",
+                attempt,
+                max_attempts: 3,
+                delay: Duration::from_millis(delay),
+                reason: &reason.to_string(),
+            };
+            assert!(history.contains(&msg.to_string()), "{history:?}");
+        }
+
+        // other errors are not retried
+        DownloadOpt::new("missing")
+            .with_handler(None)
+            .with_retry(retry)
+            .download_file(&server.url("missing"), &dest, false)
+            .unwrap_err();
+        let requests = server.requests();
+        assert_eq!(requests.iter().filter(|r| r.path == "/missing").count(), 1);
+    }
+
+    #[test]
+    fn downloads_through_proxy() {
+        let proxy_server = MockServer::start();
+        proxy_server.serve(
+            "http://dist.example.invalid/file",
+            MockResponse::new(b"proxied".to_vec()),
+        );
+        proxy_server.serve(
+            "http://dist.example.invalid/private",
+            MockResponse::new(vec![]).status(407),
+        );
+        let proxy = CrateProxy {
+            no_proxy: Some("localhost".into()),
+            ..CrateProxy::from_url(proxy_server.url("")).unwrap()
+        };
+        let opt = DownloadOpt::new("file")
+            .with_handler(None)
+            .with_proxy(Some(proxy));
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("file");
+
+        let url = Url::parse("http://dist.example.invalid/file").unwrap();
+        opt.clone().download_file(&url, &dest, false).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"proxied");
+
+        let url = Url::parse("http://dist.example.invalid/private").unwrap();
+        let err = opt.download_file(&url, &dest, false).unwrap_err();
+        let expected_proxy = format!("127.0.0.1:{}", proxy_server.addr().port());
+        assert_eq!(
+            err.downcast_ref::<DownloadError>(),
+            Some(&DownloadError::ProxyAuth {
+                url: url.to_string(),
+                proxy: Some(expected_proxy),
+            })
+        );
+        assert!(!DownloadError::is_temporary(&err));
+    }
+
+    #[test]
+    fn interrupted_download_resumes_from_part_file() {
+        let body = (0..200_000_u32)
+            .map(|i| (i % 241) as u8)
+            .collect::<Vec<_>>();
+        let server = MockServer::start();
+        server.serve("/big", MockResponse::new(body.clone()).truncate_at(150_000));
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("big");
+        let opt = || DownloadOpt::new("big").with_handler(None);
+
+        assert!(opt()
+            .download_file(&server.url("big"), &dest, true)
+            .is_err());
+        assert!(!dest.exists());
+        let checkpoint = fs::read_to_string(dir.path().join("big.part.offset")).unwrap();
+        let offset = checkpoint
+            .strip_prefix("200000 ")
+            .and_then(|o| o.parse::<u64>().ok())
+            .unwrap();
+        assert!(offset > 0, "{checkpoint}");
+
+        server.serve("/big", MockResponse::new(body.clone()));
+        let mut first_pos = None;
+        opt()
+            .download_file_(&server.url("big"), &dest, true, None, |pos, total| {
+                assert_eq!(total, 200_000);
+                first_pos.get_or_insert(pos);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), body);
+        assert!(first_pos.unwrap() > offset);
+        let last = server.requests().pop().unwrap();
+        assert_eq!(last.range, Some(format!("bytes={offset}-")));
+        assert_eq!(last.status, 206);
+        assert!(!dir.path().join("big.part").exists());
+        assert!(!dir.path().join("big.part.offset").exists());
+
+        // restarts if the server doesn't support ranges
+        fs::remove_file(&dest).unwrap();
+        fs::write(dir.path().join("big.part"), &body[..1000]).unwrap();
+        server.serve("/big", MockResponse::new(body.clone()).ignore_range());
+        opt()
+            .download_file(&server.url("big"), &dest, true)
+            .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), body);
+    }
+
+    #[test]
+    fn cancelled_download_removes_partial_file() {
+        let body = vec![7_u8; 300_000];
+        let server = MockServer::start();
+        server.serve("/big", MockResponse::new(body));
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("big");
+
+        // a resumable download is discarded rather than kept for the next attempt
+        let token = CancellationToken::new();
+        let err = DownloadOpt::new("big")
+            .with_handler(None)
+            .with_cancellation_token(token.clone())
+            .download_file_(&server.url("big"), &dest, true, None, |_, _| {
+                token.cancel();
+                Ok(())
+            })
+            .unwrap_err();
+        assert!(err.is::<Cancelled>());
+        assert!(crate::utils::walk_dir(dir.path(), false)
+            .unwrap()
+            .is_empty());
+
+        // cancelled through the progress
+        let token = CancellationToken::new();
+        let pos_cb = |_: f32| {
+            token.cancel();
+            Ok(())
+        };
+        let progress = Progress::new(&pos_cb)
+            .with_len(10.0)
+            .with_cancellation_token(token.clone());
+        let err = DownloadOpt::new("big")
+            .with_handler(None)
+            .download_with_progress(&server.url("big"), &dest, &progress)
+            .unwrap_err();
+        assert!(err.is::<Cancelled>());
+        assert!(crate::utils::walk_dir(dir.path(), false)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn content_range_parsing() {
+        assert_eq!(parse_content_range("bytes 100-199/1000"), Some((100, 1000)));
+        assert_eq!(parse_content_range("bytes 100-199/*"), None);
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+    }
+}
@@ -1,101 +1,393 @@
 use anyhow::{anyhow, bail, Context, Result};
 use flate2::read::GzDecoder;
 use sevenz_rust::{Password, SevenZReader};
+use std::cell::Cell;
 use std::ffi::OsStr;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
 use xz2::read::XzDecoder;
 use zip::ZipArchive;
+use zstd::stream::read::Decoder as ZstdDecoder;
 
 use crate::utils::progress_bar::Style;
 
-use super::progress_bar::CliProgress;
+use super::progress_bar::{AggregateProgress, CliBar, CliProgress, Progress, ProgressMsg};
+use super::{CancellationToken, Cancelled};
 
 enum ExtractableKind {
     /// `7-zip` compressed files, ended with `.7z`
-    SevenZ(SevenZReader<File>),
-    Gz(tar::Archive<GzDecoder<File>>),
-    Xz(tar::Archive<XzDecoder<File>>),
+    SevenZ(Box<SevenZReader<File>>),
     Zip(ZipArchive<File>),
+    /// Tarballs that are either uncompressed, or compressed with `gzip`, `xz` or `zstd`.
+    Tar {
+        archive: tar::Archive<Box<dyn Read + Send>>,
+        /// The number of (compressed) bytes read from the file, and the size of the file,
+        /// which are used to estimate the progress, as the entries are unknown beforehand.
+        consumed: Arc<AtomicU64>,
+        size: u64,
+    },
 }
 
+const SEVENZ_MAGIC: &[u8] = &[b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c];
+const ZIP_MAGICS: &[&[u8]] = &[b"PK\x03\x04", b"PK\x05\x06", b"PK\x07\x08"];
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+/// The magic of `ustar` tarballs, which is placed after the name and metadata of the first entry.
+const TAR_MAGIC: (usize, &[u8]) = (257, b"ustar");
+
+/// The supported archive formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveFormat {
+    SevenZ,
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// Detect the format from the leading bytes of a file.
+    fn from_magic(header: &[u8]) -> Option<Self> {
+        let (tar_offset, tar_magic) = TAR_MAGIC;
+        let format = if header.starts_with(SEVENZ_MAGIC) {
+            Self::SevenZ
+        } else if ZIP_MAGICS.iter().any(|magic| header.starts_with(magic)) {
+            Self::Zip
+        } else if header.starts_with(GZIP_MAGIC) {
+            Self::TarGz
+        } else if header.starts_with(XZ_MAGIC) {
+            Self::TarXz
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Self::TarZst
+        } else if header
+            .get(tar_offset..)
+            .is_some_and(|h| h.starts_with(tar_magic))
+        {
+            Self::Tar
+        } else {
+            return None;
+        };
+        Some(format)
+    }
+
+    fn from_extension(ext: &str) -> Option<Self> {
+        let format = match ext.to_ascii_lowercase().as_str() {
+            "7z" => Self::SevenZ,
+            "zip" => Self::Zip,
+            "tar" => Self::Tar,
+            "gz" | "tgz" => Self::TarGz,
+            "xz" | "txz" => Self::TarXz,
+            "zst" | "tzst" => Self::TarZst,
+            _ => return None,
+        };
+        Some(format)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::SevenZ => "7z",
+            Self::Zip => "zip",
+            Self::Tar => "tar",
+            Self::TarGz => "tar.gz",
+            Self::TarXz => "tar.xz",
+            Self::TarZst => "tar.zst",
+        }
+    }
+}
+
+/// How the progress of extraction is counted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractProgressUnit {
+    /// Use the default unit of each format: entries for `zip` and bytes for `7z`.
+    #[default]
+    Auto,
+    /// The number of bytes extracted, which is smoother for a few large files.
+    Bytes,
+    /// The number of entries extracted, such as `342/1024`, which is more intuitive
+    /// for many small files.
+    Entries,
+}
+
+// NB: The number of entries of tarballs cannot be known without decompressing the whole
+// archive, so their progress is always displayed as spinners, regardless of the unit.
 pub struct Extractable<'a> {
     path: &'a Path,
     kind: ExtractableKind,
+    unit: ExtractProgressUnit,
+    indicator: CliProgress<CliBar>,
+    progress: Option<&'a Progress<'a>>,
+    cancel: CancellationToken,
 }
 
 impl<'a> Extractable<'a> {
+    /// Load an archive, its format is detected by the leading magic bytes, so that a
+    /// mislabeled archive (such as a `.tar.gz` that is actually compressed with `xz`)
+    /// still gets extracted.
+    ///
+    /// Only files with a supported archive extension (`.7z`, `.zip`, `.tar`, `.gz`, `.xz`,
+    /// `.zst`, etc.), or no extension at all, are considered extractable, so that the
+    /// archive-like files, such as `.vsix` (which is a zip), are kept as is.
     pub fn load(path: &'a Path) -> Result<Self> {
-        let ext = path
-            .extension()
-            .ok_or_else(|| {
-                anyhow!(
-                    "'{}' is not extractable because it appears to have no file extension",
-                    path.display()
-                )
-            })?
-            .to_str()
-            .ok_or_else(|| {
-                anyhow!(
-                "'{}' is not extractable because its extension contains invalid unicode characters",
-                path.display()
-            )
-            })?;
-
-        let kind = match ext {
-            "7z" => {
-                info!(
-                    "{}",
-                    t!("loading_archive_info", kind = ext, path = path.display())
-                );
-                ExtractableKind::SevenZ(SevenZReader::open(path, Password::empty())?)
-            }
-            "zip" => {
-                info!(
-                    "{}",
-                    t!("loading_archive_info", kind = ext, path = path.display())
-                );
-                ExtractableKind::Zip(ZipArchive::new(File::open(path)?)?)
+        let by_ext = match path.extension() {
+            Some(ext) => {
+                let ext = ext.to_str().ok_or_else(|| {
+                    anyhow!(
+                        "'{}' is not extractable because its extension contains invalid unicode characters",
+                        path.display()
+                    )
+                })?;
+                let format = ArchiveFormat::from_extension(ext)
+                    .ok_or_else(|| anyhow!("'{ext}' is not a supported extractable file format"))?;
+                Some(format)
             }
-            "gz" => {
-                info!(
-                    "{}",
-                    t!("loading_archive_info", kind = ext, path = path.display())
+            None => None,
+        };
+
+        let mut header = Vec::with_capacity(TAR_MAGIC.0 + TAR_MAGIC.1.len());
+        File::open(path)
+            .with_context(|| format!("unable to open '{}'", path.display()))?
+            .take(header.capacity() as u64)
+            .read_to_end(&mut header)?;
+        let format = match (ArchiveFormat::from_magic(&header), by_ext) {
+            (Some(format), Some(expected)) if format != expected => {
+                debug!(
+                    "'{}' is a {} archive despite of its extension",
+                    path.display(),
+                    format.name()
                 );
-                let tar_gz = GzDecoder::new(File::open(path)?);
-                ExtractableKind::Gz(tar::Archive::new(tar_gz))
+                format
             }
-            "xz" => {
-                info!(
-                    "{}",
-                    t!("loading_archive_info", kind = ext, path = path.display())
-                );
-                let tar_xz = XzDecoder::new(File::open(path)?);
-                ExtractableKind::Xz(tar::Archive::new(tar_xz))
+            (Some(format), _) => format,
+            // such as old tarballs without `ustar` magic
+            (None, Some(format)) => format,
+            (None, None) => bail!(
+                "'{}' is not extractable because its format cannot be recognized",
+                path.display()
+            ),
+        };
+
+        info!(
+            "{}",
+            t!(
+                "loading_archive_info",
+                kind = format.name(),
+                path = path.display()
+            )
+        );
+        let tar = |decode: fn(CountingReader) -> Result<Box<dyn Read + Send>>| -> Result<_> {
+            let file = File::open(path)?;
+            let size = file.metadata()?.len();
+            let consumed = Arc::new(AtomicU64::new(0));
+            let reader = CountingReader {
+                inner: file,
+                consumed: Arc::clone(&consumed),
+            };
+            Ok(ExtractableKind::Tar {
+                archive: tar::Archive::new(decode(reader)?),
+                consumed,
+                size,
+            })
+        };
+        let kind = match format {
+            ArchiveFormat::SevenZ => {
+                ExtractableKind::SevenZ(Box::new(SevenZReader::open(path, Password::empty())?))
             }
-            _ => bail!("'{ext}' is not a supported extractable file format"),
+            ArchiveFormat::Zip => ExtractableKind::Zip(ZipArchive::new(File::open(path)?)?),
+            ArchiveFormat::Tar => tar(|r| Ok(Box::new(r)))?,
+            ArchiveFormat::TarGz => tar(|r| Ok(Box::new(GzDecoder::new(r))))?,
+            ArchiveFormat::TarXz => tar(|r| Ok(Box::new(XzDecoder::new(r))))?,
+            ArchiveFormat::TarZst => tar(|r| Ok(Box::new(ZstdDecoder::new(r)?)))?,
         };
 
-        Ok(Self { path, kind })
+        Ok(Self {
+            path,
+            kind,
+            unit: ExtractProgressUnit::default(),
+            indicator: CliProgress::new(),
+            progress: None,
+            cancel: CancellationToken::new(),
+        })
+    }
+
+    /// Choose how the progress is counted, check [`ExtractProgressUnit`] for details.
+    pub fn with_progress_unit(mut self, unit: ExtractProgressUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Use a different progress indicator than the default CLI progress bar.
+    pub fn with_indicator(mut self, indicator: CliProgress<CliBar>) -> Self {
+        self.indicator = indicator;
+        self
+    }
+
+    /// Advance the `progress` by its `len` as the entries are extracted, rather than
+    /// all at once after the extraction.
+    ///
+    /// The progress of tarballs is estimated by how much of the file has been read, as
+    /// their entries are unknown beforehand.
+    pub fn with_progress(mut self, progress: &'a Progress<'a>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Stop extracting once the `token` is cancelled, which is checked before each entry.
+    ///
+    /// The entries extracted so far are removed when that happens, check
+    /// [`extract_to`](Extractable::extract_to) for details.
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
     }
 
     /// Extract current file into a specific directory.
     ///
     /// This will extract file under the `root`, make sure it's an empty folder before using this function.
+    ///
+    /// If the extraction was cancelled, the files and directories extracted directly under
+    /// `root` are removed (or `root` itself, if it was created by this function), then
+    /// [`Cancelled`] is returned.
     pub fn extract_to(&mut self, root: &Path) -> Result<()> {
         let helper = ExtractHelper {
             file_path: self.path,
             output_dir: root,
-            indicator: CliProgress::new(),
+            indicator: self.indicator.clone(),
+            unit: self.unit,
+            progress: self.progress.map(ProgressShare::new),
+            cancel: &self.cancel,
+        };
+
+        remove_extracted_on_cancel(root, || {
+            match &mut self.kind {
+                ExtractableKind::Zip(archive) => helper.extract_zip(archive),
+                ExtractableKind::SevenZ(archive) => helper.extract_7z(archive),
+                ExtractableKind::Tar {
+                    archive,
+                    consumed,
+                    size,
+                } => helper.extract_tar(archive, consumed, *size),
+            }?;
+            helper
+                .progress
+                .as_ref()
+                .map_or(Ok(()), ProgressShare::finish)
+        })
+    }
+
+    /// Extract current file into a specific directory like [`extract_to`](Extractable::extract_to),
+    /// but with up to `workers` threads decompressing and writing the members at the same time.
+    ///
+    /// This only works for formats whose members can be read independently, which is `zip`
+    /// for now, other formats are extracted serially. Every directory is created (in the order
+    /// of the archive) before any file gets written.
+    ///
+    /// Each member is reported as a task of the `aggregate` progress, weighted by its size.
+    /// Cancelling either this or the progress is handled the same as in
+    /// [`extract_to`](Extractable::extract_to).
+    pub fn extract_parallel_to(
+        &mut self,
+        root: &Path,
+        workers: usize,
+        aggregate: &AggregateProgress,
+    ) -> Result<()> {
+        if !matches!(self.kind, ExtractableKind::Zip(_)) {
+            let task = aggregate.task();
+            task.set_total(1)?;
+            self.extract_to(root)?;
+            return task.advance(1);
+        }
+        remove_extracted_on_cancel(root, || self.extract_zip_parallel(root, workers, aggregate))
+    }
+
+    fn extract_zip_parallel(
+        &mut self,
+        root: &Path,
+        workers: usize,
+        aggregate: &AggregateProgress,
+    ) -> Result<()> {
+        let ExtractableKind::Zip(archive) = &mut self.kind else {
+            unreachable!("only zip archives can be extracted in parallel");
         };
 
-        match &mut self.kind {
-            ExtractableKind::Zip(archive) => helper.extract_zip(archive),
-            ExtractableKind::SevenZ(archive) => helper.extract_7z(archive),
-            ExtractableKind::Gz(archive) => helper.extract_tar(archive),
-            ExtractableKind::Xz(archive) => helper.extract_tar(archive),
+        // create the directories first, and collect the files to extract
+        let mut files = vec![];
+        for idx in 0..archive.len() {
+            let member = archive.by_index_raw(idx)?;
+            let Some(out_path) = member.enclosed_name().map(|path| root.join(path)) else {
+                continue;
+            };
+            if member.is_dir() {
+                super::ensure_dir(&out_path)?;
+            } else {
+                super::ensure_parent_dir(&out_path)?;
+                files.push((idx, out_path, member.size()));
+            }
         }
+        let tasks = files
+            .iter()
+            .map(|(_, _, size)| {
+                let task = aggregate.task();
+                task.set_total(*size)?;
+                Ok(task)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let (path, files, next, failed, cancel) = (self.path, &files, &next, &failed, &self.cancel);
+        let (tx, rx) = mpsc::channel::<(usize, u64)>();
+        std::thread::scope(|s| {
+            let handles = (0..workers.clamp(1, files.len().max(1)))
+                .map(|_| {
+                    let tx = tx.clone();
+                    let work = move || -> Result<()> {
+                        // every worker needs its own reader, as members are read by seeking
+                        let mut archive = ZipArchive::new(File::open(path)?)?;
+                        while !failed.load(Ordering::Relaxed) {
+                            cancel.check()?;
+                            let n = next.fetch_add(1, Ordering::Relaxed);
+                            let Some((idx, out_path, _)) = files.get(n) else {
+                                break;
+                            };
+                            extract_zip_member(&mut archive, *idx, out_path, |bytes| {
+                                // the receiver only hangs up after every worker finished
+                                let _ = tx.send((n, bytes));
+                            })?;
+                        }
+                        Ok(())
+                    };
+                    s.spawn(move || {
+                        // any error stops the other workers from taking more members
+                        let res = work();
+                        if res.is_err() {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        res
+                    })
+                })
+                .collect::<Vec<_>>();
+            drop(tx);
+
+            let mut progress_err = None;
+            for (n, bytes) in rx {
+                if let Err(e) = tasks[n].advance(bytes) {
+                    failed.store(true, Ordering::Relaxed);
+                    progress_err.get_or_insert(e);
+                }
+            }
+            for handle in handles {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| bail!("extraction worker panicked"))
+                    .with_context(|| format!("failed to extract '{}'", path.display()))?;
+            }
+            progress_err.map_or(Ok(()), Err)
+        })
     }
 
     /// Extract file into a specific root like [`extract_to`](Extractable::extract_to),
@@ -154,6 +446,175 @@ impl<'a> Extractable<'a> {
     }
 }
 
+/// Extract the `zip` member at `idx` to `out_path`, `on_write` is called with the number
+/// of bytes of each written chunk.
+fn extract_zip_member<F: FnMut(u64)>(
+    archive: &mut ZipArchive<File>,
+    idx: usize,
+    out_path: &Path,
+    mut on_write: F,
+) -> Result<()> {
+    let mut member = archive.by_index(idx)?;
+    let mut out_file = File::create(out_path)?;
+    let mut buf = vec![0_u8; 65536];
+    loop {
+        let n = member.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        out_file.write_all(&buf[..n])?;
+        on_write(n as u64);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Some(mode) = member.unix_mode() {
+            std::fs::set_permissions(out_path, std::fs::Permissions::from_mode(mode))?;
+        }
+    }
+    Ok(())
+}
+
+/// Decompress a `gzip`, `xz` or `zstd` compressed stream into memory, the codec is detected
+/// by the leading magic bytes of the data.
+///
+/// The progress advances by the number of **input** bytes consumed, which requires the
+/// input size to be set with [`Progress::with_byte_total`], otherwise the progress
+/// only increases its whole `len` once finished.
+pub fn decompress_with_progress<R: Read>(reader: R, progress: &Progress) -> Result<Vec<u8>> {
+    let mut consumed = 0_u64;
+    let mut reported = 0_f32;
+    let total = progress.byte_total().filter(|t| *t > 0);
+    let mut buffered = BufReader::new(ProgressReader {
+        inner: reader,
+        on_read: |n: usize| -> Result<()> {
+            consumed += n as u64;
+            if let Some(total) = total {
+                let target = progress.len() * (consumed.min(total) as f32 / total as f32);
+                if target > reported {
+                    progress.inc(Some(target - reported))?;
+                    reported = target;
+                }
+            }
+            Ok(())
+        },
+    });
+
+    let magic = buffered.fill_buf()?;
+    let mut output = vec![];
+    if magic.starts_with(GZIP_MAGIC) {
+        GzDecoder::new(&mut buffered).read_to_end(&mut output)?;
+    } else if magic.starts_with(XZ_MAGIC) {
+        XzDecoder::new(&mut buffered).read_to_end(&mut output)?;
+    } else if magic.starts_with(ZSTD_MAGIC) {
+        ZstdDecoder::with_buffer(&mut buffered)?.read_to_end(&mut output)?;
+    } else {
+        bail!("unable to decompress data because its compression format is not supported");
+    }
+    drop(buffered);
+
+    if progress.len() > reported {
+        progress.inc(Some(progress.len() - reported))?;
+    }
+    Ok(output)
+}
+
+/// A reader that reports the number of bytes read, failing to report is treated as IO error.
+struct ProgressReader<R, F> {
+    inner: R,
+    on_read: F,
+}
+
+impl<R: Read, F: FnMut(usize) -> Result<()>> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        (self.on_read)(n).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        Ok(n)
+    }
+}
+
+/// A file reader that counts the bytes read, so the progress of decompressing it can be
+/// estimated.
+struct CountingReader {
+    inner: File,
+    consumed: Arc<AtomicU64>,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.consumed.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// Advances a [`Progress`] by the fraction of an extraction that is done, up to its `len`.
+struct ProgressShare<'p, 'a> {
+    progress: &'p Progress<'a>,
+    reported: Cell<f32>,
+}
+
+impl<'p, 'a> ProgressShare<'p, 'a> {
+    fn new(progress: &'p Progress<'a>) -> Self {
+        Self {
+            progress,
+            reported: Cell::new(0.0),
+        }
+    }
+
+    fn report(&self, done: u64, total: u64) -> Result<()> {
+        if total == 0 {
+            return Ok(());
+        }
+        let target = self.progress.len() * (done.min(total) as f32 / total as f32);
+        self.advance_to(target)
+    }
+
+    /// Advance the rest of the `len`, such as when the estimated total was too large.
+    fn finish(&self) -> Result<()> {
+        self.advance_to(self.progress.len())
+    }
+
+    fn advance_to(&self, target: f32) -> Result<()> {
+        let reported = self.reported.get();
+        if target > reported {
+            self.progress.inc(Some(target - reported))?;
+            self.reported.set(target);
+        }
+        Ok(())
+    }
+}
+
+/// Run `extract` into `root`, then remove the entries it created under `root` if it was
+/// cancelled, so that no incomplete files are left behind.
+///
+/// Only the top-level entries are compared, the ones that existed before are kept as is.
+fn remove_extracted_on_cancel<F>(root: &Path, extract: F) -> Result<()>
+where
+    F: FnOnce() -> Result<()>,
+{
+    let existed = root.is_dir();
+    let before = if existed {
+        super::walk_dir(root, false)?
+    } else {
+        vec![]
+    };
+    let res = extract();
+    if matches!(&res, Err(e) if e.is::<Cancelled>()) {
+        if !existed {
+            super::remove(root)?;
+        } else {
+            for entry in super::walk_dir(root, false)? {
+                if !before.contains(&entry) {
+                    super::remove(&entry)?;
+                }
+            }
+        }
+    }
+    res
+}
+
 fn filename_matches_keyword<S: AsRef<OsStr>>(path: &Path, keyword: S) -> bool {
     if let Some(name) = path.file_name() {
         name == keyword.as_ref()
@@ -162,18 +623,21 @@ fn filename_matches_keyword<S: AsRef<OsStr>>(path: &Path, keyword: S) -> bool {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
 struct ExtractHelper<'a, T: Sized> {
     file_path: &'a Path,
     output_dir: &'a Path,
     indicator: CliProgress<T>,
+    unit: ExtractProgressUnit,
+    progress: Option<ProgressShare<'a, 'a>>,
+    cancel: &'a CancellationToken,
 }
 
 impl<T: Sized> ExtractHelper<'_, T> {
     fn start_progress_bar(&self, style: Style) -> Result<T> {
         (self.indicator.start)(
-            format!("extracting file '{}'", self.file_path.display()),
+            ProgressMsg::Extracting(&self.file_path.display().to_string()).to_string(),
             style,
+            self.indicator.opt,
         )
     }
 
@@ -182,16 +646,39 @@ impl<T: Sized> ExtractHelper<'_, T> {
     }
 
     fn end_progress_bar(&self, bar: &T) {
-        (self.indicator.stop)(bar, "extraction complete.".into());
+        (self.indicator.stop)(bar, ProgressMsg::Extracted.to_string());
+    }
+
+    fn report_progress(&self, done: u64, total: u64) -> Result<()> {
+        self.progress
+            .as_ref()
+            .map_or(Ok(()), |share| share.report(done, total))
     }
 
     fn extract_zip(&self, archive: &mut ZipArchive<File>) -> Result<()> {
         let zip_len = archive.len();
+        let mut entries = 0_u64;
+        let mut bytes = 0_u64;
+        for i in 0..zip_len {
+            let zip_file = archive.by_index_raw(i)?;
+            if zip_file.enclosed_name().is_some() {
+                entries += 1;
+                bytes += zip_file.size();
+            }
+        }
+        let by_bytes = self.unit == ExtractProgressUnit::Bytes;
 
         // Init progress
-        let bar = self.start_progress_bar(Style::Len(zip_len.try_into()?))?;
+        let bar = self.start_progress_bar(if by_bytes {
+            Style::Bytes(bytes)
+        } else {
+            Style::Len(entries)
+        })?;
 
+        let mut extracted = 0_u64;
+        let mut extracted_bytes = 0_u64;
         for i in 0..zip_len {
+            self.cancel.check()?;
             let mut zip_file = archive.by_index(i)?;
             let Some(out_path) = zip_file
                 .enclosed_name()
@@ -200,13 +687,14 @@ impl<T: Sized> ExtractHelper<'_, T> {
                 continue;
             };
 
-            if zip_file.is_dir() {
+            let written = if zip_file.is_dir() {
                 super::ensure_dir(&out_path)?;
+                0
             } else {
                 super::ensure_parent_dir(&out_path)?;
                 let mut out_file = std::fs::File::create(&out_path)?;
-                std::io::copy(&mut zip_file, &mut out_file)?;
-            }
+                std::io::copy(&mut zip_file, &mut out_file)?
+            };
 
             #[cfg(unix)]
             {
@@ -216,7 +704,15 @@ impl<T: Sized> ExtractHelper<'_, T> {
                 }
             }
 
-            self.update_progress_bar(&bar, Some(i.try_into()?));
+            extracted += 1;
+            extracted_bytes += written;
+            if by_bytes {
+                (self.indicator.inc)(&bar, written);
+                self.report_progress(extracted_bytes, bytes)?;
+            } else {
+                self.update_progress_bar(&bar, Some(extracted));
+                self.report_progress(extracted, entries)?;
+            }
         }
         self.end_progress_bar(&bar);
 
@@ -225,16 +721,43 @@ impl<T: Sized> ExtractHelper<'_, T> {
 
     fn extract_7z(&self, archive: &mut SevenZReader<File>) -> Result<()> {
         let entries = &archive.archive().files;
-        let sz_len: u64 = entries
-            .iter()
-            .filter_map(|e| e.has_stream().then_some(e.size()))
-            .sum();
-        let mut extracted_len: u64 = 0;
+        let by_entries = self.unit == ExtractProgressUnit::Entries;
+        let total = if by_entries {
+            entries.len() as u64
+        } else {
+            entries
+                .iter()
+                .filter_map(|e| e.has_stream().then_some(e.size()))
+                .sum()
+        };
+        let style = if by_entries {
+            Style::Len(total)
+        } else {
+            Style::Bytes(total)
+        };
 
         // Init progress bar
-        let bar = self.start_progress_bar(Style::Bytes(sz_len))?;
-
+        let bar = self.start_progress_bar(style)?;
+
+        let mut done = 0_u64;
+        // errors of the progress cannot be returned through `sevenz_rust::Error`
+        let mut progress_err = None;
+        let mut advance = |n: u64| {
+            done += n;
+            (self.indicator.inc)(&bar, n);
+            match self.report_progress(done, total) {
+                Ok(()) => true,
+                Err(e) => {
+                    progress_err = Some(e);
+                    false
+                }
+            }
+        };
         archive.for_each_entries(|entry, reader| {
+            if self.cancel.is_cancelled() {
+                // stop iterating, this is reported after the iteration
+                return Ok(false);
+            }
             let mut buf = [0_u8; 1024];
             let entry_path = PathBuf::from(entry.name());
             let out_path = self.output_dir.join(&entry_path);
@@ -246,7 +769,7 @@ impl<T: Sized> ExtractHelper<'_, T> {
                         out_path.display()
                     ))
                 })?;
-                Ok(true)
+                Ok(!by_entries || advance(1))
             } else {
                 super::ensure_parent_dir(&out_path).map_err(|_| {
                     sevenz_rust::Error::other(format!(
@@ -259,23 +782,31 @@ impl<T: Sized> ExtractHelper<'_, T> {
                 loop {
                     let read_size = reader.read(&mut buf)?;
                     if read_size == 0 {
-                        break Ok(true);
+                        break Ok(!by_entries || advance(1));
                     }
                     out_file.write_all(&buf[..read_size])?;
-                    extracted_len += read_size as u64;
                     // Update progress bar
-                    self.update_progress_bar(&bar, Some(extracted_len));
+                    if !by_entries && !advance(read_size as u64) {
+                        break Ok(false);
+                    }
                 }
             }
             // NB: sevenz-rust does not support `unix-mode` like `zip` does, so we might ended up
             // mess up the extracted file's permission... let's hope that never happens.
         })?;
+        progress_err.map_or(Ok(()), Err)?;
+        self.cancel.check()?;
 
         self.end_progress_bar(&bar);
         Ok(())
     }
 
-    fn extract_tar<R: Read>(&self, archive: &mut tar::Archive<R>) -> Result<()> {
+    fn extract_tar<R: Read>(
+        &self,
+        archive: &mut tar::Archive<R>,
+        consumed: &AtomicU64,
+        size: u64,
+    ) -> Result<()> {
         #[cfg(unix)]
         archive.set_preserve_permissions(true);
 
@@ -290,10 +821,22 @@ impl<T: Sized> ExtractHelper<'_, T> {
         })?;
 
         for (idx, mut entry) in entries.into_iter().filter_map(|e| e.ok()).enumerate() {
-            let entry_path = entry.path()?;
+            self.cancel.check()?;
+            let entry_path = entry.path()?.into_owned();
             let out_path = self.output_dir.join(&entry_path);
+            let entry_type = entry.header().entry_type();
 
-            if entry.header().entry_type().is_dir() {
+            if entry_type.is_symlink() || entry_type.is_hard_link() {
+                // Links have no content of their own, recreate them as links instead of
+                // writing empty files, which also resolves hard links within `output_dir`.
+                entry.unpack_in(self.output_dir).with_context(|| {
+                    format!(
+                        "failed to create link '{}' when extracting '{}'",
+                        entry_path.display(),
+                        self.file_path.display()
+                    )
+                })?;
+            } else if entry_type.is_dir() {
                 super::ensure_dir(&out_path).with_context(|| {
                     format!(
                         "failed to create directory when extracting '{}'",
@@ -314,6 +857,7 @@ impl<T: Sized> ExtractHelper<'_, T> {
 
             // Update progress bar
             self.update_progress_bar(&bar, Some(u64::try_from(idx)? + 1));
+            self.report_progress(consumed.load(Ordering::Relaxed), size)?;
         }
 
         // Stop progress bar's progress
@@ -1,5 +1,6 @@
 use anyhow::bail;
 use anyhow::{anyhow, Context, Result};
+use std::collections::HashSet;
 use std::env;
 use std::fs;
 use std::io::Write;
@@ -7,6 +8,8 @@ use std::path::{Component, Path, PathBuf};
 use std::time::Duration;
 use tempfile::NamedTempFile;
 
+use super::progress_bar::{Progress, StepProgress};
+
 /// Get a path to user's "home" directory.
 ///
 /// # Panic
@@ -164,51 +167,398 @@ where
 }
 
 /// Copy file or directory to a specified path.
+///
+/// Symbolic links inside of a directory are copied as links if they point to somewhere
+/// within `from`, with absolute links rewritten to point to the same path under `to`, so
+/// that the copy still works after `from` is removed (such as a temporary extraction
+/// directory). Links pointing outside of `from` are followed, so their targets are copied
+/// instead, the same as how `from` itself is copied if it's a link. Files that are hard
+/// linked together are copied as hard links of each other (on Unix), see [`disk_size`].
 pub fn copy_as<P, Q>(from: P, to: Q) -> Result<()>
 where
     P: AsRef<Path>,
     Q: AsRef<Path>,
 {
-    fn copy_dir_(src: &Path, dest: &Path) -> Result<()> {
+    copy_as_with_progress(from, to, &Progress::noop())
+}
+
+/// Copy file or directory to a specified path like [`copy_as`], while advancing the
+/// `progress` by its whole `len` according to the number of bytes copied.
+///
+/// The total number of bytes is counted by [`disk_size`], thus a tree full of links
+/// won't make the progress overshoot.
+pub fn copy_as_with_progress<P, Q>(from: P, to: Q, progress: &Progress) -> Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    if !from.as_ref().exists() {
+        bail!(
+            "failed to copy '{}': path does not exist",
+            from.as_ref().display()
+        );
+    }
+
+    let total = disk_size(&from)?;
+    let mut copier = LinkAwareCopier::new(
+        CopyRoots::new(from.as_ref(), to.as_ref())?,
+        StepProgress::new(progress.clone(), total as f32),
+    );
+
+    if from.as_ref().is_file() {
+        copier
+            .copy_file(from.as_ref(), to.as_ref())
+            .with_context(|| {
+                format!(
+                    "could not copy file '{}' to '{}'",
+                    from.as_ref().display(),
+                    to.as_ref().display()
+                )
+            })?;
+    } else {
+        copier
+            .copy_dir(from.as_ref(), to.as_ref())
+            .with_context(|| {
+                format!(
+                    "could not copy directory '{}' to '{}'",
+                    from.as_ref().display(),
+                    to.as_ref().display()
+                )
+            })?;
+    }
+    // make sure the progress is completed even if there's nothing to copy
+    copier.steps.advance_step(0.0)?;
+    Ok(())
+}
+
+/// Copy file or directory to a specified path like [`copy_as_with_progress`], but skip the
+/// files that are already up to date, such as when re-installing over an existing toolchain.
+///
+/// A file is considered up to date if the destination has the same size and is not older
+/// than the source. The `progress` only represents the work to be done, which is the total
+/// size of the files that need to be copied, so it always advances by its whole `len`
+/// (even if nothing needs to be copied).
+///
+/// Returns the number of bytes copied.
+pub fn copy_changed_with_progress<P, Q>(from: P, to: Q, progress: &Progress) -> Result<u64>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    enum Op {
+        Dir(PathBuf),
+        File(PathBuf, PathBuf, u64),
+        /// The link at the source, the target of the link to create, and the destination.
+        Symlink(PathBuf, PathBuf, PathBuf),
+    }
+
+    /// `linked` is the `(device, inode)` of the hard linked files to be copied, which are
+    /// only counted once, as [`LinkAwareCopier`] links the others to the first copy.
+    fn plan_(
+        src: &Path,
+        meta: fs::Metadata,
+        dest: &Path,
+        roots: &CopyRoots,
+        ops: &mut Vec<Op>,
+        linked: &mut HashSet<(u64, u64)>,
+    ) -> Result<()> {
+        if meta.file_type().is_symlink() {
+            match roots.link_target(src)? {
+                Some(target) => {
+                    if !fs::read_link(dest).is_ok_and(|t| t == target) {
+                        ops.push(Op::Symlink(src.to_path_buf(), target, dest.to_path_buf()));
+                    }
+                }
+                None => plan_(src, fs::metadata(src)?, dest, roots, ops, linked)?,
+            }
+        } else if meta.is_dir() {
+            if !dest.is_dir() {
+                ops.push(Op::Dir(dest.to_path_buf()));
+            }
+            for maybe_entry in src.read_dir()? {
+                let entry = maybe_entry?;
+                let src = entry.path();
+                let meta = fs::symlink_metadata(&src)?;
+                plan_(
+                    &src,
+                    meta,
+                    &dest.join(entry.file_name()),
+                    roots,
+                    ops,
+                    linked,
+                )?;
+            }
+        } else {
+            let up_to_date = fs::symlink_metadata(dest).is_ok_and(|dest_meta| {
+                dest_meta.is_file()
+                    && dest_meta.len() == meta.len()
+                    && matches!(
+                        (dest_meta.modified(), meta.modified()),
+                        (Ok(dest_time), Ok(src_time)) if dest_time >= src_time
+                    )
+            });
+            if !up_to_date {
+                let size = if is_first_link(&meta, linked) {
+                    meta.len()
+                } else {
+                    0
+                };
+                ops.push(Op::File(src.to_path_buf(), dest.to_path_buf(), size));
+            }
+        }
+        Ok(())
+    }
+
+    let (from, to) = (from.as_ref(), to.as_ref());
+    if !from.exists() {
+        bail!("failed to copy '{}': path does not exist", from.display());
+    }
+
+    let roots = CopyRoots::new(from, to)?;
+    let mut ops = vec![];
+    let meta = fs::metadata(from)?;
+    plan_(from, meta, to, &roots, &mut ops, &mut HashSet::new()).with_context(|| {
+        format!(
+            "failed to compare '{}' with '{}'",
+            from.display(),
+            to.display()
+        )
+    })?;
+    let total = ops
+        .iter()
+        .map(|op| match op {
+            Op::File(_, _, size) => *size,
+            _ => 0,
+        })
+        .sum::<u64>();
+    let mut copier = LinkAwareCopier::new(roots, StepProgress::new(progress.clone(), total as f32));
+
+    // the outdated destination could be anything, such as a directory or a link that
+    // should not be written through, so it's removed first
+    let clear_dest = |dest: &Path| -> Result<()> {
+        match fs::symlink_metadata(dest) {
+            Ok(meta) if meta.is_dir() => fs::remove_dir_all(dest)?,
+            Ok(_) => fs::remove_file(dest).or_else(|_| fs::remove_dir(dest))?,
+            Err(_) => (),
+        }
+        Ok(())
+    };
+    for op in ops {
+        match op {
+            Op::Dir(dest) => {
+                clear_dest(&dest)?;
+                fs::create_dir(&dest)?;
+            }
+            Op::File(src, dest, _) => {
+                clear_dest(&dest)?;
+                copier
+                    .copy_file(&src, &dest)
+                    .with_context(|| format!("could not copy file '{}'", src.display()))?;
+            }
+            Op::Symlink(src, target, dest) => {
+                clear_dest(&dest)?;
+                copier.link(&src, &target, &dest)?;
+            }
+        }
+    }
+    copier.steps.advance_step(0.0)?;
+    Ok(total)
+}
+
+/// Count the number of bytes under `path`, which is the total amount of data to write
+/// when copying it with [`copy_as`].
+///
+/// Files that are linked are only counted once, that is:
+/// - symbolic links pointing to somewhere within `path` are counted as zero bytes, as only
+///   the links themselves are copied (their targets are counted where they are), while the
+///   others are counted by their targets, which are copied instead.
+/// - (on Unix) files that are hard linked to each other are counted only once, by their
+///   first occurrence.
+/// - directories are counted as zero bytes.
+pub fn disk_size<P: AsRef<Path>>(path: P) -> Result<u64> {
+    fn size_(
+        path: &Path,
+        meta: fs::Metadata,
+        roots: &CopyRoots,
+        seen: &mut HashSet<(u64, u64)>,
+    ) -> Result<u64> {
+        if meta.is_dir() {
+            let mut sum = 0;
+            for maybe_entry in path.read_dir()? {
+                let path = maybe_entry?.path();
+                sum += size_(&path, metadata_of(&path, false)?, roots, seen)?;
+            }
+            Ok(sum)
+        } else if meta.file_type().is_symlink() {
+            if roots.link_target(path)?.is_some() {
+                Ok(0)
+            } else {
+                size_(path, metadata_of(path, true)?, roots, seen)
+            }
+        } else if is_first_link(&meta, seen) {
+            Ok(meta.len())
+        } else {
+            Ok(0)
+        }
+    }
+    fn metadata_of(path: &Path, follow: bool) -> Result<fs::Metadata> {
+        let meta = if follow {
+            fs::metadata(path)
+        } else {
+            fs::symlink_metadata(path)
+        };
+        meta.with_context(|| format!("unable to read metadata of '{}'", path.display()))
+    }
+
+    let path = path.as_ref();
+    // the counted path is never copied as a link even if it is one, see `copy_as`
+    let roots = CopyRoots::new(path, path)?;
+    size_(path, metadata_of(path, true)?, &roots, &mut HashSet::new())
+}
+
+/// The source and destination directories of a copy, which decide how the symbolic links
+/// inside of the source are copied, see [`copy_as`].
+struct CopyRoots {
+    /// The canonicalized source.
+    from: PathBuf,
+    /// The absolute destination.
+    to: PathBuf,
+}
+
+impl CopyRoots {
+    fn new(from: &Path, to: &Path) -> Result<Self> {
+        let from = fs::canonicalize(from)
+            .with_context(|| format!("unable to resolve path '{}'", from.display()))?;
+        let to = if to.is_absolute() {
+            to.to_path_buf()
+        } else {
+            env::current_dir()?.join(to)
+        };
+        Ok(Self { from, to })
+    }
+
+    /// Get the target of the link to create for the symbolic link at `link`, or `None` if
+    /// it points outside of the source and should be followed.
+    ///
+    /// Relative links within the source are kept as they are, and absolute ones are
+    /// rewritten to point into the destination. Dangling links are kept as well, as there
+    /// is nothing to copy in place of them.
+    fn link_target(&self, link: &Path) -> Result<Option<PathBuf>> {
+        let target = fs::read_link(link)
+            .with_context(|| format!("unable to read link '{}'", link.display()))?;
+        let resolved = match link.parent() {
+            Some(parent) => parent.join(&target),
+            None => target.clone(),
+        };
+        let Ok(real) = fs::canonicalize(resolved) else {
+            return Ok(Some(target));
+        };
+        match real.strip_prefix(&self.from) {
+            Ok(rel) if target.is_absolute() => Ok(Some(self.to.join(rel))),
+            Ok(_) => Ok(Some(target)),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// Check if the file of `meta` is not hard linked to any file in `seen` (on Unix),
+/// by its `(device, inode)`, which is added to `seen` if it's linked elsewhere.
+fn is_first_link(meta: &fs::Metadata, seen: &mut HashSet<(u64, u64)>) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        meta.nlink() <= 1 || seen.insert((meta.dev(), meta.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (meta, seen);
+        true
+    }
+}
+
+/// Copies files while keeping links by the rules of [`copy_as`], and reports the copied
+/// bytes by the same rule as [`disk_size`].
+struct LinkAwareCopier<'a> {
+    roots: CopyRoots,
+    steps: StepProgress<'a>,
+    /// The files that are hard linked elsewhere, by their `(device, inode)`,
+    /// and the path they were copied to.
+    #[cfg(unix)]
+    copied: std::collections::HashMap<(u64, u64), PathBuf>,
+}
+
+impl<'a> LinkAwareCopier<'a> {
+    fn new(roots: CopyRoots, steps: StepProgress<'a>) -> Self {
+        Self {
+            roots,
+            steps,
+            #[cfg(unix)]
+            copied: std::collections::HashMap::new(),
+        }
+    }
+
+    fn copy_dir(&mut self, src: &Path, dest: &Path) -> Result<()> {
         fs::create_dir(dest)?;
         for maybe_entry in src.read_dir()? {
             let entry = maybe_entry?;
             let src = entry.path();
             let dest = dest.join(entry.file_name());
-            if entry.file_type()?.is_dir() {
-                copy_dir_(&src, &dest)?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                self.copy_dir(&src, &dest)?;
+            } else if file_type.is_symlink() {
+                self.copy_symlink(&src, &dest)?;
             } else {
-                fs::copy(&src, &dest)?;
+                self.copy_file(&src, &dest)?;
             }
         }
         Ok(())
     }
 
-    if !from.as_ref().exists() {
-        bail!(
-            "failed to copy '{}': path does not exist",
-            from.as_ref().display()
-        );
+    fn copy_symlink(&mut self, src: &Path, dest: &Path) -> Result<()> {
+        match self.roots.link_target(src)? {
+            Some(target) => self.link(src, &target, dest),
+            None if src.is_dir() => self.copy_dir(src, dest),
+            None => self.copy_file(src, dest),
+        }
     }
 
-    if from.as_ref().is_file() {
-        fs::copy(&from, &to).with_context(|| {
-            format!(
-                "could not copy file '{}' to '{}'",
-                from.as_ref().display(),
-                to.as_ref().display()
-            )
-        })?;
-    } else {
-        copy_dir_(from.as_ref(), to.as_ref()).with_context(|| {
-            format!(
-                "could not copy directory '{}' to '{}'",
-                from.as_ref().display(),
-                to.as_ref().display()
-            )
-        })?;
+    fn copy_file(&mut self, src: &Path, dest: &Path) -> Result<()> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let meta = fs::metadata(src)?;
+            if meta.nlink() > 1 {
+                let key = (meta.dev(), meta.ino());
+                if let Some(first) = self.copied.get(&key) {
+                    fs::hard_link(first, dest)?;
+                    return Ok(());
+                }
+                self.copied.insert(key, dest.to_path_buf());
+            }
+        }
+        let copied = fs::copy(src, dest)?;
+        self.steps.advance_step(copied as f32)
+    }
+
+    /// Create a link to `target` at `dest` in place of the link at `src`.
+    #[cfg(unix)]
+    fn link(&mut self, _src: &Path, target: &Path, dest: &Path) -> Result<()> {
+        std::os::unix::fs::symlink(target, dest)?;
+        Ok(())
+    }
+
+    /// Creating symbolic links requires special privilege on Windows,
+    /// so the link targets are copied instead (without being reported).
+    #[cfg(not(unix))]
+    fn link(&mut self, src: &Path, _target: &Path, dest: &Path) -> Result<()> {
+        if src.is_dir() {
+            copy_as(src, dest)?;
+        } else {
+            fs::copy(src, dest)?;
+        }
+        Ok(())
     }
-    Ok(())
 }
 
 /// Set file permissions (executable)
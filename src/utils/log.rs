@@ -1,18 +1,47 @@
-use anyhow::Result;
+//! Logging of each run, which is displayed on the console (or sent to GUI), and also written
+//! into a separated log file, so that the log of a failed run can be attached when reporting it.
+
+use anyhow::{Context, Result};
 use chrono::Local;
-use fern::colors::{Color, ColoredLevelConfig};
-use log::LevelFilter;
-use std::io;
+use console::style;
+use std::fmt::{self, Write as _};
+use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::Sender;
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::fmt::time::ChronoLocal;
+use tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// The environment variable to choose which messages are logged, such as `debug` or
+/// `rim::utils=trace,info`, check [`EnvFilter`] for the syntax.
+pub const RIM_LOG: &str = "RIM_LOG";
+
+/// The target of the events that are only written into log file, such as the errors that are
+/// already printed by other means:
+///
+/// ```ignore
+/// tracing::error!(target: LOG_FILE_ONLY, "{err:?}");
+/// ```
+pub const LOG_FILE_ONLY: &str = "rim::log_file";
+
+/// The number of log files to keep, the oldest ones are removed when a new run starts.
+const MAX_LOG_FILES: usize = 20;
 
 #[derive(Debug)]
 pub struct Logger {
     output_sender: Option<Sender<String>>,
     /// This level only effects displayed log,
-    /// the file logger will still be using max log level.
+    /// the file logger will be using at least the `debug` level.
     level: LevelFilter,
+    /// Print each message as a JSON event on `stdout`.
+    json: bool,
+    /// Write the log of this run into a new file under [`log_dir`].
+    log_file: bool,
 }
 
 impl Default for Logger {
@@ -24,111 +53,275 @@ impl Default for Logger {
 impl Logger {
     pub fn new() -> Self {
         #[cfg(not(debug_assertions))]
-        let level = LevelFilter::Info;
+        let level = LevelFilter::INFO;
         #[cfg(debug_assertions)]
-        let level = LevelFilter::Debug;
+        let level = LevelFilter::DEBUG;
 
         Self {
             output_sender: None,
             level,
+            json: false,
+            log_file: true,
         }
     }
-    /// Set verbose output, this will print `trace!` messages as well.
-    pub fn verbose(mut self, v: bool) -> Self {
-        if v {
-            self.level = LevelFilter::Trace;
+    /// Set the verbosity of output, `1` prints `debug!` messages as well, and `2` or more
+    /// prints everything including `trace!` messages.
+    pub fn verbose(mut self, v: u8) -> Self {
+        match v {
+            0 => (),
+            1 => self.level = LevelFilter::DEBUG,
+            _ => self.level = LevelFilter::TRACE,
         }
         self
     }
     /// Ignore most output, keep only the `error` messages.
     pub fn quiet(mut self, q: bool) -> Self {
         if q {
-            self.level = LevelFilter::Error;
+            self.level = LevelFilter::ERROR;
         }
         self
     }
+    /// Print each message as a line of JSON event on `stdout` instead of colored text,
+    /// such as `{"event":"log","level":"info","message":"..."}`.
+    pub fn json(mut self, j: bool) -> Self {
+        self.json = j;
+        self
+    }
     /// Send output using a specific sender rather than printing on `stdout`.
     pub fn sender(mut self, sender: Sender<String>) -> Self {
         self.output_sender = Some(sender);
         self
     }
+    /// Whether to write a log file of this run, which is `true` by default.
+    pub fn log_file(mut self, yes: bool) -> Self {
+        self.log_file = yes;
+        self
+    }
 
-    /// Setup logger using [`log`] and [`fern`], this must be called first before
+    /// Setup logger using [`tracing`], this must be called first before
     /// any of the `info!`, `warn!`, `trace!`, `debug!`, `error!` macros.
     ///
-    /// - If [`verbose`](Logger::verbose) was called with `true`, this will output more
+    /// - If [`verbose`](Logger::verbose) was called, this will output more
     ///     detailed log messages including `debug!`.
     /// - If [`quiet`](Logger::quiet) was called with `true`, this will not output any message
     ///     on `stdout`, but will still output them into log file.
+    /// - If the [`RIM_LOG`] environment variable is set, it decides which messages are
+    ///   displayed (unless `quiet`) and written into log file.
+    ///
+    /// Each line of the log file has a timestamp, the level, the spans it happened in
+    /// (such as the phase of installation, the component, and the url being downloaded),
+    /// and the message. If the log file cannot be created, a warning is displayed, and the
+    /// messages are only displayed.
     pub fn setup(self) -> Result<()> {
-        let dispatch = fern::Dispatch::new().level(LevelFilter::Trace);
-        let filter_log_for_output = move |md: &log::Metadata| -> bool {
-            md.level() <= self.level && md.level() != LevelFilter::Trace
+        let env_filter = std::env::var(RIM_LOG).unwrap_or_default();
+        let filter = |default: LevelFilter, directives: &str| {
+            EnvFilter::builder()
+                .with_default_directive(default.into())
+                .parse_lossy(directives)
         };
 
-        // decide if `Sender` or `Stdout` should be used as message medium.
-        let output = if let Some(sender) = self.output_sender {
-            fern::Dispatch::new()
-                .filter(filter_log_for_output)
-                .format(|out, msg, rec| {
-                    out.finish(format_args!(
-                        "{}: {msg}",
-                        rec.level().to_string().to_lowercase()
-                    ));
-                })
-                .chain(sender)
+        let console = if let Some(sender) = self.output_sender {
+            Console::Sender(Mutex::new(sender))
+        } else if self.json {
+            Console::Json
+        } else {
+            Console::Text
+        };
+        // `quiet` means errors only, whatever `RIM_LOG` is
+        let console_filter = if self.level == LevelFilter::ERROR {
+            filter(self.level, "")
         } else {
-            fern::Dispatch::new()
-                .filter(filter_log_for_output)
-                .format(|out, msg, rec| {
-                    out.finish(format_args!(
-                        "{}: {msg}",
-                        ColoredLevelConfig::new()
-                            .info(Color::BrightBlue)
-                            .debug(Color::Magenta)
-                            .color(rec.level())
-                            .to_string()
-                            .to_lowercase(),
-                    ));
-                })
-                .chain(io::stdout())
+            filter(self.level, &env_filter)
         };
 
-        let file_config = fern::Dispatch::new()
-            .format(|out, msg, rec| {
-                out.finish(format_args!(
-                    "[{} {}] {msg}",
-                    Local::now().to_rfc3339(),
-                    rec.target(),
-                ))
-            })
-            .chain(fern::log_file(log_file_path()?)?);
-
-        dispatch.chain(output).chain(file_config).apply()?;
+        // NB: failing to create log file (such as a read-only data directory) should not
+        // prevent the program from running, it's reported once the logger is ready.
+        let (log_file, log_file_err) = match self.log_file.then(create_log_file) {
+            Some(Ok(file)) => (Some(file), None),
+            Some(Err(e)) => (None, Some(e)),
+            None => (None, None),
+        };
+        let file_layer = log_file.map(|file| {
+            let file_level = self.level.max(LevelFilter::DEBUG);
+            tracing_subscriber::fmt::layer()
+                .with_writer(Mutex::new(file))
+                .with_ansi(false)
+                .with_timer(ChronoLocal::rfc_3339())
+                .with_filter(filter(file_level, &env_filter))
+        });
+
+        tracing_subscriber::registry()
+            .with(console.with_filter(console_filter))
+            .with(file_layer)
+            .try_init()?;
+
+        if let Some(e) = log_file_err {
+            warn!("{}", t!("log_file_unavailable", reason = format!("{e:#}")));
+        }
+
+        debug!(
+            "{} v{} ({}), running on {} {}",
+            super::lowercase_program_name().unwrap_or(env!("CARGO_PKG_NAME").to_string()),
+            env!("CARGO_PKG_VERSION"),
+            env!("TARGET"),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+        );
         Ok(())
     }
 }
 
-static LOG_FILE_PATH: OnceLock<PathBuf> = OnceLock::new();
-/// Get the path to log file to write.
-///
-/// We put the log directory besides current binary, so that it should be easier for users to find them.
-/// If for some reason the path to current binary cannot be found, we'll assume the user is running this
-/// binary in their current working dir, and create a log dir there.
-///
-/// Note: the log file might not exists.
-///
-/// # Error
+/// Where the log messages are displayed.
+enum Console {
+    Sender(Mutex<Sender<String>>),
+    Json,
+    Text,
+}
+
+impl<S: Subscriber> Layer<S> for Console {
+    fn on_event(&self, event: &Event<'_>, _ctx: LayerContext<'_, S>) {
+        if event.metadata().target() == LOG_FILE_ONLY {
+            return;
+        }
+        let level = event.metadata().level();
+        let mut message = Message::default();
+        event.record(&mut message);
+        let message = message.0;
+
+        match self {
+            Self::Sender(sender) => {
+                let line = format!("{}: {message}\n", level.to_string().to_lowercase());
+                // the receiver might have gone, such as when the GUI is closing
+                _ = sender.lock().unwrap().send(line);
+            }
+            Self::Json => {
+                let json = super::log_to_json(&level.to_string().to_lowercase(), &message);
+                println!("{json}");
+            }
+            Self::Text => {
+                let level_str = level.to_string().to_lowercase();
+                let colored = match *level {
+                    Level::ERROR => style(level_str).red(),
+                    Level::WARN => style(level_str).yellow(),
+                    Level::INFO => style(level_str).blue().bright(),
+                    Level::DEBUG => style(level_str).magenta(),
+                    Level::TRACE => style(level_str).black().bright(),
+                };
+                // print through the progress bar, so the output won't be mixed with it
+                super::progress_bar::println_above_bar(format_args!("{colored}: {message}"));
+            }
+        }
+    }
+}
+
+/// The message of a log event, without the other fields.
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            _ = write!(self.0, "{value:?}");
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0.push_str(value);
+        }
+    }
+}
+
+/// Get the directory of log files, which is the `logs` directory under the user's data
+/// directory, such as `~/.local/share/<vendor>/logs` on Linux, so that the logs of installer
+/// and manager are in the same place.
 ///
-/// Because this will attemp to create a directory named `log` to place the actual log file,
-/// this function might fail if it cannot be created.
-pub fn log_file_path() -> Result<&'static Path> {
-    let mut log_dir = super::parent_dir_of_cur_exe().unwrap_or(PathBuf::from("."));
-    log_dir.push("log");
-    super::ensure_dir(&log_dir)?;
+/// If the data directory cannot be determined, it is the `log` directory besides current binary.
+pub fn log_dir() -> PathBuf {
+    match crate::core::directories::vendor_dir(dirs::data_local_dir()) {
+        Some(dir) => dir.join("logs"),
+        None => super::parent_dir_of_cur_exe()
+            .unwrap_or(PathBuf::from("."))
+            .join("log"),
+    }
+}
+
+static LOG_FILE_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Get the path to the log file of current run, which is `None` if the logger was
+/// not setup to write one.
+pub fn log_file_path() -> Option<&'static Path> {
+    LOG_FILE_PATH.get().map(PathBuf::as_path)
+}
+
+/// Get every log file in [`log_dir`], the most recent first.
+pub fn log_files() -> Result<Vec<PathBuf>> {
+    log_files_in(&log_dir())
+}
+
+/// Create a new log file for current run, named after the program and the time it starts,
+/// such as `rim-cli-20241014-153000-1234.log`, then remove the oldest log files.
+fn create_log_file() -> Result<File> {
+    let dir = log_dir();
+    super::ensure_dir(&dir)?;
 
     let bin_name = super::lowercase_program_name().unwrap_or(env!("CARGO_PKG_NAME").to_string());
+    let path = dir.join(format!(
+        "{bin_name}-{}-{}.log",
+        Local::now().format("%Y%m%d-%H%M%S"),
+        std::process::id()
+    ));
+    let file = File::create(&path)
+        .with_context(|| format!("unable to create log file '{}'", path.display()))?;
+    let path = LOG_FILE_PATH.get_or_init(|| path);
+
+    // NB: failing to remove old logs should not prevent the program from running
+    if let Ok(files) = log_files_in(&dir) {
+        for old in files.iter().filter(|f| f != &path).skip(MAX_LOG_FILES - 1) {
+            _ = fs::remove_file(old);
+        }
+    }
+    Ok(file)
+}
+
+fn log_files_in(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "log") {
+            let modified = fs::metadata(&path)?.modified()?;
+            files.push((modified, path));
+        }
+    }
+    // the names are compared as well, in case some files were modified at the same time
+    files.sort_by(|a, b| b.cmp(a));
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(LOG_FILE_PATH
-        .get_or_init(|| log_dir.join(format!("{bin_name}-{}.log", Local::now().date_naive()))))
+    #[test]
+    fn most_recent_log_files_first() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["rim-cli-1.log", "rim-cli-2.log", "notes.txt", "rim-3.log"] {
+            fs::write(dir.path().join(name), name).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        fs::create_dir(dir.path().join("dir.log")).unwrap();
+
+        let files = log_files_in(dir.path()).unwrap();
+        let names = files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["rim-3.log", "rim-cli-2.log", "rim-cli-1.log"]);
+        assert!(log_files_in(&dir.path().join("missing"))
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -0,0 +1,294 @@
+//! Downloading from multiple mirrors of the same server, which fails over to the next mirror
+//! once one of them is unreachable, and prefers the fastest one.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+
+use url::Url;
+
+use super::{url_join, CliBar, DownloadError, DownloadOpt, RetryPolicy};
+
+/// What is known about a mirror from the previous requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MirrorStat {
+    /// The (smoothed) time it took to receive the first response.
+    Latency(Duration),
+    /// The last request failed with a temporary error.
+    Failed,
+}
+
+/// A list of base urls of mirrors that serve the same files.
+///
+/// Requests are sent to one mirror at a time, and are sent to the next one if it failed with
+/// a temporary error, such as a timeout or a `5xx` status (check
+/// [`DownloadError::is_temporary`]), other errors (such as `404`) are returned immediately.
+///
+/// The latency of each mirror is recorded after every successful request, so the mirrors
+/// are tried in this order:
+/// 1. Mirrors that have never been used, in the configured order, so their latency gets known.
+/// 2. Mirrors that succeeded previously, the fastest first.
+/// 3. Mirrors that failed the last time.
+///
+/// The records are only kept in memory unless a file is given to
+/// [`with_latency_file`](Mirrors::with_latency_file).
+#[derive(Debug)]
+pub struct Mirrors {
+    bases: Vec<Url>,
+    latency_file: Option<PathBuf>,
+    stats: Mutex<HashMap<String, MirrorStat>>,
+}
+
+impl Mirrors {
+    /// Create a mirror list from base urls, duplicated urls are ignored.
+    pub fn new(bases: Vec<Url>) -> Self {
+        let mut unique: Vec<Url> = Vec::with_capacity(bases.len());
+        for base in bases {
+            if !unique.contains(&base) {
+                unique.push(base);
+            }
+        }
+        Self {
+            bases: unique,
+            latency_file: None,
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Load the latency records from `path`, and save them there after every request,
+    /// so they are preserved across runs. The file is created if it does not exist.
+    ///
+    /// Each line of the file is the base url of a mirror and its latency in milliseconds,
+    /// or `failed`, separated by a space.
+    pub fn with_latency_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        let path = path.as_ref();
+        if let Ok(content) = fs::read_to_string(path) {
+            let stats = content
+                .lines()
+                .filter_map(|line| {
+                    let (url, stat) = line.trim().rsplit_once(' ')?;
+                    let stat = match stat {
+                        "failed" => MirrorStat::Failed,
+                        millis => MirrorStat::Latency(Duration::from_millis(millis.parse().ok()?)),
+                    };
+                    Some((url.to_string(), stat))
+                })
+                .collect();
+            self.stats = Mutex::new(stats);
+        }
+        self.latency_file = Some(path.to_path_buf());
+        self
+    }
+
+    /// Get the base urls, in the order of they will be tried.
+    pub fn ordered(&self) -> Vec<&Url> {
+        let stats = self.stats.lock().unwrap();
+        let mut ordered = self.bases.iter().collect::<Vec<_>>();
+        // NB: this is a stable sort, unused mirrors keep their configured order
+        ordered.sort_by_key(|base| match stats.get(base.as_str()) {
+            None => (0, Duration::ZERO),
+            Some(MirrorStat::Latency(latency)) => (1, *latency),
+            Some(MirrorStat::Failed) => (2, Duration::ZERO),
+        });
+        ordered
+    }
+
+    /// Get the recorded latency of a mirror, if it succeeded the last time.
+    pub fn latency(&self, base: &Url) -> Option<Duration> {
+        match self.stats.lock().unwrap().get(base.as_str()) {
+            Some(MirrorStat::Latency(latency)) => Some(*latency),
+            _ => None,
+        }
+    }
+
+    /// Call `f` with the url of `path` under each mirror, until one of them succeeds or fails
+    /// with a non-temporary error.
+    ///
+    /// The time `f` takes is recorded as the latency of that mirror, so this is meant for
+    /// small requests, use [`download`](Mirrors::download) for (potentially) large files.
+    pub fn fetch<T, F>(&self, path: &str, mut f: F) -> Result<T>
+    where
+        F: FnMut(&Url) -> Result<T>,
+    {
+        self.try_each(path, |url, _| {
+            let start = Instant::now();
+            f(url).map(|res| (res, start.elapsed()))
+        })
+    }
+
+    /// Read the text content of `path` from one of the mirrors, check
+    /// [`fetch`](Mirrors::fetch) for detail.
+    ///
+    /// Like [`download`](Mirrors::download), only the last mirror is retried with the
+    /// [`retry`](DownloadOpt::retry) policy.
+    pub fn read(&self, path: &str, opt: DownloadOpt<CliBar>) -> Result<String> {
+        self.try_each(path, |url, last| {
+            let start = Instant::now();
+            retried_on_last(&opt, last)
+                .read(url)
+                .map(|res| (res, start.elapsed()))
+        })
+    }
+
+    /// Download `path` from one of the mirrors to `dest`, the time until receiving the first
+    /// response is recorded as the latency of the mirror.
+    ///
+    /// Failing over is faster than waiting for the retries, so only the last mirror is retried
+    /// with the [`retry`](DownloadOpt::retry) policy.
+    pub fn download(&self, path: &str, opt: DownloadOpt<CliBar>, dest: &Path) -> Result<()> {
+        self.try_each(path, |url, last| {
+            let start = Instant::now();
+            let mut first_response = None;
+            retried_on_last(&opt, last).download_file_(url, dest, false, None, |_, _| {
+                first_response.get_or_insert_with(|| start.elapsed());
+                Ok(())
+            })?;
+            Ok(((), first_response.unwrap_or_else(|| start.elapsed())))
+        })
+    }
+
+    fn try_each<T, F>(&self, path: &str, mut f: F) -> Result<T>
+    where
+        F: FnMut(&Url, bool) -> Result<(T, Duration)>,
+    {
+        let ordered = self.ordered();
+        let Some((last, rest)) = ordered.split_last() else {
+            bail!("unable to request '{path}' because there's no mirror configured");
+        };
+        for base in rest {
+            let url = url_join(base, path)?;
+            match f(&url, false) {
+                Ok((res, latency)) => {
+                    self.record(base, MirrorStat::Latency(latency));
+                    return Ok(res);
+                }
+                Err(e) if DownloadError::is_temporary(&e) => {
+                    warn!("{}", t!("mirror_failed", url = base, reason = e));
+                    self.record(base, MirrorStat::Failed);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let url = url_join(last, path)?;
+        let res = f(&url, true);
+        match &res {
+            Ok((_, latency)) => self.record(last, MirrorStat::Latency(*latency)),
+            Err(e) if DownloadError::is_temporary(e) => self.record(last, MirrorStat::Failed),
+            Err(_) => (),
+        }
+        res.map(|(res, _)| res)
+    }
+
+    fn record(&self, base: &Url, stat: MirrorStat) {
+        let mut stats = self.stats.lock().unwrap();
+        let stat = match (stats.get(base.as_str()), stat) {
+            // smooth out the occasional slow responses
+            (Some(MirrorStat::Latency(prev)), MirrorStat::Latency(new)) => {
+                MirrorStat::Latency((*prev + new) / 2)
+            }
+            (_, stat) => stat,
+        };
+        stats.insert(base.to_string(), stat);
+
+        let Some(file) = &self.latency_file else {
+            return;
+        };
+        let mut content = stats
+            .iter()
+            .map(|(url, stat)| match stat {
+                MirrorStat::Latency(latency) => format!("{url} {}", latency.as_millis()),
+                MirrorStat::Failed => format!("{url} failed"),
+            })
+            .collect::<Vec<_>>();
+        content.sort();
+        let saved =
+            super::ensure_parent_dir(file).and_then(|_| Ok(fs::write(file, content.join("\n"))?));
+        if let Err(e) = saved {
+            debug!("unable to save mirror latency to '{}': {e}", file.display());
+        }
+    }
+}
+
+fn retried_on_last(opt: &DownloadOpt<CliBar>, last: bool) -> DownloadOpt<CliBar> {
+    if last {
+        opt.clone()
+    } else {
+        opt.clone().with_retry(RetryPolicy::none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mock_server::{MockResponse, MockServer};
+
+    fn base(server: &MockServer, prefix: &str) -> Url {
+        server.url(&format!("{prefix}/"))
+    }
+
+    #[test]
+    fn fails_over_to_next_mirror() {
+        let server = MockServer::start();
+        server.serve("/a/file", MockResponse::new(vec![]).status(503));
+        server.serve("/b/file", MockResponse::new(b"from b".to_vec()));
+        let (a, b) = (base(&server, "a"), base(&server, "b"));
+        let dir = tempfile::tempdir().unwrap();
+        let latency_file = dir.path().join("latency");
+
+        let mirrors = Mirrors::new(vec![a.clone(), b.clone()]).with_latency_file(&latency_file);
+        let dest = dir.path().join("file");
+        mirrors
+            .download("file", DownloadOpt::new("file").with_handler(None), &dest)
+            .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"from b");
+        // the failed one is tried last from now on
+        assert_eq!(mirrors.ordered(), [&b, &a]);
+        assert!(mirrors.latency(&b).is_some());
+
+        // the records are loaded from the file
+        let mirrors = Mirrors::new(vec![a.clone(), b.clone()]).with_latency_file(&latency_file);
+        assert_eq!(mirrors.ordered(), [&b, &a]);
+    }
+
+    #[test]
+    fn not_found_is_not_failed_over() {
+        let server = MockServer::start();
+        server.serve("/b/file", MockResponse::new(b"from b".to_vec()));
+        let (a, b) = (base(&server, "a"), base(&server, "b"));
+
+        let mirrors = Mirrors::new(vec![a, b]);
+        let err = mirrors
+            .read("file", DownloadOpt::new("file").with_handler(None))
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DownloadError>(),
+            Some(DownloadError::Status { status: 404, .. })
+        ));
+    }
+
+    #[test]
+    fn prefers_the_fastest_mirror() {
+        let (a, b, c) = (
+            Url::parse("https://a.example/").unwrap(),
+            Url::parse("https://b.example/").unwrap(),
+            Url::parse("https://c.example/").unwrap(),
+        );
+        let mirrors = Mirrors::new(vec![a.clone(), b.clone(), c.clone(), a.clone()]);
+        assert_eq!(mirrors.ordered(), [&a, &b, &c]);
+        mirrors.record(&a, MirrorStat::Latency(Duration::from_millis(300)));
+        mirrors.record(&b, MirrorStat::Latency(Duration::from_millis(100)));
+        // unknown mirrors are tried first
+        assert_eq!(mirrors.ordered(), [&c, &b, &a]);
+        mirrors.record(&c, MirrorStat::Latency(Duration::from_millis(200)));
+        assert_eq!(mirrors.ordered(), [&b, &c, &a]);
+        // smoothed with the previous latency
+        mirrors.record(&b, MirrorStat::Latency(Duration::from_millis(700)));
+        assert_eq!(mirrors.latency(&b), Some(Duration::from_millis(400)));
+        assert_eq!(mirrors.ordered(), [&c, &a, &b]);
+    }
+}
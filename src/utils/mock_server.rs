@@ -0,0 +1,377 @@
+//! A minimal HTTP server for testing the network-facing features deterministically,
+//! such as downloading, resuming and retrying.
+//!
+//! This is only available in tests, or with the `test-util` feature.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use url::Url;
+
+/// A canned response of a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    body: Vec<u8>,
+    status: u16,
+    honor_range: bool,
+    fail_first: usize,
+    fail_after: Option<usize>,
+    delay: Duration,
+    truncate_at: Option<usize>,
+    stall_at: Option<usize>,
+}
+
+impl MockResponse {
+    /// Respond with `200 OK` and the given `body`, `Range` requests are honored by default.
+    pub fn new<B: Into<Vec<u8>>>(body: B) -> Self {
+        Self {
+            body: body.into(),
+            status: 200,
+            honor_range: true,
+            fail_first: 0,
+            fail_after: None,
+            delay: Duration::ZERO,
+            truncate_at: None,
+            stall_at: None,
+        }
+    }
+
+    /// Respond with a different status code (and the body), such as `404`.
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Always respond with the whole body, like servers that don't support ranges.
+    pub fn ignore_range(mut self) -> Self {
+        self.honor_range = false;
+        self
+    }
+
+    /// Respond with `503 Service Unavailable` to the first `times` `GET` requests.
+    pub fn fail_first(mut self, times: usize) -> Self {
+        self.fail_first = times;
+        self
+    }
+
+    /// Respond with `503 Service Unavailable` once `times` `GET` requests were served.
+    pub fn fail_after(mut self, times: usize) -> Self {
+        self.fail_after = Some(times);
+        self
+    }
+
+    /// Wait for `delay` before responding.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Drop the connection after sending `len` bytes of the body, while still reporting
+    /// the full length, like a connection that was interrupted.
+    pub fn truncate_at(mut self, len: usize) -> Self {
+        self.truncate_at = Some(len);
+        self
+    }
+
+    /// Stop sending after `len` bytes of the body, while keeping the connection open until
+    /// the server is dropped, like a connection that stalled.
+    pub fn stall_at(mut self, len: usize) -> Self {
+        self.stall_at = Some(len);
+        self
+    }
+}
+
+/// A request received by a [`MockServer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    /// The value of the `Range` header, such as `bytes=0-99`.
+    pub range: Option<String>,
+    /// The status code it was responded with.
+    pub status: u16,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    routes: HashMap<String, MockResponse>,
+    /// The number of `GET` requests of each path.
+    served: HashMap<String, usize>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// A HTTP/1.1 server bound to an ephemeral port on localhost, which serves the
+/// [`MockResponse`] of each path, and responds `404` to the others.
+///
+/// Every connection is closed after one response, the server stops once dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl MockServer {
+    /// Start a server without any routes.
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("unable to bind a local port");
+        let addr = listener
+            .local_addr()
+            .expect("unable to get the local address");
+        let state = Arc::new(Mutex::new(State::default()));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let (state_, stopped_) = (state.clone(), stopped.clone());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if stopped_.load(Ordering::Relaxed) {
+                    break;
+                }
+                let (state, stopped) = (state_.clone(), stopped_.clone());
+                std::thread::spawn(move || handle(stream, &state, &stopped));
+            }
+        });
+        Self {
+            addr,
+            state,
+            stopped,
+        }
+    }
+
+    /// Serve the `response` on `path` (such as `/dist/file.tar.gz`), replacing the previous
+    /// response of it.
+    ///
+    /// The `path` could also be an absolute url, which is the path of a request that is sent
+    /// through this server as an HTTP proxy.
+    pub fn serve<S: Into<String>>(&self, path: S, response: MockResponse) -> &Self {
+        let mut path = path.into();
+        if !path.starts_with('/') && !path.contains("://") {
+            path.insert(0, '/');
+        }
+        self.state.lock().unwrap().routes.insert(path, response);
+        self
+    }
+
+    /// Get the address that the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Get the url of a path on this server.
+    pub fn url(&self, path: &str) -> Url {
+        Url::parse(&format!("http://{}/", self.addr))
+            .and_then(|base| base.join(path))
+            .expect("invalid path")
+    }
+
+    /// Get the requests received so far, in the order of they were responded.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // unblock the listener
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+fn handle(mut stream: TcpStream, state: &Mutex<State>, stopped: &AtomicBool) {
+    let Some((method, path, range)) = read_request(&stream) else {
+        return;
+    };
+
+    let (response, status, body) = {
+        let mut state = state.lock().unwrap();
+        let Some(response) = state.routes.get(&path).cloned() else {
+            state.requests.push(RecordedRequest {
+                method,
+                path,
+                range,
+                status: 404,
+            });
+            drop(state);
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+            return;
+        };
+        let served = if method == "GET" {
+            let served = state.served.entry(path.clone()).or_default();
+            *served += 1;
+            *served
+        } else {
+            0
+        };
+        let failing = method == "GET"
+            && (served <= response.fail_first || response.fail_after.is_some_and(|n| served > n));
+        let (status, body) = if failing {
+            (503, None)
+        } else {
+            match range
+                .as_deref()
+                .and_then(|r| parse_range(r, response.body.len()))
+            {
+                Some((start, end)) if response.honor_range && response.status == 200 => {
+                    (206, Some((start, end)))
+                }
+                _ => (response.status, None),
+            }
+        };
+        state.requests.push(RecordedRequest {
+            method: method.clone(),
+            path,
+            range,
+            status,
+        });
+        (response, status, body)
+    };
+
+    std::thread::sleep(response.delay);
+    let total = response.body.len();
+    let (head, content) = match (status, body) {
+        (503, _) => ("503 Service Unavailable".to_string(), &[][..]),
+        (206, Some((start, end))) => (
+            format!("206 Partial Content\r\nContent-Range: bytes {start}-{end}/{total}"),
+            &response.body[start..=end],
+        ),
+        (status, _) => (format!("{status} {}", reason(status)), &response.body[..]),
+    };
+    let head = format!(
+        "HTTP/1.1 {head}\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\nConnection: close\r\n\r\n",
+        content.len()
+    );
+    if stream.write_all(head.as_bytes()).is_err() || method == "HEAD" {
+        return;
+    }
+    let sent = response
+        .truncate_at
+        .or(response.stall_at)
+        .map_or(content.len(), |n| n.min(content.len()));
+    let _ = stream.write_all(&content[..sent]);
+    let _ = stream.flush();
+    if response.stall_at.is_some() {
+        while !stopped.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+    if sent < content.len() {
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Read the request line and headers, returns the method, path and the `Range` header.
+fn read_request(stream: &TcpStream) -> Option<(String, String, Option<String>)> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_uppercase();
+    let path = parts.next()?.to_string();
+
+    let mut range = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).ok()? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("range") {
+                range = Some(value.trim().to_string());
+            }
+        }
+    }
+    Some((method, path, range))
+}
+
+/// Parse a `Range` header like `bytes=100-199` or `bytes=100-` of a body with `len` bytes,
+/// returns the inclusive range, or `None` if it's unsatisfiable or unsupported.
+fn parse_range(range: &str, len: usize) -> Option<(usize, usize)> {
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let start = start.trim().parse::<usize>().ok()?;
+    let end = match end.trim() {
+        "" => len.checked_sub(1)?,
+        end => end.parse::<usize>().ok()?.min(len.checked_sub(1)?),
+    };
+    (start <= end).then_some((start, end))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        404 => "Not Found",
+        407 => "Proxy Authentication Required",
+        416 => "Range Not Satisfiable",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::DownloadOpt;
+
+    #[test]
+    fn serve_and_download() {
+        let body = (0..4096_u32).map(|i| (i % 253) as u8).collect::<Vec<_>>();
+        let server = MockServer::start();
+        server.serve("/dist/file.bin", MockResponse::new(body.clone()));
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("file.bin");
+
+        DownloadOpt::new("file")
+            .with_handler(None)
+            .download_file(&server.url("dist/file.bin"), &dest, false)
+            .unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+
+        let missing = DownloadOpt::new("missing")
+            .with_handler(None)
+            .download_file(&server.url("nope"), &dest, false);
+        assert!(missing.is_err());
+        let statuses = server
+            .requests()
+            .iter()
+            .map(|r| (r.path.clone(), r.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [
+                ("/dist/file.bin".to_string(), 200),
+                ("/nope".to_string(), 404)
+            ]
+        );
+    }
+
+    #[test]
+    fn ranges_and_failures() {
+        assert_eq!(parse_range("bytes=10-19", 100), Some((10, 19)));
+        assert_eq!(parse_range("bytes=90-", 100), Some((90, 99)));
+        assert_eq!(parse_range("bytes=90-200", 100), Some((90, 99)));
+        assert_eq!(parse_range("bytes=100-", 100), None);
+        assert_eq!(parse_range("items=0-1", 100), None);
+
+        let server = MockServer::start();
+        server
+            .serve("/a", MockResponse::new("hello world").fail_first(1))
+            .serve("/b", MockResponse::new("hello world").ignore_range());
+        let client = reqwest::blocking::Client::new();
+        let get = |path: &str| {
+            let resp = client
+                .get(server.url(path))
+                .header("Range", "bytes=6-")
+                .send()
+                .unwrap();
+            (resp.status().as_u16(), resp.text().unwrap())
+        };
+        assert_eq!(get("a"), (503, String::new()));
+        assert_eq!(get("a"), (206, "world".to_string()));
+        assert_eq!(get("b"), (200, "hello world".to_string()));
+    }
+}
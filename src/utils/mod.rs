@@ -3,26 +3,53 @@
 //! NOTE: Most of these are moved from the `experimental` branch,
 //! some of them might turns out to be unused, so remember to clean those after version `1.0`.
 
+mod cache;
+mod cancel;
 mod download;
 mod extraction;
 mod file_system;
 mod log;
+mod mirror;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock_server;
 mod process;
 mod progress_bar;
+mod retry;
+mod throttle;
+mod verify;
 
 use std::{
     ffi::OsStr,
     path::{Path, PathBuf},
 };
 
-pub use download::{download, download_with_proxy, DownloadOpt};
-pub use extraction::Extractable;
+pub use cache::{CacheEntry, DownloadCache, DEFAULT_CACHE_SIZE};
+pub use cancel::{CancellationToken, Cancelled};
+pub use download::{
+    download, download_all_async, download_with_proxy, fetch_and_extract,
+    fetch_and_extract_estimated, verify_and_extract, DownloadError, DownloadOpt, PrefixDigest,
+    DEFAULT_CHUNK_SIZE, DEFAULT_DOWNLOAD_JOBS, DEFAULT_DOWNLOAD_TIMEOUT,
+};
+pub use extraction::{decompress_with_progress, ExtractProgressUnit, Extractable};
 pub use file_system::*;
-pub use log::{log_file_path, Logger};
+pub use log::{log_dir, log_file_path, log_files, Logger, LOG_FILE_ONLY, RIM_LOG};
+pub use mirror::Mirrors;
 pub use process::*;
-pub use progress_bar::{CliProgress, Progress, Style as CliProgressStyle};
+pub use progress_bar::{
+    error_to_json, event_to_json, log_to_json, print_json_line, println_above_bar,
+    suspend_for_prompt, AggregateProgress, AggregateTask, CallbackErrorPolicy, CliBar, CliProgress,
+    CliProgressOpt, Clock, EventSender, HeaderLayout, JsonEventWriter, LogFileObserver,
+    MessageHistory, MsgCallback, MultiCliProgress, OpId, Phase, PosCallback, Progress,
+    ProgressBuilder, ProgressEvent, ProgressEventKind, ProgressIter, ProgressMsg, ProgressObserver,
+    ProgressReporter, ProgressSet, RateWindow, ScopedProgress, SpinnerStyle, StepProgress,
+    Style as CliProgressStyle, SystemClock, TickInterval, TransferCallback, TransferSession,
+    TransferStats,
+};
+pub use retry::{Retry, RetryPolicy, DEFAULT_RETRY_ATTEMPTS};
+pub use throttle::{parse_rate, RateLimiter};
+pub use verify::{is_verify_skipped, set_skip_verify, verify_minisign, verify_sha256};
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use url::Url;
 
 /// Insert a `.exe` postfix to given input.
@@ -110,6 +137,31 @@ pub fn force_parse_url(url: &str) -> Url {
     Url::parse(url).unwrap_or_else(|e| panic!("failed to parse url '{url}': {e}"))
 }
 
+/// Parse a size in bytes, with an optional binary unit (`K`, `M`, `G` or `T`, with or
+/// without a `B`/`iB` postfix), such as `500M` or `2GiB`.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let split_at = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (num, unit) = input.split_at(split_at);
+    let shift = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        "T" | "TB" | "TIB" => 40,
+        _ => {
+            bail!("invalid size '{input}', expecting a number with an optional unit, such as '2G'")
+        }
+    };
+    let num: u64 = num
+        .parse()
+        .with_context(|| format!("invalid size '{input}'"))?;
+    num.checked_mul(1 << shift)
+        .with_context(|| format!("size '{input}' is too large"))
+}
+
 /// Basically [`Url::join`], but will push a forward slash (`/`) to the root if necessary.
 ///
 /// [`Url::join`] will replace the last part of a root if the root does not have trailing slash,
@@ -0,0 +1,186 @@
+//! Combining the progress of multiple tasks into a single [`Progress`].
+
+use std::sync::Mutex;
+
+use anyhow::Result;
+
+use super::Progress;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct TaskBytes {
+    done: u64,
+    total: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct AggregateState {
+    tasks: Vec<TaskBytes>,
+    /// The amount of progress that was already sent to the inner [`Progress`].
+    reported: f32,
+}
+
+impl AggregateState {
+    fn totals(&self) -> (u64, u64) {
+        self.tasks.iter().fold((0, 0), |(done, total), task| {
+            (done + task.done, total + task.total.unwrap_or(task.done))
+        })
+    }
+
+    /// The completed fraction of all tasks, in range `0.0..=1.0`.
+    ///
+    /// Tasks without known total are assumed to be as big as the average known total,
+    /// so that the progress won't jump to the end before those totals are known.
+    fn fraction(&self) -> f64 {
+        let known = self
+            .tasks
+            .iter()
+            .filter_map(|t| t.total)
+            .collect::<Vec<_>>();
+        if known.is_empty() {
+            return 0.0;
+        }
+        let avg_total = known.iter().sum::<u64>() / known.len() as u64;
+        let (done, total) = self.tasks.iter().fold((0, 0), |(done, total), task| {
+            let task_total = task.total.unwrap_or(task.done.max(avg_total));
+            (done + task.done, total + task_total)
+        });
+        if total == 0 {
+            1.0
+        } else {
+            done as f64 / total as f64
+        }
+    }
+}
+
+/// Aggregates the progress of multiple tasks, such as concurrent downloads.
+///
+/// The whole `len` of the inner [`Progress`] is shared by all tasks, weighted by the
+/// number of bytes of each task, which means a single big download takes up more space
+/// than a few smaller ones.
+pub struct AggregateProgress<'a> {
+    progress: Progress<'a>,
+    state: Mutex<AggregateState>,
+}
+
+impl<'a> AggregateProgress<'a> {
+    pub fn new(progress: Progress<'a>) -> Self {
+        Self {
+            progress,
+            state: Mutex::new(AggregateState::default()),
+        }
+    }
+
+    /// Register a new task, its total size can be set later via [`AggregateTask::set_total`].
+    pub fn task(&self) -> AggregateTask<'_, 'a> {
+        let mut state = self.state.lock().unwrap();
+        state.tasks.push(TaskBytes::default());
+        AggregateTask {
+            parent: self,
+            id: state.tasks.len() - 1,
+        }
+    }
+
+    /// Get the sum of completed bytes and total bytes of every task,
+    /// tasks without known total are counted as their completed bytes.
+    pub fn totals(&self) -> (u64, u64) {
+        self.state.lock().unwrap().totals()
+    }
+
+    /// Get a reference to the inner progress.
+    pub fn progress(&self) -> &Progress<'a> {
+        &self.progress
+    }
+
+    fn update<F: FnOnce(&mut TaskBytes)>(&self, id: usize, f: F) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        f(&mut state.tasks[id]);
+
+        let target = self.progress.len() * state.fraction() as f32;
+        // never move backward, which could happen when a bigger total was discovered
+        if target > state.reported {
+            self.progress.inc(Some(target - state.reported))?;
+            state.reported = target;
+        }
+        Ok(())
+    }
+}
+
+/// A handle of a single task in an [`AggregateProgress`].
+pub struct AggregateTask<'p, 'a> {
+    parent: &'p AggregateProgress<'a>,
+    id: usize,
+}
+
+impl AggregateTask<'_, '_> {
+    /// Set the total number of bytes of this task.
+    pub fn set_total(&self, total: u64) -> Result<()> {
+        self.parent.update(self.id, |task| task.total = Some(total))
+    }
+
+    /// Mark a certain number of bytes as completed.
+    pub fn advance(&self, bytes: u64) -> Result<()> {
+        self.parent.update(self.id, |task| {
+            task.done = task
+                .total
+                .map_or(task.done + bytes, |total| (task.done + bytes).min(total));
+        })
+    }
+
+    /// Mark this task as having completed at least `bytes` in total, unlike
+    /// [`advance`](AggregateTask::advance), reporting a smaller number than before
+    /// is a no-op, so this can be used with out-of-order reports from multiple workers.
+    pub fn advance_to(&self, bytes: u64) -> Result<()> {
+        self.parent.update(self.id, |task| {
+            let bytes = task.total.map_or(bytes, |total| bytes.min(total));
+            task.done = task.done.max(bytes);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighted_by_bytes() {
+        let pos_cb = |_: f32| Ok(());
+        let aggregate = AggregateProgress::new(Progress::new(&pos_cb).with_len(50.0));
+        let big = aggregate.task();
+        let small = aggregate.task();
+        small.set_total(100).unwrap();
+        small.advance(50).unwrap();
+        // the unknown total is assumed to be the same as the average
+        assert_eq!(aggregate.progress().pos.load(), 12.5);
+        // a bigger total was discovered, but the progress does not go backward
+        big.set_total(300).unwrap();
+        assert_eq!(aggregate.progress().pos.load(), 12.5);
+
+        small.advance(100).unwrap();
+        assert_eq!(aggregate.totals(), (100, 400));
+        assert_eq!(aggregate.progress().pos.load(), 12.5);
+
+        big.advance(150).unwrap();
+        assert_eq!(aggregate.progress().pos.load(), 31.25);
+        // overshooting is capped by the task total
+        big.advance(1000).unwrap();
+        assert_eq!(aggregate.totals(), (400, 400));
+        assert_eq!(aggregate.progress().pos.load(), 50.0);
+    }
+
+    #[test]
+    fn out_of_order_reports() {
+        let pos_cb = |_: f32| Ok(());
+        let aggregate = AggregateProgress::new(Progress::new(&pos_cb).with_len(40.0));
+        let task = aggregate.task();
+        task.set_total(200).unwrap();
+        task.advance_to(150).unwrap();
+        assert_eq!(aggregate.progress().position(), 30.0);
+        // a lower report after a higher one is a no-op
+        task.advance_to(100).unwrap();
+        assert_eq!(aggregate.totals(), (150, 200));
+        assert_eq!(aggregate.progress().position(), 30.0);
+        task.advance_to(500).unwrap();
+        assert_eq!(aggregate.totals(), (200, 200));
+        assert_eq!(aggregate.progress().position(), 40.0);
+    }
+}
@@ -0,0 +1,182 @@
+//! A builder to construct a fully configured [`Progress`].
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+
+use super::{
+    Bounds, CallbackErrorPolicy, LogFileObserver, MessageHistory, MsgCallback, PosCallback,
+    Progress, ProgressObserver, TransferCallback, TransferStats,
+};
+
+/// Collects the options of a [`Progress`], then validates them all at once when
+/// [`build`](ProgressBuilder::build)ing it.
+///
+/// For simple cases, [`Progress::new`] with [`with_len`](Progress::with_len) is enough.
+pub struct ProgressBuilder<'a> {
+    pos_callback: PosCallback<'a>,
+    msg_callback: Option<MsgCallback<'a>>,
+    transfer_callback: Option<TransferCallback<'a>>,
+    len: f32,
+    bounds: (f32, f32),
+    byte_total: Option<u64>,
+    observers: Vec<Arc<dyn ProgressObserver>>,
+    error_policy: CallbackErrorPolicy,
+    history_capacity: usize,
+}
+
+impl<'a> ProgressBuilder<'a> {
+    pub fn new<P>(pos_cb: &'a P) -> Self
+    where
+        P: Fn(f32) -> Result<()> + Send + Sync,
+    {
+        let bounds = Bounds::default();
+        Self {
+            pos_callback: pos_cb,
+            msg_callback: None,
+            transfer_callback: None,
+            len: 0.0,
+            bounds: (bounds.0, bounds.1),
+            byte_total: None,
+            observers: vec![],
+            error_policy: CallbackErrorPolicy::default(),
+            history_capacity: MessageHistory::DEFAULT_CAPACITY,
+        }
+    }
+
+    /// Set the amount of position to increase when calling [`inc(None)`](Progress::inc),
+    /// must be in range `0..=100`.
+    pub fn len(mut self, len: f32) -> Self {
+        self.len = len;
+        self
+    }
+
+    /// Limit the position to be within `min` and `max`, which must be in range `0..=100`,
+    /// and `min` must not be greater than `max`.
+    pub fn bounds(mut self, min: f32, max: f32) -> Self {
+        self.bounds = (min, max);
+        self
+    }
+
+    /// Set the number of bytes that the whole progress represents.
+    pub fn byte_total(mut self, total: u64) -> Self {
+        self.byte_total = Some(total);
+        self
+    }
+
+    /// Set a callback that will be called to display messages.
+    pub fn msg_callback<M>(mut self, msg_cb: &'a M) -> Self
+    where
+        M: Fn(String) -> Result<()> + Send + Sync,
+    {
+        self.msg_callback = Some(msg_cb);
+        self
+    }
+
+    /// Set a callback that will be called with the speed and ETA of the transfers.
+    pub fn transfer_callback<T>(mut self, transfer_cb: &'a T) -> Self
+    where
+        T: Fn(TransferStats) -> Result<()> + Send + Sync,
+    {
+        self.transfer_callback = Some(transfer_cb);
+        self
+    }
+
+    /// Add an observer that will be notified after every position change and message.
+    pub fn observer<O: ProgressObserver + 'static>(mut self, observer: O) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Append every message to the file at `path` with timestamps.
+    pub fn log_file<P: AsRef<Path>>(self, path: P) -> Self {
+        self.observer(LogFileObserver::new(path))
+    }
+
+    /// Choose what to do when one of the callbacks returns an error.
+    pub fn callback_error_policy(mut self, policy: CallbackErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Set the number of recent messages to keep, see [`Progress::message_history`].
+    pub fn message_history(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        self
+    }
+
+    /// Build the progress.
+    ///
+    /// # Error
+    ///
+    /// Unlike [`Progress::with_len`] and [`Progress::with_bounds`], which silently correct
+    /// the invalid values, this returns an error if the `len` or bounds are invalid.
+    pub fn build(self) -> Result<Progress<'a>> {
+        let in_range = |val: f32| (0.0..=100.0).contains(&val);
+        if !in_range(self.len) {
+            bail!("invalid progress length '{}', expecting 0 to 100", self.len);
+        }
+        let (min, max) = self.bounds;
+        if !in_range(min) || !in_range(max) || min > max {
+            bail!("invalid progress bounds '{min}..{max}', expecting a range within 0 to 100");
+        }
+
+        let mut progress = Progress::with_pos_callback(self.pos_callback)
+            .with_len(self.len)
+            .with_message_history(self.history_capacity);
+        progress.bounds = Bounds(min, max);
+        *progress.byte_total.lock().unwrap() = self.byte_total;
+        progress.msg_callback = self.msg_callback;
+        progress.transfer_callback = self.transfer_callback;
+        progress.observers = self.observers;
+        progress.error_policy = self.error_policy;
+        Ok(progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equivalent_to_setters() {
+        let pos_cb = |_: f32| Ok(());
+        let msg_cb = |_: String| Ok(());
+        let built = ProgressBuilder::new(&pos_cb)
+            .len(30.0)
+            .bounds(10.0, 50.0)
+            .byte_total(2000)
+            .msg_callback(&msg_cb)
+            .build()
+            .unwrap();
+        let chained = Progress::new(&pos_cb)
+            .with_len(30.0)
+            .with_bounds(10.0, 50.0)
+            .with_byte_total(2000)
+            .with_msg_callback(&msg_cb);
+
+        assert_eq!(built.len(), chained.len());
+        assert_eq!(built.bounds, chained.bounds);
+        assert_eq!(built.byte_total(), chained.byte_total());
+        assert!(built.msg_callback.is_some());
+        for progress in [&built, &chained] {
+            progress.inc(None).unwrap();
+            progress.inc(None).unwrap();
+        }
+        assert_eq!(built.position(), 50.0);
+        assert_eq!(built.position_bytes(), chained.position_bytes());
+    }
+
+    #[test]
+    fn invalid_options_rejected() {
+        let pos_cb = |_: f32| Ok(());
+        let builder = || ProgressBuilder::new(&pos_cb);
+        assert!(builder().len(f32::NAN).build().is_err());
+        assert!(builder().len(-1.0).build().is_err());
+        assert!(builder().len(101.0).build().is_err());
+        assert!(builder().bounds(60.0, 40.0).build().is_err());
+        assert!(builder().bounds(0.0, f32::INFINITY).build().is_err());
+        assert!(builder().len(100.0).bounds(0.0, 100.0).build().is_ok());
+    }
+}
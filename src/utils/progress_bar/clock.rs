@@ -0,0 +1,19 @@
+//! The source of time of progress timing, which can be replaced in tests.
+
+use std::time::Instant;
+
+/// Provides the current time for the timing of a [`Progress`](super::Progress),
+/// such as its [`elapsed`](super::Progress::elapsed) time and [`eta`](super::Progress::eta).
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// The default clock that uses [`Instant::now`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
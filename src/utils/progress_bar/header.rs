@@ -0,0 +1,115 @@
+//! A layout of a block of static lines, with progress bars pinned below it.
+
+use anyhow::Result;
+use indicatif::{MultiProgress, ProgressBar as CliProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use super::{draw_hidden, styled_bar, CliProgressOpt, Style};
+
+/// Renders a header, such as `Installing: rustc, cargo, rust-std`, that stays visible
+/// above the progress bars during the whole operation.
+///
+/// Unlike printing the header before starting a bar, the header is redrawn together
+/// with the bars, so it won't be scrolled away by the output printed above them.
+#[derive(Debug)]
+pub struct HeaderLayout {
+    multi: MultiProgress,
+    header: CliProgressBar,
+    opt: CliProgressOpt,
+}
+
+impl HeaderLayout {
+    /// Create a layout with the given header `lines` that renders on `stderr`,
+    /// which will be hidden in quiet mode, summary mode, JSON mode, or if drawing has
+    /// failed before.
+    pub fn new<I, S>(lines: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let target = if draw_hidden() {
+            ProgressDrawTarget::hidden()
+        } else {
+            ProgressDrawTarget::stderr()
+        };
+        Self::with_draw_target(lines, target)
+    }
+
+    /// Create a layout with the given header `lines` that renders on a specific target.
+    pub fn with_draw_target<I, S>(lines: I, target: ProgressDrawTarget) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let multi = MultiProgress::with_draw_target(target);
+        let header = multi.add(CliProgressBar::new_spinner());
+        header.set_style(ProgressStyle::with_template("{msg}").expect("valid template"));
+        let layout = Self {
+            multi,
+            header,
+            opt: CliProgressOpt::default(),
+        };
+        layout.set_header(lines);
+        layout
+    }
+
+    /// Set the options of the bars that will be added.
+    pub fn with_opt(mut self, opt: CliProgressOpt) -> Self {
+        self.opt = opt;
+        self
+    }
+
+    /// Replace the header lines.
+    pub fn set_header<I, S>(&self, lines: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let lines = lines
+            .into_iter()
+            .map(|line| line.as_ref().to_string())
+            .collect::<Vec<_>>();
+        self.header.set_message(lines.join("\n"));
+    }
+
+    /// Add a new bar below the header and the existing bars, and return it for updating.
+    pub fn add_bar(&self, msg: String, style: Style) -> Result<CliProgressBar> {
+        Ok(self.multi.add(styled_bar(msg, style, self.opt)?))
+    }
+
+    /// Print a line above the header.
+    pub fn println<S: AsRef<str>>(&self, msg: S) -> Result<()> {
+        self.multi.println(msg)?;
+        Ok(())
+    }
+
+    /// Stop redrawing the header, leaving it on the screen above the finished bars.
+    pub fn finish(&self) {
+        self.header.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::RecordingTerm;
+    use super::*;
+
+    #[test]
+    fn header_lines_precede_the_bar() {
+        let term = RecordingTerm::default();
+        let target = ProgressDrawTarget::term_like(Box::new(term.clone()));
+        let layout = HeaderLayout::with_draw_target(["Installing:", "rustc, cargo"], target);
+        let bar = layout
+            .add_bar("downloading".into(), Style::Len(10))
+            .unwrap();
+        bar.set_style(ProgressStyle::with_template("BAR {pos}/{len}").unwrap());
+        bar.finish();
+        layout.finish();
+
+        let ops = term.0.lock().unwrap();
+        let last_pos = |pat: &str| ops.iter().rposition(|op| op.contains(pat)).unwrap();
+        let first = last_pos("Installing:");
+        let second = last_pos("rustc, cargo");
+        let bar = last_pos("BAR 10/10");
+        assert!(first < second && second < bar, "{ops:?}");
+    }
+}
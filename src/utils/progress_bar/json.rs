@@ -0,0 +1,228 @@
+//! The machine readable output mode, where the progress and log messages are printed
+//! on `stdout` as line-delimited JSON events, instead of bars and colored text.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+
+use super::{Phase, ProgressEvent, ProgressEventKind, ProgressObserver};
+
+/// Whether the output is printed as JSON events.
+static JSON_MODE: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn set_enabled(enabled: bool) {
+    JSON_MODE.store(enabled, Ordering::Relaxed);
+}
+
+pub(super) fn is_enabled() -> bool {
+    JSON_MODE.load(Ordering::Relaxed)
+}
+
+/// Convert a progress event to its JSON representation.
+pub fn event_to_json(event: &ProgressEvent) -> Value {
+    let op = event.op_id.get();
+    let phase = event.phase.as_ref().map(Phase::as_str);
+    match &event.kind {
+        ProgressEventKind::Position { pos, determinate } => json!({
+            "event": "progress",
+            "op": op,
+            "phase": phase,
+            "percent": pos,
+            "determinate": determinate,
+            "bytes": event.bytes.map(|(pos, _)| pos),
+            "total_bytes": event.bytes.map(|(_, total)| total),
+        }),
+        ProgressEventKind::Message(msg) => json!({
+            "event": "message",
+            "op": op,
+            "phase": phase,
+            "message": msg,
+        }),
+        ProgressEventKind::Transfer(stats) => json!({
+            "event": "transfer",
+            "op": op,
+            "phase": phase,
+            "bytes": stats.bytes,
+            "total_bytes": stats.total_bytes,
+            "speed": stats.speed.round() as u64,
+            "eta_secs": stats.eta.map(|eta| eta.as_secs()),
+        }),
+        ProgressEventKind::PhaseStarted(started) => json!({
+            "event": "phase_started",
+            "op": op,
+            "phase": started.as_str(),
+        }),
+        ProgressEventKind::PhaseEnded {
+            phase: ended,
+            duration,
+        } => json!({
+            "event": "phase_ended",
+            "op": op,
+            "phase": ended.as_str(),
+            "duration_ms": duration.as_millis() as u64,
+        }),
+    }
+}
+
+/// Convert a log message of `level` (such as `warn`) to its JSON representation.
+pub fn log_to_json(level: &str, msg: &str) -> Value {
+    json!({ "event": "log", "level": level, "message": msg })
+}
+
+/// Convert an error, along with its causes, to its JSON representation.
+pub fn error_to_json(err: &anyhow::Error) -> Value {
+    json!({
+        "event": "error",
+        "message": err.to_string(),
+        "causes": err.chain().skip(1).map(ToString::to_string).collect::<Vec<_>>(),
+    })
+}
+
+/// Print a JSON value as a single line on `stdout`.
+pub fn print_json_line(value: &Value) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{value}");
+    let _ = stdout.flush();
+}
+
+/// Writes every change of a progress as a line of JSON, on `stdout` by default.
+///
+/// Each line is a JSON object with an `event` field, the other fields depend on its value:
+///
+/// | `event`         | fields                                                         |
+/// |-----------------|----------------------------------------------------------------|
+/// | `progress`      | `op`, `phase`, `percent`, `determinate`, `bytes`, `total_bytes` |
+/// | `message`       | `op`, `phase`, `message`                                       |
+/// | `transfer`      | `op`, `phase`, `bytes`, `total_bytes`, `speed`, `eta_secs`     |
+/// | `phase_started` | `op`, `phase`                                                  |
+/// | `phase_ended`   | `op`, `phase`, `duration_ms`                                   |
+/// | `log`           | `level`, `message`                                             |
+/// | `error`         | `message`, `causes`                                            |
+///
+/// where `op` is the [`OpId`](crate::utils::OpId) of the operation, `phase` is the identifier of
+/// the running [`Phase`] (such as `download`) or `null`, `percent` is in range `0..=100`,
+/// `bytes` and `total_bytes` of `progress` are `null` unless the progress counts bytes,
+/// `speed` is in bytes per second, `eta_secs` is `null` if the speed is unknown yet, and
+/// `causes` is the list of the underlying errors, from the outermost to the innermost.
+///
+/// New fields and events might be added in the future, consumers should ignore the ones
+/// they don't know.
+pub struct JsonEventWriter {
+    out: Mutex<Option<Box<dyn Write + Send>>>,
+}
+
+impl JsonEventWriter {
+    /// Write the events on `stdout`.
+    pub fn stdout() -> Self {
+        Self {
+            out: Mutex::new(None),
+        }
+    }
+
+    /// Write the events to a specific writer, such as a file or a pipe.
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            out: Mutex::new(Some(Box::new(writer))),
+        }
+    }
+}
+
+impl std::fmt::Debug for JsonEventWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonEventWriter").finish_non_exhaustive()
+    }
+}
+
+impl ProgressObserver for JsonEventWriter {
+    fn on_event(&self, event: &ProgressEvent) {
+        let value = event_to_json(event);
+        match &mut *self.out.lock().unwrap() {
+            Some(out) => {
+                let _ = writeln!(out, "{value}");
+            }
+            None => print_json_line(&value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Progress;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn events_as_json_lines() {
+        let buf = Buffer::default();
+        let progress = Progress::noop()
+            .with_byte_total(1000)
+            .with_observer(JsonEventWriter::new(buf.clone()));
+        let op = progress.op_id().get();
+        progress.start_phase(Phase::Download);
+        progress.show_msg("downloading a").unwrap();
+        progress.inc(Some(25.0)).unwrap();
+        progress.end_phase();
+        drop(progress);
+
+        let output = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let events = output
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 4, "{output}");
+        assert_eq!(
+            events[0],
+            json!({ "event": "phase_started", "op": op, "phase": "download" })
+        );
+        assert_eq!(
+            events[1],
+            json!({ "event": "message", "op": op, "phase": "download", "message": "downloading a" })
+        );
+        assert_eq!(
+            events[2],
+            json!({
+                "event": "progress",
+                "op": op,
+                "phase": "download",
+                "percent": 25.0,
+                "determinate": true,
+                "bytes": 250,
+                "total_bytes": 1000,
+            })
+        );
+        assert_eq!(events[3]["event"], "phase_ended");
+        assert!(events[3]["duration_ms"].is_u64());
+    }
+
+    #[test]
+    fn errors_with_causes() {
+        let err = anyhow::anyhow!("connection refused").context("failed to download 'a'");
+        assert_eq!(
+            error_to_json(&err),
+            json!({
+                "event": "error",
+                "message": "failed to download 'a'",
+                "causes": ["connection refused"],
+            })
+        );
+        assert_eq!(
+            log_to_json("warn", "retrying"),
+            json!({ "event": "log", "level": "warn", "message": "retrying" })
+        );
+    }
+}
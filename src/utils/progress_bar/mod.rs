@@ -0,0 +1,2637 @@
+//! Progress bar indicator for commandline user interface.
+
+mod aggregate;
+mod builder;
+mod clock;
+mod header;
+mod json;
+mod multi;
+mod observer;
+mod phase;
+mod rate;
+mod reporter;
+mod set;
+mod steps;
+mod summary;
+mod taskbar;
+
+use std::{
+    cell::Cell,
+    ops::Deref,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use indicatif::{
+    HumanBytes, ProgressBar as CliProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle,
+    TermLike,
+};
+
+use crate::core::GlobalOpts;
+use crate::utils::{CancellationToken, RateLimiter};
+
+pub use aggregate::{AggregateProgress, AggregateTask};
+pub use builder::ProgressBuilder;
+pub use clock::{Clock, SystemClock};
+pub use header::HeaderLayout;
+pub use json::{error_to_json, event_to_json, log_to_json, print_json_line, JsonEventWriter};
+pub use multi::MultiCliProgress;
+pub use observer::{
+    EventSender, LogFileObserver, MessageHistory, OpId, ProgressEvent, ProgressEventKind,
+    ProgressObserver,
+};
+pub use phase::Phase;
+pub use rate::{RateWindow, TransferSession, TransferStats};
+pub use reporter::{ProgressReporter, ScopedProgress};
+pub use set::ProgressSet;
+pub use steps::{ProgressIter, StepProgress};
+
+struct ProgressPos(Mutex<f32>);
+
+impl ProgressPos {
+    fn new(value: f32) -> Self {
+        Self(Mutex::new(value))
+    }
+    fn load(&self) -> f32 {
+        *self.0.lock().unwrap()
+    }
+    /// Increment position value, and ensure the end result stays within the given `bounds`.
+    ///
+    /// The new value is passed to `on_change` first, and will only be stored if it returns `Ok`,
+    /// otherwise the position stays unchanged and the error is returned.
+    fn add<F>(&self, value: f32, bounds: Bounds, on_change: F) -> Result<()>
+    where
+        F: FnOnce(f32) -> Result<()>,
+    {
+        let mut guard = self.0.lock().unwrap();
+        let new_value = (*guard + value).clamp(bounds.0, bounds.1);
+        on_change(new_value)?;
+        *guard = new_value;
+        Ok(())
+    }
+    /// Move the position forward to `target` (within the given `bounds`), does nothing
+    /// and returns `false` if the position is already at or past it.
+    ///
+    /// `on_change` works the same as in [`add`](ProgressPos::add).
+    fn advance_to<F>(&self, target: f32, bounds: Bounds, on_change: F) -> Result<bool>
+    where
+        F: FnOnce(f32) -> Result<()>,
+    {
+        let mut guard = self.0.lock().unwrap();
+        let new_value = target.clamp(bounds.0, bounds.1);
+        if new_value <= *guard || new_value.is_nan() {
+            return Ok(false);
+        }
+        on_change(new_value)?;
+        *guard = new_value;
+        Ok(true)
+    }
+}
+
+/// What to do when a callback of [`Progress`] returns an error, such as when the
+/// channel to the GUI was closed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CallbackErrorPolicy {
+    /// Return the error, which typically aborts the whole operation.
+    #[default]
+    Abort,
+    /// Warn about the error, then stop calling the callbacks and carry on without UI.
+    ContinueWithoutUi,
+}
+
+/// Built-in messages of common progress phases.
+///
+/// These are translated using the current locale when displayed,
+/// or use [`localized`](ProgressMsg::localized) to get the message for a specific locale.
+/// Unknown locales fallback to English.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressMsg<'s> {
+    /// Downloading a file with the given name.
+    Downloading(&'s str),
+    /// Downloading a file with the given name, through the given proxy.
+    DownloadingViaProxy(&'s str, &'s str),
+    /// A file with the given name was downloaded.
+    Downloaded(&'s str),
+    /// A file with the given name was found in the download cache.
+    Cached(&'s str),
+    /// Downloading the given number of files concurrently.
+    DownloadingFiles(usize),
+    /// The given number of files were downloaded.
+    DownloadedFiles(usize),
+    /// Extracting a file with the given name.
+    Extracting(&'s str),
+    Extracted,
+    Paused,
+    /// Verifying the integrity of a file with the given name.
+    Verifying(&'s str),
+    /// A file with the given name was verified.
+    Verified(&'s str),
+    /// Retrying an operation of a file with the given name after `delay`, because of `reason`.
+    Retrying {
+        name: &'s str,
+        attempt: u32,
+        max_attempts: u32,
+        delay: Duration,
+        reason: &'s str,
+    },
+}
+
+impl ProgressMsg<'_> {
+    /// Get the translated message of a specific locale, such as `en` or `zh-CN`.
+    pub fn localized(&self, locale: &str) -> String {
+        match self {
+            Self::Downloading(name) => t!("progress_downloading", locale = locale, name = name),
+            Self::DownloadingViaProxy(name, proxy) => t!(
+                "progress_downloading_via_proxy",
+                locale = locale,
+                name = name,
+                proxy = proxy
+            ),
+            Self::Downloaded(name) => t!("progress_downloaded", locale = locale, name = name),
+            Self::Cached(name) => t!("progress_cached", locale = locale, name = name),
+            Self::DownloadingFiles(count) => {
+                t!("progress_downloading_files", locale = locale, count = count)
+            }
+            Self::DownloadedFiles(count) => {
+                t!("progress_downloaded_files", locale = locale, count = count)
+            }
+            Self::Extracting(name) => t!("progress_extracting", locale = locale, name = name),
+            Self::Extracted => t!("progress_extracted", locale = locale),
+            Self::Paused => t!("progress_paused", locale = locale),
+            Self::Verifying(name) => t!("progress_verifying", locale = locale, name = name),
+            Self::Verified(name) => t!("progress_verified", locale = locale, name = name),
+            Self::Retrying {
+                name,
+                attempt,
+                max_attempts,
+                delay,
+                reason,
+            } => t!(
+                "progress_retrying",
+                locale = locale,
+                name = name,
+                reason = reason,
+                secs = format!("{:.1}", delay.as_secs_f32()),
+                attempt = attempt,
+                max = max_attempts
+            ),
+        }
+        .to_string()
+    }
+}
+
+impl std::fmt::Display for ProgressMsg<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localized(&rust_i18n::locale()))
+    }
+}
+
+/// The lower and upper bound of a progress position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds(f32, f32);
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self(0.0, 100.0)
+    }
+}
+
+/// Keeps track of how long a progress has been running, excluding the time it was paused.
+struct ProgressTimer {
+    /// A custom clock, or `None` to use the [`SystemClock`] directly.
+    clock: Option<Arc<dyn Clock>>,
+    started: Instant,
+    paused_at: Option<Instant>,
+    /// Total duration of previous pauses.
+    paused: Duration,
+}
+
+impl ProgressTimer {
+    fn new(clock: Option<Arc<dyn Clock>>) -> Self {
+        let started = clock.as_ref().map_or_else(Instant::now, |c| c.now());
+        Self {
+            clock,
+            started,
+            paused_at: None,
+            paused: Duration::ZERO,
+        }
+    }
+
+    fn now(&self) -> Instant {
+        self.clock.as_ref().map_or_else(Instant::now, |c| c.now())
+    }
+
+    fn elapsed(&self) -> Duration {
+        let until = self.paused_at.unwrap_or_else(|| self.now());
+        until
+            .duration_since(self.started)
+            .saturating_sub(self.paused)
+    }
+
+    /// Returns `false` if it was already paused.
+    fn pause(&mut self) -> bool {
+        if self.paused_at.is_some() {
+            return false;
+        }
+        self.paused_at = Some(self.now());
+        true
+    }
+
+    /// Returns `false` if it wasn't paused.
+    fn resume(&mut self) -> bool {
+        let Some(paused_at) = self.paused_at.take() else {
+            return false;
+        };
+        self.paused += self.now().saturating_duration_since(paused_at);
+        true
+    }
+}
+
+/// Durations of each phase of a progress.
+#[derive(Debug, Default)]
+struct PhaseTimings {
+    /// The running phase, and the elapsed time when it started.
+    current: Option<(Phase, Duration)>,
+    finished: Vec<(Phase, Duration)>,
+}
+
+impl PhaseTimings {
+    /// End the running phase at `now`, returns it with its duration.
+    fn end_current(&mut self, now: Duration) -> Option<(Phase, Duration)> {
+        let (phase, started) = self.current.take()?;
+        let ended = (phase, now.saturating_sub(started));
+        self.finished.push(ended.clone());
+        Some(ended)
+    }
+}
+
+/// The callback that receives the new position of a [`Progress`].
+pub type PosCallback<'a> = &'a (dyn Fn(f32) -> Result<()> + Send + Sync);
+/// The callback that displays the messages of a [`Progress`].
+pub type MsgCallback<'a> = &'a (dyn Fn(String) -> Result<()> + Send + Sync);
+/// The callback that receives the speed and ETA of the transfers of a [`Progress`].
+pub type TransferCallback<'a> = &'a (dyn Fn(TransferStats) -> Result<()> + Send + Sync);
+
+/// The time window to average the speed of [`TransferStats`] over.
+const TRANSFER_RATE_WINDOW: Duration = Duration::from_secs(3);
+/// The minimum interval between two reports of [`TransferStats`].
+const TRANSFER_REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The progress of an operation, which reports its position (in range `0..=100`)
+/// and messages through callbacks, such as to a GUI.
+///
+/// # Thread safety
+///
+/// `Progress` is `Send + Sync`, as the callbacks are required to be, so it can be shared
+/// by reference or cloned across threads, the clones share the same position.
+/// Every method can be called concurrently:
+/// - Increments are atomic, the position callback is called while the position is locked,
+///   so the positions it receives are in the same order as they are stored.
+/// - Messages are not serialized with increments, concurrent callers of
+///   [`show_msg`](Progress::show_msg) may have their messages displayed in any order.
+#[derive(Clone)]
+pub struct Progress<'a> {
+    pos: Arc<ProgressPos>,
+    op_id: OpId,
+    /// The amount of position to increase by [`inc(None)`](Progress::inc),
+    /// always in range `0..=100`.
+    len: f32,
+    /// The number of bytes that the full (`100`) progress represents, if known,
+    /// shared by clones, as it could be discovered mid-flight.
+    byte_total: Arc<Mutex<Option<u64>>>,
+    bounds: Bounds,
+    timer: Arc<Mutex<ProgressTimer>>,
+    phases: Arc<Mutex<PhaseTimings>>,
+    /// The last message shown by [`show_msg`](Progress::show_msg).
+    last_msg: Arc<Mutex<Option<String>>>,
+    /// The recent messages shown by [`show_msg`](Progress::show_msg).
+    history: Arc<MessageHistory>,
+    /// Whether the total amount of work is known, see [`ProgressEventKind::Position`].
+    determinate: Arc<AtomicBool>,
+    /// The speed of the transfers reported by [`report_transfer`](Progress::report_transfer).
+    transfer: Arc<Mutex<rate::TransferMeter>>,
+    pos_callback: PosCallback<'a>,
+    msg_callback: Option<MsgCallback<'a>>,
+    transfer_callback: Option<TransferCallback<'a>>,
+    observers: Vec<Arc<dyn ProgressObserver>>,
+    error_policy: CallbackErrorPolicy,
+    /// Set once a callback failed under [`CallbackErrorPolicy::ContinueWithoutUi`].
+    ui_disabled: Arc<AtomicBool>,
+    /// Stops the operation reporting to this progress, shared by clones and children.
+    cancel: CancellationToken,
+    /// The end of the range reserved by [`ProgressReporter::child`]ren.
+    reserved: reporter::Reserved,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new<P>(pos_cb: &'a P) -> Self
+    where
+        P: Fn(f32) -> Result<()> + Send + Sync,
+    {
+        Self::with_pos_callback(pos_cb)
+    }
+
+    fn with_pos_callback(pos_cb: PosCallback<'a>) -> Self {
+        Self {
+            pos: Arc::new(ProgressPos::new(0.0)),
+            op_id: OpId::next(),
+            len: 0.0,
+            byte_total: Arc::new(Mutex::new(None)),
+            bounds: Bounds::default(),
+            timer: Arc::new(Mutex::new(ProgressTimer::new(None))),
+            phases: Arc::new(Mutex::new(PhaseTimings::default())),
+            last_msg: Arc::new(Mutex::new(None)),
+            history: Arc::new(MessageHistory::default()),
+            determinate: Arc::new(AtomicBool::new(true)),
+            transfer: Arc::new(Mutex::new(new_transfer_meter())),
+            pos_callback: pos_cb,
+            msg_callback: None,
+            transfer_callback: None,
+            observers: vec![],
+            error_policy: CallbackErrorPolicy::default(),
+            ui_disabled: Arc::new(AtomicBool::new(false)),
+            cancel: CancellationToken::new(),
+            reserved: Arc::new(Mutex::new(0.0)),
+        }
+    }
+
+    /// Create a [`ProgressBuilder`] for more configurations.
+    pub fn builder<P>(pos_cb: &'a P) -> ProgressBuilder<'a>
+    where
+        P: Fn(f32) -> Result<()> + Send + Sync,
+    {
+        ProgressBuilder::new(pos_cb)
+    }
+
+    /// Create a progress with callbacks that do nothing.
+    ///
+    /// This is useful when using the installation APIs in a context without any UI.
+    /// Note that this is different than [`CliProgress::hidden`], which still creates
+    /// a (hidden) progress bar.
+    pub fn noop() -> Self {
+        fn noop_pos(_: f32) -> Result<()> {
+            Ok(())
+        }
+        fn noop_msg(_: String) -> Result<()> {
+            Ok(())
+        }
+        Self::new(&noop_pos).with_msg_callback(&noop_msg)
+    }
+
+    /// Use a specific token to cancel the operation reporting to this progress, such as one
+    /// that is cancelled by a button in GUI.
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Get the token that cancels the operation reporting to this progress, which is
+    /// checked by [`inc`](Progress::inc) and should be passed to the long-running work.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    /// Ask the operation reporting to this progress to stop, the next call of
+    /// [`inc`](Progress::inc) or [`inc_to`](Progress::inc_to) will return [`Cancelled`].
+    ///
+    /// [`Cancelled`]: crate::utils::Cancelled
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Returns `true` if the operation has been [`cancel`](Progress::cancel)led.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Choose what to do when the position or message callback returns an error,
+    /// default is [`CallbackErrorPolicy::Abort`].
+    pub fn with_callback_error_policy(mut self, policy: CallbackErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Set the `len` of this progress, see [`set_len`](Progress::set_len) for details.
+    pub fn with_len(mut self, len: f32) -> Self {
+        self.set_len(len);
+        self
+    }
+
+    /// Set the amount of position to increase when calling [`inc(None)`](Progress::inc).
+    ///
+    /// Because the whole progress is `100`, the value is clamped in range `0..=100`,
+    /// negative, `NaN` and infinite values are treated as `0` (with a warning),
+    /// so that a miscomputed length won't corrupt the position.
+    pub fn set_len(&mut self, len: f32) {
+        self.len = if len.is_finite() && len >= 0.0 {
+            len.min(100.0)
+        } else {
+            warn!("invalid progress length '{len}', using '0' instead");
+            0.0
+        };
+    }
+
+    /// Get the amount of position to increase when calling [`inc(None)`](Progress::inc).
+    pub fn len(&self) -> f32 {
+        self.len
+    }
+
+    /// Limit the position to be within `min` and `max` when incrementing with this progress,
+    /// which is useful when this progress only represents a certain phase of the whole progress,
+    /// such as from `40` to `60`.
+    ///
+    /// Both values will be clamped in range `0..=100`, and swapped if `min` is greater than `max`.
+    pub fn with_bounds(mut self, min: f32, max: f32) -> Self {
+        let (min, max) = (min.clamp(0.0, 100.0), max.clamp(0.0, 100.0));
+        self.bounds = Bounds(min.min(max), max.max(min));
+        self
+    }
+
+    /// Set a callback that will be called to display messages, such as the current status.
+    pub fn with_msg_callback<M>(mut self, msg_cb: &'a M) -> Self
+    where
+        M: Fn(String) -> Result<()> + Send + Sync,
+    {
+        self.msg_callback = Some(msg_cb);
+        self
+    }
+
+    /// Set a callback that will be called with the speed and ETA of the transfers,
+    /// see [`report_transfer`](Progress::report_transfer).
+    pub fn with_transfer_callback<T>(mut self, transfer_cb: &'a T) -> Self
+    where
+        T: Fn(TransferStats) -> Result<()> + Send + Sync,
+    {
+        self.transfer_callback = Some(transfer_cb);
+        self
+    }
+
+    /// Add an observer that will be notified after every position change and message.
+    pub fn with_observer<O: ProgressObserver + 'static>(mut self, observer: O) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Append every message to the file at `path` with timestamps, which is handy
+    /// to be attached to bug reports.
+    ///
+    /// This is a shorthand of adding a [`LogFileObserver`], failing to write the file
+    /// only prints a warning once, the progress itself will not be affected.
+    pub fn with_log_file<P: AsRef<Path>>(self, path: P) -> Self {
+        self.with_observer(LogFileObserver::new(path))
+    }
+
+    /// Use a custom clock for timing, instead of the [`SystemClock`],
+    /// this restarts the timing of this progress.
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.timer = Arc::new(Mutex::new(ProgressTimer::new(Some(Arc::new(clock)))));
+        self
+    }
+
+    /// Get the current position, in range of `0..=100`.
+    pub fn position(&self) -> f32 {
+        self.pos.load()
+    }
+
+    /// Set the number of bytes that the whole progress (`100`) represents,
+    /// which is needed by [`position_bytes`](Progress::position_bytes) and
+    /// [`len_bytes`](Progress::len_bytes).
+    pub fn with_byte_total(self, total: u64) -> Self {
+        *self.byte_total.lock().unwrap() = Some(total);
+        self
+    }
+
+    /// Get the number of bytes set by [`with_byte_total`](Progress::with_byte_total)
+    /// or [`promote_to_determinate`](Progress::promote_to_determinate).
+    pub fn byte_total(&self) -> Option<u64> {
+        *self.byte_total.lock().unwrap()
+    }
+
+    /// Get the current position as number of bytes of the byte total.
+    ///
+    /// The value is rounded to the nearest byte (halves are rounded up), and never exceeds
+    /// the byte total. Returns `0` if no byte total was set with
+    /// [`with_byte_total`](Progress::with_byte_total).
+    pub fn position_bytes(&self) -> u64 {
+        self.percentage_to_bytes(self.pos.load())
+    }
+
+    /// Get the `len` as number of bytes of the byte total.
+    ///
+    /// Rounding behaves the same as [`position_bytes`](Progress::position_bytes).
+    pub fn len_bytes(&self) -> u64 {
+        self.percentage_to_bytes(self.len)
+    }
+
+    fn percentage_to_bytes(&self, value: f32) -> u64 {
+        let Some(total) = self.byte_total() else {
+            return 0;
+        };
+        // NB: use `f64` to make sure large byte totals don't lose precision.
+        let bytes = (f64::from(value) / 100.0 * total as f64).round();
+        (bytes.max(0.0) as u64).min(total)
+    }
+
+    /// Update the position of progress bar by increment a certain value.
+    ///
+    /// If a value given is `None`, this will increase the position by the whole `len`,
+    /// otherwise it will increase the desired value instead.
+    ///
+    /// The position callback is called with the new position **before** it gets stored,
+    /// and if the callback returns an error, the position will not be changed,
+    /// so the same increment could be safely retried.
+    ///
+    /// Note that the position callback should not access this progress (or its clones),
+    /// because the position is locked while calling it.
+    ///
+    /// Returns [`Cancelled`](crate::utils::Cancelled) without changing anything if the
+    /// progress has been [`cancel`](Progress::cancel)led, so the operation stops at its
+    /// next report.
+    // FIXME: split `inc(None)` to a new function, such as `inc_len`, cuz this is kinda confusing.
+    pub fn inc(&self, value: Option<f32>) -> Result<()> {
+        self.cancel.check()?;
+        let delta = value.unwrap_or(self.len);
+        self.pos.add(delta, self.bounds, |pos| {
+            self.call_ui(|| (self.pos_callback)(pos))
+        })?;
+        self.notify_pos();
+        Ok(())
+    }
+
+    /// Move the position forward to the absolute `target` (within the bounds), unlike
+    /// [`inc`](Progress::inc), this never moves backward: the call is ignored if the
+    /// current position is already at or past `target`.
+    ///
+    /// This is useful when multiple workers report that they have "reached at least"
+    /// a certain position, out-of-order reports won't make the progress jump back.
+    ///
+    /// Callbacks and cancellation behave the same as in [`inc`](Progress::inc), but the
+    /// callbacks are not called if the call is ignored.
+    pub fn inc_to(&self, target: f32) -> Result<()> {
+        self.cancel.check()?;
+        let moved = self.pos.advance_to(target, self.bounds, |pos| {
+            self.call_ui(|| (self.pos_callback)(pos))
+        })?;
+        if moved {
+            self.notify_pos();
+        }
+        Ok(())
+    }
+
+    fn notify_pos(&self) {
+        if !self.observers.is_empty() {
+            let pos = self.pos.load();
+            let event = self.event(ProgressEventKind::Position {
+                pos,
+                determinate: self.is_determinate(),
+            });
+            self.observers.iter().for_each(|o| {
+                o.on_pos(pos);
+                o.on_event(&event);
+            });
+        }
+    }
+
+    /// Report that `bytes` of `total` have been transferred, such as by the downloads,
+    /// the current speed and ETA are sent to the transfer callback and the observers as
+    /// [`TransferStats`], so frontends can display them.
+    ///
+    /// This is independent of the position, which should still be increased as usual.
+    /// To avoid flooding the frontends, the stats are reported at most a few times per
+    /// second, except the first and the final ones.
+    pub fn report_transfer(&self, bytes: u64, total: u64) -> Result<()> {
+        let now = self.timer.lock().unwrap().now();
+        let Some(stats) = self.transfer.lock().unwrap().record(now, bytes, total) else {
+            return Ok(());
+        };
+        if !self.observers.is_empty() {
+            let event = self.event(ProgressEventKind::Transfer(stats));
+            self.observers.iter().for_each(|o| {
+                o.on_transfer(&stats);
+                o.on_event(&event);
+            });
+        }
+        if let Some(cb) = self.transfer_callback {
+            self.call_ui(|| cb(stats))?;
+        }
+        Ok(())
+    }
+
+    /// Get the [`TransferStats`] reported most recently.
+    pub fn transfer_stats(&self) -> Option<TransferStats> {
+        self.transfer.lock().unwrap().last()
+    }
+
+    /// Mark the total amount of work as unknown (until [`set_total_known`] is called),
+    /// such as a download before its length is received.
+    ///
+    /// The position works as usual, but the position events will be flagged as
+    /// indeterminate, so that frontends can render spinners instead of bars.
+    ///
+    /// [`set_total_known`]: Progress::set_total_known
+    pub fn with_unknown_total(self) -> Self {
+        self.determinate.store(false, Ordering::Relaxed);
+        self
+    }
+
+    /// Mark the total amount of work as known, this affects all clones of this progress.
+    pub fn set_total_known(&self) {
+        self.determinate.store(true, Ordering::Relaxed);
+    }
+
+    /// Transition a progress created [`with_unknown_total`](Progress::with_unknown_total)
+    /// to a determinate one, once its `total` number of bytes was discovered mid-flight,
+    /// such as from a trailer of the response.
+    ///
+    /// Unlike creating a new progress, the position and timing are kept as is.
+    /// This affects all clones of this progress.
+    pub fn promote_to_determinate(&self, total: u64) {
+        *self.byte_total.lock().unwrap() = Some(total);
+        self.set_total_known();
+    }
+
+    /// Returns `true` if the total amount of work is known.
+    pub fn is_determinate(&self) -> bool {
+        self.determinate.load(Ordering::Relaxed)
+    }
+
+    /// Wrap an iterator with known length, so that this progress advances by its `len`
+    /// evenly as the items are consumed, reaching the end once the iterator is exhausted.
+    ///
+    /// Errors returned by the callbacks are ignored, as iterators cannot return them.
+    pub fn wrap_iter<I>(&self, iter: I) -> ProgressIter<'a, I::IntoIter>
+    where
+        I: IntoIterator,
+        I::IntoIter: ExactSizeIterator,
+    {
+        ProgressIter::new(iter.into_iter(), self.clone())
+    }
+
+    /// Show a message and increase the position together, such as `extracting file X`
+    /// and the progress of that file, so the new position never shows up with an old message.
+    ///
+    /// The message callback is called first, then the position callback,
+    /// the position will not be changed if either of them fails.
+    pub fn inc_with_msg<S: ToString>(&self, value: Option<f32>, msg: S) -> Result<()> {
+        self.show_msg(msg)?;
+        self.inc(value)
+    }
+
+    /// Display a message using the message callback, this does nothing if there's no
+    /// message callback.
+    pub fn show_msg<S: ToString>(&self, msg: S) -> Result<()> {
+        let msg = msg.to_string();
+        *self.last_msg.lock().unwrap() = Some(msg.clone());
+        self.history.on_msg(&msg);
+        self.send_msg(msg)
+    }
+
+    /// Keep at most `capacity` recent messages for [`message_history`](Progress::message_history),
+    /// default is [`MessageHistory::DEFAULT_CAPACITY`].
+    ///
+    /// This starts a new history, the messages kept before will be discarded.
+    pub fn with_message_history(mut self, capacity: usize) -> Self {
+        self.history = Arc::new(MessageHistory::new(capacity));
+        self
+    }
+
+    /// Get the recent messages shown by [`show_msg`](Progress::show_msg),
+    /// from the oldest to the newest.
+    pub fn message_history(&self) -> Vec<String> {
+        self.history.messages()
+    }
+
+    fn send_msg(&self, msg: String) -> Result<()> {
+        if !self.observers.is_empty() {
+            let event = self.event(ProgressEventKind::Message(msg.clone()));
+            self.observers.iter().for_each(|o| {
+                o.on_msg(&msg);
+                o.on_event(&event);
+            });
+        }
+        if let Some(cb) = self.msg_callback {
+            self.call_ui(|| cb(msg))?;
+        }
+        Ok(())
+    }
+
+    /// Call one of the callbacks, and handle its error according to the error policy.
+    fn call_ui<F: FnOnce() -> Result<()>>(&self, callback: F) -> Result<()> {
+        if self.ui_disabled.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        match callback() {
+            Err(e) if self.error_policy == CallbackErrorPolicy::ContinueWithoutUi => {
+                if !self.ui_disabled.swap(true, Ordering::Relaxed) {
+                    warn!("{}", t!("progress_ui_disabled", reason = e));
+                }
+                Ok(())
+            }
+            res => res,
+        }
+    }
+
+    /// Pause the progress, the time spent while paused does not count towards
+    /// [`elapsed`](Progress::elapsed) time, so the [`rate`](Progress::rate) and
+    /// [`eta`](Progress::eta) won't be affected by pauses.
+    ///
+    /// This will also show a "paused" message, which will be replaced by the last message
+    /// once [`resume`](Progress::resume)d.
+    pub fn pause(&self) -> Result<()> {
+        if self.timer.lock().unwrap().pause() {
+            self.send_msg(ProgressMsg::Paused.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Resume a paused progress, this does nothing if the progress wasn't paused.
+    pub fn resume(&self) -> Result<()> {
+        if self.timer.lock().unwrap().resume() {
+            let last_msg = self.last_msg.lock().unwrap().clone();
+            if let Some(msg) = last_msg {
+                self.send_msg(msg)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the progress is paused.
+    pub fn is_paused(&self) -> bool {
+        self.timer.lock().unwrap().paused_at.is_some()
+    }
+
+    /// The time since this progress was created, excluding paused durations.
+    pub fn elapsed(&self) -> Duration {
+        self.timer.lock().unwrap().elapsed()
+    }
+
+    /// Start timing a new phase, such as [`Phase::Download`] or [`Phase::Extract`],
+    /// the previous phase (if there is one) will be ended automatically.
+    ///
+    /// The identifiers of built-in phases (such as `"download"`) can be used as well,
+    /// observers are notified with [`ProgressEventKind::PhaseStarted`] and
+    /// [`ProgressEventKind::PhaseEnded`].
+    pub fn start_phase<P: Into<Phase>>(&self, phase: P) {
+        let phase = phase.into();
+        let now = self.elapsed();
+        let ended = {
+            let mut phases = self.phases.lock().unwrap();
+            let ended = phases.end_current(now);
+            phases.current = Some((phase.clone(), now));
+            ended
+        };
+        if let Some((phase, duration)) = ended {
+            self.notify_event(ProgressEventKind::PhaseEnded { phase, duration });
+        }
+        self.notify_event(ProgressEventKind::PhaseStarted(phase));
+    }
+
+    /// End the timing of current phase, this does nothing if there's no running phase.
+    pub fn end_phase(&self) {
+        let now = self.elapsed();
+        let ended = self.phases.lock().unwrap().end_current(now);
+        if let Some((phase, duration)) = ended {
+            self.notify_event(ProgressEventKind::PhaseEnded { phase, duration });
+        }
+    }
+
+    fn notify_event(&self, kind: ProgressEventKind) {
+        if !self.observers.is_empty() {
+            let event = self.event(kind);
+            self.observers.iter().for_each(|o| o.on_event(&event));
+        }
+    }
+
+    /// Create an event of this operation, in its running phase.
+    fn event(&self, kind: ProgressEventKind) -> ProgressEvent {
+        let phases = self.phases.lock().unwrap();
+        ProgressEvent {
+            op_id: self.op_id,
+            phase: phases.current.as_ref().map(|(phase, _)| phase.clone()),
+            bytes: self
+                .byte_total()
+                .map(|total| (self.position_bytes(), total)),
+            kind,
+        }
+    }
+
+    /// Get the id of the operation this progress is tracking, see [`OpId`].
+    pub fn op_id(&self) -> OpId {
+        self.op_id
+    }
+
+    /// Create a clone that shares the position and callbacks of this progress,
+    /// but tracks a different operation, with its own id and phases,
+    /// such as one of the components being downloaded concurrently.
+    ///
+    /// To report a part of this progress in its own `0..=100` range, use
+    /// [`ProgressReporter::child`] instead.
+    pub fn new_op(&self) -> Self {
+        let mut child = self.clone();
+        child.op_id = OpId::next();
+        child.phases = Arc::new(Mutex::new(PhaseTimings::default()));
+        child.transfer = Arc::new(Mutex::new(new_transfer_meter()));
+        child
+    }
+
+    /// Get each finished phase and its duration, in the order of they were started.
+    ///
+    /// Like [`elapsed`](Progress::elapsed), paused time are not counted.
+    pub fn phase_timings(&self) -> Vec<(Phase, Duration)> {
+        self.phases.lock().unwrap().finished.clone()
+    }
+
+    /// Show how long each finished phase took, one phase per line.
+    pub fn show_phase_summary(&self) -> Result<()> {
+        let summary = self
+            .phase_timings()
+            .iter()
+            .map(|(phase, dur)| {
+                t!(
+                    "progress_phase_timing",
+                    phase = phase,
+                    secs = format!("{:.2}", dur.as_secs_f64())
+                )
+                .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if summary.is_empty() {
+            return Ok(());
+        }
+        self.show_msg(summary)
+    }
+
+    /// The average progressing rate, in percentage per second.
+    pub fn rate(&self) -> f32 {
+        let secs = self.elapsed().as_secs_f32();
+        if secs > 0.0 {
+            self.pos.load() / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Estimated time until the progress reaches `100`, base on the average [`rate`](Progress::rate).
+    ///
+    /// Returns `None` if there's no progress yet.
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.rate();
+        (rate > 0.0).then(|| Duration::from_secs_f32((100.0 - self.pos.load()).max(0.0) / rate))
+    }
+}
+
+fn new_transfer_meter() -> rate::TransferMeter {
+    rate::TransferMeter::new(TRANSFER_RATE_WINDOW, TRANSFER_REPORT_INTERVAL)
+}
+
+/// Convinent struct with methods that are useful to indicate various progress.
+#[derive(Debug, Clone, Copy)]
+pub struct CliProgress<T: Sized> {
+    /// A start/initializing function which will be called to setup progress bar.
+    pub start: fn(String, Style, CliProgressOpt) -> Result<T>,
+    /// A update function that will be called upon each step completion.
+    pub update: fn(&T, Option<u64>),
+    /// Similar to `update`, but increase the position by a delta instead,
+    /// which is handy when processing data by chunks.
+    pub inc: fn(&T, u64),
+    /// A function that will be called once to terminate progress.
+    pub stop: fn(&T, String),
+    /// Extra options that will be passed to the `start` function.
+    pub opt: CliProgressOpt,
+}
+
+impl<T> CliProgress<T> {
+    /// Start a progress bar, then run `f` with it, the bar will be stopped once `f` returns,
+    /// even if it returns early with an error or panics.
+    ///
+    /// `stop_msg` is displayed if `f` succeeded, otherwise the bar is stopped without message.
+    pub fn scoped<R, F>(&self, style: Style, msg: String, stop_msg: String, f: F) -> Result<R>
+    where
+        F: FnOnce(&T) -> Result<R>,
+    {
+        struct StopGuard<'p, T> {
+            stop: fn(&T, String),
+            bar: &'p T,
+            msg: String,
+        }
+        impl<T> Drop for StopGuard<'_, T> {
+            fn drop(&mut self) {
+                (self.stop)(self.bar, std::mem::take(&mut self.msg));
+            }
+        }
+
+        let bar = (self.start)(msg, style, self.opt)?;
+        let mut guard = StopGuard {
+            stop: self.stop,
+            bar: &bar,
+            msg: String::new(),
+        };
+        let res = f(&bar);
+        if res.is_ok() {
+            guard.msg = stop_msg;
+        }
+        res
+    }
+}
+
+/// Options that affect how a CLI progress bar looks.
+#[derive(Debug, Clone, Copy)]
+pub struct CliProgressOpt {
+    /// The animation of the `{spinner}` field.
+    pub spinner: SpinnerStyle,
+    /// The time window of the `{rate}` field, which displays the average speed
+    /// during this amount of time.
+    pub rate_window: Duration,
+    /// How often the spinner ticks by itself.
+    pub tick_interval: TickInterval,
+    /// Don't draw the bar until it has been running for this amount of time, so that
+    /// operations finished within it won't flash a bar on and off.
+    pub draw_delay: Duration,
+    /// The number of decimal places of the `{percent}`, `{rate}` and `{eta}` fields,
+    /// `None` to use the defaults, which are `0`, `2` and `1` respectively.
+    pub decimals: Option<usize>,
+}
+
+impl Default for CliProgressOpt {
+    fn default() -> Self {
+        Self {
+            spinner: SpinnerStyle::default(),
+            rate_window: Duration::from_secs(3),
+            tick_interval: TickInterval::default(),
+            draw_delay: Duration::ZERO,
+            decimals: None,
+        }
+    }
+}
+
+/// The interval of the steady ticking of a spinner.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TickInterval {
+    /// Use the `auto_tick_duration` of [`Style::Spinner`].
+    #[default]
+    Default,
+    /// Tick at the given interval, regardless of the style.
+    Every(Duration),
+    /// Never tick by itself, the spinner only moves when updated,
+    /// which saves some cycles on low-power devices.
+    Disabled,
+}
+
+impl TickInterval {
+    /// Get the actual interval for the spinner, which has an `auto_tick_duration`.
+    fn resolve(self, auto_tick_duration: Option<Duration>) -> Option<Duration> {
+        match self {
+            Self::Default => auto_tick_duration,
+            Self::Every(dur) => Some(dur),
+            Self::Disabled => None,
+        }
+    }
+}
+
+/// Preset tick strings for the spinner animation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SpinnerStyle {
+    /// Braille patterns, this is the default spinner of `indicatif`.
+    #[default]
+    Braille,
+    /// Three dots with a bouncing highlight.
+    Dots,
+    /// A rotating line drawn by box-drawing characters.
+    Line,
+    /// Plain ASCII characters, which can be rendered on every terminal.
+    Ascii,
+}
+
+impl SpinnerStyle {
+    /// Get the tick strings of this style, the last one is used when the progress is finished.
+    pub fn tick_strings(self) -> &'static [&'static str] {
+        match self {
+            Self::Braille => &["⠁", "⠂", "⠄", "⡀", "⢀", "⠠", "⠐", "⠈", " "],
+            Self::Dots => &["●∙∙", "∙●∙", "∙∙●", "∙∙∙"],
+            Self::Line => &["─", "╲", "│", "╱", " "],
+            Self::Ascii => &["-", "\\", "|", "/", " "],
+        }
+    }
+
+    /// Fallback to [`SpinnerStyle::Ascii`] if the terminal is known to be unable
+    /// to render unicode characters, such as when `TERM=dumb`.
+    fn ascii_fallback(self) -> Self {
+        if is_dumb_term() {
+            Self::Ascii
+        } else {
+            self
+        }
+    }
+}
+
+/// Whether the terminal is known to be unable to render unicode characters (`TERM=dumb`).
+fn is_dumb_term() -> bool {
+    matches!(std::env::var("TERM").as_deref(), Ok("dumb"))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Style {
+    /// Display the progress base on number of bytes.
+    Bytes(u64),
+    /// Display the progress base on position & length parameters.
+    Len(u64),
+    /// A spinner that spins as the progress goes, this does not require
+    /// length information.
+    Spinner {
+        /// Set continuous spinning for a given amount of time.
+        auto_tick_duration: Option<Duration>,
+    },
+}
+
+/// Terminals narrower than this cannot fit a progress bar, so only the message is displayed.
+const MIN_BAR_WIDTH: u16 = 20;
+
+impl Style {
+    /// Get the template to use on a terminal with `width` columns (`None` if unknown),
+    /// which falls back to displaying the message only if the terminal is too narrow.
+    fn pattern_for_width(&self, width: Option<u16>) -> &str {
+        match width {
+            Some(w) if w >= MIN_BAR_WIDTH => self.pattern(),
+            _ => "{msg}",
+        }
+    }
+
+    /// The pattern of a [`Style::Bytes`] bar that was resumed, which shows the bytes
+    /// transferred in this session as well.
+    fn resumed_pattern() -> String {
+        format!(
+            "{{prefix}}{{msg}}\n{{spinner:.green}} [{{elapsed_precise}}] [{{wide_bar:.cyan/blue}}] \
+            {{bytes}}/{{total_bytes}} ({{session_bytes}} {}, {{rate}}, {{eta}})",
+            t!("progress_this_session")
+        )
+    }
+
+    fn pattern(&self) -> &str {
+        match self {
+            Style::Bytes(_) => "{prefix}{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({rate}, {eta})",
+            Style::Len(_) => "{prefix}{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} ({percent}%, {eta})",
+            Style::Spinner{..} => "{spinner:.green} [{elapsed_precise}] {prefix}{msg}"
+        }
+    }
+}
+
+/// The CLI progress bar that was started most recently and not yet stopped.
+static ACTIVE_BAR: Mutex<Option<CliProgressBar>> = Mutex::new(None);
+
+/// Print a line on `stdout`, without messing up the active CLI progress bar.
+///
+/// A bare `println!` would be printed over the bar, so the bar is cleared first,
+/// then redrawn below the printed line.
+pub fn println_above_bar<S: std::fmt::Display>(msg: S) {
+    let active = ACTIVE_BAR.lock().unwrap().clone();
+    print_suspending(active.as_ref(), || println!("{msg}"));
+}
+
+/// Suspend the active CLI progress bar while running `f`, such as asking the user a question
+/// mid-install, so that the prompt and the input won't be overwritten by the repaints
+/// of the bar, which is redrawn once `f` returns.
+///
+/// Note that the bar cannot be updated (by other threads) until `f` returns.
+pub fn suspend_for_prompt<R, F: FnOnce() -> R>(f: F) -> R {
+    let active = ACTIVE_BAR.lock().unwrap().clone();
+    print_suspending(active.as_ref(), f)
+}
+
+thread_local! {
+    /// Whether a bar is suspended by current thread, the state of a suspended bar is locked
+    /// until it's resumed, so it must not be suspended again, such as logging a warning
+    /// about an invalid answer while prompting.
+    static SUSPENDED: Cell<bool> = const { Cell::new(false) };
+}
+
+fn print_suspending<R, F: FnOnce() -> R>(bar: Option<&CliProgressBar>, print: F) -> R {
+    match bar {
+        // NB: checking whether it's finished locks the state as well
+        Some(pb) if !SUSPENDED.with(Cell::get) && !pb.is_finished() => pb.suspend(|| {
+            // reset the flag even if `print` panics
+            struct Resume;
+            impl Drop for Resume {
+                fn drop(&mut self) {
+                    SUSPENDED.with(|s| s.set(false));
+                }
+            }
+            SUSPENDED.with(|s| s.set(true));
+            let _resume = Resume;
+            print()
+        }),
+        _ => print(),
+    }
+}
+
+/// Finish the bar with a message, then move the cursor to a new line.
+///
+/// Indicatif leaves the cursor at the end of the bar line, hoping the terminal wraps it,
+/// which is not always the case, so the following output might be appended to the bar.
+fn finish_on_new_line(pb: &CliProgressBar, msg: String, term: &dyn TermLike) {
+    pb.finish_with_message(msg);
+    if !pb.is_hidden() {
+        // failing to write a newline is not a big deal
+        let _ = term.write_line("").and_then(|_| term.flush());
+    }
+}
+
+/// Set once a progress bar failed to be drawn, after which every CLI progress bar
+/// will be hidden, and only the messages are printed.
+static DRAW_FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the interactive bars should not be drawn at all, which is the case in quiet mode,
+/// summary mode, JSON mode, or if drawing has failed before.
+fn draw_hidden() -> bool {
+    GlobalOpts::get().quiet
+        || summary::is_enabled()
+        || json::is_enabled()
+        || DRAW_FAILED.load(Ordering::Relaxed)
+}
+
+/// Write a custom field of a progress bar, which should never panic even if the writing fails.
+fn write_or_degrade(w: &mut dyn std::fmt::Write, args: std::fmt::Arguments<'_>) {
+    if w.write_fmt(args).is_err() {
+        // NB: the bar cannot be changed here, because its state is locked while drawing.
+        DRAW_FAILED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Hide the bar if any progress bar failed to be drawn, returns `true` if it's hidden.
+fn degrade_if_draw_failed(pb: &CliProgressBar) -> bool {
+    if !DRAW_FAILED.load(Ordering::Relaxed) {
+        return false;
+    }
+    if !pb.is_hidden() {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    true
+}
+
+/// Build a bar with `build`, or fallback to a hidden bar that only prints the messages if
+/// that failed, such as because of an invalid template, so that failing to display the
+/// progress never aborts the operation itself.
+///
+/// The fallback also switches every other bar to message-only, as if drawing has failed.
+fn start_or_fallback<F>(msg: String, style: Style, build: F) -> CliProgressBar
+where
+    F: FnOnce(String) -> Result<CliProgressBar>,
+{
+    match build(msg.clone()) {
+        Ok(pb) => pb,
+        Err(e) => {
+            warn!("{}", t!("progress_fallback_to_messages", reason = e));
+            DRAW_FAILED.store(true, Ordering::Relaxed);
+            let len = match style {
+                Style::Bytes(total) | Style::Len(total) => Some(total),
+                Style::Spinner { .. } => None,
+            };
+            let pb = CliProgressBar::with_draw_target(len, ProgressDrawTarget::hidden());
+            println!("{msg}");
+            pb.set_message(msg);
+            pb
+        }
+    }
+}
+
+/// Hide the bar for the first `delay`, then draw it on `target` if it's still running.
+fn delay_drawing(pb: &CliProgressBar, delay: Duration, target: ProgressDrawTarget) {
+    if delay.is_zero() {
+        pb.set_draw_target(target);
+        return;
+    }
+    pb.set_draw_target(ProgressDrawTarget::hidden());
+    let weak = pb.downgrade();
+    std::thread::spawn(move || {
+        std::thread::sleep(delay);
+        if let Some(pb) = weak.upgrade() {
+            // NB: the bar will be hidden again by the next update if drawing has failed
+            if !pb.is_finished() {
+                pb.set_draw_target(target);
+            }
+        }
+    });
+}
+
+/// Format a number of bytes with binary units, such as `1.50 MiB`, using the given number
+/// of `decimals`, or the same format of [`HumanBytes`] if it's `None`.
+fn format_bytes(bytes: f64, decimals: Option<usize>) -> String {
+    let Some(decimals) = decimals else {
+        return HumanBytes(bytes as u64).to_string();
+    };
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    if bytes < 1024.0 {
+        return format!("{bytes:.0} B");
+    }
+    let mut value = bytes / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.decimals$} {}", UNITS[unit])
+}
+
+/// Apply the template `pattern` to a CLI progress bar, with the custom keys.
+fn apply_style(pb: &CliProgressBar, pattern: &str, opt: CliProgressOpt) -> Result<()> {
+    pb.set_style(custom_style(pattern, opt, None)?);
+    Ok(())
+}
+
+/// Build a style with the custom keys, the `{eta}` of a resumed bar is based on its
+/// `session`, which also provides the `{session_bytes}` key.
+fn custom_style(
+    pattern: &str,
+    opt: CliProgressOpt,
+    session: Option<TransferSession>,
+) -> Result<ProgressStyle> {
+    let rate = Arc::new(Mutex::new(RateWindow::new(opt.rate_window)));
+    let rate_limit = RateLimiter::global().map(|limiter| {
+        let limit = format_bytes(limiter.bytes_per_sec() as f64, opt.decimals);
+        t!("progress_rate_limit", limit = limit).to_string()
+    });
+    let style = ProgressStyle::with_template(pattern)?
+        .tick_strings(opt.spinner.ascii_fallback().tick_strings())
+        .with_key(
+            "eta",
+            move |state: &ProgressState, w: &mut dyn std::fmt::Write| {
+                let decimals = opt.decimals.unwrap_or(1);
+                let eta = match (session, state.len()) {
+                    (Some(session), Some(len)) => session
+                        .eta(state.pos(), len, Instant::now())
+                        .unwrap_or_default(),
+                    _ => state.eta(),
+                };
+                let eta = eta.as_secs_f64();
+                write_or_degrade(w, format_args!("{eta:.decimals$}s"));
+            },
+        )
+        .with_key(
+            "rate",
+            move |state: &ProgressState, w: &mut dyn std::fmt::Write| {
+                let mut rate = rate.lock().unwrap();
+                rate.record(Instant::now(), state.pos());
+                let rate = format_bytes(rate.rate(), opt.decimals);
+                match &rate_limit {
+                    Some(limit) => write_or_degrade(w, format_args!("{rate}/s, {limit}")),
+                    None => write_or_degrade(w, format_args!("{rate}/s")),
+                }
+            },
+        )
+        .with_key(
+            "percent",
+            move |state: &ProgressState, w: &mut dyn std::fmt::Write| {
+                let decimals = opt.decimals.unwrap_or(0);
+                let percent = state.fraction() * 100.0;
+                write_or_degrade(w, format_args!("{percent:.decimals$}"));
+            },
+        )
+        .progress_chars("#>-");
+    let Some(session) = session else {
+        return Ok(style);
+    };
+    Ok(style.with_key(
+        "session_bytes",
+        move |state: &ProgressState, w: &mut dyn std::fmt::Write| {
+            let bytes = session.session_bytes(state.pos()) as f64;
+            write_or_degrade(w, format_args!("{}", format_bytes(bytes, opt.decimals)));
+        },
+    ))
+}
+
+/// Create a CLI progress bar with custom styles.
+fn styled_bar(msg: String, style: Style, opt: CliProgressOpt) -> Result<CliProgressBar> {
+    let pb = match style {
+        Style::Bytes(total) | Style::Len(total) => CliProgressBar::new(total),
+        Style::Spinner { auto_tick_duration } => {
+            let spinner = CliProgressBar::new_spinner();
+            if let Some(dur) = opt.tick_interval.resolve(auto_tick_duration) {
+                spinner.enable_steady_tick(dur);
+            }
+            spinner
+        }
+    };
+    let width = console::Term::stderr().size_checked().map(|(_, cols)| cols);
+    apply_style(&pb, style.pattern_for_width(width), opt)?;
+    pb.set_message(msg);
+    Ok(pb)
+}
+
+/// A bar started by [`CliProgress::new`], which derefs to the underlying `indicatif` bar.
+#[derive(Debug, Clone)]
+pub struct CliBar {
+    bar: CliProgressBar,
+    /// Whether the bar counts bytes, which is shared by all clones of the bar.
+    counts_bytes: Arc<AtomicBool>,
+}
+
+impl CliBar {
+    fn new(bar: CliProgressBar, style: Style) -> Self {
+        let counts_bytes = matches!(style, Style::Bytes(_));
+        Self {
+            bar,
+            counts_bytes: Arc::new(AtomicBool::new(counts_bytes)),
+        }
+    }
+
+    /// Check if the bar was started as, or promoted to, a [`Style::Bytes`] bar.
+    pub fn counts_bytes(&self) -> bool {
+        self.counts_bytes.load(Ordering::Relaxed)
+    }
+
+    fn mark_bytes(&self) {
+        self.counts_bytes.store(true, Ordering::Relaxed);
+    }
+}
+
+impl From<CliProgressBar> for CliBar {
+    fn from(bar: CliProgressBar) -> Self {
+        Self {
+            bar,
+            counts_bytes: Arc::default(),
+        }
+    }
+}
+
+impl Deref for CliBar {
+    type Target = CliProgressBar;
+
+    fn deref(&self) -> &Self::Target {
+        &self.bar
+    }
+}
+
+// TODO: Mark this with cfg(feature = "cli")
+impl CliProgress<CliBar> {
+    /// Create a new progress bar for CLI to indicate download progress.
+    ///
+    /// `progress_for`: used for displaying what the progress is for.
+    /// i.e.: ("downloading", "download"), ("extracting", "extraction"), etc.
+    pub fn new() -> Self {
+        fn start(msg: String, style: Style, opt: CliProgressOpt) -> Result<CliBar> {
+            if json::is_enabled() {
+                return Ok(CliBar::new(CliProgressBar::hidden(), style));
+            }
+            if summary::is_enabled() {
+                return Ok(summary::hidden_bar(msg, style));
+            }
+            let pb = start_or_fallback(msg, style, |msg| styled_bar(msg, style, opt));
+            if !opt.draw_delay.is_zero() && !pb.is_hidden() {
+                delay_drawing(&pb, opt.draw_delay, ProgressDrawTarget::stderr());
+            }
+            *ACTIVE_BAR.lock().unwrap() = Some(pb.clone());
+            taskbar::report(&pb);
+            Ok(CliBar::new(pb, style))
+        }
+        fn update(pb: &CliBar, pos: Option<u64>) {
+            degrade_if_draw_failed(pb);
+            if let Some(p) = pos {
+                pb.set_position(p);
+            } else {
+                pb.tick();
+            }
+            taskbar::report(pb);
+        }
+        fn inc(pb: &CliBar, delta: u64) {
+            degrade_if_draw_failed(pb);
+            pb.inc(delta);
+            taskbar::report(pb);
+        }
+        fn stop(pb: &CliBar, msg: String) {
+            taskbar::clear();
+            if json::is_enabled() {
+                pb.finish();
+                return;
+            }
+            if summary::is_enabled() && pb.is_hidden() {
+                println_above_bar(summary::finish(pb, &msg));
+                return;
+            }
+            if degrade_if_draw_failed(pb) {
+                pb.finish();
+                println!("{msg}");
+            } else {
+                finish_on_new_line(pb, msg, &console::Term::stderr());
+            }
+            let mut active = ACTIVE_BAR.lock().unwrap();
+            if active.as_ref().is_some_and(|a| a.is_finished()) {
+                *active = None;
+            }
+        }
+
+        if GlobalOpts::get().quiet {
+            Self::hidden()
+        } else {
+            CliProgress {
+                start,
+                update,
+                inc,
+                stop,
+                opt: CliProgressOpt::default(),
+            }
+        }
+    }
+
+    /// Create a hidden progress bar that does not render anything.
+    pub fn hidden() -> Self {
+        CliProgress {
+            start: |_: String, style: Style, _: CliProgressOpt| {
+                Ok(CliBar::new(CliProgressBar::hidden(), style))
+            },
+            update: |_: &CliBar, _: Option<u64>| {},
+            inc: |_: &CliBar, _: u64| {},
+            stop: |_: &CliBar, _: String| {},
+            opt: CliProgressOpt::default(),
+        }
+    }
+
+    /// Use a different spinner animation, check [`SpinnerStyle`] for available presets.
+    pub fn with_spinner(mut self, spinner: SpinnerStyle) -> Self {
+        self.opt.spinner = spinner;
+        self
+    }
+
+    /// Set the time window of the download speed, default is 3 seconds.
+    ///
+    /// A longer window gives a steadier speed readout, but reacts slower to the changes.
+    pub fn with_rate_window(mut self, window: Duration) -> Self {
+        self.opt.rate_window = window;
+        self
+    }
+
+    /// Get a handle of the underlying `indicatif` bar that was started most recently and
+    /// not yet stopped, which allows further customization, such as adding a prefix.
+    ///
+    /// Note that this bypasses the abstractions of this module, changing things like
+    /// the style or draw target of the bar might break the features provided here.
+    pub fn active_bar() -> Option<CliProgressBar> {
+        ACTIVE_BAR
+            .lock()
+            .unwrap()
+            .clone()
+            .filter(|pb| !pb.is_finished())
+    }
+
+    /// Print exactly one line for each progress once it's stopped, such as
+    /// `✓ downloaded rust-std (120.00 MiB, 8s)`, instead of displaying animated bars.
+    ///
+    /// This is meant for scripts, it affects every bar created by [`CliProgress::new`]
+    /// afterwards, and has no effect in quiet mode.
+    pub fn set_summary_mode(enabled: bool) {
+        summary::set_enabled(enabled);
+    }
+
+    /// Replace the bars with JSON events printed on `stdout`, disabled by default.
+    ///
+    /// This affects every bar created by [`CliProgress::new`] afterwards, which are hidden,
+    /// the progress should be reported by a [`Progress`] observed by a [`JsonEventWriter`]
+    /// instead, which documents the schema of the events.
+    pub fn set_json_mode(enabled: bool) {
+        json::set_enabled(enabled);
+    }
+
+    /// Check if the JSON mode was enabled by [`set_json_mode`](CliProgress::set_json_mode).
+    pub fn is_json_mode() -> bool {
+        json::is_enabled()
+    }
+
+    /// Mirror the percentage of the bars to the terminal tab (via `OSC 9;4` sequences)
+    /// or the taskbar button of the console window on Windows, disabled by default.
+    ///
+    /// This affects every bar created by [`CliProgress::new`], terminals that don't
+    /// support it should simply ignore the sequences, which are never written if `stderr`
+    /// is not a terminal.
+    pub fn set_taskbar_progress(enabled: bool) {
+        taskbar::set_enabled(enabled);
+    }
+
+    /// Only draw the bars that have been running for at least `delay`, so that very fast
+    /// operations skip the bar entirely (like other tools do for sub-100ms work),
+    /// default is zero, means the bars are drawn immediately.
+    pub fn with_draw_delay(mut self, delay: Duration) -> Self {
+        self.opt.draw_delay = delay;
+        self
+    }
+
+    /// Set the number of decimal places of the percentage, speed and ETA displays,
+    /// such as `2` for `33.33%`, default to `0`, `2` and `1` places respectively.
+    pub fn with_decimals(mut self, decimals: usize) -> Self {
+        self.opt.decimals = Some(decimals);
+        self
+    }
+
+    /// Change how often the spinner ticks, or disable the steady ticking entirely.
+    pub fn with_tick_interval(mut self, interval: TickInterval) -> Self {
+        self.opt.tick_interval = interval;
+        self
+    }
+
+    /// Create the bars of several concurrent tasks with an overall bar of `total` bytes
+    /// below them, which use the same options as this.
+    pub fn multi(&self, msg: String, total: u64) -> Result<MultiCliProgress> {
+        MultiCliProgress::new(msg, total, self.opt)
+    }
+
+    /// Pin a label, such as the component name `rust-std:`, at the start of the message
+    /// of `pb`, which stays the same while the message changes, default is empty.
+    ///
+    /// Non-empty prefixes are separated from the message by a space.
+    pub fn set_prefix<S: AsRef<str>>(&self, pb: &CliProgressBar, prefix: S) {
+        let prefix = prefix.as_ref();
+        let prefix = if prefix.is_empty() || prefix.ends_with(char::is_whitespace) {
+            prefix.to_string()
+        } else {
+            format!("{prefix} ")
+        };
+        pb.set_prefix(prefix);
+    }
+
+    /// Mark a [`Style::Bytes`] bar as resumed from `pos`, such as a download continued from
+    /// a partially downloaded file, then move it to `pos`.
+    ///
+    /// The fields of the bar reflect different numbers after this:
+    /// - `{bytes}/{total_bytes}` and the bar itself: the overall position, including the
+    ///   `pos` bytes transferred previously.
+    /// - `{session_bytes}`: the bytes transferred since resumed.
+    /// - `{rate}`: the speed since resumed.
+    /// - `{eta}`: the remaining bytes divided by the average speed since resumed.
+    pub fn resume_from(&self, pb: &CliProgressBar, pos: u64) -> Result<()> {
+        pb.set_position(pos);
+        pb.reset_eta();
+        if pb.is_hidden() {
+            return Ok(());
+        }
+        let session = TransferSession::new(pos, Instant::now());
+        let width = console::Term::stderr().size_checked().map(|(_, cols)| cols);
+        let pattern = match width {
+            Some(w) if w >= MIN_BAR_WIDTH => Style::resumed_pattern(),
+            _ => "{msg}".into(),
+        };
+        pb.set_style(custom_style(&pattern, self.opt, Some(session))?);
+        Ok(())
+    }
+
+    /// Turn a bar started with [`Style::Spinner`] into a [`Style::Bytes`] bar of `total`
+    /// bytes mid-flight, such as when the size of a download was discovered later.
+    ///
+    /// The bar is not restarted, so the bytes counted so far and the elapsed time are kept.
+    pub fn promote_to_determinate(&self, pb: &CliBar, total: u64) -> Result<()> {
+        pb.disable_steady_tick();
+        pb.set_length(total);
+        pb.mark_bytes();
+        if pb.is_hidden() {
+            return Ok(());
+        }
+        let width = console::Term::stderr().size_checked().map(|(_, cols)| cols);
+        apply_style(pb, Style::Bytes(total).pattern_for_width(width), self.opt)?;
+        taskbar::report(pb);
+        Ok(())
+    }
+}
+
+impl Default for CliProgress<CliBar> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Bounds, CallbackErrorPolicy, CliProgress, CliProgressBar, Progress, ProgressMsg,
+        ProgressPos, SpinnerStyle, Style, TermLike, TickInterval, TransferStats,
+    };
+    use std::{
+        sync::{Mutex, MutexGuard, PoisonError},
+        time::Duration,
+    };
+
+    /// Whether drawing has failed is global, so the tests that make it fail are serialized
+    /// by this lock.
+    static DRAW_STATE: Mutex<()> = Mutex::new(());
+
+    /// Allow the drawing to fail until the returned guard is dropped, which resets the
+    /// failure, so the bars of other tests are not hidden.
+    fn draw_failure() -> DrawFailure {
+        DrawFailure {
+            _lock: DRAW_STATE.lock().unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+
+    struct DrawFailure {
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl Drop for DrawFailure {
+        fn drop(&mut self) {
+            super::DRAW_FAILED.store(false, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn progress_pos_add() {
+        let orig = ProgressPos::new(0.0);
+        let noop = |_| Ok(());
+
+        let bounds = Bounds::default();
+
+        orig.add(1.0, bounds, noop).unwrap();
+        assert_eq!(orig.load(), 1.0);
+        orig.add(2.0, bounds, noop).unwrap();
+        assert_eq!(orig.load(), 3.0);
+        orig.add(10.0, bounds, noop).unwrap();
+        assert_eq!(orig.load(), 13.0);
+    }
+
+    #[test]
+    fn progress_bytes_conversion() {
+        let cb = |_: f32| Ok(());
+        let progress = Progress::new(&cb).with_len(25.0).with_byte_total(1000);
+        assert_eq!(progress.position_bytes(), 0);
+        assert_eq!(progress.len_bytes(), 250);
+
+        progress.inc(Some(12.52)).unwrap();
+        assert_eq!(progress.position_bytes(), 125);
+        // 12.58% of 1000 bytes is 125.8 bytes, which rounds to 126
+        progress.inc(Some(0.06)).unwrap();
+        assert_eq!(progress.position_bytes(), 126);
+
+        progress.inc(None).unwrap();
+        assert_eq!(progress.position_bytes(), 376);
+        progress.inc(Some(100.0)).unwrap();
+        assert_eq!(progress.position_bytes(), 1000);
+
+        let no_total = Progress::new(&cb).with_len(50.0);
+        assert_eq!(no_total.len_bytes(), 0);
+    }
+
+    #[test]
+    fn transfer_callback_reports_stats() {
+        let pos_cb = |_: f32| Ok(());
+        let reported = Mutex::new(vec![]);
+        let transfer_cb = |stats: TransferStats| {
+            reported.lock().unwrap().push(stats);
+            Ok(())
+        };
+        let progress = Progress::new(&pos_cb).with_transfer_callback(&transfer_cb);
+        assert!(progress.transfer_stats().is_none());
+
+        progress.report_transfer(0, 1000).unwrap();
+        // too soon to report again
+        progress.report_transfer(400, 1000).unwrap();
+        progress.report_transfer(1000, 1000).unwrap();
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 2);
+        assert_eq!((reported[0].bytes, reported[0].total_bytes), (0, 1000));
+        assert_eq!(reported[1].bytes, 1000);
+        assert_eq!(progress.transfer_stats(), Some(reported[1]));
+    }
+
+    #[test]
+    fn ascii_spinner_tick_strings() {
+        let ticks = SpinnerStyle::Ascii.tick_strings();
+        assert!(ticks.len() >= 2);
+        assert!(ticks.iter().all(|t| t.is_ascii()));
+        assert!(!SpinnerStyle::Braille
+            .tick_strings()
+            .iter()
+            .all(|t| t.is_ascii()));
+    }
+
+    #[test]
+    fn pause_does_not_inflate_elapsed_time() {
+        let pos_cb = |_: f32| Ok(());
+        let msgs = Mutex::new(vec![]);
+        let msg_cb = |m: String| {
+            msgs.lock().unwrap().push(m);
+            Ok(())
+        };
+        let progress = Progress::new(&pos_cb).with_msg_callback(&msg_cb);
+        progress.show_msg("downloading").unwrap();
+        progress.inc(Some(50.0)).unwrap();
+
+        progress.pause().unwrap();
+        assert!(progress.is_paused());
+        let elapsed_at_pause = progress.elapsed();
+        let rate_at_pause = progress.rate();
+        std::thread::sleep(Duration::from_millis(200));
+        // nothing changes while paused
+        assert_eq!(progress.elapsed(), elapsed_at_pause);
+        assert_eq!(progress.rate(), rate_at_pause);
+
+        progress.resume().unwrap();
+        assert!(!progress.is_paused());
+        assert!(progress.elapsed() < elapsed_at_pause + Duration::from_millis(100));
+        assert!(progress.rate() > 50.0 / 0.1);
+
+        let msgs = msgs.lock().unwrap();
+        assert_eq!(msgs.len(), 3);
+        assert_eq!(msgs[0], "downloading");
+        assert_eq!(msgs[2], "downloading");
+    }
+
+    #[test]
+    fn failed_pos_callback_keeps_position() {
+        let fail = std::sync::atomic::AtomicBool::new(false);
+        let reported = Mutex::new(0.0);
+        let pos_cb = |pos: f32| {
+            if fail.load(std::sync::atomic::Ordering::Relaxed) {
+                anyhow::bail!("channel closed");
+            }
+            *reported.lock().unwrap() = pos;
+            Ok(())
+        };
+        let progress = Progress::new(&pos_cb).with_len(20.0);
+
+        progress.inc(None).unwrap();
+        assert_eq!(progress.pos.load(), 20.0);
+
+        fail.store(true, std::sync::atomic::Ordering::Relaxed);
+        assert!(progress.inc(None).is_err());
+        // the position is still consistent with what the callback received last time
+        assert_eq!(progress.pos.load(), 20.0);
+        assert_eq!(*reported.lock().unwrap(), 20.0);
+
+        // retry after the callback recovered
+        fail.store(false, std::sync::atomic::Ordering::Relaxed);
+        progress.inc(None).unwrap();
+        assert_eq!(progress.pos.load(), 40.0);
+        assert_eq!(*reported.lock().unwrap(), 40.0);
+    }
+
+    #[test]
+    fn inc_to_never_moves_backward() {
+        let calls = Mutex::new(vec![]);
+        let pos_cb = |pos: f32| {
+            calls.lock().unwrap().push(pos);
+            Ok(())
+        };
+        let progress = Progress::new(&pos_cb).with_bounds(10.0, 80.0);
+        progress.inc_to(30.0).unwrap();
+        assert_eq!(progress.position(), 30.0);
+        // a late report from a slower worker is a no-op
+        progress.inc_to(20.0).unwrap();
+        progress.inc_to(30.0).unwrap();
+        assert_eq!(progress.position(), 30.0);
+        progress.inc_to(f32::NAN).unwrap();
+        assert_eq!(progress.position(), 30.0);
+        // still limited by the bounds
+        progress.inc_to(95.0).unwrap();
+        assert_eq!(progress.position(), 80.0);
+        assert_eq!(*calls.lock().unwrap(), [30.0, 80.0]);
+    }
+
+    #[test]
+    fn increments_respect_bounds() {
+        let pos_cb = |_: f32| Ok(());
+        let overall = Progress::new(&pos_cb);
+        let phase = overall.clone().with_len(5.0).with_bounds(40.0, 60.0);
+
+        // jumping into the phase from an earlier position
+        phase.inc(None).unwrap();
+        assert_eq!(phase.pos.load(), 40.0);
+        phase.inc(Some(15.0)).unwrap();
+        assert_eq!(phase.pos.load(), 55.0);
+        // off-by-one delta should not leak into the next phase
+        phase.inc(Some(6.0)).unwrap();
+        assert_eq!(phase.pos.load(), 60.0);
+        // nor going back before the phase start
+        phase.inc(Some(-30.0)).unwrap();
+        assert_eq!(phase.pos.load(), 40.0);
+
+        // the overall progress is not limited
+        overall.inc(Some(50.0)).unwrap();
+        assert_eq!(overall.pos.load(), 90.0);
+
+        let swapped = overall.with_bounds(120.0, 10.0);
+        assert_eq!(swapped.bounds, Bounds(10.0, 100.0));
+    }
+
+    #[test]
+    fn noop_progress() {
+        let progress = Progress::noop().with_len(30.0);
+        progress.show_msg("nothing to see here").unwrap();
+        progress.inc(None).unwrap();
+        progress.inc(Some(80.0)).unwrap();
+        assert_eq!(progress.pos.load(), 100.0);
+    }
+
+    #[test]
+    fn localized_progress_msg() {
+        let msg = ProgressMsg::Downloading("rustup-init");
+        assert_eq!(msg.localized("en"), "downloading 'rustup-init'");
+        assert_eq!(msg.localized("zh-CN"), "正在下载 'rustup-init'");
+        // unknown locale fallback to English
+        assert_eq!(msg.localized("xx-unknown"), "downloading 'rustup-init'");
+        assert_eq!(
+            ProgressMsg::Extracted.localized("xx-unknown"),
+            ProgressMsg::Extracted.localized("en")
+        );
+    }
+
+    #[test]
+    fn phase_timings_in_order() {
+        let progress = Progress::noop();
+        progress.start_phase("download");
+        std::thread::sleep(Duration::from_millis(30));
+        progress.start_phase("extract");
+        std::thread::sleep(Duration::from_millis(10));
+        // the running phase is not included
+        assert_eq!(progress.phase_timings().len(), 1);
+        progress.end_phase();
+        progress.end_phase();
+
+        let timings = progress.phase_timings();
+        let names = timings.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["download", "extract"]);
+        assert!(timings[0].1 >= Duration::from_millis(30));
+        assert!(timings[1].1 >= Duration::from_millis(10));
+        assert!(timings[0].1 + timings[1].1 <= progress.elapsed());
+    }
+
+    #[test]
+    fn phase_transitions_emit_typed_events() {
+        use super::{EventSender, Phase, ProgressEventKind};
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let progress = Progress::noop().with_observer(EventSender::new(tx));
+        progress.start_phase(Phase::Download);
+        progress.start_phase("extract");
+        progress.start_phase(Phase::Custom("configure".into()));
+        progress.end_phase();
+        drop(progress);
+
+        let events = rx
+            .iter()
+            .map(|event| match event.kind {
+                ProgressEventKind::PhaseEnded { phase, .. } => (false, phase),
+                ProgressEventKind::PhaseStarted(phase) => (true, phase),
+                other => panic!("unexpected event: {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                (true, Phase::Download),
+                (false, Phase::Download),
+                (true, Phase::Extract),
+                (false, Phase::Extract),
+                (true, Phase::Custom("configure".into())),
+                (false, Phase::Custom("configure".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn concurrent_progresses_have_distinct_ids() {
+        use super::{EventSender, Phase};
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let overall = Progress::noop().with_observer(EventSender::new(tx));
+        let rustc = overall.new_op();
+        let cargo = overall.new_op();
+        assert_ne!(rustc.op_id(), cargo.op_id());
+        assert_eq!(overall.clone().op_id(), overall.op_id());
+
+        std::thread::scope(|s| {
+            for progress in [&rustc, &cargo] {
+                s.spawn(move || {
+                    progress.start_phase(Phase::Download);
+                    progress.inc(Some(10.0)).unwrap();
+                    progress.show_msg("downloaded").unwrap();
+                });
+            }
+        });
+        let events = rx.try_iter().collect::<Vec<_>>();
+        assert_eq!(events.len(), 6);
+        for id in [rustc.op_id(), cargo.op_id()] {
+            let own = events.iter().filter(|e| e.op_id == id).collect::<Vec<_>>();
+            assert_eq!(own.len(), 3);
+            assert!(own.iter().all(|e| e.phase == Some(Phase::Download)));
+        }
+        assert_eq!(rustc.position(), 20.0);
+    }
+
+    #[test]
+    fn messages_logged_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("progress.log");
+        let progress = Progress::noop().with_log_file(&log);
+        progress.show_msg("downloading").unwrap();
+        progress.show_msg("multi\nline").unwrap();
+
+        let content = std::fs::read_to_string(&log).unwrap();
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        for (line, msg) in lines.iter().zip(["downloading", "multi", "line"]) {
+            let (timestamp, rest) = line.strip_prefix('[').unwrap().split_once("] ").unwrap();
+            assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+            assert_eq!(rest, msg);
+        }
+    }
+
+    #[test]
+    fn unwritable_log_file_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        // a directory cannot be opened as a log file
+        let progress = Progress::noop().with_log_file(dir.path());
+        assert!(progress.show_msg("still works").is_ok());
+    }
+
+    #[test]
+    fn invalid_len_is_sanitized() {
+        let mut progress = Progress::noop().with_len(f32::NAN);
+        assert_eq!(progress.len(), 0.0);
+        progress.set_len(-5.0);
+        assert_eq!(progress.len(), 0.0);
+        progress.set_len(f32::INFINITY);
+        assert_eq!(progress.len(), 0.0);
+        progress.set_len(150.0);
+        assert_eq!(progress.len(), 100.0);
+        progress.set_len(12.5);
+        assert_eq!(progress.len(), 12.5);
+
+        progress.inc(None).unwrap();
+        assert_eq!(progress.position(), 12.5);
+    }
+
+    #[test]
+    fn cli_update_and_inc_reach_same_position() {
+        let cli = CliProgress::new();
+        let chunks = [100, 250, 0, 4096, 1];
+
+        let absolute = super::CliBar::from(CliProgressBar::hidden());
+        let mut total = 0;
+        for chunk in chunks {
+            total += chunk;
+            (cli.update)(&absolute, Some(total));
+        }
+        let delta = super::CliBar::from(CliProgressBar::hidden());
+        for chunk in chunks {
+            (cli.inc)(&delta, chunk);
+        }
+
+        assert_eq!(absolute.position(), 4447);
+        assert_eq!(delta.position(), absolute.position());
+    }
+
+    /// A terminal that records every operation on it.
+    #[derive(Debug, Clone, Default)]
+    pub(super) struct RecordingTerm(
+        pub(super) std::sync::Arc<Mutex<Vec<String>>>,
+        pub(super) Option<u16>,
+    );
+
+    impl indicatif::TermLike for RecordingTerm {
+        fn width(&self) -> u16 {
+            self.1.unwrap_or(80)
+        }
+        fn move_cursor_up(&self, n: usize) -> std::io::Result<()> {
+            self.record(format!("up {n}"));
+            Ok(())
+        }
+        fn move_cursor_down(&self, n: usize) -> std::io::Result<()> {
+            self.record(format!("down {n}"));
+            Ok(())
+        }
+        fn move_cursor_right(&self, _n: usize) -> std::io::Result<()> {
+            Ok(())
+        }
+        fn move_cursor_left(&self, _n: usize) -> std::io::Result<()> {
+            Ok(())
+        }
+        fn write_line(&self, s: &str) -> std::io::Result<()> {
+            self.record(format!("line {s}"));
+            Ok(())
+        }
+        fn write_str(&self, s: &str) -> std::io::Result<()> {
+            self.record(format!("str {s}"));
+            Ok(())
+        }
+        fn clear_line(&self) -> std::io::Result<()> {
+            self.record("clear".into());
+            Ok(())
+        }
+        fn flush(&self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl RecordingTerm {
+        fn record(&self, op: String) {
+            self.0.lock().unwrap().push(op);
+        }
+    }
+
+    #[test]
+    fn print_does_not_interleave_with_bar() {
+        let term = RecordingTerm::default();
+        let bar = CliProgressBar::with_draw_target(
+            Some(10),
+            indicatif::ProgressDrawTarget::term_like(Box::new(term.clone())),
+        );
+        bar.set_style(indicatif::ProgressStyle::with_template("BAR {pos}/{len}").unwrap());
+        bar.set_position(3);
+        bar.tick();
+
+        super::print_suspending(Some(&bar), || term.record("MESSAGE".into()));
+        let ops = term.0.lock().unwrap().clone();
+        let msg_idx = ops.iter().position(|op| op == "MESSAGE").unwrap();
+        // the bar was drawn, then cleared before the message
+        let before = &ops[..msg_idx];
+        let last_drawn = before.iter().rposition(|op| op.contains("BAR 3/10"));
+        let last_cleared = before.iter().rposition(|op| op == "clear");
+        assert!(last_drawn.is_some());
+        assert!(last_cleared > last_drawn);
+        // then redrawn after it
+        assert!(ops[msg_idx + 1..].iter().any(|op| op.contains("BAR 3/10")));
+
+        // printing without an active bar
+        bar.finish();
+        let len = term.0.lock().unwrap().len();
+        super::print_suspending(Some(&bar), || term.record("AFTER".into()));
+        assert_eq!(term.0.lock().unwrap()[len..], ["AFTER".to_string()]);
+    }
+
+    #[test]
+    fn prompt_is_not_clobbered_while_suspended() {
+        let term = RecordingTerm::default();
+        let bar = CliProgressBar::with_draw_target(
+            Some(10),
+            indicatif::ProgressDrawTarget::term_like(Box::new(term.clone())),
+        );
+        bar.set_style(indicatif::ProgressStyle::with_template("BAR {pos}/{len}").unwrap());
+        bar.enable_steady_tick(Duration::from_millis(5));
+        bar.set_position(3);
+
+        let answer = super::print_suspending(Some(&bar), || {
+            term.record("PROMPT overwrite? [y/N]".into());
+            // the bar keeps ticking in the background meanwhile
+            std::thread::sleep(Duration::from_millis(50));
+            term.record("ANSWER".into());
+            false
+        });
+        assert!(!answer);
+        std::thread::sleep(Duration::from_millis(20));
+        bar.finish();
+
+        let ops = term.0.lock().unwrap().clone();
+        let prompt = ops.iter().position(|op| op.starts_with("PROMPT")).unwrap();
+        assert_eq!(ops[prompt + 1], "ANSWER");
+        assert!(ops[prompt + 2..].iter().any(|op| op.contains("BAR")));
+        assert_eq!(super::suspend_for_prompt(|| 42), 42);
+    }
+
+    #[test]
+    fn logging_while_prompting_does_not_deadlock() {
+        use tracing_subscriber::layer::{Context, SubscriberExt};
+
+        // the same as the console logger, which prints every message above the bar
+        struct AboveBar(CliProgressBar, RecordingTerm);
+        impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for AboveBar {
+            fn on_event(&self, _event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+                super::print_suspending(Some(&self.0), || self.1.record("WARNING".into()));
+            }
+        }
+
+        let term = RecordingTerm::default();
+        let bar = CliProgressBar::with_draw_target(
+            Some(10),
+            indicatif::ProgressDrawTarget::term_like(Box::new(term.clone())),
+        );
+        bar.set_position(3);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (bar_, term_) = (bar.clone(), term.clone());
+        std::thread::spawn(move || {
+            let subscriber = tracing_subscriber::registry().with(AboveBar(bar_.clone(), term_));
+            tracing::subscriber::with_default(subscriber, || {
+                super::print_suspending(Some(&bar_), || {
+                    // such as an invalid answer of the prompt
+                    tracing::warn!("invalid input");
+                });
+                // the bar is suspended again by later messages
+                tracing::warn!("after prompt");
+            });
+            tx.send(()).unwrap();
+        });
+        rx.recv_timeout(Duration::from_secs(5))
+            .expect("logging inside a prompt deadlocked");
+
+        let ops = term.0.lock().unwrap().clone();
+        assert_eq!(ops.iter().filter(|op| *op == "WARNING").count(), 2);
+    }
+
+    #[test]
+    fn zero_width_terminal_shows_message_only() {
+        let style = Style::Bytes(1000);
+        assert_eq!(style.pattern_for_width(Some(80)), style.pattern());
+        assert_eq!(style.pattern_for_width(Some(0)), "{msg}");
+        assert_eq!(style.pattern_for_width(Some(5)), "{msg}");
+        assert_eq!(style.pattern_for_width(None), "{msg}");
+
+        // rendering on narrow terminals never panics
+        for width in [0, 15] {
+            let term = RecordingTerm(Default::default(), Some(width));
+            let bar = CliProgressBar::with_draw_target(
+                Some(1000),
+                indicatif::ProgressDrawTarget::term_like(Box::new(term.clone())),
+            );
+            let pattern = style.pattern_for_width(Some(term.width()));
+            bar.set_style(indicatif::ProgressStyle::with_template(pattern).unwrap());
+            bar.set_message("downloading");
+            bar.set_position(500);
+            bar.tick();
+            bar.finish();
+
+            let ops = term.0.lock().unwrap();
+            assert_eq!(ops.iter().any(|op| op.contains("downloading")), width >= 11);
+            assert!(!ops.iter().any(|op| op.contains("500")));
+        }
+    }
+
+    #[test]
+    fn prefix_is_rendered_before_message() {
+        let term = RecordingTerm::default();
+        let bar = CliProgressBar::with_draw_target(
+            Some(10),
+            indicatif::ProgressDrawTarget::term_like(Box::new(term.clone())),
+        );
+        let style = Style::Len(10);
+        super::apply_style(&bar, style.pattern(), Default::default()).unwrap();
+        bar.set_message("downloading");
+        let cli = CliProgress::hidden();
+        cli.set_prefix(&bar, "rust-std:");
+        assert_eq!(bar.prefix(), "rust-std: ");
+        bar.set_position(5);
+        bar.abandon();
+        assert!(term
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|op| op.contains("rust-std: downloading")));
+
+        // empty by default
+        let bar = CliProgressBar::hidden();
+        assert_eq!(bar.prefix(), "");
+        cli.set_prefix(&bar, "");
+        assert_eq!(bar.prefix(), "");
+    }
+
+    #[test]
+    fn output_ends_with_newline_after_stop() {
+        let term = RecordingTerm::default();
+        let bar = CliProgressBar::with_draw_target(
+            Some(10),
+            indicatif::ProgressDrawTarget::term_like(Box::new(term.clone())),
+        );
+        bar.set_position(3);
+        super::finish_on_new_line(&bar, "done".into(), &term);
+
+        let ops = term.0.lock().unwrap();
+        assert!(bar.is_finished());
+        assert_eq!(ops.last().map(String::as_str), Some("line "));
+    }
+
+    #[test]
+    fn instant_operation_skips_drawing() {
+        let delay = Duration::from_millis(100);
+        let new_bar = |term: &RecordingTerm| {
+            let bar = CliProgressBar::new(10);
+            let target = indicatif::ProgressDrawTarget::term_like(Box::new(term.clone()));
+            super::delay_drawing(&bar, delay, target);
+            bar
+        };
+
+        let term = RecordingTerm::default();
+        let bar = new_bar(&term);
+        bar.inc(10);
+        super::finish_on_new_line(&bar, "done".into(), &term);
+        std::thread::sleep(delay * 2);
+        assert!(bar.is_finished());
+        assert!(term.0.lock().unwrap().is_empty());
+
+        let term = RecordingTerm::default();
+        let bar = new_bar(&term);
+        std::thread::sleep(delay * 2);
+        bar.inc(5);
+        assert!(!term.0.lock().unwrap().is_empty());
+    }
+
+    /// A clock that only moves when told to.
+    #[derive(Clone)]
+    struct FakeClock(std::sync::Arc<Mutex<std::time::Instant>>);
+
+    impl FakeClock {
+        fn new() -> Self {
+            Self(std::sync::Arc::new(Mutex::new(std::time::Instant::now())))
+        }
+        fn advance(&self, dur: Duration) {
+            *self.0.lock().unwrap() += dur;
+        }
+    }
+
+    impl super::Clock for FakeClock {
+        fn now(&self) -> std::time::Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    #[test]
+    fn eta_with_fake_clock() {
+        let clock = FakeClock::new();
+        let progress = Progress::noop().with_clock(clock.clone());
+        assert_eq!(progress.eta(), None);
+
+        clock.advance(Duration::from_secs(10));
+        progress.inc(Some(25.0)).unwrap();
+        assert_eq!(progress.elapsed(), Duration::from_secs(10));
+        assert_eq!(progress.rate(), 2.5);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(30)));
+
+        // paused time does not count
+        progress.pause().unwrap();
+        clock.advance(Duration::from_secs(100));
+        progress.resume().unwrap();
+        clock.advance(Duration::from_secs(10));
+        progress.inc(Some(25.0)).unwrap();
+        assert_eq!(progress.elapsed(), Duration::from_secs(20));
+        assert_eq!(progress.eta(), Some(Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn configured_tick_interval() {
+        let auto = Some(Duration::from_millis(100));
+        let slow = Duration::from_secs(1);
+        let cli = CliProgress::new();
+        assert_eq!(cli.opt.tick_interval.resolve(auto), auto);
+        assert_eq!(cli.opt.tick_interval.resolve(None), None);
+
+        let cli = cli.with_tick_interval(TickInterval::Every(slow));
+        assert_eq!(cli.opt.tick_interval.resolve(auto), Some(slow));
+        assert_eq!(cli.opt.tick_interval.resolve(None), Some(slow));
+
+        let cli = cli.with_tick_interval(TickInterval::Disabled);
+        assert_eq!(cli.opt.tick_interval.resolve(auto), None);
+    }
+
+    #[test]
+    fn spinners_tick_at_the_configured_interval() {
+        let spinner = Style::Spinner {
+            auto_tick_duration: Some(Duration::from_secs(3600)),
+        };
+        // the number of times the spinner drew itself without being updated
+        let draws_with = |tick_interval| {
+            let opt = super::CliProgressOpt {
+                tick_interval,
+                ..Default::default()
+            };
+            let term = RecordingTerm::default();
+            let bar = super::styled_bar("checking".into(), spinner, opt).unwrap();
+            bar.set_draw_target(indicatif::ProgressDrawTarget::term_like(Box::new(
+                term.clone(),
+            )));
+            std::thread::sleep(Duration::from_millis(300));
+            let ops = term.0.lock().unwrap();
+            ops.iter().filter(|op| op.contains("checking")).count()
+        };
+
+        assert!(draws_with(TickInterval::Every(Duration::from_millis(10))) >= 2);
+        // only the first tick of the hourly `auto_tick_duration` could be drawn
+        assert!(draws_with(TickInterval::Default) <= 1);
+        assert_eq!(draws_with(TickInterval::Disabled), 0);
+    }
+
+    #[test]
+    fn failing_writer_does_not_panic() {
+        struct FailingWriter;
+        impl std::fmt::Write for FailingWriter {
+            fn write_str(&mut self, _: &str) -> std::fmt::Result {
+                Err(std::fmt::Error)
+            }
+        }
+
+        let _failure = draw_failure();
+        let term = RecordingTerm::default();
+        let bar = CliProgressBar::with_draw_target(
+            Some(10),
+            indicatif::ProgressDrawTarget::term_like(Box::new(term)),
+        );
+        // bars are kept as is, until drawing fails
+        super::write_or_degrade(&mut String::new(), format_args!("ok"));
+        assert!(!bar.is_hidden());
+        super::write_or_degrade(&mut FailingWriter, format_args!("{}s", 1.5));
+        assert!(super::degrade_if_draw_failed(&bar));
+        assert!(bar.is_hidden());
+        // a hidden bar can still be updated and stopped
+        let bar = super::CliBar::from(bar);
+        let cli = CliProgress::new();
+        (cli.inc)(&bar, 5);
+        assert_eq!(bar.position(), 5);
+        (cli.stop)(&bar, "done".into());
+        assert!(bar.is_finished());
+    }
+
+    #[test]
+    fn failed_style_falls_back_to_messages() {
+        let _failure = draw_failure();
+        let bar = super::start_or_fallback("downloading".into(), Style::Len(10), |_| {
+            anyhow::bail!("invalid template")
+        });
+        let bar = super::CliBar::from(bar);
+        assert!(bar.is_hidden());
+        assert_eq!(bar.message(), "downloading");
+        assert!(super::DRAW_FAILED.load(std::sync::atomic::Ordering::Relaxed));
+
+        let cli = CliProgress::new();
+        (cli.inc)(&bar, 4);
+        (cli.update)(&bar, Some(7));
+        assert_eq!(bar.position(), 7);
+        assert_eq!(bar.length(), Some(10));
+        (cli.stop)(&bar, "downloaded".into());
+        assert!(bar.is_finished());
+    }
+
+    fn recording_cli() -> CliProgress<std::sync::Arc<Mutex<Option<String>>>> {
+        CliProgress {
+            start: |_, _, _| Ok(Default::default()),
+            update: |_, _| {},
+            inc: |_, _| {},
+            stop: |bar, msg| *bar.lock().unwrap() = Some(msg),
+            opt: Default::default(),
+        }
+    }
+
+    #[test]
+    fn scoped_bar_is_always_stopped() {
+        let cli = recording_cli();
+        let style = Style::Len(3);
+        let mut handle = None;
+        let val = cli
+            .scoped(style, "start".into(), "done".into(), |bar| {
+                handle = Some(bar.clone());
+                Ok(42)
+            })
+            .unwrap();
+        assert_eq!(val, 42);
+        assert_eq!(*handle.unwrap().lock().unwrap(), Some("done".into()));
+
+        let mut handle = None;
+        let res: anyhow::Result<()> = cli.scoped(style, "start".into(), "done".into(), |bar| {
+            handle = Some(bar.clone());
+            anyhow::bail!("failed")
+        });
+        assert!(res.is_err());
+        assert_eq!(*handle.unwrap().lock().unwrap(), Some(String::new()));
+
+        let handle = Mutex::new(None);
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            cli.scoped(
+                style,
+                "start".into(),
+                "done".into(),
+                |bar| -> anyhow::Result<()> {
+                    *handle.lock().unwrap() = Some(bar.clone());
+                    panic!("oops");
+                },
+            )
+        }));
+        assert!(res.is_err());
+        let handle = handle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .unwrap();
+        assert_eq!(*handle.lock().unwrap(), Some(String::new()));
+    }
+
+    #[test]
+    fn inc_with_msg_fires_both_callbacks_in_order() {
+        let calls = Mutex::new(vec![]);
+        let pos_cb = |pos: f32| {
+            calls.lock().unwrap().push(format!("pos {pos}"));
+            Ok(())
+        };
+        let msg_cb = |msg: String| {
+            calls.lock().unwrap().push(format!("msg {msg}"));
+            Ok(())
+        };
+        let progress = Progress::new(&pos_cb)
+            .with_msg_callback(&msg_cb)
+            .with_len(10.0);
+        progress.inc_with_msg(None, "extracting a").unwrap();
+        progress.inc_with_msg(Some(5.0), "extracting b").unwrap();
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["msg extracting a", "pos 10", "msg extracting b", "pos 15"]
+        );
+        assert_eq!(progress.position(), 15.0);
+    }
+
+    #[test]
+    fn callback_error_policies() {
+        let calls = Mutex::new(0);
+        let failing_pos = |_: f32| {
+            *calls.lock().unwrap() += 1;
+            anyhow::bail!("channel closed")
+        };
+        let failing_msg = |_: String| anyhow::bail!("channel closed");
+
+        let aborting = Progress::new(&failing_pos)
+            .with_msg_callback(&failing_msg)
+            .with_len(10.0);
+        assert!(aborting.inc(None).is_err());
+        assert!(aborting.show_msg("hi").is_err());
+        assert_eq!(aborting.position(), 0.0);
+
+        *calls.lock().unwrap() = 0;
+        let continuing = Progress::new(&failing_pos)
+            .with_msg_callback(&failing_msg)
+            .with_len(10.0)
+            .with_callback_error_policy(CallbackErrorPolicy::ContinueWithoutUi);
+        continuing.inc(None).unwrap();
+        continuing.inc(None).unwrap();
+        continuing.show_msg("hi").unwrap();
+        assert_eq!(continuing.position(), 20.0);
+        // the callbacks are no longer called after the first failure
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn message_history_keeps_recent_messages() {
+        let progress = Progress::noop().with_message_history(3);
+        for i in 0..5 {
+            progress.show_msg(format!("step {i}")).unwrap();
+        }
+        assert_eq!(progress.message_history(), ["step 2", "step 3", "step 4"]);
+        // shared by clones
+        progress.clone().show_msg("done").unwrap();
+        assert_eq!(progress.message_history(), ["step 3", "step 4", "done"]);
+
+        let progress = Progress::noop().with_message_history(0);
+        progress.show_msg("ignored").unwrap();
+        assert!(progress.message_history().is_empty());
+    }
+
+    #[test]
+    fn events_flagged_indeterminate_until_total_known() {
+        use super::{EventSender, ProgressEventKind};
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let progress = Progress::noop()
+            .with_observer(EventSender::new(tx))
+            .with_unknown_total();
+        progress.inc(Some(1.0)).unwrap();
+        progress.show_msg("connecting").unwrap();
+        progress.clone().set_total_known();
+        progress.inc(Some(9.0)).unwrap();
+        drop(progress);
+
+        assert_eq!(
+            rx.iter().map(|e| e.kind).collect::<Vec<_>>(),
+            [
+                ProgressEventKind::Position {
+                    pos: 1.0,
+                    determinate: false
+                },
+                ProgressEventKind::Message("connecting".into()),
+                ProgressEventKind::Position {
+                    pos: 10.0,
+                    determinate: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn promoting_mid_operation_keeps_counted_bytes() {
+        let clock = FakeClock::new();
+        let progress = Progress::noop()
+            .with_clock(clock.clone())
+            .with_unknown_total();
+        let clone = progress.clone();
+        progress.inc(Some(25.0)).unwrap();
+        clock.advance(Duration::from_secs(4));
+        progress.promote_to_determinate(2000);
+        assert!(progress.is_determinate());
+        assert_eq!(progress.position_bytes(), 500);
+        // the clones are promoted as well
+        assert!(clone.is_determinate());
+        assert_eq!(clone.byte_total(), Some(2000));
+        assert_eq!(clone.position_bytes(), 500);
+        assert_eq!(progress.elapsed(), Duration::from_secs(4));
+
+        let opt = super::CliProgressOpt {
+            tick_interval: TickInterval::Disabled,
+            ..Default::default()
+        };
+        let spinner = Style::Spinner {
+            auto_tick_duration: None,
+        };
+        let term = RecordingTerm::default();
+        let bar = super::styled_bar("downloading".into(), spinner, opt).unwrap();
+        bar.set_draw_target(indicatif::ProgressDrawTarget::term_like(Box::new(
+            term.clone(),
+        )));
+        let bar = super::CliBar::from(bar);
+        bar.inc(500);
+        std::thread::sleep(Duration::from_millis(20));
+        let elapsed = bar.elapsed();
+        CliProgress::hidden()
+            .promote_to_determinate(&bar, 2000)
+            .unwrap();
+        assert_eq!(bar.position(), 500);
+        assert_eq!(bar.length(), Some(2000));
+        assert!(bar.counts_bytes());
+        assert!(bar.elapsed() >= elapsed);
+        bar.inc(1500);
+        bar.finish();
+        assert!(term
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|op| op.contains("downloading")));
+    }
+
+    #[test]
+    fn rendered_decimal_precision() {
+        let render = |decimals: Option<usize>| {
+            let term = RecordingTerm::default();
+            let bar = CliProgressBar::with_draw_target(
+                Some(3),
+                indicatif::ProgressDrawTarget::term_like(Box::new(term.clone())),
+            );
+            let opt = super::CliProgressOpt {
+                decimals,
+                ..Default::default()
+            };
+            super::apply_style(&bar, "{pos}/{len} {percent}%", opt).unwrap();
+            bar.set_position(1);
+            bar.abandon();
+            let ops = term.0.lock().unwrap();
+            ops.iter()
+                .rev()
+                .find(|op| op.contains('%'))
+                .cloned()
+                .unwrap()
+        };
+        assert!(render(None).contains("1/3 33%"));
+        assert!(render(Some(2)).contains("1/3 33.33%"));
+
+        assert_eq!(super::format_bytes(1536.0, None), "1.50 KiB");
+        assert_eq!(super::format_bytes(1536.0, Some(1)), "1.5 KiB");
+        assert_eq!(super::format_bytes(3.5 * 1024.0 * 1024.0, Some(0)), "4 MiB");
+        assert_eq!(super::format_bytes(512.0, Some(3)), "512 B");
+    }
+
+    #[test]
+    fn resumed_bar_shows_session_bytes() {
+        let term = RecordingTerm::default();
+        let bar = CliProgressBar::with_draw_target(
+            Some(1000),
+            indicatif::ProgressDrawTarget::term_like(Box::new(term.clone())),
+        );
+        bar.set_style(
+            super::custom_style(
+                "{bytes}/{total_bytes} +{session_bytes}",
+                Default::default(),
+                Some(super::TransferSession::new(600, std::time::Instant::now())),
+            )
+            .unwrap(),
+        );
+        bar.set_position(700);
+        bar.abandon();
+        assert!(term
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|op| op.contains("700 B/1000 B +100 B")));
+        assert!(super::Style::resumed_pattern().contains("{session_bytes}"));
+    }
+
+    static_assertions::assert_impl_all!(Progress<'static>: Send, Sync);
+    static_assertions::assert_impl_all!(super::ProgressBuilder<'static>: Send, Sync);
+    static_assertions::assert_impl_all!(super::StepProgress<'static>: Send, Sync);
+    static_assertions::assert_impl_all!(super::AggregateProgress<'static>: Send, Sync);
+
+    #[test]
+    fn thread_safe_types() {
+        let progress = Progress::noop().with_len(1.0);
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| (0..25).for_each(|_| progress.inc(None).unwrap()));
+            }
+        });
+        assert_eq!(progress.position(), 100.0);
+    }
+}
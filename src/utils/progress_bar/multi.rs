@@ -0,0 +1,132 @@
+//! Rendering the bars of several concurrent tasks at once, with an overall bar below them.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use indicatif::{MultiProgress, ProgressBar as CliProgressBar, ProgressDrawTarget};
+
+use super::{apply_style, draw_hidden, styled_bar, CliProgressOpt, Style};
+
+/// Each in-flight task, such as a download, gets its own bar, which is removed once the
+/// task is finished, while the overall bar stays at the bottom until every task is done.
+#[derive(Debug)]
+pub struct MultiCliProgress {
+    multi: MultiProgress,
+    overall: CliProgressBar,
+    opt: CliProgressOpt,
+}
+
+impl MultiCliProgress {
+    /// Create the bars that render on `stderr` with an overall bar of `total` bytes
+    /// (which can be changed later), displaying the given `msg`.
+    ///
+    /// Nothing is rendered in quiet mode, summary mode, JSON mode, or if drawing has failed
+    /// before.
+    pub fn new(msg: String, total: u64, opt: CliProgressOpt) -> Result<Self> {
+        let target = if draw_hidden() {
+            ProgressDrawTarget::hidden()
+        } else {
+            ProgressDrawTarget::stderr()
+        };
+        Self::with_draw_target(msg, total, opt, target)
+    }
+
+    /// Create the bars that render on a specific target.
+    pub fn with_draw_target(
+        msg: String,
+        total: u64,
+        opt: CliProgressOpt,
+        target: ProgressDrawTarget,
+    ) -> Result<Self> {
+        let multi = MultiProgress::with_draw_target(target);
+        let overall = multi.add(styled_bar(msg, Style::Bytes(total), opt)?);
+        Ok(Self {
+            multi,
+            overall,
+            opt,
+        })
+    }
+
+    /// Add a bar of a task above the overall bar, which is a spinner until its size is
+    /// set by [`set_task_total`](MultiCliProgress::set_task_total).
+    pub fn add_task(&self, msg: String) -> Result<CliProgressBar> {
+        let style = Style::Spinner {
+            auto_tick_duration: Some(Duration::from_millis(100)),
+        };
+        Ok(self
+            .multi
+            .insert_before(&self.overall, styled_bar(msg, style, self.opt)?))
+    }
+
+    /// Turn the bar of a task into a bar of `total` bytes.
+    pub fn set_task_total(&self, pb: &CliProgressBar, total: u64) -> Result<()> {
+        pb.disable_steady_tick();
+        pb.set_length(total);
+        if !pb.is_hidden() {
+            let width = console::Term::stderr().size_checked().map(|(_, cols)| cols);
+            apply_style(pb, Style::Bytes(total).pattern_for_width(width), self.opt)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the bar of a finished task, then print `msg` in place of it.
+    pub fn finish_task(&self, pb: &CliProgressBar, msg: String) -> Result<()> {
+        pb.finish_and_clear();
+        self.multi.remove(pb);
+        if !self.multi.is_hidden() {
+            self.multi.println(msg)?;
+        }
+        Ok(())
+    }
+
+    /// Update the overall bar with the completed and total bytes of all tasks.
+    pub fn set_overall(&self, done: u64, total: u64) {
+        self.overall.set_length(total);
+        self.overall.set_position(done);
+    }
+
+    /// Finish the overall bar with a message.
+    pub fn finish(&self, msg: String) {
+        self.overall.finish_with_message(msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::RecordingTerm;
+    use super::*;
+    use indicatif::ProgressStyle;
+
+    #[test]
+    fn task_bars_stay_above_the_overall_bar() {
+        let term = RecordingTerm::default();
+        let target = ProgressDrawTarget::term_like(Box::new(term.clone()));
+        let bars = MultiCliProgress::with_draw_target(
+            "downloading".into(),
+            0,
+            CliProgressOpt::default(),
+            target,
+        )
+        .unwrap();
+        bars.overall
+            .set_style(ProgressStyle::with_template("ALL {pos}/{len}").unwrap());
+        let a = bars.add_task("a".into()).unwrap();
+        let b = bars.add_task("b".into()).unwrap();
+        for (pb, name) in [(&a, "A"), (&b, "B")] {
+            bars.set_task_total(pb, 10).unwrap();
+            pb.set_style(ProgressStyle::with_template(&format!("{name} {{pos}}")).unwrap());
+        }
+        a.set_position(4);
+        b.set_position(6);
+        bars.set_overall(10, 20);
+        bars.finish_task(&a, "a done".into()).unwrap();
+        bars.finish_task(&b, "b done".into()).unwrap();
+        bars.finish("downloaded".into());
+
+        let ops = term.0.lock().unwrap();
+        let last = |pat: &str| ops.iter().rposition(|op| op.contains(pat)).unwrap();
+        assert!(last("A 4") < last("ALL 10/20"), "{ops:?}");
+        assert!(last("B 6") < last("ALL 10/20"), "{ops:?}");
+        assert!(last("b done") < last("ALL 10/20"), "{ops:?}");
+    }
+}
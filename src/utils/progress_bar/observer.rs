@@ -0,0 +1,213 @@
+//! Observers that get notified about every change of a [`Progress`](super::Progress),
+//! in addition to its position and message callbacks.
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::Local;
+
+use super::{Phase, TransferStats};
+
+/// Observes the changes of a progress.
+///
+/// Unlike the callbacks, observers cannot fail, they are implemented for
+/// side-effects that are not essential for the progress itself, such as logging.
+pub trait ProgressObserver: Send + Sync {
+    /// Called after the position has been changed.
+    fn on_pos(&self, _pos: f32) {}
+    /// Called when a message is being displayed.
+    fn on_msg(&self, _msg: &str) {}
+    /// Called when the speed and ETA of the transfers are reported.
+    fn on_transfer(&self, _stats: &TransferStats) {}
+    /// Called with every change as an event, after [`on_pos`](ProgressObserver::on_pos),
+    /// [`on_msg`](ProgressObserver::on_msg) or [`on_transfer`](ProgressObserver::on_transfer),
+    /// or when a phase starts or ends.
+    fn on_event(&self, _event: &ProgressEvent) {}
+}
+
+/// Identifies an operation that a [`Progress`](super::Progress) is tracking, so that the
+/// events of concurrent operations can be told apart, such as to route them to the
+/// corresponding rows in GUI.
+///
+/// Every progress gets a unique id once created, which is shared by its clones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OpId(u64);
+
+impl OpId {
+    pub(super) fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Get the id as a number, which is never `0`.
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for OpId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// A change of a progress, for consumers that receive them from channels or streams.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressEvent {
+    /// The operation that this event belongs to.
+    pub op_id: OpId,
+    /// The running phase of the operation, if there is one.
+    pub phase: Option<Phase>,
+    /// The current position and the total in bytes, if the progress has a byte total,
+    /// see [`Progress::with_byte_total`](super::Progress::with_byte_total).
+    pub bytes: Option<(u64, u64)>,
+    pub kind: ProgressEventKind,
+}
+
+/// What has been changed in a [`ProgressEvent`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEventKind {
+    /// The position has been changed.
+    Position {
+        pos: f32,
+        /// Whether the total amount of work is known, if not, the position is not very
+        /// meaningful, and a spinner should be rendered instead of a bar.
+        determinate: bool,
+    },
+    /// A message is being displayed.
+    Message(String),
+    /// The speed and ETA of the transfers, see
+    /// [`Progress::report_transfer`](super::Progress::report_transfer).
+    Transfer(TransferStats),
+    /// A new phase has been started.
+    PhaseStarted(Phase),
+    /// A phase has been ended, after running for the given amount of time.
+    PhaseEnded { phase: Phase, duration: Duration },
+}
+
+/// Sends every change of a progress as a [`ProgressEvent`] through a channel.
+///
+/// The events are dropped once the receiver is disconnected.
+#[derive(Debug)]
+pub struct EventSender(Sender<ProgressEvent>);
+
+impl EventSender {
+    pub fn new(sender: Sender<ProgressEvent>) -> Self {
+        Self(sender)
+    }
+}
+
+impl ProgressObserver for EventSender {
+    fn on_event(&self, event: &ProgressEvent) {
+        let _ = self.0.send(event.clone());
+    }
+}
+
+/// Keeps the most recent messages in a ring buffer, such as for a scrollback of
+/// status lines in GUI.
+#[derive(Debug)]
+pub struct MessageHistory {
+    capacity: usize,
+    messages: Mutex<VecDeque<String>>,
+}
+
+impl MessageHistory {
+    /// The number of messages kept by default.
+    pub const DEFAULT_CAPACITY: usize = 20;
+
+    /// Create a history that keeps at most `capacity` messages.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            messages: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Get the kept messages, from the oldest to the newest.
+    pub fn messages(&self) -> Vec<String> {
+        self.messages.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl Default for MessageHistory {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl ProgressObserver for MessageHistory {
+    fn on_msg(&self, msg: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut messages = self.messages.lock().unwrap();
+        if messages.len() == self.capacity {
+            messages.pop_front();
+        }
+        messages.push_back(msg.to_string());
+    }
+}
+
+/// Appends every progress message to a file, with a timestamp at the start of each line.
+#[derive(Debug)]
+pub struct LogFileObserver {
+    path: PathBuf,
+    /// The opened file, or `None` if it could not be opened or written,
+    /// in which case the messages will be ignored (with a warning printed once).
+    file: Mutex<Option<File>>,
+}
+
+impl LogFileObserver {
+    /// Open (or create) the file at `path` for appending.
+    ///
+    /// This never fails, the messages just won't be logged if the file cannot be opened.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let file = match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                warn_log_failure(&path, &e);
+                None
+            }
+        };
+        Self {
+            path,
+            file: Mutex::new(file),
+        }
+    }
+}
+
+impl ProgressObserver for LogFileObserver {
+    fn on_msg(&self, msg: &str) {
+        let mut guard = self.file.lock().unwrap();
+        let Some(file) = guard.as_mut() else {
+            return;
+        };
+        let timestamp = Local::now().to_rfc3339();
+        let content = msg
+            .lines()
+            .map(|line| format!("[{timestamp}] {line}\n"))
+            .collect::<String>();
+        if let Err(e) = file.write_all(content.as_bytes()) {
+            warn_log_failure(&self.path, &e);
+            *guard = None;
+        }
+    }
+}
+
+fn warn_log_failure(path: &Path, err: &std::io::Error) {
+    warn!(
+        "{}",
+        t!(
+            "progress_log_unavailable",
+            path = path.display(),
+            reason = err
+        )
+    );
+}
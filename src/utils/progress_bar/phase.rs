@@ -0,0 +1,96 @@
+//! The phases of a [`Progress`](super::Progress).
+
+use std::borrow::Cow;
+
+/// A phase of a progress, such as downloading or extracting.
+///
+/// The name is translated using the current locale when displayed, or use
+/// [`localized`](Phase::localized) to get the name for a specific locale.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Download,
+    Verify,
+    Extract,
+    Copy,
+    Install,
+    /// Any other phase with the given name, which is displayed as is.
+    Custom(String),
+}
+
+impl Phase {
+    /// Get the identifier of this phase, such as `download`, which is not translated.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Download => "download",
+            Self::Verify => "verify",
+            Self::Extract => "extract",
+            Self::Copy => "copy",
+            Self::Install => "install",
+            Self::Custom(name) => name,
+        }
+    }
+
+    /// Get the translated name of a specific locale, such as `en` or `zh-CN`.
+    pub fn localized(&self, locale: &str) -> Cow<'_, str> {
+        let translated = match self {
+            Self::Download => t!("phase_download", locale = locale),
+            Self::Verify => t!("phase_verify", locale = locale),
+            Self::Extract => t!("phase_extract", locale = locale),
+            Self::Copy => t!("phase_copy", locale = locale),
+            Self::Install => t!("phase_install", locale = locale),
+            Self::Custom(name) => return Cow::Borrowed(name),
+        };
+        Cow::Owned(translated.to_string())
+    }
+}
+
+impl std::fmt::Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localized(&rust_i18n::locale()))
+    }
+}
+
+/// Parse the identifier of a built-in phase, any other names are [`Phase::Custom`].
+impl From<&str> for Phase {
+    fn from(name: &str) -> Self {
+        match name {
+            "download" => Self::Download,
+            "verify" => Self::Verify,
+            "extract" => Self::Extract,
+            "copy" => Self::Copy,
+            "install" => Self::Install,
+            _ => Self::Custom(name.to_string()),
+        }
+    }
+}
+
+impl From<String> for Phase {
+    fn from(name: String) -> Self {
+        match Self::from(name.as_str()) {
+            Self::Custom(_) => Self::Custom(name),
+            builtin => builtin,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_names_round_trip() {
+        for phase in [
+            Phase::Download,
+            Phase::Verify,
+            Phase::Extract,
+            Phase::Copy,
+            Phase::Install,
+        ] {
+            assert_eq!(Phase::from(phase.as_str()), phase);
+            assert_ne!(phase.localized("zh-CN"), phase.localized("en"));
+        }
+        let custom = Phase::from("configure".to_string());
+        assert_eq!(custom, Phase::Custom("configure".into()));
+        assert_eq!(custom.localized("zh-CN"), "configure");
+    }
+}
@@ -0,0 +1,241 @@
+//! Calculating a steady transfer rate over a sliding window.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Averages the rate of a growing position (such as bytes downloaded) over the last
+/// `window` of time, so that short bursts won't make the readout jump around.
+#[derive(Debug, Clone)]
+pub struct RateWindow {
+    window: Duration,
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl RateWindow {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Record the position at a certain time, samples should be recorded in time order.
+    pub fn record(&mut self, at: Instant, pos: u64) {
+        // the position was reset, older samples are meaningless now
+        if self.samples.back().is_some_and(|&(_, last)| pos < last) {
+            self.samples.clear();
+        }
+        self.samples.push_back((at, pos));
+        while self
+            .samples
+            .front()
+            .is_some_and(|&(t, _)| at.saturating_duration_since(t) > self.window)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The average rate (per second) of the recorded samples within the window,
+    /// which will be `0.0` if there aren't enough samples.
+    pub fn rate(&self) -> f64 {
+        let (Some(&(t0, p0)), Some(&(t1, p1))) = (self.samples.front(), self.samples.back()) else {
+            return 0.0;
+        };
+        let secs = t1.saturating_duration_since(t0).as_secs_f64();
+        if secs == 0.0 {
+            0.0
+        } else {
+            (p1 - p0) as f64 / secs
+        }
+    }
+}
+
+/// The throughput of a transfer in the current session, which might be resumed from
+/// a number of bytes that were transferred previously, such as resuming a download.
+///
+/// Including those bytes would make the speed look much faster than it actually is,
+/// so the rate is only based on the bytes of this session, then the ETA is the remaining
+/// bytes (of the whole transfer) divided by that rate.
+#[derive(Debug, Clone, Copy)]
+pub struct TransferSession {
+    resumed_from: u64,
+    started: Instant,
+}
+
+impl TransferSession {
+    /// Start a session at `started`, with `resumed_from` bytes transferred previously.
+    pub fn new(resumed_from: u64, started: Instant) -> Self {
+        Self {
+            resumed_from,
+            started,
+        }
+    }
+
+    /// The number of bytes this session transferred, when the overall position is `pos`.
+    pub fn session_bytes(&self, pos: u64) -> u64 {
+        pos.saturating_sub(self.resumed_from)
+    }
+
+    /// The average rate (per second) of this session, when the overall position is `pos`.
+    pub fn rate(&self, pos: u64, now: Instant) -> f64 {
+        let secs = now.saturating_duration_since(self.started).as_secs_f64();
+        if secs == 0.0 {
+            0.0
+        } else {
+            self.session_bytes(pos) as f64 / secs
+        }
+    }
+
+    /// The estimated time to transfer the rest of `total` bytes at the rate of this session,
+    /// returns `None` if nothing was transferred in this session yet.
+    pub fn eta(&self, pos: u64, total: u64, now: Instant) -> Option<Duration> {
+        let rate = self.rate(pos, now);
+        (rate > 0.0).then(|| Duration::from_secs_f64(total.saturating_sub(pos) as f64 / rate))
+    }
+}
+
+/// The speed and ETA of a transfer, which are reported with
+/// [`Progress::report_transfer`](super::Progress::report_transfer).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferStats {
+    /// The number of bytes transferred.
+    pub bytes: u64,
+    pub total_bytes: u64,
+    /// The current speed in bytes per second, averaged over the last few seconds.
+    pub speed: f64,
+    /// The estimated time to transfer the rest at the current speed, which is `None` if
+    /// the speed is not known yet.
+    pub eta: Option<Duration>,
+}
+
+/// Calculates the [`TransferStats`] of the reported positions, and decides which of them
+/// should be reported, so that frontends won't get flooded with updates of every chunk.
+#[derive(Debug, Clone)]
+pub(super) struct TransferMeter {
+    rate: RateWindow,
+    interval: Duration,
+    last: Option<(Instant, TransferStats)>,
+}
+
+impl TransferMeter {
+    /// Report the stats at most once every `interval`, with the speed averaged over `window`.
+    pub(super) fn new(window: Duration, interval: Duration) -> Self {
+        Self {
+            rate: RateWindow::new(window),
+            interval,
+            last: None,
+        }
+    }
+
+    /// Record `bytes` of `total` transferred at `now`, returns the stats if they should
+    /// be reported, which are the first ones, the final ones, and the ones after `interval`.
+    pub(super) fn record(&mut self, now: Instant, bytes: u64, total: u64) -> Option<TransferStats> {
+        let last_bytes = self.last.map(|(_, stats)| stats.bytes);
+        // a concurrent transfer might report an older position, only a restart resets it
+        if last_bytes.is_some_and(|last| bytes < last && bytes > 0) {
+            return None;
+        }
+        self.rate.record(now, bytes);
+        let speed = self.rate.rate();
+        let remaining = total.saturating_sub(bytes);
+        let stats = TransferStats {
+            bytes,
+            total_bytes: total,
+            speed,
+            eta: if remaining == 0 {
+                Some(Duration::ZERO)
+            } else {
+                (speed > 0.0).then(|| Duration::from_secs_f64(remaining as f64 / speed))
+            },
+        };
+        let due = match self.last {
+            None => true,
+            Some((at, last)) => {
+                (remaining == 0 && last.bytes != bytes)
+                    || now.saturating_duration_since(at) >= self.interval
+            }
+        };
+        if due {
+            self.last = Some((now, stats));
+        }
+        due.then_some(stats)
+    }
+
+    /// The stats reported most recently.
+    pub(super) fn last(&self) -> Option<TransferStats> {
+        self.last.map(|(_, stats)| stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttled_transfer_stats() {
+        let base = Instant::now();
+        let at = |millis: u64| base + Duration::from_millis(millis);
+        let mut meter = TransferMeter::new(Duration::from_secs(3), Duration::from_millis(200));
+
+        let first = meter.record(at(0), 0, 1000).unwrap();
+        assert_eq!(first.speed, 0.0);
+        assert_eq!(first.eta, None);
+        // too soon
+        assert!(meter.record(at(100), 100, 1000).is_none());
+        let stats = meter.record(at(200), 200, 1000).unwrap();
+        assert_eq!(stats.speed, 1000.0);
+        assert_eq!(stats.eta, Some(Duration::from_millis(800)));
+        // an older position of a concurrent transfer
+        assert!(meter.record(at(250), 150, 1000).is_none());
+        assert_eq!(meter.last(), Some(stats));
+        // the final one is always reported, even if too soon
+        let done = meter.record(at(300), 1000, 1000).unwrap();
+        assert_eq!(done.eta, Some(Duration::ZERO));
+        assert!(meter.record(at(310), 1000, 1000).is_none());
+    }
+
+    #[test]
+    fn resumed_transfer_eta() {
+        let base = Instant::now();
+        let at = |secs: u64| base + Duration::from_secs(secs);
+        // resumed at 60%
+        let session = TransferSession::new(600, at(0));
+        assert_eq!(session.eta(600, 1000, at(0)), None);
+        assert_eq!(session.eta(600, 1000, at(5)), None);
+
+        // 100 bytes in 10 seconds, 300 bytes to go
+        assert_eq!(session.session_bytes(700), 100);
+        assert_eq!(session.rate(700, at(10)), 10.0);
+        assert_eq!(
+            session.eta(700, 1000, at(10)),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(session.eta(1000, 1000, at(40)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn windowed_average() {
+        let base = Instant::now();
+        let at = |secs: u64| base + Duration::from_secs(secs);
+        let mut rate = RateWindow::new(Duration::from_secs(3));
+        rate.record(at(0), 0);
+        assert_eq!(rate.rate(), 0.0);
+
+        // a burst at the beginning
+        rate.record(at(1), 1000);
+        assert_eq!(rate.rate(), 1000.0);
+        rate.record(at(2), 1100);
+        rate.record(at(3), 1200);
+        assert_eq!(rate.rate(), 400.0);
+        // the burst slides out of the window
+        rate.record(at(4), 1300);
+        rate.record(at(5), 1400);
+        assert_eq!(rate.rate(), 100.0);
+
+        // restarted
+        rate.record(at(6), 50);
+        assert_eq!(rate.rate(), 0.0);
+        rate.record(at(7), 150);
+        assert_eq!(rate.rate(), 100.0);
+    }
+}
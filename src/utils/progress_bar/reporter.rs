@@ -0,0 +1,247 @@
+//! Reporting progress in a local `0..=100` range, which is mapped into the range allocated
+//! by the parent, so multi-phase operations (such as download → verify → extract → configure)
+//! can be composed without computing the global fractions manually.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use super::Progress;
+
+/// Something that reports the progress of an operation as a percentage in `0..=100`,
+/// no matter which part of the whole progress it actually takes up.
+///
+/// A part of the range can be handed to a sub-operation with [`child`](ProgressReporter::child),
+/// which reports its own `0..=100` as well:
+///
+/// ```
+/// use rim::utils::{Progress, ProgressReporter};
+///
+/// let progress = Progress::noop();
+/// let install = progress.child(60.0);
+/// let download = install.child(50.0);
+/// download.set_percent(50.0)?;
+/// // half of the download is a quarter of the installation
+/// assert_eq!(install.percent(), 25.0);
+/// assert_eq!(progress.position(), 15.0);
+/// # anyhow::Ok(())
+/// ```
+pub trait ProgressReporter: Send + Sync {
+    /// Get the current position, in range of `0..=100`.
+    fn percent(&self) -> f32;
+
+    /// Move the position forward to `percent` (clamped in range `0..=100`), the call is
+    /// ignored if the current position is already at or past it.
+    fn set_percent(&self, percent: f32) -> Result<()>;
+
+    /// Display a message, such as the current status of the operation.
+    fn message(&self, msg: &str) -> Result<()>;
+
+    /// Reserve the next `weight` of the range for a sub-operation, starting from the end of
+    /// the previous reservation, or the current position if that is further.
+    ///
+    /// Returns the start of the reserved range and its actual weight, which might be smaller
+    /// than `weight` if there's not enough range left.
+    fn reserve(&self, weight: f32) -> (f32, f32);
+
+    /// Returns `true` if the operation has been cancelled, see
+    /// [`CancellationToken`](crate::utils::CancellationToken).
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+
+    /// Move the position forward by `delta`.
+    fn advance(&self, delta: f32) -> Result<()> {
+        self.set_percent(self.percent() + delta)
+    }
+
+    /// Move the position to the end of the range.
+    fn finish(&self) -> Result<()> {
+        self.set_percent(100.0)
+    }
+
+    /// Create a sub-reporter that takes up the next `weight` of this reporter's range,
+    /// check [`reserve`](ProgressReporter::reserve) for how the range is allocated.
+    ///
+    /// Children are meant to run one after another, concurrent operations should use
+    /// [`AggregateProgress`](crate::utils::AggregateProgress) instead.
+    fn child(&self, weight: f32) -> ScopedProgress<'_>
+    where
+        Self: Sized,
+    {
+        ScopedProgress::new(self, weight)
+    }
+}
+
+/// A sub-reporter created by [`ProgressReporter::child`], whose `0..=100` is mapped into a
+/// part of the parent's range.
+pub struct ScopedProgress<'p> {
+    parent: &'p dyn ProgressReporter,
+    start: f32,
+    weight: f32,
+    /// The current position, and the end of the last reservation.
+    state: Mutex<(f32, f32)>,
+}
+
+impl<'p> ScopedProgress<'p> {
+    /// Reserve the next `weight` of the `parent`'s range, this is the same as
+    /// [`ProgressReporter::child`], but works with trait objects as well.
+    pub fn new(parent: &'p dyn ProgressReporter, weight: f32) -> Self {
+        let (start, weight) = parent.reserve(weight);
+        Self {
+            parent,
+            start,
+            weight,
+            state: Mutex::new((0.0, 0.0)),
+        }
+    }
+}
+
+impl ProgressReporter for ScopedProgress<'_> {
+    fn percent(&self) -> f32 {
+        self.state.lock().unwrap().0
+    }
+
+    fn set_percent(&self, percent: f32) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let percent = clamp_percent(percent);
+        if percent <= state.0 {
+            return Ok(());
+        }
+        if self.weight > 0.0 {
+            self.parent
+                .set_percent(map_percent(self.start, self.weight, percent))?;
+        }
+        state.0 = percent;
+        Ok(())
+    }
+
+    fn message(&self, msg: &str) -> Result<()> {
+        self.parent.message(msg)
+    }
+
+    fn reserve(&self, weight: f32) -> (f32, f32) {
+        let (pos, reserved) = &mut *self.state.lock().unwrap();
+        reserve_from(reserved, *pos, weight)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.parent.is_cancelled()
+    }
+}
+
+/// The percentage of a [`Progress`] is relative to its bounds (the whole `0..=100` by
+/// default), so that a progress limited to `40..=60` reports `50` once at `50`.
+impl ProgressReporter for Progress<'_> {
+    fn percent(&self) -> f32 {
+        let (min, max) = (self.bounds.0, self.bounds.1);
+        if max > min {
+            clamp_percent((self.position() - min) / (max - min) * 100.0)
+        } else {
+            100.0
+        }
+    }
+
+    fn set_percent(&self, percent: f32) -> Result<()> {
+        let (min, max) = (self.bounds.0, self.bounds.1);
+        self.inc_to(map_percent(min, max - min, clamp_percent(percent)))
+    }
+
+    fn message(&self, msg: &str) -> Result<()> {
+        self.show_msg(msg)
+    }
+
+    fn reserve(&self, weight: f32) -> (f32, f32) {
+        let mut reserved = self.reserved.lock().unwrap();
+        reserve_from(&mut reserved, self.percent(), weight)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        Progress::is_cancelled(self)
+    }
+}
+
+/// The end of the last reservation of a [`Progress`], shared by its clones.
+pub(super) type Reserved = Arc<Mutex<f32>>;
+
+fn reserve_from(reserved: &mut f32, pos: f32, weight: f32) -> (f32, f32) {
+    let start = reserved.max(pos);
+    let end = (start + clamp_percent(weight)).min(100.0);
+    *reserved = end;
+    (start, end - start)
+}
+
+/// Map a `percent` of a range from `start` with `len`.
+fn map_percent(start: f32, len: f32, percent: f32) -> f32 {
+    // NB: use `f64` so nested ranges won't accumulate rounding errors as much
+    (f64::from(start) + f64::from(len) * f64::from(percent) / 100.0) as f32
+}
+
+/// Clamp a percentage in range `0..=100`, treating `NaN` as `0`.
+fn clamp_percent(percent: f32) -> f32 {
+    if percent.is_nan() {
+        0.0
+    } else {
+        percent.clamp(0.0, 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_children_map_into_parent() {
+        let progress = Progress::noop();
+        let download = progress.child(40.0);
+        download.set_percent(50.0).unwrap();
+        assert_eq!(progress.position(), 20.0);
+
+        // never moves backward
+        download.set_percent(10.0).unwrap();
+        assert_eq!(download.percent(), 50.0);
+        download.finish().unwrap();
+        assert_eq!(progress.position(), 40.0);
+
+        let install = progress.child(60.0);
+        let extract = install.child(50.0);
+        let configure = install.child(50.0);
+        extract.set_percent(100.0).unwrap();
+        configure.advance(50.0).unwrap();
+        assert_eq!(install.percent(), 75.0);
+        assert_eq!(progress.position(), 85.0);
+
+        // there's no range left
+        let extra = progress.child(10.0);
+        extra.finish().unwrap();
+        assert_eq!(progress.position(), 85.0);
+        install.finish().unwrap();
+        assert_eq!(progress.position(), 100.0);
+    }
+
+    #[test]
+    fn children_of_bounded_progress() {
+        let progress = Progress::noop().with_bounds(40.0, 60.0);
+        let child = progress.child(50.0);
+        child.finish().unwrap();
+        assert_eq!(progress.position(), 50.0);
+        assert_eq!(progress.percent(), 50.0);
+
+        // starts after the current position, even if it was moved by `inc`
+        progress.inc(Some(5.0)).unwrap();
+        let child = progress.child(10.0);
+        child.finish().unwrap();
+        assert_eq!(progress.position(), 57.0);
+    }
+
+    #[test]
+    fn cancelled_children() {
+        let progress = Progress::noop();
+        let child = progress.child(50.0);
+        let grandchild = child.child(50.0);
+        progress.cancel();
+        assert!(grandchild.is_cancelled());
+        assert!(grandchild.set_percent(10.0).is_err());
+        assert_eq!(progress.position(), 0.0);
+    }
+}
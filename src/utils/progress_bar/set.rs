@@ -0,0 +1,137 @@
+//! A dynamic set of named CLI progress bars that are rendered together.
+
+use std::sync::Mutex;
+
+use anyhow::Result;
+use indexmap::IndexMap;
+use indicatif::{MultiProgress, ProgressBar as CliProgressBar, ProgressDrawTarget};
+
+use super::{styled_bar, CliProgressOpt, Style};
+use crate::core::GlobalOpts;
+
+/// Named progress bars that can be added and removed at any time (from any thread),
+/// such as one bar per downloading component, all of them are rendered under one
+/// [`MultiProgress`].
+#[derive(Debug)]
+pub struct ProgressSet {
+    multi: MultiProgress,
+    bars: Mutex<IndexMap<String, CliProgressBar>>,
+    opt: CliProgressOpt,
+}
+
+impl Default for ProgressSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressSet {
+    /// Create an empty set that renders on `stderr`, which will be hidden in quiet mode.
+    pub fn new() -> Self {
+        let target = if GlobalOpts::get().quiet {
+            ProgressDrawTarget::hidden()
+        } else {
+            ProgressDrawTarget::stderr()
+        };
+        Self::with_draw_target(target)
+    }
+
+    /// Create an empty set that renders on a specific target.
+    pub fn with_draw_target(target: ProgressDrawTarget) -> Self {
+        Self {
+            multi: MultiProgress::with_draw_target(target),
+            bars: Mutex::new(IndexMap::new()),
+            opt: CliProgressOpt::default(),
+        }
+    }
+
+    /// Set the options of the bars that will be added.
+    pub fn with_opt(mut self, opt: CliProgressOpt) -> Self {
+        self.opt = opt;
+        self
+    }
+
+    /// Add a new bar with the given `name` at the bottom, and return it for updating.
+    ///
+    /// If a bar with the same name exists, it will be replaced.
+    pub fn add<S: ToString>(&self, name: S, msg: String, style: Style) -> Result<CliProgressBar> {
+        let bar = self.multi.add(styled_bar(msg, style, self.opt)?);
+        let replaced = self
+            .bars
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), bar.clone());
+        if let Some(old) = replaced {
+            self.clear(&old);
+        }
+        Ok(bar)
+    }
+
+    /// Get the bar with the given `name`.
+    pub fn get(&self, name: &str) -> Option<CliProgressBar> {
+        self.bars.lock().unwrap().get(name).cloned()
+    }
+
+    /// Finish the bar with the given `name`, then clear it from the display.
+    ///
+    /// Returns `false` if there's no such bar.
+    pub fn finish(&self, name: &str) -> bool {
+        let removed = self.bars.lock().unwrap().shift_remove(name);
+        if let Some(bar) = &removed {
+            self.clear(bar);
+        }
+        removed.is_some()
+    }
+
+    /// The names of the bars that are not finished yet, in the order of they were added.
+    pub fn names(&self) -> Vec<String> {
+        self.bars.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Get the underlying `indicatif` [`MultiProgress`] for further customization,
+    /// such as inserting other bars at specific positions.
+    ///
+    /// Note that bars added directly to it are not managed by this set.
+    pub fn multi_progress(&self) -> &MultiProgress {
+        &self.multi
+    }
+
+    /// Print a line above all the bars.
+    pub fn println<S: AsRef<str>>(&self, msg: S) -> Result<()> {
+        self.multi.println(msg)?;
+        Ok(())
+    }
+
+    fn clear(&self, bar: &CliProgressBar) {
+        bar.finish_and_clear();
+        self.multi.remove(bar);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finished_bars_are_removed() {
+        let set = ProgressSet::with_draw_target(ProgressDrawTarget::hidden());
+        let names = ["rustc", "cargo", "rust-std"];
+        let handles = names.map(|name| {
+            let bar = set.add(name, format!("downloading {name}"), Style::Len(10));
+            bar.unwrap()
+        });
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                handles[1].inc(10);
+                assert!(set.finish("cargo"));
+            });
+        });
+
+        assert!(handles[1].is_finished());
+        assert!(!handles[0].is_finished());
+        assert_eq!(set.names(), ["rustc", "rust-std"]);
+        assert!(set.get("cargo").is_none());
+        assert!(!set.finish("cargo"));
+    }
+}
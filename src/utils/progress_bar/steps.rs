@@ -0,0 +1,122 @@
+//! Advancing a [`Progress`] by steps with different weights.
+
+use std::sync::Mutex;
+
+use anyhow::Result;
+
+use super::Progress;
+
+/// Splits the `len` of a [`Progress`] into steps with different weights, such as
+/// installing components with different sizes, so that a huge step takes up more
+/// space than a tiny one.
+pub struct StepProgress<'a> {
+    progress: Progress<'a>,
+    total_weight: f32,
+    /// The completed weight, and the amount of progress that was already reported.
+    state: Mutex<(f32, f32)>,
+}
+
+impl<'a> StepProgress<'a> {
+    /// Create a steps progress with the sum of weights of every step.
+    pub fn new(progress: Progress<'a>, total_weight: f32) -> Self {
+        Self {
+            progress,
+            total_weight: total_weight.max(0.0),
+            state: Mutex::new((0.0, 0.0)),
+        }
+    }
+
+    /// Mark a step with the given `weight` as completed.
+    ///
+    /// Once the completed weights reach the total weight, the progress is increased by
+    /// its whole `len` exactly, regardless of floating point errors.
+    pub fn advance_step(&self, weight: f32) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let done = state.0 + weight.max(0.0);
+        let len = self.progress.len();
+        // tolerate the rounding errors of summing up the weights
+        let target = if done >= self.total_weight * (1.0 - f32::EPSILON * 8.0) {
+            len
+        } else {
+            len * done / self.total_weight
+        };
+        if target > state.1 {
+            self.progress.inc(Some(target - state.1))?;
+            state.1 = target;
+        }
+        state.0 = done;
+        Ok(())
+    }
+
+    /// Get a reference to the inner progress.
+    pub fn progress(&self) -> &Progress<'a> {
+        &self.progress
+    }
+}
+
+/// An iterator that advances a [`Progress`] by one step for each item yielded,
+/// created by [`Progress::wrap_iter`].
+pub struct ProgressIter<'a, I> {
+    iter: I,
+    steps: StepProgress<'a>,
+}
+
+impl<'a, I: ExactSizeIterator> ProgressIter<'a, I> {
+    pub(super) fn new(iter: I, progress: Progress<'a>) -> Self {
+        let len = iter.len();
+        Self {
+            iter,
+            steps: StepProgress::new(progress, len as f32),
+        }
+    }
+}
+
+impl<I: Iterator> Iterator for ProgressIter<'_, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.iter.next();
+        // NB: iterators cannot return errors, use `CallbackErrorPolicy` to deal with them.
+        let _ = self
+            .steps
+            .advance_step(if next.is_some() { 1.0 } else { 0.0 });
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I: ExactSizeIterator> ExactSizeIterator for ProgressIter<'_, I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unequal_weights() {
+        let steps = StepProgress::new(Progress::noop().with_len(60.0), 1.1 + 0.2 + 3.7);
+        steps.advance_step(1.1).unwrap();
+        assert!((steps.progress().position() - 13.2).abs() < 1e-4);
+        steps.advance_step(0.2).unwrap();
+        assert!((steps.progress().position() - 15.6).abs() < 1e-4);
+        steps.advance_step(3.7).unwrap();
+        assert_eq!(steps.progress().position(), 60.0);
+    }
+
+    #[test]
+    fn wrapped_iterator_advances_to_completion() {
+        let progress = Progress::noop().with_len(30.0);
+        let mut iter = progress.wrap_iter(["rustc", "cargo", "clippy"]);
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some("rustc"));
+        assert_eq!(progress.position(), 10.0);
+        assert_eq!(iter.by_ref().count(), 2);
+        assert_eq!(progress.position(), 30.0);
+
+        let progress = Progress::noop().with_len(30.0);
+        assert_eq!(progress.wrap_iter(Vec::<u8>::new()).count(), 0);
+        assert_eq!(progress.position(), 30.0);
+    }
+}
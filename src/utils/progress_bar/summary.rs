@@ -0,0 +1,107 @@
+//! The compact output mode, where each progress prints exactly one line once it's finished,
+//! instead of an animated bar.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use indicatif::{HumanBytes, ProgressBar as CliProgressBar, ProgressDrawTarget};
+
+use super::{CliBar, Style};
+
+/// Whether the CLI progress bars are replaced by summary lines.
+static SUMMARY_MODE: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn set_enabled(enabled: bool) {
+    SUMMARY_MODE.store(enabled, Ordering::Relaxed);
+}
+
+pub(super) fn is_enabled() -> bool {
+    SUMMARY_MODE.load(Ordering::Relaxed)
+}
+
+/// Create a hidden bar that only keeps track of the stats, which are printed when stopped.
+pub(super) fn hidden_bar(msg: String, style: Style) -> CliBar {
+    let pb = match style {
+        Style::Bytes(total) | Style::Len(total) => {
+            CliProgressBar::with_draw_target(Some(total), ProgressDrawTarget::hidden())
+        }
+        Style::Spinner { .. } => CliProgressBar::hidden(),
+    };
+    pb.set_message(msg);
+    CliBar::new(pb, style)
+}
+
+/// Finish a bar that was created by [`hidden_bar`], and get its summary line.
+pub(super) fn finish(pb: &CliBar, msg: &str) -> String {
+    pb.finish();
+    let bytes = pb.counts_bytes().then_some(pb.position());
+    summary_line(
+        &format!("{}{msg}", pb.prefix()),
+        bytes,
+        pb.elapsed(),
+        prefers_ascii(),
+    )
+}
+
+/// Format a line like `✓ downloaded rust-std (120.00 MiB, 8s)`, `bytes` is omitted if
+/// the progress was not counting bytes.
+fn summary_line(msg: &str, bytes: Option<u64>, elapsed: Duration, ascii: bool) -> String {
+    let mark = if ascii { "OK" } else { "✓" };
+    let elapsed = compact_duration(elapsed);
+    match bytes {
+        Some(b) => format!("{mark} {msg} ({}, {elapsed})", HumanBytes(b)),
+        None => format!("{mark} {msg} ({elapsed})"),
+    }
+}
+
+fn compact_duration(dur: Duration) -> String {
+    let secs = dur.as_secs();
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m{}s", secs / 60, secs % 60),
+        _ => format!("{}h{}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Whether non-ASCII symbols should be avoided, because the output is meant to be plain
+/// (`NO_COLOR` is set) or cannot render unicode characters (`TERM=dumb`).
+fn prefers_ascii() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|val| !val.is_empty()) || super::is_dumb_term()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completed_phase_summary() {
+        let line = summary_line(
+            "downloaded rust-std",
+            Some(120 * 1024 * 1024),
+            Duration::from_millis(8400),
+            false,
+        );
+        assert_eq!(line, "✓ downloaded rust-std (120.00 MiB, 8s)");
+
+        let line = summary_line("extracted", None, Duration::from_secs(75), true);
+        assert_eq!(line, "OK extracted (1m15s)");
+
+        let pb = hidden_bar("downloading".into(), Style::Bytes(2048));
+        pb.inc(2048);
+        assert!(finish(&pb, "downloaded").contains("downloaded (2.00 KiB, 0s)"));
+        assert!(pb.is_finished());
+
+        // changing the prefix directly, such as through `CliProgress::active_bar`
+        let pb = hidden_bar("downloading".into(), Style::Bytes(10));
+        pb.set_prefix("rust-std: ");
+        assert!(finish(&pb, "downloaded").contains("rust-std: downloaded (10 B, 0s)"));
+
+        let pb = hidden_bar("extracting".into(), Style::Len(10));
+        pb.inc(10);
+        pb.mark_bytes();
+        // replacing the style directly doesn't affect the summary
+        pb.set_style(indicatif::ProgressStyle::default_bar());
+        pb.set_prefix("rust-std: ");
+        assert!(finish(&pb, "extracted").contains("rust-std: extracted (10 B, 0s)"));
+    }
+}
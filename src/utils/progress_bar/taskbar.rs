@@ -0,0 +1,215 @@
+//! Mirroring the progress of CLI bars to the terminal tab or the taskbar button.
+//!
+//! On Windows this uses the taskbar API of the console window, elsewhere it emits the
+//! `OSC 9;4` escape sequences, which are understood by many modern terminals.
+
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use indicatif::ProgressBar as CliProgressBar;
+
+/// Whether the progress should be mirrored to the terminal/taskbar.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// The last reported percentage, or [`NOT_REPORTED`], to avoid emitting the same state
+/// on every increment.
+static LAST_REPORTED: AtomicU8 = AtomicU8::new(NOT_REPORTED);
+const NOT_REPORTED: u8 = u8::MAX;
+const INDETERMINATE: u8 = u8::MAX - 1;
+
+/// The progress state shown on the terminal tab or taskbar button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TaskbarState {
+    /// Remove the progress indicator.
+    Clear,
+    /// Show the completed percentage, in range `0..=100`.
+    Percent(u8),
+    /// Show a busy indicator, such as for spinners.
+    Indeterminate,
+}
+
+pub(super) fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Report the current state of `pb`, bars without a length are shown as indeterminate.
+pub(super) fn report(pb: &CliProgressBar) {
+    let state = match pb.length() {
+        Some(0) => TaskbarState::Percent(100),
+        Some(len) => TaskbarState::Percent((pb.position().min(len) * 100 / len) as u8),
+        None => TaskbarState::Indeterminate,
+    };
+    emit(state);
+}
+
+/// Remove the progress indicator, such as when a bar was stopped.
+pub(super) fn clear() {
+    emit(TaskbarState::Clear);
+}
+
+fn emit(state: TaskbarState) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let code = match state {
+        TaskbarState::Clear => NOT_REPORTED,
+        TaskbarState::Percent(p) => p,
+        TaskbarState::Indeterminate => INDETERMINATE,
+    };
+    if LAST_REPORTED.swap(code, Ordering::Relaxed) == code {
+        return;
+    }
+    set_state(state);
+}
+
+#[cfg(windows)]
+fn set_state(state: TaskbarState) {
+    windows_taskbar::set_state(state);
+}
+
+#[cfg(not(windows))]
+fn set_state(state: TaskbarState) {
+    use std::io::Write;
+
+    // writing these to a file or pipe would only pollute the output
+    let term = console::Term::stderr();
+    if term.is_term() {
+        let mut stderr = std::io::stderr();
+        let _ = stderr
+            .write_all(osc_sequence(state).as_bytes())
+            .and_then(|_| stderr.flush());
+    }
+}
+
+/// The `OSC 9;4` sequence that sets the progress state, i.e. `ESC ] 9 ; 4 ; st ; pr ST`,
+/// where `st` is the state (`0` = clear, `1` = normal, `3` = indeterminate),
+/// and `pr` is the percentage.
+#[cfg_attr(windows, allow(dead_code))]
+fn osc_sequence(state: TaskbarState) -> String {
+    let (st, pr) = match state {
+        TaskbarState::Clear => (0, 0),
+        TaskbarState::Percent(p) => (1, p.min(100)),
+        TaskbarState::Indeterminate => (3, 0),
+    };
+    format!("\x1b]9;4;{st};{pr}\x1b\\")
+}
+
+#[cfg(windows)]
+mod windows_taskbar {
+    use std::ptr;
+
+    use winapi::shared::winerror::SUCCEEDED;
+    use winapi::shared::wtypesbase::CLSCTX_INPROC_SERVER;
+    use winapi::um::combaseapi::{CoCreateInstance, CoInitializeEx, CoUninitialize};
+    use winapi::um::objbase::COINIT_APARTMENTTHREADED;
+    use winapi::um::shobjidl_core::{
+        CLSID_TaskbarList, ITaskbarList3, TBPF_INDETERMINATE, TBPF_NOPROGRESS, TBPF_NORMAL,
+    };
+    use winapi::um::wincon::GetConsoleWindow;
+    use winapi::Interface;
+
+    use super::TaskbarState;
+
+    thread_local! {
+        // NB: COM objects can only be used by the thread that created them,
+        // and the progress could be updated from any thread.
+        static TASKBAR: Option<TaskbarList> = TaskbarList::new();
+    }
+
+    /// The taskbar interface created by the current thread, which is released
+    /// (and COM is uninitialized) when the thread exits.
+    struct TaskbarList {
+        taskbar: *mut ITaskbarList3,
+        /// Whether `CoInitializeEx` succeeded, which needs a matching `CoUninitialize`.
+        com_initialized: bool,
+    }
+
+    impl TaskbarList {
+        fn new() -> Option<Self> {
+            let mut list = Self {
+                taskbar: ptr::null_mut(),
+                com_initialized: false,
+            };
+            // SAFETY: the out pointer is valid, and the interface is only used when
+            // both the creation and initialization succeeded, otherwise it's cleaned up
+            // by dropping `list`.
+            unsafe {
+                // this fails if COM was initialized in a different mode on this thread,
+                // which is fine, as it's initialized anyway.
+                list.com_initialized =
+                    SUCCEEDED(CoInitializeEx(ptr::null_mut(), COINIT_APARTMENTTHREADED));
+                let hr = CoCreateInstance(
+                    &CLSID_TaskbarList,
+                    ptr::null_mut(),
+                    CLSCTX_INPROC_SERVER,
+                    &ITaskbarList3::uuidof(),
+                    &mut list.taskbar as *mut *mut ITaskbarList3 as *mut _,
+                );
+                (SUCCEEDED(hr) && !list.taskbar.is_null() && SUCCEEDED((*list.taskbar).HrInit()))
+                    .then_some(list)
+            }
+        }
+    }
+
+    impl Drop for TaskbarList {
+        fn drop(&mut self) {
+            // SAFETY: the interface was created by this thread, and is not used after this.
+            unsafe {
+                if !self.taskbar.is_null() {
+                    (*self.taskbar).Release();
+                }
+                if self.com_initialized {
+                    CoUninitialize();
+                }
+            }
+        }
+    }
+
+    pub(super) fn set_state(state: TaskbarState) {
+        // SAFETY: simply returns null if there's no console window.
+        let hwnd = unsafe { GetConsoleWindow() };
+        if hwnd.is_null() {
+            return;
+        }
+        TASKBAR.with(|list| {
+            let Some(TaskbarList { taskbar, .. }) = *list else {
+                return;
+            };
+            // SAFETY: the interface was successfully created by this thread.
+            unsafe {
+                match state {
+                    TaskbarState::Clear => {
+                        (*taskbar).SetProgressState(hwnd, TBPF_NOPROGRESS);
+                    }
+                    TaskbarState::Percent(p) => {
+                        (*taskbar).SetProgressState(hwnd, TBPF_NORMAL);
+                        (*taskbar).SetProgressValue(hwnd, u64::from(p.min(100)), 100);
+                    }
+                    TaskbarState::Indeterminate => {
+                        (*taskbar).SetProgressState(hwnd, TBPF_INDETERMINATE);
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn osc_progress_sequences() {
+        assert_eq!(
+            osc_sequence(TaskbarState::Percent(42)),
+            "\x1b]9;4;1;42\x1b\\"
+        );
+        assert_eq!(
+            osc_sequence(TaskbarState::Percent(250)),
+            "\x1b]9;4;1;100\x1b\\"
+        );
+        assert_eq!(
+            osc_sequence(TaskbarState::Indeterminate),
+            "\x1b]9;4;3;0\x1b\\"
+        );
+        assert_eq!(osc_sequence(TaskbarState::Clear), "\x1b]9;4;0;0\x1b\\");
+    }
+}
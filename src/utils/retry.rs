@@ -0,0 +1,249 @@
+//! Retrying operations that failed with temporary errors, such as network timeouts.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use super::{CancellationToken, DownloadError};
+use crate::setter;
+
+/// The default number of attempts of [`RetryPolicy`], including the first one.
+pub const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
+
+/// The policy set by [`RetryPolicy::set_global`].
+static GLOBAL_POLICY: Mutex<Option<RetryPolicy>> = Mutex::new(None);
+
+/// How many times, and how long to wait before retrying an operation that failed with a
+/// temporary error, check [`DownloadError::is_temporary`] for which errors are retried.
+///
+/// The delay doubles after each retry (up to [`max_delay`](RetryPolicy::max_delay)), and is
+/// randomized by default, so that clients failed at the same time won't retry together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one, so `1` means no retry.
+    pub max_attempts: u32,
+    /// The delay before the first retry.
+    pub initial_delay: Duration,
+    /// The upper limit of the delays.
+    pub max_delay: Duration,
+    /// Randomize each delay between its half and itself.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_RETRY_ATTEMPTS,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+/// A retry that is about to happen, which is passed to the callback of [`RetryPolicy::run`].
+#[derive(Debug)]
+pub struct Retry<'e> {
+    /// The number of the next attempt, starting from `2`.
+    pub attempt: u32,
+    pub max_attempts: u32,
+    /// How long it waits before the next attempt.
+    pub delay: Duration,
+    /// The error of the previous attempt.
+    pub error: &'e anyhow::Error,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    setter!(with_max_attempts(self.max_attempts, u32));
+    setter!(with_initial_delay(self.initial_delay, Duration));
+    setter!(with_max_delay(self.max_delay, Duration));
+    setter!(jitter(self.jitter, bool));
+
+    /// Set the policy used by [`DownloadOpt::new`](super::DownloadOpt::new), such as the one
+    /// configured by command line options.
+    pub fn set_global(self) {
+        *GLOBAL_POLICY.lock().unwrap() = Some(self);
+    }
+
+    /// Get the policy set by [`set_global`](RetryPolicy::set_global), or the default one.
+    pub fn global() -> Self {
+        GLOBAL_POLICY.lock().unwrap().unwrap_or_default()
+    }
+
+    /// Get the delay before the `retry`-th retry (starting from `1`).
+    pub fn delay_for(&self, retry: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self
+            .initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |d| d.min(self.max_delay));
+        if !self.jitter {
+            return delay;
+        }
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(retry);
+        let ratio = 0.5 + (hasher.finish() % 1001) as f64 / 2000.0;
+        delay.mul_f64(ratio)
+    }
+
+    /// Call `op` until it succeeds, fails with a non-temporary error, or runs out of attempts.
+    ///
+    /// `on_retry` is called before waiting for each retry, such as to tell the user why it is
+    /// stalling. The waiting stops with [`Cancelled`](super::Cancelled) once any of the
+    /// `cancel` tokens is cancelled.
+    pub fn run<T, F, R>(
+        &self,
+        cancel: &[&CancellationToken],
+        mut op: F,
+        mut on_retry: R,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+        R: FnMut(&Retry<'_>) -> Result<()>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op() {
+                Err(error)
+                    if attempt < self.max_attempts && DownloadError::is_temporary(&error) =>
+                {
+                    let delay = self.delay_for(attempt);
+                    attempt += 1;
+                    on_retry(&Retry {
+                        attempt,
+                        max_attempts: self.max_attempts,
+                        delay,
+                        error: &error,
+                    })?;
+                    sleep_unless_cancelled(delay, cancel)?;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+fn sleep_unless_cancelled(delay: Duration, cancel: &[&CancellationToken]) -> Result<()> {
+    let deadline = Instant::now() + delay;
+    loop {
+        for token in cancel {
+            token.check()?;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(());
+        }
+        std::thread::sleep((deadline - now).min(Duration::from_millis(50)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Cancelled;
+
+    fn temporary_error() -> anyhow::Error {
+        DownloadError::Status {
+            url: "https://example.com".into(),
+            status: 503,
+        }
+        .into()
+    }
+
+    #[test]
+    fn exponential_delays() {
+        let policy = RetryPolicy::new()
+            .with_initial_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(500))
+            .jitter(false);
+        let delays = (1..=5).map(|r| policy.delay_for(r)).collect::<Vec<_>>();
+        assert_eq!(delays, [100, 200, 400, 500, 500].map(Duration::from_millis));
+        assert_eq!(policy.delay_for(u32::MAX), Duration::from_millis(500));
+
+        let policy = policy.jitter(true);
+        for retry in 1..=5 {
+            let delay = policy.delay_for(retry);
+            let max = policy.jitter(false).delay_for(retry);
+            assert!(delay >= max / 2 && delay <= max, "{delay:?} of {max:?}");
+        }
+    }
+
+    #[test]
+    fn retries_temporary_errors_only() {
+        let policy = RetryPolicy::new()
+            .with_initial_delay(Duration::from_millis(1))
+            .with_max_attempts(3);
+        let mut calls = 0;
+        let mut retries = vec![];
+        let res = policy.run(
+            &[],
+            || {
+                calls += 1;
+                if calls < 3 {
+                    Err(temporary_error())
+                } else {
+                    Ok(calls)
+                }
+            },
+            |retry| {
+                retries.push((retry.attempt, retry.max_attempts));
+                Ok(())
+            },
+        );
+        assert_eq!(res.unwrap(), 3);
+        assert_eq!(retries, [(2, 3), (3, 3)]);
+
+        // out of attempts
+        let mut calls = 0;
+        let res: Result<()> = policy.run(
+            &[],
+            || {
+                calls += 1;
+                Err(temporary_error())
+            },
+            |_| Ok(()),
+        );
+        assert!(res.is_err());
+        assert_eq!(calls, 3);
+
+        // not temporary
+        let mut calls = 0;
+        let res: Result<()> = policy.run(
+            &[],
+            || {
+                calls += 1;
+                anyhow::bail!("not found")
+            },
+            |_| Ok(()),
+        );
+        assert!(res.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn cancelled_while_waiting() {
+        let policy = RetryPolicy::new().with_initial_delay(Duration::from_secs(60));
+        let token = CancellationToken::new();
+        let res: Result<()> = policy.run(
+            &[&token],
+            || Err(temporary_error()),
+            |_| {
+                token.cancel();
+                Ok(())
+            },
+        );
+        assert!(res.unwrap_err().is::<Cancelled>());
+    }
+}
@@ -0,0 +1,192 @@
+//! Limiting the bandwidth of downloads, so they won't saturate a shared network.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+
+/// The limiter set by [`RateLimiter::set_global`].
+static GLOBAL_LIMITER: Mutex<Option<Arc<RateLimiter>>> = Mutex::new(None);
+
+/// Limits the number of bytes transferred per second, which is shared by every download
+/// using it (such as the concurrent ones), so the limit applies to their total bandwidth.
+///
+/// This is a token bucket that holds at most one [`chunk_size`](RateLimiter::chunk_size)
+/// of bytes, so a transfer can never burst much faster than the limit.
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// The number of bytes that can be transferred without waiting, which is negative
+    /// when the reserved bytes are not yet paid off.
+    available: f64,
+    updated: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// Create a limiter of `bytes_per_sec`, which is at least `1`.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec.max(1);
+        let limiter = Self {
+            bytes_per_sec,
+            bucket: Mutex::new(Bucket {
+                available: 0.0,
+                updated: None,
+            }),
+        };
+        limiter.bucket.lock().unwrap().available = limiter.chunk_size() as f64;
+        limiter
+    }
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    /// Limit the downloads created by [`DownloadOpt::new`](super::DownloadOpt::new)
+    /// afterwards, such as by the `--limit-rate` option.
+    pub fn set_global(self) {
+        *GLOBAL_LIMITER.lock().unwrap() = Some(Arc::new(self));
+    }
+
+    /// Get the limiter set by [`set_global`](RateLimiter::set_global), if there is one.
+    pub fn global() -> Option<Arc<Self>> {
+        GLOBAL_LIMITER.lock().unwrap().clone()
+    }
+
+    /// The maximum number of bytes to transfer at once, which is about a tenth of a second
+    /// worth of bytes, so that the waits are short, and the transfer looks steady.
+    pub fn chunk_size(&self) -> usize {
+        (self.bytes_per_sec / 10).clamp(1024, 65535) as usize
+    }
+
+    /// Take `bytes` from the bucket at `now`, returns how long to wait before transferring
+    /// more, which is zero if there were enough bytes in the bucket.
+    pub fn reserve(&self, bytes: u64, now: Instant) -> Duration {
+        let rate = self.bytes_per_sec as f64;
+        let mut bucket = self.bucket.lock().unwrap();
+        if let Some(updated) = bucket.updated {
+            let refilled = now.saturating_duration_since(updated).as_secs_f64() * rate;
+            bucket.available = (bucket.available + refilled).min(self.chunk_size() as f64);
+        }
+        bucket.updated = Some(now);
+        bucket.available -= bytes as f64;
+        if bucket.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.available / rate)
+        }
+    }
+
+    /// Take `bytes` from the bucket, and wait until they are paid off.
+    pub fn throttle(&self, bytes: u64) {
+        let wait = self.reserve(bytes, Instant::now());
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// A reader that is slowed down by a [`RateLimiter`], or just reads the `inner` one if
+/// there's no limiter.
+pub(super) struct Throttled<'l, R> {
+    inner: R,
+    limiter: Option<&'l RateLimiter>,
+}
+
+impl<'l, R> Throttled<'l, R> {
+    pub(super) fn new(inner: R, limiter: Option<&'l RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+impl<R: io::Read> io::Read for Throttled<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(limiter) = self.limiter else {
+            return self.inner.read(buf);
+        };
+        let len = buf.len().min(limiter.chunk_size());
+        let n = self.inner.read(&mut buf[..len])?;
+        limiter.throttle(n as u64);
+        Ok(n)
+    }
+}
+
+/// Parse a transfer rate in bytes per second, with an optional binary unit and `/s` postfix,
+/// such as `500K`, `5MiB/s` or `1G/s`.
+pub fn parse_rate(input: &str) -> Result<u64> {
+    let trimmed = input.trim();
+    let size = match trimmed.len().checked_sub(2) {
+        Some(idx) if trimmed.is_char_boundary(idx) && trimmed[idx..].eq_ignore_ascii_case("/s") => {
+            &trimmed[..idx]
+        }
+        _ => trimmed,
+    };
+    let rate = super::parse_size(size)?;
+    if rate == 0 {
+        bail!("invalid rate '{input}', expecting a positive number of bytes per second");
+    }
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn rates() {
+        assert_eq!(parse_rate("5MiB/s").unwrap(), 5 << 20);
+        assert_eq!(parse_rate("500k").unwrap(), 500 << 10);
+        assert_eq!(parse_rate("1024/S").unwrap(), 1024);
+        assert_eq!(parse_rate(" 2 GB/s ").unwrap(), 2 << 30);
+        assert!(parse_rate("0").is_err());
+        assert!(parse_rate("/s").is_err());
+        assert!(parse_rate("5M/min").is_err());
+    }
+
+    #[test]
+    fn waits_for_the_reserved_bytes() {
+        let limiter = RateLimiter::new(10240);
+        assert_eq!(limiter.chunk_size(), 1024);
+        let start = Instant::now();
+        let at = |millis: u64| start + Duration::from_millis(millis);
+
+        // a burst of one chunk is allowed
+        assert_eq!(limiter.reserve(1024, at(0)), Duration::ZERO);
+        assert_eq!(limiter.reserve(1024, at(0)), Duration::from_millis(100));
+        // concurrent transfers wait for each other
+        assert_eq!(limiter.reserve(1024, at(0)), Duration::from_millis(200));
+        // paid off, but the bucket never holds more than a chunk
+        assert_eq!(limiter.reserve(512, at(10_000)), Duration::ZERO);
+        assert_eq!(limiter.reserve(1024, at(10_000)), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn throttled_reads() {
+        let limiter = RateLimiter::new(20 * 1024);
+        let data = vec![0_u8; 8 * 1024];
+        let mut reader = Throttled::new(data.as_slice(), Some(&limiter));
+        let mut buf = vec![0_u8; 65535];
+        // each read is limited to a chunk
+        assert_eq!(reader.read(&mut buf).unwrap(), 2048);
+
+        let start = Instant::now();
+        let mut rest = vec![];
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest.len(), 6 * 1024);
+        // 6 KiB at 20 KiB/s
+        assert!(
+            start.elapsed() >= Duration::from_millis(250),
+            "{:?}",
+            start.elapsed()
+        );
+
+        let mut unlimited = Throttled::new(data.as_slice(), None);
+        assert_eq!(unlimited.read(&mut buf).unwrap(), 8 * 1024);
+    }
+}
@@ -0,0 +1,216 @@
+//! Verifying the integrity of downloaded files, by their SHA-256 checksums
+//! or [minisign](https://jedisct1.github.io/minisign/) signatures.
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use blake2::{Blake2b512, Digest};
+
+use super::download::sha256_of_prefix;
+use super::progress_bar::{CliProgress, ProgressMsg, Style};
+
+/// Whether the verifications are skipped, which is set by `--insecure-skip-verify`.
+static SKIP_VERIFY: AtomicBool = AtomicBool::new(false);
+
+/// Skip every checksum and signature verification afterwards, such as for internal mirrors
+/// that repackage the files, default is `false`.
+///
+/// The skipped verifications are reported as warnings.
+pub fn set_skip_verify(skip: bool) {
+    SKIP_VERIFY.store(skip, Ordering::Relaxed);
+}
+
+/// Check if the verifications are skipped by [`set_skip_verify`].
+pub fn is_verify_skipped() -> bool {
+    SKIP_VERIFY.load(Ordering::Relaxed)
+}
+
+/// Verify the SHA-256 checksum of the file at `path` against the `expected` hex string,
+/// with a CLI progress bar, this does nothing but warning if verifications are skipped.
+pub fn verify_sha256(path: &Path, expected: &str) -> Result<()> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+    if is_verify_skipped() {
+        warn!("{}", t!("verify_skipped", name = name));
+        return Ok(());
+    }
+
+    let mut file =
+        fs::File::open(path).with_context(|| format!("unable to open '{}'", path.display()))?;
+    let file_len = file.metadata()?.len();
+    let cli = CliProgress::new();
+    let digest = cli.scoped(
+        Style::Bytes(file_len),
+        ProgressMsg::Verifying(&name).to_string(),
+        ProgressMsg::Verified(&name).to_string(),
+        |bar| {
+            sha256_of_prefix(&mut file, file_len, |hashed| {
+                (cli.update)(bar, Some(hashed))
+            })
+        },
+    )?;
+    if !digest.eq_ignore_ascii_case(expected) {
+        bail!(
+            "checksum mismatch of '{}', expecting '{expected}' but got '{digest}'",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// The algorithm of a minisign signature.
+const LEGACY_ALG: &[u8; 2] = b"Ed";
+/// The algorithm of a minisign signature of the BLAKE2b-512 hash of the file,
+/// which is the default since minisign 0.10.
+const PREHASHED_ALG: &[u8; 2] = b"ED";
+
+/// Verify the minisign `signature` (the content of a `.minisig` file) of `data`, using the
+/// `public_key`, which can be either the content of a `.pub` file or just its key line.
+///
+/// Both the signature of the data and the global signature of the trusted comment are
+/// verified, as `minisign -V` does.
+pub fn verify_minisign(data: &[u8], signature: &str, public_key: &str) -> Result<()> {
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let decode = |line: &str, what: &str| {
+        base64
+            .decode(line.trim())
+            .map_err(|e| anyhow!("invalid {what}: {e}"))
+    };
+
+    let key_line = public_key
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+        .ok_or_else(|| anyhow!("empty minisign public key"))?;
+    let key = decode(key_line, "minisign public key")?;
+    if key.len() != 42 || &key[..2] != LEGACY_ALG {
+        bail!("unsupported minisign public key");
+    }
+    let (key_id, key) = (&key[2..10], &key[10..]);
+
+    let mut lines = signature
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("untrusted comment:"));
+    let mut next_line = |what: &str| {
+        lines
+            .next()
+            .ok_or_else(|| anyhow!("incomplete minisign signature, missing the {what}"))
+    };
+    let sig = decode(next_line("signature")?, "minisign signature")?;
+    let trusted_comment = next_line("trusted comment")?
+        .strip_prefix("trusted comment: ")
+        .ok_or_else(|| anyhow!("invalid trusted comment of minisign signature"))?;
+    let global_sig = decode(next_line("global signature")?, "minisign global signature")?;
+    if sig.len() != 74 || global_sig.len() != 64 {
+        bail!("invalid length of minisign signature");
+    }
+    let (alg, sig_key_id, sig) = (&sig[..2], &sig[2..10], &sig[10..]);
+    if sig_key_id != key_id {
+        bail!("the minisign signature was not signed by the given public key");
+    }
+
+    let key = ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, key);
+    let verified = if alg == PREHASHED_ALG {
+        key.verify(&blake2b_512(data), sig)
+    } else if alg == LEGACY_ALG {
+        key.verify(data, sig)
+    } else {
+        bail!("unsupported algorithm of minisign signature");
+    };
+    verified.map_err(|_| anyhow!("signature verification failed"))?;
+
+    let global_data = [sig, trusted_comment.as_bytes()].concat();
+    key.verify(&global_data, &global_sig)
+        .map_err(|_| anyhow!("signature verification of the trusted comment failed"))
+}
+
+/// Compute the BLAKE2b-512 hash of `data`, which is needed for the prehashed
+/// minisign signatures.
+fn blake2b_512(data: &[u8]) -> [u8; 64] {
+    Blake2b512::digest(data).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn blake2b_test_vectors() {
+        assert_eq!(
+            hex(&blake2b_512(b"abc")),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+             7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+        assert_eq!(
+            hex(&blake2b_512(b"")),
+            "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419\
+             d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce"
+        );
+    }
+
+    /// Sign `data` like `minisign -S`, returns the public key and the signature files.
+    fn minisign(data: &[u8], prehashed: bool) -> (String, String) {
+        let base64 = base64::engine::general_purpose::STANDARD;
+        let pair = Ed25519KeyPair::from_seed_unchecked(&[42; 32]).unwrap();
+        let key_id = [1, 2, 3, 4, 5, 6, 7, 8];
+        let public_key = [&LEGACY_ALG[..], &key_id, pair.public_key().as_ref()].concat();
+
+        let (alg, sig) = if prehashed {
+            (PREHASHED_ALG, pair.sign(&blake2b_512(data)))
+        } else {
+            (LEGACY_ALG, pair.sign(data))
+        };
+        let trusted_comment = "timestamp:1700000000\tfile:toolset-manifest.toml";
+        let global_sig = pair.sign(&[sig.as_ref(), trusted_comment.as_bytes()].concat());
+        let signature = format!(
+            "untrusted comment: signature from minisign secret key\n{}\ntrusted comment: {trusted_comment}\n{}\n",
+            base64.encode([&alg[..], &key_id, sig.as_ref()].concat()),
+            base64.encode(global_sig.as_ref()),
+        );
+        (
+            format!(
+                "untrusted comment: minisign public key\n{}\n",
+                base64.encode(public_key)
+            ),
+            signature,
+        )
+    }
+
+    #[test]
+    fn minisign_signatures() {
+        let data = b"[rust]\nversion = \"1.80.0\"\n";
+        for prehashed in [true, false] {
+            let (public_key, signature) = minisign(data, prehashed);
+            verify_minisign(data, &signature, &public_key).unwrap();
+            // the key line only
+            verify_minisign(data, &signature, public_key.lines().nth(1).unwrap()).unwrap();
+
+            assert!(verify_minisign(b"tampered", &signature, &public_key).is_err());
+            let forged_comment = signature.replace("timestamp:1700000000", "timestamp:0");
+            assert!(verify_minisign(data, &forged_comment, &public_key).is_err());
+        }
+        assert!(verify_minisign(data, "untrusted comment: x\n", "garbage").is_err());
+    }
+
+    #[test]
+    fn sha256_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        fs::write(&path, "hello").unwrap();
+        let expected = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        verify_sha256(&path, expected).unwrap();
+        verify_sha256(&path, &expected.to_uppercase()).unwrap();
+        assert!(verify_sha256(&path, &"0".repeat(64)).is_err());
+    }
+}
@@ -149,3 +149,370 @@ fn extracting_zip_with_prefix() {
     let temp_dir = extract_to_temp("zip_with_prefixes.zip", true);
     assert_extracted_with_prefixes(&temp_dir.0);
 }
+
+#[test]
+fn decompress_gzip_with_progress() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("data")
+        .join("simple_gz.tar.gz");
+    let compressed = std::fs::read(&path).unwrap();
+
+    let positions = std::sync::Mutex::new(vec![]);
+    let pos_cb = |pos: f32| {
+        positions.lock().unwrap().push(pos);
+        Ok(())
+    };
+    let progress = utils::Progress::new(&pos_cb)
+        .with_len(40.0)
+        .with_byte_total(compressed.len() as u64);
+    let decompressed = utils::decompress_with_progress(compressed.as_slice(), &progress).unwrap();
+
+    assert_eq!(decompressed.len(), 10240);
+    let positions = positions.lock().unwrap();
+    assert!(positions.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(progress.position(), 40.0);
+}
+
+#[test]
+fn decompress_unknown_format() {
+    let progress = utils::Progress::noop();
+    assert!(utils::decompress_with_progress(&b"plain text"[..], &progress).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn links_are_extracted_and_copied_as_links() {
+    let (extracted, _dir) = extract_to_temp("tar_with_links.tar.gz", true);
+    let is_symlink = |path: PathBuf| path.symlink_metadata().unwrap().file_type().is_symlink();
+    assert!(is_symlink(extracted.join("lib-link")));
+    assert!(is_symlink(extracted.join("lib").join("libstd-link.so")));
+    assert!(extracted.join("lib-link").join("libstd.so").is_file());
+    assert_eq!(
+        std::fs::read(extracted.join("bin").join("tool-hard")).unwrap(),
+        b"#!/bin/sh\n"
+    );
+
+    // the links and the second name of the hard linked file are not counted.
+    assert_eq!(utils::disk_size(&extracted).unwrap(), 4096 + 10);
+
+    let max_pos = std::sync::Mutex::new(0.0_f32);
+    let pos_cb = |pos: f32| {
+        let mut max = max_pos.lock().unwrap();
+        *max = max.max(pos);
+        Ok(())
+    };
+    let progress = utils::Progress::new(&pos_cb).with_len(50.0);
+    let copied = extracted.with_file_name("copied");
+    utils::copy_as_with_progress(&extracted, &copied, &progress).unwrap();
+
+    assert_eq!(progress.position(), 50.0);
+    assert_eq!(*max_pos.lock().unwrap(), 50.0);
+    assert!(is_symlink(copied.join("lib-link")));
+    assert!(is_symlink(copied.join("lib").join("libstd-link.so")));
+    assert_eq!(utils::disk_size(&copied).unwrap(), 4096 + 10);
+}
+
+/// Create a zip archive with `dirs` directories, each containing `files` files of
+/// `file_size` bytes, returns the path to the archive and its expected contents.
+fn make_zip(
+    root: &Path,
+    dirs: usize,
+    files: usize,
+    file_size: usize,
+) -> (PathBuf, Vec<(PathBuf, Vec<u8>)>) {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    let path = root.join("generated.zip");
+    let mut writer = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+    let mut expected = vec![];
+    for d in 0..dirs {
+        writer
+            .add_directory(format!("dir{d}/nested"), SimpleFileOptions::default())
+            .unwrap();
+        for f in 0..files {
+            let name = format!("dir{d}/nested/file{f}.txt");
+            let content = (0..file_size)
+                .map(|i| (i * 31 + d * 7 + f) as u8)
+                .collect::<Vec<_>>();
+            writer
+                .start_file(name.as_str(), SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(&content).unwrap();
+            expected.push((PathBuf::from(name), content));
+        }
+    }
+    writer.finish().unwrap();
+    (path, expected)
+}
+
+#[test]
+fn parallel_zip_extraction() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (archive, expected) = make_zip(temp_dir.path(), 4, 25, 4096);
+    let dest = temp_dir.path().join("out");
+
+    let aggregate = utils::AggregateProgress::new(utils::Progress::noop().with_len(60.0));
+    Extractable::load(&archive)
+        .unwrap()
+        .extract_parallel_to(&dest, 4, &aggregate)
+        .unwrap();
+
+    for (name, content) in &expected {
+        assert_eq!(&std::fs::read(dest.join(name)).unwrap(), content);
+    }
+    assert_eq!(aggregate.totals(), (100 * 4096, 100 * 4096));
+    assert_eq!(aggregate.progress().position(), 60.0);
+}
+
+/// The workers open the archive themselves, which fails once it's removed after loading.
+#[cfg(unix)]
+#[test]
+fn parallel_extraction_stops_when_workers_fail() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (archive, expected) = make_zip(temp_dir.path(), 2, 10, 100);
+    let dest = temp_dir.path().join("out");
+
+    let mut extractable = Extractable::load(&archive).unwrap();
+    std::fs::remove_file(&archive).unwrap();
+    let aggregate = utils::AggregateProgress::new(utils::Progress::noop().with_len(10.0));
+    let err = extractable
+        .extract_parallel_to(&dest, 4, &aggregate)
+        .unwrap_err();
+
+    assert!(err.to_string().contains("failed to extract"), "{err:?}");
+    assert!(expected.iter().all(|(name, _)| !dest.join(name).exists()));
+}
+
+#[test]
+fn parallel_extraction_falls_back_to_serial() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/simple_xz.tar.xz");
+    let aggregate = utils::AggregateProgress::new(utils::Progress::noop().with_len(10.0));
+    Extractable::load(&path)
+        .unwrap()
+        .extract_parallel_to(temp_dir.path(), 4, &aggregate)
+        .unwrap();
+
+    assert!(temp_dir.path().join("simple_xz").join("aaa.txt").is_file());
+    assert_eq!(aggregate.progress().position(), 10.0);
+}
+
+/// Compares the parallel extraction against the serial one,
+/// run with `cargo test --release -- --ignored --nocapture bench_`.
+#[test]
+#[ignore = "benchmark"]
+fn bench_parallel_zip_extraction() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (archive, _) = make_zip(temp_dir.path(), 8, 100, 256 * 1024);
+
+    let start = std::time::Instant::now();
+    Extractable::load(&archive)
+        .unwrap()
+        .extract_to(&temp_dir.path().join("serial"))
+        .unwrap();
+    let serial = start.elapsed();
+
+    for workers in [1, 2, 4, 8] {
+        let aggregate = utils::AggregateProgress::new(utils::Progress::noop().with_len(100.0));
+        let start = std::time::Instant::now();
+        Extractable::load(&archive)
+            .unwrap()
+            .extract_parallel_to(
+                &temp_dir.path().join(format!("p{workers}")),
+                workers,
+                &aggregate,
+            )
+            .unwrap();
+        println!(
+            "serial: {serial:?}, {workers} workers: {:?}",
+            start.elapsed()
+        );
+    }
+}
+
+/// The final `(position, length)` of the bars stopped by [`recording_indicator`].
+static STOPPED_BARS: std::sync::Mutex<Vec<(u64, u64)>> = std::sync::Mutex::new(vec![]);
+
+fn recording_indicator() -> utils::CliProgress<utils::CliBar> {
+    use utils::{CliProgressOpt, CliProgressStyle};
+    utils::CliProgress {
+        start: |_: String, style: CliProgressStyle, _: CliProgressOpt| {
+            let pb = indicatif::ProgressBar::hidden();
+            if let CliProgressStyle::Bytes(len) | CliProgressStyle::Len(len) = style {
+                pb.set_length(len);
+            }
+            Ok(pb.into())
+        },
+        update: |pb, pos| pb.set_position(pos.unwrap_or_default()),
+        inc: |pb, delta| pb.inc(delta),
+        stop: |pb, _| {
+            let finished = (pb.position(), pb.length().unwrap_or_default());
+            STOPPED_BARS.lock().unwrap().push(finished);
+        },
+        opt: CliProgressOpt::default(),
+    }
+}
+
+#[test]
+fn extraction_progress_units() {
+    use utils::ExtractProgressUnit;
+
+    let extract = |path: &Path, unit| {
+        let temp_dir = tempfile::tempdir().unwrap();
+        Extractable::load(path)
+            .unwrap()
+            .with_progress_unit(unit)
+            .with_indicator(recording_indicator())
+            .extract_to(temp_dir.path())
+            .unwrap();
+        STOPPED_BARS.lock().unwrap().pop().unwrap()
+    };
+
+    // 2 directories with 5 files of 100 bytes each
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (zip, _) = make_zip(temp_dir.path(), 2, 5, 100);
+    assert_eq!(extract(&zip, ExtractProgressUnit::Entries), (12, 12));
+    assert_eq!(extract(&zip, ExtractProgressUnit::Bytes), (1000, 1000));
+
+    let sz = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/7z_with_sub_folders.7z");
+    let (pos, len) = extract(&sz, ExtractProgressUnit::Entries);
+    assert!(len > 0);
+    assert_eq!(pos, len);
+    let (pos, len) = extract(&sz, ExtractProgressUnit::Bytes);
+    assert_eq!(pos, len);
+}
+
+#[test]
+fn cancelled_extraction_removes_extracted_entries() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let archive = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/simple_gz.tar.gz");
+    let token = utils::CancellationToken::new();
+    token.cancel();
+    let dest = temp_dir.path().join("gz");
+    let err = Extractable::load(&archive)
+        .unwrap()
+        .with_cancellation_token(token)
+        .extract_to(&dest)
+        .unwrap_err();
+    assert!(err.is::<utils::Cancelled>());
+    assert!(!dest.exists());
+
+    // cancelled by the progress midway, the existing entries are kept
+    let (zip, _) = make_zip(temp_dir.path(), 2, 5, 100);
+    let dest = temp_dir.path().join("zip");
+    utils::ensure_dir(&dest).unwrap();
+    utils::write_file(dest.join("keep.txt"), "", false).unwrap();
+    let token = utils::CancellationToken::new();
+    let pos_cb = |_: f32| {
+        token.cancel();
+        Ok(())
+    };
+    let progress = utils::Progress::new(&pos_cb)
+        .with_len(10.0)
+        .with_cancellation_token(token.clone());
+    let aggregate = utils::AggregateProgress::new(progress);
+    let err = Extractable::load(&zip)
+        .unwrap()
+        .extract_parallel_to(&dest, 2, &aggregate)
+        .unwrap_err();
+    assert!(err.is::<utils::Cancelled>());
+    assert_eq!(
+        utils::walk_dir(&dest, false).unwrap(),
+        [dest.join("keep.txt")]
+    );
+}
+
+/// Decompress a gzipped tarball in `tests/data` into a plain tarball.
+fn tarball_of(filename: &str) -> Vec<u8> {
+    use std::io::Read;
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(filename);
+    let mut tarball = vec![];
+    flate2::read::GzDecoder::new(std::fs::File::open(path).unwrap())
+        .read_to_end(&mut tarball)
+        .unwrap();
+    tarball
+}
+
+#[test]
+fn extracting_normal_zstd() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let archive = temp_dir.path().join("zst_with_sub_folders.tar.zst");
+    let tarball = tarball_of("gz_with_sub_folders.tar.gz");
+    std::fs::write(&archive, zstd::encode_all(tarball.as_slice(), 0).unwrap()).unwrap();
+
+    let extracted = Extractable::load(&archive)
+        .unwrap()
+        .extract_then_skip_solo_dir(&temp_dir.path().join("out"), None::<&str>)
+        .unwrap();
+    assert_normal_archive(&extracted);
+}
+
+#[test]
+fn format_detected_by_magic_bytes() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let gz = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/simple_gz.tar.gz");
+    let extract = |name: &str, content: &[u8]| {
+        let archive = temp_dir.path().join(name);
+        std::fs::write(&archive, content).unwrap();
+        let dest = temp_dir.path().join(format!("{name}-out"));
+        Extractable::load(&archive)
+            .and_then(|mut extractable| extractable.extract_then_skip_solo_dir(&dest, None::<&str>))
+    };
+
+    // mislabeled or without extension
+    let gz_content = std::fs::read(gz).unwrap();
+    for name in ["mislabeled.tar.xz", "no_extension"] {
+        let extracted = extract(name, &gz_content).unwrap();
+        assert!(extracted.join("aaa.txt").is_file(), "{name}");
+    }
+    let extracted = extract("plain.tar", &tarball_of("simple_gz.tar.gz")).unwrap();
+    assert!(extracted.join("bbb.txt").is_file());
+
+    // archive-like files are not extracted
+    let (zip, _) = make_zip(temp_dir.path(), 1, 1, 10);
+    assert!(extract("extension.vsix", &std::fs::read(zip).unwrap()).is_err());
+    assert!(extract("unknown", b"#!/bin/sh\n").is_err());
+}
+
+#[test]
+fn extraction_advances_progress() {
+    use utils::ExtractProgressUnit;
+
+    let extract = |path: &Path, unit| {
+        let positions = std::sync::Mutex::new(vec![]);
+        let pos_cb = |pos: f32| {
+            positions.lock().unwrap().push(pos);
+            Ok(())
+        };
+        let progress = utils::Progress::new(&pos_cb).with_len(40.0);
+        let temp_dir = tempfile::tempdir().unwrap();
+        Extractable::load(path)
+            .unwrap()
+            .with_progress_unit(unit)
+            .with_progress(&progress)
+            .extract_to(temp_dir.path())
+            .unwrap();
+        assert_eq!(progress.position(), 40.0);
+        let positions = positions.into_inner().unwrap();
+        assert!(positions.windows(2).all(|w| w[0] <= w[1]));
+        positions.len()
+    };
+
+    // 2 directories with 5 files each, advanced by every entry
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (zip, _) = make_zip(temp_dir.path(), 2, 5, 100);
+    assert_eq!(extract(&zip, ExtractProgressUnit::Auto), 12);
+    assert_eq!(extract(&zip, ExtractProgressUnit::Bytes), 10);
+
+    let data = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let sz = data.join("7z_with_sub_folders.7z");
+    assert!(extract(&sz, ExtractProgressUnit::Entries) > 1);
+    // estimated by the compressed bytes read
+    let gz = data.join("gz_with_sub_folders.tar.gz");
+    assert!(extract(&gz, ExtractProgressUnit::Auto) >= 1);
+}
@@ -60,3 +60,132 @@ fn target_override() {
     #[cfg(all(target_arch = "x86_64", target_os = "windows", target_env = "msvc"))]
     assert_eq!(target, "x86_64-pc-windows-msvc");
 }
+
+#[test]
+fn incremental_copy_skips_unchanged_files() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let src = temp_dir.path().join("src");
+    let dest = temp_dir.path().join("dest");
+    let write = |path: PathBuf, len: usize| {
+        utils::ensure_parent_dir(&path).unwrap();
+        std::fs::write(path, vec![b'a'; len]).unwrap();
+    };
+    write(src.join("a.txt"), 100);
+    write(src.join("sub").join("b.txt"), 200);
+    write(src.join("sub").join("c.txt"), 300);
+
+    let progress = utils::Progress::noop().with_len(10.0);
+    let copied = utils::copy_changed_with_progress(&src, &dest, &progress).unwrap();
+    assert_eq!(copied, 600);
+    assert_eq!(progress.position(), 10.0);
+
+    // an unchanged file (it only looks unchanged, so we can tell if it was skipped),
+    // a changed file, and a new file.
+    std::fs::write(dest.join("a.txt"), vec![b'x'; 100]).unwrap();
+    write(src.join("sub").join("b.txt"), 250);
+    write(src.join("d.txt"), 50);
+
+    let positions = std::sync::Mutex::new(vec![]);
+    let pos_cb = |pos: f32| {
+        positions.lock().unwrap().push(pos);
+        Ok(())
+    };
+    let progress = utils::Progress::new(&pos_cb).with_len(30.0);
+    let copied = utils::copy_changed_with_progress(&src, &dest, &progress).unwrap();
+
+    assert_eq!(copied, 250 + 50);
+    assert_eq!(std::fs::read(dest.join("a.txt")).unwrap(), vec![b'x'; 100]);
+    assert_eq!(
+        std::fs::read(dest.join("sub").join("b.txt")).unwrap().len(),
+        250
+    );
+    assert_eq!(std::fs::read(dest.join("d.txt")).unwrap().len(), 50);
+    // the progress only represents the changed bytes
+    let positions = positions.lock().unwrap();
+    assert_eq!(positions.len(), 2);
+    assert_eq!(positions.last(), Some(&30.0));
+
+    // nothing changed
+    let progress = utils::Progress::noop().with_len(5.0);
+    assert_eq!(
+        utils::copy_changed_with_progress(&src, &dest, &progress).unwrap(),
+        0
+    );
+    assert_eq!(progress.position(), 5.0);
+}
+
+#[cfg(unix)]
+#[test]
+fn incremental_copy_counts_hard_links_once() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let src = temp_dir.path().join("src");
+    let dest = temp_dir.path().join("dest");
+    std::fs::create_dir(&src).unwrap();
+    std::fs::write(src.join("a"), vec![b'a'; 100]).unwrap();
+    std::fs::hard_link(src.join("a"), src.join("b")).unwrap();
+    std::fs::write(src.join("c"), vec![b'c'; 50]).unwrap();
+
+    let positions = std::sync::Mutex::new(vec![]);
+    let pos_cb = |pos: f32| {
+        positions.lock().unwrap().push(pos);
+        Ok(())
+    };
+    let progress = utils::Progress::new(&pos_cb).with_len(15.0);
+    let copied = utils::copy_changed_with_progress(&src, &dest, &progress).unwrap();
+
+    assert_eq!(copied, 150);
+    assert_eq!(utils::disk_size(&dest).unwrap(), 150);
+    // the bar reaches its end by the copied files, rather than the final completion
+    let positions = positions.lock().unwrap();
+    assert_eq!(positions.len(), 2);
+    assert_eq!(positions.last(), Some(&15.0));
+}
+
+#[cfg(unix)]
+#[test]
+fn copied_links_still_work_without_the_source() {
+    use std::os::unix::fs::symlink;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let outside = temp_dir.path().join("outside");
+    std::fs::write(&outside, vec![b'o'; 30]).unwrap();
+    let src = temp_dir.path().join("src");
+    std::fs::create_dir_all(src.join("lib")).unwrap();
+    std::fs::write(src.join("lib").join("libstd.so"), vec![b's'; 100]).unwrap();
+    symlink(src.join("lib").join("libstd.so"), src.join("absolute")).unwrap();
+    symlink("lib/libstd.so", src.join("relative")).unwrap();
+    symlink(&outside, src.join("external")).unwrap();
+
+    // only the target outside of `src` is copied along with the file
+    assert_eq!(utils::disk_size(&src).unwrap(), 130);
+
+    let copied = temp_dir.path().join("copied");
+    utils::copy_as(&src, &copied).unwrap();
+    let changed = temp_dir.path().join("changed");
+    let progress = utils::Progress::noop();
+    assert_eq!(
+        utils::copy_changed_with_progress(&src, &changed, &progress).unwrap(),
+        130
+    );
+    std::fs::remove_dir_all(&src).unwrap();
+    std::fs::remove_file(&outside).unwrap();
+
+    for dest in [copied, changed] {
+        let libstd = dest.join("lib").join("libstd.so");
+        assert_eq!(std::fs::read_link(dest.join("absolute")).unwrap(), libstd);
+        assert_eq!(
+            std::fs::read_link(dest.join("relative")).unwrap(),
+            PathBuf::from("lib/libstd.so")
+        );
+        assert_eq!(
+            std::fs::read(dest.join("absolute")).unwrap(),
+            vec![b's'; 100]
+        );
+        assert!(!dest.join("external").is_symlink());
+        assert_eq!(
+            std::fs::read(dest.join("external")).unwrap(),
+            vec![b'o'; 30]
+        );
+        assert_eq!(utils::disk_size(&dest).unwrap(), 130);
+    }
+}
@@ -0,0 +1,100 @@
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+/// Create an installed toolkit `kit` 1.0.0 under `root/kit`, with the manager, the toolchain
+/// and two tools, `a` and `b`, and a dist server under `root/server` providing version 2.0.0,
+/// which updates all of them.
+fn installed_kit_with_update(root: &Path) {
+    let target = env!("TARGET");
+    let write = |path: &str, content: &str| {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    };
+    for tool in ["a", "b"] {
+        write(&format!("pkgs/{tool}"), "#!/bin/sh\n");
+        let path = root.join("pkgs").join(tool);
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let manifest = |version: &str, rust: &str, tools: &str| {
+        let pkgs = root.join("pkgs");
+        let pkgs = pkgs.display();
+        format!(
+            "name = \"kit\"\nversion = \"{version}\"\n[rust]\nversion = \"{rust}\"\n\
+            [tools.target.{target}]\n\
+            a = {{ path = \"{pkgs}/a\", version = \"{tools}\" }}\n\
+            b = {{ path = \"{pkgs}/b\", version = \"{tools}\" }}\n"
+        )
+    };
+
+    write(
+        "kit/toolset-manifest.toml",
+        &manifest("1.0.0", "1.0.0", "1.0"),
+    );
+    write(
+        "kit/.fingerprint.toml",
+        &format!(
+            "name = \"kit\"\nversion = \"1.0.0\"\nroot = \"{}\"\n\
+            [rust]\nversion = \"1.0.0\"\ncomponents = []\n\
+            [tools.a]\nkind = \"executables\"\nversion = \"1.0\"\n\
+            [tools.b]\nkind = \"executables\"\nversion = \"1.0\"\n",
+            root.join("kit").display()
+        ),
+    );
+    std::fs::copy(
+        env!("CARGO_BIN_EXE_rim-cli"),
+        root.join("kit").join("rim-cli"),
+    )
+    .unwrap();
+
+    write("server/manifest.toml", &manifest("2.0.0", "1.1.0", "2.0"));
+    let manifest_url = url::Url::from_file_path(root.join("server/manifest.toml")).unwrap();
+    write(
+        "server/dist/distribution-manifest.toml",
+        &format!(
+            "[[packages]]\nname = \"kit\"\nversion = \"2.0.0\"\nmanifest-url = \"{manifest_url}\"\n"
+        ),
+    );
+}
+
+/// Run the installed manager with `args`, returns what's printed on `stdout`.
+fn run_manager(root: &Path, args: &[&str]) -> String {
+    let server = url::Url::from_directory_path(root.join("server")).unwrap();
+    let out = Command::new(root.join("kit").join("rim-cli"))
+        .env("MODE", "manager")
+        .env("HOME", root.join("home"))
+        .env("XDG_DATA_HOME", root.join("data"))
+        .env("XDG_CACHE_HOME", root.join("cache"))
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("RIM_DIST_SERVER", server.as_str())
+        .env_remove("RIM_LOG")
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn partial_update_keeps_the_other_updates() {
+    let root = tempfile::tempdir().unwrap();
+    let root = root.path().canonicalize().unwrap();
+    installed_kit_with_update(&root);
+
+    run_manager(&root, &["-y", "update", "a"]);
+    let record = std::fs::read_to_string(root.join("kit").join(".fingerprint.toml")).unwrap();
+    assert!(record.contains("version = \"1.0.0\""), "{record}");
+
+    let stdout = run_manager(&root, &["check"]);
+    assert!(stdout.contains("1.0.0 -> 2.0.0"), "{stdout}");
+    assert!(stdout.contains("b: 1.0 -> 2.0"), "{stdout}");
+    assert!(stdout.contains(": 1.0.0 -> 1.1.0"), "{stdout}");
+    assert!(!stdout.contains("a: "), "{stdout}");
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+use std::process::Command;
+
+use rim::utils;
+
+/// Create a fake dist server containing a toolchain with only a `rustc` package,
+/// and `rustup-init` of current target.
+fn fake_dist_server(root: &Path) {
+    let target = env!("TARGET");
+    let write = |path: &str, content: &str| {
+        let path = root.join(path);
+        utils::ensure_parent_dir(&path).unwrap();
+        std::fs::write(path, content).unwrap();
+    };
+    let rustup_init = format!("rustup-init{}", std::env::consts::EXE_SUFFIX);
+    write(&format!("rustup/dist/{target}/{rustup_init}"), "rustup");
+    let file = format!("rustc-1.0.0-{target}.tar.xz");
+    write(&format!("dist/2024-01-01/{file}"), "rustc");
+    // the SHA-256 of "rustc"
+    let hash = "6fcd351889eb0caf54c459904e996ac7480a7843a5e8a25c8c6b59a0f975ca19";
+    write(
+        "dist/channel-rust-1.0.0.toml",
+        &format!(
+            "[profiles]\nminimal = [\"rustc\"]\n\
+            [pkg.rustc.target.\"{target}\"]\navailable = true\n\
+            xz_url = \"https://static.rust-lang.org/dist/2024-01-01/{file}\"\n\
+            xz_hash = \"{hash}\"\n"
+        ),
+    );
+    write("dist/channel-rust-1.0.0.toml.sha256", "checksum");
+    write(
+        "manifest.toml",
+        "[rust]\nversion = \"1.0.0\"\nprofile = { name = \"minimal\" }\n",
+    );
+}
+
+/// Create an offline bundle from the fake dist server, with the given `--output` format,
+/// returns what's printed on `stdout`.
+fn create_bundle(root: &Path, output: &str) -> String {
+    let server = url::Url::from_directory_path(root).unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_rim-cli"))
+        .env("MODE", "manager")
+        .env("HOME", root.join("home"))
+        .env("XDG_DATA_HOME", root.join("data"))
+        .env("XDG_CACHE_HOME", root.join("cache"))
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("TERM", "xterm")
+        .env_remove("NO_COLOR")
+        .env_remove("RIM_LOG")
+        .args(["--output", output, "bundle", "create", "--manifest"])
+        .arg(root.join("manifest.toml"))
+        .arg("--rustup-dist-server")
+        .arg(server.as_str())
+        .arg("--rustup-update-root")
+        .arg(server.join("rustup/").unwrap().as_str())
+        .arg(root.join(format!("{output}.tar.gz")))
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn summary_output_prints_a_line_for_each_operation() {
+    let root = tempfile::tempdir().unwrap();
+    fake_dist_server(root.path());
+
+    let stdout = create_bundle(root.path(), "summary");
+    let target = env!("TARGET");
+    let line = format!("✓ 'rustc-1.0.0-{target}.tar.xz' verified. (5 B, 0s)");
+    assert!(stdout.lines().any(|l| l == line), "{stdout}");
+
+    let stdout = create_bundle(root.path(), "human");
+    assert!(!stdout.contains('✓'), "{stdout}");
+}